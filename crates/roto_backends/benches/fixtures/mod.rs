@@ -0,0 +1,60 @@
+//! Generated schemas for the benchmarks. They are generated instead of checked in, so that their
+//! size can be changed without committing megabytes of schemas.
+
+use std::fmt::Write;
+
+/// `count` types that refer to each other: structs, enums and instantiations of two generics.
+/// Every type refers to an earlier one, so every type is reachable from the last. Members are
+/// tagged, so that the backends that need field numbers accept it too.
+pub fn wide(count: usize) -> String {
+    let mut source = String::from(
+        "type Identified<T> = T & struct {\n  @tag(value=100) id: string,\n};\n\n\
+         type Page<T> = struct {\n  @tag(value=1) items: T,\n  @tag(value=2) total: int,\n  @tag(value=3) cursor: string,\n};\n\n",
+    );
+    for i in 0..count {
+        match i % 10 {
+            0 => writeln!(
+                source,
+                "type Type{} = enum {{\n  @tag(value=1) active(unit),\n  @tag(value=2) count(int),\n  @tag(value=3) other(string),\n}};\n",
+                i
+            ),
+            5 => writeln!(source, "type Type{} = Page<T=Type{}>;\n", i, i - 1),
+            7 => writeln!(source, "type Type{} = Identified<T=Type{}>;\n", i, i - 2),
+            _ => writeln!(
+                source,
+                "// The {}th type.\ntype Type{} = struct {{\n  @tag(value=1) name: string,\n  \
+                 @tag(value=2) count?: int,\n  @tag(value=3) status: Type{},\n  \
+                 @tag(value=4) parent: Type{},\n}};\n",
+                i,
+                i,
+                i / 10 * 10,
+                i / 2
+            ),
+        }
+        .unwrap();
+    }
+    source
+}
+
+/// Generics instantiated with other instantiations: `Deep` nests `Wrap` `depth` times, and each
+/// `ChainN` instantiates `Pair` with `ChainN-1`, so that every level is a new instantiation. Every
+/// level also instantiates `Wrap` with the same argument, which is only compiled once.
+pub fn deep_generics(depth: usize) -> String {
+    let mut source = String::from(
+        "type Wrap<T> = struct {\n  @tag(value=1) value: T,\n  @tag(value=2) tag: string,\n};\n\n\
+         type Pair<A, B> = struct {\n  @tag(value=1) left: A,\n  @tag(value=2) right: B,\n};\n\n",
+    );
+    let nested = (0..depth).fold(String::from("int"), |inner, _| format!("Wrap<T={}>", inner));
+    writeln!(source, "type Deep = {};\n", nested).unwrap();
+    writeln!(source, "type Chain0 = Pair<A=int, B=string>;\n").unwrap();
+    for i in 1..depth {
+        writeln!(
+            source,
+            "type Chain{} = Pair<A=Chain{}, B=Wrap<T=string>>;\n",
+            i,
+            i - 1
+        )
+        .unwrap();
+    }
+    source
+}
@@ -0,0 +1,63 @@
+//! Benchmarks of each stage of turning a schema into code: parsing, compiling and the emission of
+//! every backend, on generated large schemas. Run with `cargo bench -p roto_backends`, or
+//! `cargo bench -p roto_backends -- <filter>` for a part, like `compile/wide-10000`.
+
+mod fixtures;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use roto_core::backend::BackendConfig;
+
+fn schemas() -> Vec<(String, String)> {
+    vec![
+        ("wide-1000".to_string(), fixtures::wide(1_000)),
+        ("wide-10000".to_string(), fixtures::wide(10_000)),
+        ("deep-generics-64".to_string(), fixtures::deep_generics(64)),
+    ]
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    for (name, source) in schemas() {
+        group.bench_with_input(BenchmarkId::from_parameter(&name), &source, |b, source| {
+            b.iter(|| roto_core::parse(black_box(source), "bench.roto").unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn compile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compile");
+    group.sample_size(10);
+    for (name, source) in schemas() {
+        group.bench_with_input(BenchmarkId::from_parameter(&name), &source, |b, source| {
+            b.iter(|| {
+                let (compiler, diagnostics) =
+                    roto_backends::compile(black_box(source), "bench.roto", &[]);
+                compiler.unwrap_or_else(|| panic!("{:?}", diagnostics))
+            })
+        });
+    }
+    group.finish();
+}
+
+fn generate(c: &mut Criterion) {
+    let registry = roto_backends::registry();
+    let config = BackendConfig::new();
+    for (name, source) in schemas() {
+        let backends: Vec<_> = registry.iter().collect();
+        let (compiler, diagnostics) = roto_backends::compile(&source, "bench.roto", &backends);
+        let compiler = compiler.unwrap_or_else(|| panic!("{:?}", diagnostics));
+        let mut group = c.benchmark_group(format!("generate/{}", name));
+        group.sample_size(10);
+        for backend in &backends {
+            group.bench_function(backend.name(), |b| {
+                b.iter(|| backend.generate(black_box(&compiler), &config).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, parse, compile, generate);
+criterion_main!(benches);
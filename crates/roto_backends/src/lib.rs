@@ -0,0 +1,602 @@
+//! Every backend of the workspace, for the tools that select them by name: the command line,
+//! the bindings for other languages and the C API.
+//!
+//! [`compile`] and [`compile_files`] compile sources that are already read, for tools that are
+//! handed schemas instead of finding them on disk. [`compile_paths`] reads and parses files in
+//! parallel, for large schema repositories.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+
+use roto_core::ast::Item;
+use roto_core::backend::BackendRegistry;
+use roto_core::backend::CodegenBackend;
+use roto_core::backend::Unconfigured;
+use roto_core::diagnostics::has_errors;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::frontend::CollisionPolicy;
+use roto_core::frontend::IRCompiler;
+use roto_core::migrate::MigrationWriter;
+use roto_core::ParseDiagnostic;
+use roto_cpp_backend::CppBackend;
+use roto_csharp_backend::CSharpBackend;
+use roto_descriptor_backend::python::PythonDescriptorBackend;
+use roto_descriptor_backend::rust::RustDescriptorBackend;
+use roto_doc_backend::html::HtmlDocBackend;
+use roto_doc_backend::markdown::MarkdownDocBackend;
+use roto_dot_backend::DotBackend;
+use roto_json_example_backend::JsonExampleBackend;
+use roto_openapi_backend::OpenApiBackend;
+use roto_openapi_backend::OutputFormat;
+use roto_plantuml_backend::PlantUmlBackend;
+use roto_proto_backend::ProtoBackend;
+use roto_py_msgspec_backend::MsgspecBackend;
+use roto_py_pydantic_backend::PydanticBackend;
+use roto_thrift_backend::ThriftBackend;
+use roto_ts_backend::zod::ZodBackend;
+use roto_ts_backend::TsBackend;
+use roto_wire_backend::python::PythonWireBackend;
+use roto_wire_backend::rust::RustWireBackend;
+use tracing::debug;
+
+/// Every backend, by the name it is selected by.
+pub fn registry() -> BackendRegistry {
+    let mut registry = BackendRegistry::new();
+    registry.register(Box::new(MsgspecBackend::default()));
+    registry.register(Box::new(Unconfigured {
+        name: "cpp",
+        backend: CppBackend { namespace: None },
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "csharp",
+        backend: CSharpBackend { namespace: None },
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "descriptor-python",
+        backend: PythonDescriptorBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "descriptor-rust",
+        backend: RustDescriptorBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "doc-html",
+        backend: HtmlDocBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "doc-markdown",
+        backend: MarkdownDocBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "dot",
+        backend: DotBackend {
+            collapse_temporaries: false,
+        },
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "json-example",
+        backend: JsonExampleBackend {
+            include_optional: true,
+        },
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "openapi",
+        backend: OpenApiBackend {
+            format: OutputFormat::Json,
+        },
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "plantuml",
+        backend: PlantUmlBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "proto",
+        backend: ProtoBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "py-pydantic",
+        backend: PydanticBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "thrift",
+        backend: ThriftBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "ts",
+        backend: TsBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "ts-zod",
+        backend: ZodBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "wire-python",
+        backend: PythonWireBackend,
+    }));
+    registry.register(Box::new(Unconfigured {
+        name: "wire-rust",
+        backend: RustWireBackend,
+    }));
+    registry
+}
+
+/// The backend that writes migrations for `target`, if it has one, see
+/// [`roto_core::migrate`].
+pub fn migration_writer(target: &str) -> Option<&'static dyn MigrationWriter> {
+    match target {
+        "py-pydantic" => Some(&PydanticBackend),
+        _ => None,
+    }
+}
+
+/// Parses and compiles `source`, the contents of `file_name`. The annotations of `backends` are
+/// known, others are reported. Returns the compiled types, unless there were errors, and the
+/// diagnostics.
+pub fn compile(
+    source: &str,
+    file_name: &str,
+    backends: &[&dyn CodegenBackend],
+) -> (Option<IRCompiler>, Vec<Diagnostic>) {
+    compile_files(&[(file_name, source)], backends)
+}
+
+/// Like [`compile`], for `files` of a file name and its contents. A type declared in two files
+/// is an error.
+pub fn compile_files(
+    files: &[(&str, &str)],
+    backends: &[&dyn CodegenBackend],
+) -> (Option<IRCompiler>, Vec<Diagnostic>) {
+    let mut compiler = new_compiler(backends);
+    let mut diagnostics = Vec::new();
+    for (file_name, source) in files {
+        let parsed = roto_core::parse(source, file_name);
+        register_file(&mut compiler, file_name, source, parsed, &mut diagnostics);
+    }
+    finish(compiler, diagnostics)
+}
+
+/// A file read and parsed by a worker of [`compile_paths`].
+struct ParsedFile {
+    name: String,
+    source: String,
+    items: Result<Vec<Item>, Vec<ParseDiagnostic>>,
+}
+
+fn read_and_parse(path: &Path) -> Result<ParsedFile, Diagnostic> {
+    let name = path.display().to_string();
+    let source = fs::read_to_string(path)
+        .map_err(|e| Diagnostic::error(format!("failed to read {}: {}", name, e), None))?;
+    let items = roto_core::parse(&source, &name);
+    Ok(ParsedFile {
+        name,
+        source,
+        items,
+    })
+}
+
+/// Like [`compile_files`], for the files at `paths`, which are read and parsed on as many
+/// threads as there are cores. Every file is registered as soon as it and the files before it
+/// are parsed, while later files are still being read, so the order of `paths` decides the
+/// order of diagnostics, not which thread finishes first. Types are compiled once every file is
+/// registered, since they may refer to types of any file.
+pub fn compile_paths(
+    paths: &[PathBuf],
+    backends: &[&dyn CodegenBackend],
+) -> (Option<IRCompiler>, Vec<Diagnostic>) {
+    let mut compiler = new_compiler(backends);
+    let mut diagnostics = Vec::new();
+    let threads = thread::available_parallelism()
+        .map_or(1, |threads| threads.get())
+        .min(paths.len());
+    let next = AtomicUsize::new(0);
+    debug!(files = paths.len(), threads, "compiling files");
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(index) else {
+                    break;
+                };
+                if sender.send((index, read_and_parse(path))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut registered = 0;
+        for (index, file) in receiver {
+            pending.insert(index, file);
+            while let Some(file) = pending.remove(&registered) {
+                match file {
+                    Ok(file) => register_file(
+                        &mut compiler,
+                        &file.name,
+                        &file.source,
+                        file.items,
+                        &mut diagnostics,
+                    ),
+                    Err(error) => diagnostics.push(error),
+                }
+                registered += 1;
+            }
+        }
+    });
+    finish(compiler, diagnostics)
+}
+
+fn new_compiler(backends: &[&dyn CodegenBackend]) -> IRCompiler {
+    let mut compiler = IRCompiler::new();
+    for backend in backends {
+        backend.register_annotations(&mut compiler.annotations);
+    }
+    compiler
+}
+
+/// Registers the types of a parsed file on their own and merges them into `compiler`, so that
+/// a type declared differently in two files is reported instead of silently replaced.
+fn register_file(
+    compiler: &mut IRCompiler,
+    file_name: &str,
+    source: &str,
+    parsed: Result<Vec<Item>, Vec<ParseDiagnostic>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match parsed {
+        Ok(items) => {
+            let mut file_compiler = IRCompiler::new();
+            file_compiler.register_program(items, file_name, source);
+            match compiler.merge(&file_compiler, CollisionPolicy::Reject) {
+                Ok(collisions) => diagnostics.extend(collisions.iter().map(|c| {
+                    Diagnostic::error(c.to_string(), c.incoming.clone()).with_code("duplicate-type")
+                })),
+                Err(error) => diagnostics.extend(error.into_diagnostics()),
+            }
+        }
+        Err(errors) => diagnostics.extend(errors.into_iter().map(Diagnostic::from)),
+    }
+}
+
+/// Validates the annotations of the registered files and compiles their types, unless
+/// registering them failed.
+fn finish(
+    mut compiler: IRCompiler,
+    mut diagnostics: Vec<Diagnostic>,
+) -> (Option<IRCompiler>, Vec<Diagnostic>) {
+    if has_errors(&diagnostics) {
+        return (None, diagnostics);
+    }
+    diagnostics.extend(compiler.validate_annotations());
+    if has_errors(&diagnostics) {
+        return (None, diagnostics);
+    }
+
+    if let Err(error) = compiler.compile_globals() {
+        diagnostics.extend(error.into_diagnostics());
+        return (None, diagnostics);
+    }
+    (Some(compiler), diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use roto_core::ast;
+    use roto_core::backend::Backend;
+    use roto_core::backend::BackendConfig;
+    use roto_core::frontend::TypePrototype;
+
+    use super::*;
+
+    /// Generates the only file of the backend `name` for `source`.
+    fn generate(name: &str, source: &str) -> String {
+        let registry = registry();
+        let backend = registry.get(name).unwrap();
+        let (compiler, diagnostics) = compile(source, "test.roto", &[backend]);
+        let compiler = compiler.unwrap_or_else(|| panic!("{:?}", diagnostics));
+        let files = backend
+            .generate(&compiler, &BackendConfig::default())
+            .unwrap_or_else(|error| panic!("{}: {}", name, error));
+        files.into_iter().map(|file| file.contents).collect()
+    }
+
+    const KEYWORDS: &str = "
+        type object = struct {
+            @tag(value=1) class: int,
+            @tag(value=2) class_: int,
+            @tag(value=3) Class: int,
+            @tag(value=4) self: int,
+            @tag(value=5) self_: int,
+            @tag(value=6) async: int,
+        };
+        type Choice = enum {
+            @tag(value=1) class(int),
+            @tag(value=2) Class(int),
+            @tag(value=3) self(int),
+        };
+    ";
+
+    #[test]
+    fn keywords_and_collisions_are_escaped() {
+        let expected: [(&str, &[&str]); 7] = [
+            (
+                "cpp",
+                &[
+                    "std::int64_t class_{};\n    std::int64_t class__{};",
+                    "struct Class_ {",
+                    "std::variant<Class, Class_, Self> value;",
+                ],
+            ),
+            (
+                "csharp",
+                &[
+                    "public sealed record @object",
+                    "long Class { get; init; }",
+                    "long Class_ { get; init; }",
+                    "long Class__ { get; init; }",
+                    "public sealed record Class_ : Choice",
+                ],
+            ),
+            (
+                "py-msgspec",
+                &[
+                    "class_: int = msgspec.field(name=\"class\")",
+                    "class__: int = msgspec.field(name=\"class_\")",
+                    "async_: int = msgspec.field(name=\"async\")",
+                    "class Choice_Class_(",
+                ],
+            ),
+            (
+                "py-pydantic",
+                &[
+                    "class_: int = Field(alias=\"class\")",
+                    "class__: int = Field(alias=\"class_\")",
+                    "async_: int = Field(alias=\"async\")",
+                ],
+            ),
+            (
+                "thrift",
+                &[
+                    "1: i64 class_,\n  2: i64 class__,",
+                    "4: i64 self_,\n  5: i64 self__,\n  6: i64 async_,",
+                ],
+            ),
+            (
+                "wire-python",
+                &[
+                    "class_: int\n    class__: int",
+                    "async_: int",
+                    "class Choice_Class_:",
+                ],
+            ),
+            (
+                "wire-rust",
+                &[
+                    "pub self_: i64,\n    pub self__: i64,\n    pub r#async: i64,",
+                    "Class_(i64),\n    Self_(i64),",
+                ],
+            ),
+        ];
+        for (name, snippets) in expected {
+            let output = generate(name, KEYWORDS);
+            for snippet in snippets {
+                assert!(
+                    output.contains(snippet),
+                    "{} does not contain {:?}:\n{}",
+                    name,
+                    snippet,
+                    output
+                );
+            }
+        }
+    }
+
+    /// The errors `name` reports for `source`.
+    fn error_diagnostics(name: &str, source: &str) -> Vec<Diagnostic> {
+        let registry = registry();
+        let backend = registry.get(name).unwrap();
+        let (compiler, diagnostics) = compile(source, "test.roto", &[backend]);
+        let compiler = compiler.unwrap_or_else(|| panic!("{:?}", diagnostics));
+        let error = backend
+            .generate(&compiler, &BackendConfig::default())
+            .err()
+            .unwrap_or_else(|| panic!("{} accepted {}", name, source));
+        error.diagnostics
+    }
+
+    /// The messages of the errors `name` reports for `source`.
+    fn errors(name: &str, source: &str) -> Vec<String> {
+        error_diagnostics(name, source)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    /// The lines and columns of the errors `name` reports for `source`.
+    fn error_positions(name: &str, source: &str) -> Vec<(usize, usize)> {
+        error_diagnostics(name, source)
+            .into_iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.expect("errors are located");
+                (location.line, location.column)
+            })
+            .collect()
+    }
+
+    const UNTAGGED_GENERIC: &str = "
+        type Optional<T> = enum {
+            @tag(value=1) some(T),
+            none(unit),
+        };
+        type Node<T> = struct {
+            @tag(value=1) value: T,
+        };
+        type Root = struct {
+            @tag(value=1) head: Optional<T=Node<T=int>>,
+        };
+    ";
+
+    #[test]
+    fn proto_diagnostics_write_generic_arguments_as_source() {
+        assert_eq!(
+            errors("proto", UNTAGGED_GENERIC),
+            ["Optional<T=Node<T=int>>.none has no @tag annotation"]
+        );
+        assert_eq!(error_positions("proto", UNTAGGED_GENERIC), [(4, 13)]);
+    }
+
+    #[test]
+    fn thrift_diagnostics_write_generic_arguments_as_source() {
+        let source = "
+            type Pair<T> = struct {
+                @tag(value=1) first: T,
+                @tag(value=1) second: T,
+                @tag(value=0) third: T,
+            };
+            type Root = struct {
+                @tag(value=1) pair: Pair<T=Optional<T=int>>,
+            };
+            type Optional<T> = enum {
+                @tag(value=1) some(T),
+                @tag(value=2) none(unit),
+            };
+        ";
+        assert_eq!(
+            errors("thrift", source),
+            [
+                "Pair<T=Optional<T=int>>.first and Pair<T=Optional<T=int>>.second have the same \
+                 tag 1",
+                "Pair<T=Optional<T=int>>.third has an invalid tag 0",
+            ]
+        );
+        assert_eq!(error_positions("thrift", source), [(4, 31), (5, 31)]);
+        assert_eq!(
+            errors("thrift", UNTAGGED_GENERIC),
+            ["Optional<T=Node<T=int>>.none has no @tag annotation"]
+        );
+        assert_eq!(error_positions("thrift", UNTAGGED_GENERIC), [(4, 13)]);
+    }
+
+    #[test]
+    fn msgspec_classes_are_ordered_by_dependency_then_name() {
+        let output = generate(
+            "py-msgspec",
+            "type A = struct { z: zed, b: B, };
+             type zed = struct { x: int, };
+             type B = Base;
+             type Base = struct { a: A, };",
+        );
+        let classes: Vec<_> = output
+            .lines()
+            .filter_map(|line| line.strip_prefix("class "))
+            .map(|line| &line[..line.find('(').unwrap()])
+            .collect();
+        // A and B reference each other, so they come by name after zed.
+        assert_eq!(classes, ["zed", "A", "B", "Base"]);
+    }
+
+    #[test]
+    fn csharp_writes_aliases_as_records() {
+        let output = generate(
+            "csharp",
+            "type Node = struct { value: int, };
+             type Head = Node;
+             type List = Head;
+             type Id = string;",
+        );
+        assert!(
+            output.contains("public sealed record Head\n{"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("public sealed record List\n{"),
+            "{}",
+            output
+        );
+        assert!(!output.contains("record Id"), "{}", output);
+    }
+
+    #[test]
+    fn pydantic_imports_no_return_for_empty_variants_only() {
+        let output = generate("py-pydantic", "type Point = struct { x: int, };");
+        assert!(!output.contains("NoReturn"), "{}", output);
+        // Variants without options can not be written in a schema, only built.
+        let mut compiler = IRCompiler::new();
+        compiler.register_global_type(
+            "Never".to_string(),
+            TypePrototype {
+                params: Vec::new(),
+                type_: ast::TypeExpression::Variant(ast::VariantTypeExpression {
+                    variants: Vec::new(),
+                }),
+                doc: None,
+                annotations: Vec::new(),
+                location: None,
+            },
+        );
+        compiler.compile_globals().unwrap();
+        let files = PydanticBackend.generate(&compiler).unwrap();
+        let output = &files[0].contents;
+        assert!(
+            output.contains("from typing import Annotated, Literal, NoReturn, TypeAlias, Union"),
+            "{}",
+            output
+        );
+        assert!(output.contains("Never: TypeAlias = NoReturn"), "{}", output);
+    }
+
+    #[test]
+    fn temporaries_with_the_same_joined_path_get_different_names() {
+        let source = "type User = struct {
+                          a_b: struct { x: int, },
+                          a: struct { b: struct { y: string, }, },
+                      };";
+        for (name, definition) in [
+            ("ts", "export interface User_a_b "),
+            ("py-msgspec", "class User_a_b("),
+        ] {
+            let output = generate(name, source);
+            assert_eq!(output.matches(definition).count(), 1, "{}", output);
+            assert!(output.contains(&definition.replace("User_a_b", "User_a_b_")));
+        }
+    }
+
+    #[test]
+    fn examples_only_use_known_annotations() {
+        let registry = registry();
+        for example in ["linked_list.roto", "simple.roto"] {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../examples")
+                .join(example);
+            let source = fs::read_to_string(&path).unwrap();
+            // An annotation of one backend, like `@json` of msgspec, is unknown to the others.
+            let backends: Vec<&dyn CodegenBackend> = registry.iter().collect();
+            let (compiler, diagnostics) = compile(&source, example, &backends);
+            assert!(compiler.is_some(), "{}: {:?}", example, diagnostics);
+            assert!(diagnostics.is_empty(), "{}: {:?}", example, diagnostics);
+            for backend in registry.iter() {
+                let (compiler, diagnostics) = compile(&source, example, &[backend]);
+                assert!(compiler.is_some(), "{}: {:?}", example, diagnostics);
+                assert!(
+                    diagnostics
+                        .iter()
+                        .all(|diagnostic| diagnostic.code == Some("unknown-annotation")),
+                    "{} with {}: {:?}",
+                    example,
+                    backend.name(),
+                    diagnostics
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Runs roto from a build script, so that generated code is part of the build instead of being
+//! checked in:
+//!
+//! ```no_run
+//! // build.rs
+//! use roto_build::{RotoBuild, Target};
+//!
+//! fn main() {
+//!     RotoBuild::new()
+//!         .file("schemas/user.roto")
+//!         .target(Target::WireRust)
+//!         .compile()
+//!         .unwrap();
+//! }
+//! ```
+//!
+//! The files are written to `OUT_DIR` by default, from where the crate includes them:
+//!
+//! ```ignore
+//! #[allow(dead_code, non_camel_case_types)]
+//! mod user {
+//!     include!(concat!(env!("OUT_DIR"), "/wire.rs"));
+//! }
+//! ```
+
+use std::env;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::Path;
+use std::path::PathBuf;
+
+use roto_backends::compile_paths;
+use roto_backends::registry;
+use roto_core::backend::write_files;
+use roto_core::backend::BackendConfig;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::diagnostics::Severity;
+
+/// The backends a build script can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Cpp,
+    CSharp,
+    DescriptorPython,
+    DescriptorRust,
+    DocHtml,
+    DocMarkdown,
+    Dot,
+    JsonExample,
+    OpenApi,
+    PlantUml,
+    Proto,
+    PyMsgspec,
+    PyPydantic,
+    Thrift,
+    Ts,
+    TsZod,
+    WirePython,
+    WireRust,
+}
+
+impl Target {
+    /// The name `roto build --target` selects the backend by.
+    pub fn name(self) -> &'static str {
+        match self {
+            Target::Cpp => "cpp",
+            Target::CSharp => "csharp",
+            Target::DescriptorPython => "descriptor-python",
+            Target::DescriptorRust => "descriptor-rust",
+            Target::DocHtml => "doc-html",
+            Target::DocMarkdown => "doc-markdown",
+            Target::Dot => "dot",
+            Target::JsonExample => "json-example",
+            Target::OpenApi => "openapi",
+            Target::PlantUml => "plantuml",
+            Target::Proto => "proto",
+            Target::PyMsgspec => "py-msgspec",
+            Target::PyPydantic => "py-pydantic",
+            Target::Thrift => "thrift",
+            Target::Ts => "ts",
+            Target::TsZod => "ts-zod",
+            Target::WirePython => "wire-python",
+            Target::WireRust => "wire-rust",
+        }
+    }
+}
+
+/// Why a build failed, with the diagnostics `roto build` would have reported. Printed the same
+/// way by `Display` and `Debug`, so that unwrapping the result in a build script reads well.
+pub struct Error {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Error {
+    fn new(message: String) -> Self {
+        Error {
+            diagnostics: vec![Diagnostic::error(message, None)],
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The schemas to compile and what to generate from them.
+#[derive(Default)]
+pub struct RotoBuild {
+    files: Vec<PathBuf>,
+    target: Option<Target>,
+    config: BackendConfig,
+    out_dir: Option<PathBuf>,
+}
+
+impl RotoBuild {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a schema, relative to the directory of the crate's `Cargo.toml`.
+    pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+        self.files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Sets an option of the backend, like the `<option>=<value>` arguments of `roto build`.
+    pub fn option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.set(key, value);
+        self
+    }
+
+    /// The directory the files are written to, `OUT_DIR` by default.
+    pub fn out_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.out_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Compiles the schemas and writes the generated files. Cargo runs the build script again
+    /// when a schema changes, and warnings are shown as cargo warnings. Returns the paths that
+    /// were written.
+    pub fn compile(self) -> Result<Vec<PathBuf>, Error> {
+        let target = self
+            .target
+            .ok_or_else(|| Error::new("no target given".to_string()))?;
+        let out_dir = match self.out_dir {
+            Some(out_dir) => out_dir,
+            None => env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or_else(|| Error::new("OUT_DIR is not set".to_string()))?,
+        };
+
+        // Printed before reading, so that a missing schema is picked up once it exists.
+        for file in &self.files {
+            println!("cargo:rerun-if-changed={}", file.display());
+        }
+
+        let registry = registry();
+        let backend = registry.get(target.name()).unwrap();
+        let (compiler, diagnostics) = compile_paths(&self.files, &[backend]);
+        let Some(compiler) = compiler else {
+            return Err(Error { diagnostics });
+        };
+        for warning in diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+        {
+            println!("cargo:warning={}", warning);
+        }
+        let mut files = backend
+            .generate(&compiler, &self.config)
+            .map_err(|error| Error {
+                diagnostics: error.diagnostics,
+            })?;
+
+        // `include!` does not allow inner attributes, the including module sets its lints
+        // instead.
+        for file in files.iter_mut().filter(|file| file.path.ends_with(".rs")) {
+            let lines: Vec<&str> = file
+                .contents
+                .lines()
+                .filter(|line| !line.starts_with("#!["))
+                .collect();
+            file.contents = lines.join("\n") + "\n";
+        }
+        write_files(&out_dir, &files)
+            .map_err(|e| Error::new(format!("failed to write to {}: {}", out_dir.display(), e)))
+    }
+}
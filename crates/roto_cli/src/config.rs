@@ -0,0 +1,235 @@
+//! `roto.toml`, the configuration of a project.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use roto_core::backend::BackendConfig;
+use roto_core::backend::FileHeader;
+use roto_core::backend::HeaderSource;
+use roto_core::lint::Level;
+use roto_core::lint::LintConfig;
+use roto_core::lint::Rule;
+
+/// The configuration file looked for in the current directory.
+pub const CONFIG_FILE: &str = "roto.toml";
+
+/// A target of the `[targets]` table, like `[targets.ts]`. Every key except `out-dir`,
+/// `snapshot-dir`, `source-map` and `manifest` is a backend option.
+pub struct TargetConfig {
+    pub name: String,
+    pub out_dir: String,
+    /// Where `roto test` expects the output, if not in `out_dir`.
+    pub snapshot_dir: Option<String>,
+    /// Writes a source map next to every generated file the backend maps to the schemas.
+    pub source_maps: bool,
+    /// Writes `roto-manifest.json`, the generated types, into `out_dir`.
+    pub manifest: bool,
+    pub config: BackendConfig,
+}
+
+/// The header of generated files, from the `[header]` table or the options of `roto build`.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderConfig {
+    pub banner: Option<String>,
+    /// Names every schema with a hash of its contents.
+    pub provenance: bool,
+    pub tool_version: bool,
+    pub do_not_edit: bool,
+}
+
+impl HeaderConfig {
+    /// The header of files generated from `sources`, which are only named with `provenance`.
+    pub fn file_header(&self, sources: Vec<HeaderSource>) -> FileHeader {
+        FileHeader {
+            banner: self.banner.clone(),
+            sources: if self.provenance { sources } else { Vec::new() },
+            tool_version: self.tool_version,
+            do_not_edit: self.do_not_edit,
+        }
+    }
+}
+
+/// What `roto build` builds without arguments.
+pub struct ProjectConfig {
+    /// Paths and glob patterns of the schemas, from the top level `inputs` array.
+    pub inputs: Vec<String>,
+    pub targets: Vec<TargetConfig>,
+    pub header: HeaderConfig,
+}
+
+/// The parsed file at `path`, or `None` if it does not exist.
+fn read_table(path: &Path) -> Result<Option<toml::Table>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(format!("failed to read {}: {}", path.display(), error)),
+    };
+    contents
+        .parse()
+        .map(Some)
+        .map_err(|e| format!("failed to parse {}: {}", path.display(), e))
+}
+
+/// The lint levels of the `[lint]` table, like `missing-doc = "deny"`. A missing file is the
+/// default configuration.
+pub fn load_lint_config(path: &Path) -> Result<LintConfig, String> {
+    let mut config = LintConfig::new();
+    let Some(table) = read_table(path)? else {
+        return Ok(config);
+    };
+    let Some(lint) = table.get("lint") else {
+        return Ok(config);
+    };
+    let lint = lint
+        .as_table()
+        .ok_or_else(|| format!("{}: `lint` must be a table", path.display()))?;
+    for (name, level) in lint {
+        let rule = Rule::from_name(name)
+            .ok_or_else(|| format!("{}: unknown lint rule `{}`", path.display(), name))?;
+        let level = level.as_str().and_then(Level::from_name).ok_or_else(|| {
+            format!(
+                "{}: the level of `{}` must be \"allow\", \"warn\" or \"deny\"",
+                path.display(),
+                name
+            )
+        })?;
+        config.set(rule, level);
+    }
+    Ok(config)
+}
+
+/// The names of the targets of the `[targets]` table, like `proto` for `[targets.proto]`. A
+/// missing file has none.
+pub fn load_target_names(path: &Path) -> Result<Vec<String>, String> {
+    let Some(table) = read_table(path)? else {
+        return Ok(Vec::new());
+    };
+    match table.get("targets") {
+        Some(toml::Value::Table(targets)) => Ok(targets.keys().cloned().collect()),
+        Some(_) => Err(format!("{}: `targets` must be a table", path.display())),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The inputs and targets of `path`, which has to exist.
+pub fn load_project_config(path: &Path) -> Result<ProjectConfig, String> {
+    let table = read_table(path)?.ok_or_else(|| format!("{} does not exist", path.display()))?;
+    let inputs = match table.get("inputs") {
+        Some(toml::Value::Array(inputs)) => inputs
+            .iter()
+            .map(|input| input.as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("{}: `inputs` must be an array of paths", path.display()))?;
+    let mut targets = Vec::new();
+    if let Some(table) = table.get("targets") {
+        let table = table
+            .as_table()
+            .ok_or_else(|| format!("{}: `targets` must be a table", path.display()))?;
+        for (name, target) in table {
+            targets.push(target_config(path, name, target)?);
+        }
+    }
+    let header = match table.get("header") {
+        Some(header) => header_config(path, header)?,
+        None => HeaderConfig::default(),
+    };
+    Ok(ProjectConfig {
+        inputs,
+        targets,
+        header,
+    })
+}
+
+/// The `[header]` table, like `banner = "Copyright ACME"` and `do-not-edit = true`.
+fn header_config(path: &Path, header: &toml::Value) -> Result<HeaderConfig, String> {
+    let header = header
+        .as_table()
+        .ok_or_else(|| format!("{}: `header` must be a table", path.display()))?;
+    let mut config = HeaderConfig::default();
+    for (key, value) in header {
+        let invalid =
+            |expected: &str| format!("{}: `header.{}` must be {}", path.display(), key, expected);
+        let flag = match key.as_str() {
+            "banner" => {
+                let banner = value.as_str().ok_or_else(|| invalid("a string"))?;
+                config.banner = Some(banner.to_string());
+                continue;
+            }
+            "provenance" => &mut config.provenance,
+            "tool-version" => &mut config.tool_version,
+            "do-not-edit" => &mut config.do_not_edit,
+            _ => {
+                return Err(format!(
+                    "{}: unknown header option `{}`",
+                    path.display(),
+                    key
+                ))
+            }
+        };
+        *flag = value.as_bool().ok_or_else(|| invalid("true or false"))?;
+    }
+    Ok(config)
+}
+
+fn target_config(path: &Path, name: &str, target: &toml::Value) -> Result<TargetConfig, String> {
+    let target = target
+        .as_table()
+        .ok_or_else(|| format!("{}: `targets.{}` must be a table", path.display(), name))?;
+    let mut out_dir = None;
+    let mut snapshot_dir = None;
+    let mut source_maps = false;
+    let mut manifest = false;
+    let mut config = BackendConfig::new();
+    for (key, value) in target {
+        let flag = match key.as_str() {
+            "source-map" => Some(&mut source_maps),
+            "manifest" => Some(&mut manifest),
+            _ => None,
+        };
+        if let Some(flag) = flag {
+            *flag = value.as_bool().ok_or_else(|| {
+                format!(
+                    "{}: `targets.{}.{}` must be true or false",
+                    path.display(),
+                    name,
+                    key
+                )
+            })?;
+            continue;
+        }
+        let value = match value {
+            toml::Value::String(value) => value.clone(),
+            toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                value.to_string()
+            }
+            _ => {
+                return Err(format!(
+                    "{}: `targets.{}.{}` must be a string, number or boolean",
+                    path.display(),
+                    name,
+                    key
+                ))
+            }
+        };
+        if key == "out-dir" {
+            out_dir = Some(value);
+        } else if key == "snapshot-dir" {
+            snapshot_dir = Some(value);
+        } else {
+            config.set(key, value);
+        }
+    }
+    let out_dir = out_dir
+        .ok_or_else(|| format!("{}: `targets.{}` has no `out-dir`", path.display(), name))?;
+    Ok(TargetConfig {
+        name: name.to_string(),
+        out_dir,
+        snapshot_dir,
+        source_maps,
+        manifest,
+        config,
+    })
+}
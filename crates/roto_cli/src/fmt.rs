@@ -0,0 +1,65 @@
+//! `roto fmt`, which rewrites schemas in their canonical layout.
+
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use roto_core::format::format_program;
+
+use crate::display_name;
+use crate::expand_inputs;
+use crate::read_input;
+use crate::report;
+use crate::STDIO;
+
+/// Formats every file named by `inputs` in place, and standard input to standard output. With
+/// `check`, nothing is written and the files that are not formatted are listed instead, failing
+/// if there is any.
+pub fn fmt(inputs: &[&String], check: bool) {
+    let files = expand_inputs(inputs).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+    let mut failed = false;
+    for file in files {
+        let name = display_name(&file);
+        let contents = match read_input(&file) {
+            Ok(contents) => contents,
+            Err(error) => {
+                eprintln!("{}", error);
+                failed = true;
+                continue;
+            }
+        };
+        let items = match roto_core::parse(&contents, &name) {
+            Ok(items) => items,
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{}", error);
+                }
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = format_program(&items);
+        if file == Path::new(STDIO) && !check {
+            print!("{}", formatted);
+            continue;
+        }
+        if formatted == contents {
+            continue;
+        }
+        if check {
+            eprintln!("{} is not formatted", name);
+            failed = true;
+        } else if let Err(error) = fs::write(&file, formatted) {
+            eprintln!("failed to write {}: {}", file.display(), error);
+            failed = true;
+        } else {
+            report::status(format_args!("Formatted {}", file.display()));
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,212 @@
+//! `roto graph`, which writes the dependency graph of the compiled types as DOT, Mermaid or
+//! JSON.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use roto_core::backend::TypeNameAllocator;
+use roto_core::docs::{build_doc_model, DocModel, TypeDoc, TypeDocKind, TypeRef};
+use roto_core::frontend::IRCompiler;
+use roto_doc_backend::title;
+use serde_json::json;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+impl GraphFormat {
+    pub fn from_name(name: &str) -> Option<GraphFormat> {
+        match name {
+            "dot" => Some(GraphFormat::Dot),
+            "mermaid" => Some(GraphFormat::Mermaid),
+            "json" => Some(GraphFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Which part of the graph is written.
+pub struct GraphFilter<'a> {
+    /// The declared types the graph starts from. Without roots every type is included.
+    pub roots: Vec<&'a String>,
+    /// How many references are followed from the roots, unlimited if `None`.
+    pub depth: Option<usize>,
+    /// Follows references backwards, to the types that use the roots.
+    pub reverse: bool,
+}
+
+/// A reference from one type to another, labelled with the member that holds it. Aliases have no
+/// label.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Edge {
+    from: usize,
+    to: usize,
+    label: Option<String>,
+}
+
+fn edges(t: &TypeDoc) -> Vec<Edge> {
+    let edge = |type_ref: &TypeRef, label: Option<&str>| match type_ref {
+        TypeRef::Type { id, .. } => Some(Edge {
+            from: t.id,
+            to: *id,
+            label: label.map(str::to_string),
+        }),
+        TypeRef::Builtin(_) => None,
+    };
+    match &t.kind {
+        TypeDocKind::Struct { fields: members } | TypeDocKind::Variant { options: members } => {
+            members
+                .iter()
+                .filter_map(|m| edge(&m.type_, Some(&m.name)))
+                .collect()
+        }
+        TypeDocKind::Alias { target } => edge(target, None).into_iter().collect(),
+        TypeDocKind::Builtin(_) => Vec::new(),
+    }
+}
+
+fn kind(t: &TypeDoc) -> &'static str {
+    match t.kind {
+        TypeDocKind::Struct { .. } => "struct",
+        TypeDocKind::Variant { .. } => "variant",
+        TypeDocKind::Alias { .. } | TypeDocKind::Builtin(_) => "alias",
+    }
+}
+
+/// The ids of the types `filter` selects: the roots and what is reachable from them within
+/// `filter.depth` steps.
+fn select(model: &DocModel, filter: &GraphFilter) -> Result<BTreeSet<usize>, String> {
+    if filter.roots.is_empty() {
+        return Ok(model.types.keys().copied().collect());
+    }
+    let mut frontier = Vec::new();
+    for root in &filter.roots {
+        let t = model
+            .iter_globals()
+            .find(|t| title(&t.name) == **root)
+            .ok_or_else(|| format!("Unknown type `{}`", root))?;
+        frontier.push(t.id);
+    }
+    let mut selected: BTreeSet<usize> = frontier.iter().copied().collect();
+    let mut depth = 0;
+    while !frontier.is_empty() && filter.depth.is_none_or(|max| depth < max) {
+        let mut next = Vec::new();
+        for id in frontier {
+            let t = &model.types[&id];
+            let neighbours = if filter.reverse {
+                t.referenced_by.clone()
+            } else {
+                t.references()
+            };
+            next.extend(neighbours.into_iter().filter(|id| selected.insert(*id)));
+        }
+        frontier = next;
+        depth += 1;
+    }
+    Ok(selected)
+}
+
+fn quoted(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Mermaid labels cannot escape quotes, they are written as entities instead.
+fn mermaid_label(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "#quot;"))
+}
+
+/// Writes the types of `compiler` that `filter` selects, and the references between them.
+pub fn write_graph(
+    compiler: &IRCompiler,
+    filter: &GraphFilter,
+    format: GraphFormat,
+) -> Result<String, String> {
+    let model = build_doc_model(compiler);
+    let selected = select(&model, filter)?;
+    let types: Vec<&TypeDoc> = selected.iter().map(|id| &model.types[id]).collect();
+    let edges: BTreeSet<Edge> = types
+        .iter()
+        .flat_map(|t| edges(t))
+        .filter(|edge| selected.contains(&edge.to))
+        .collect();
+    let mut name_allocator = TypeNameAllocator::new().with_declared(compiler);
+    let ids: BTreeMap<usize, String> = types
+        .iter()
+        .map(|t| (t.id, name_allocator.allocate_name(&t.name)))
+        .collect();
+
+    let mut result = String::new();
+    match format {
+        GraphFormat::Dot => {
+            result.push_str("digraph roto {\n    rankdir=LR;\n\n");
+            for t in &types {
+                let shape = match t.kind {
+                    TypeDocKind::Struct { .. } => "shape=box",
+                    TypeDocKind::Variant { .. } => "shape=box, style=rounded",
+                    TypeDocKind::Alias { .. } | TypeDocKind::Builtin(_) => "shape=ellipse",
+                };
+                result.push_str(&format!(
+                    "    {} [label={}, {}];\n",
+                    quoted(&ids[&t.id]),
+                    quoted(&title(&t.name)),
+                    shape
+                ));
+            }
+            if !edges.is_empty() {
+                result.push('\n');
+            }
+            for edge in &edges {
+                let attributes = match &edge.label {
+                    Some(label) => format!("label={}", quoted(label)),
+                    None => "style=dashed".to_string(),
+                };
+                result.push_str(&format!(
+                    "    {} -> {} [{}];\n",
+                    quoted(&ids[&edge.from]),
+                    quoted(&ids[&edge.to]),
+                    attributes
+                ));
+            }
+            result.push_str("}\n");
+        }
+        GraphFormat::Mermaid => {
+            result.push_str("graph LR\n");
+            for t in &types {
+                result.push_str(&format!(
+                    "    {}[{}]\n",
+                    ids[&t.id],
+                    mermaid_label(&title(&t.name))
+                ));
+            }
+            for edge in &edges {
+                let arrow = match &edge.label {
+                    Some(label) => format!("-->|{}|", mermaid_label(label)),
+                    None => "-.->".to_string(),
+                };
+                result.push_str(&format!(
+                    "    {} {} {}\n",
+                    ids[&edge.from], arrow, ids[&edge.to]
+                ));
+            }
+        }
+        GraphFormat::Json => {
+            let nodes: Vec<_> = types
+                .iter()
+                .map(|t| json!({"id": ids[&t.id], "name": title(&t.name), "kind": kind(t)}))
+                .collect();
+            let edges: Vec<_> = edges
+                .iter()
+                .map(|edge| {
+                    json!({"from": ids[&edge.from], "to": ids[&edge.to], "member": edge.label})
+                })
+                .collect();
+            let document = json!({"nodes": nodes, "edges": edges});
+            result = serde_json::to_string_pretty(&document).unwrap();
+            result.push('\n');
+        }
+    }
+    Ok(result)
+}
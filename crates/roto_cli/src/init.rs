@@ -0,0 +1,79 @@
+//! `roto init`, which sets up a project in the current directory: a `roto.toml` that builds an
+//! example schema for the chosen targets.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use roto_core::backend::write_file;
+
+use crate::config::CONFIG_FILE;
+
+const EXAMPLE_FILE: &str = "schemas/example.roto";
+
+/// Formatted, lint free and tagged, so that every target builds it.
+const EXAMPLE: &str = "// Whether a user can sign in.
+type UserStatus = enum {
+    @tag(value=1)
+    active(unit),
+    @tag(value=2)
+    suspended(struct {
+        @tag(value=1)
+        reason: string,
+    }),
+};
+
+// A registered user.
+type User = struct {
+    @tag(value=1)
+    id: string,
+    // The name shown to other users.
+    @tag(value=2)
+    display_name: string,
+    @tag(value=3)
+    email?: string,
+    @tag(value=4)
+    status: UserStatus,
+};
+";
+
+/// A `roto.toml` with a stanza per target, each writing to its own directory below `generated`.
+fn config(targets: &[&String]) -> String {
+    let mut result = String::from(
+        "# The schemas `roto build` compiles for every target.\ninputs = [\"schemas/**/*.roto\"]\n",
+    );
+    if targets.is_empty() {
+        result.push_str("\n# A target per backend, see `roto --list`:\n");
+        result.push_str("# [targets.ts]\n# out-dir = \"generated/ts\"\n");
+    }
+    for target in targets {
+        result.push_str(&format!(
+            "\n[targets.{}]\nout-dir = \"generated/{}\"\n",
+            target, target
+        ));
+    }
+    result.push_str("\n[header]\ndo-not-edit = true\n# banner = \"Copyright ...\"\n# provenance = true\n# tool-version = true\n");
+    result.push_str("\n[lint]\n# missing-tag = \"warn\"\n");
+    result
+}
+
+/// Writes `roto.toml` and the example schema, unless either of them exists. Returns the paths
+/// that were written.
+pub fn init(targets: &[&String]) -> Result<Vec<PathBuf>, String> {
+    let files = [
+        (CONFIG_FILE, config(targets)),
+        (EXAMPLE_FILE, EXAMPLE.to_string()),
+    ];
+    for (path, _) in &files {
+        if Path::new(path).exists() {
+            return Err(format!("{} already exists", path));
+        }
+    }
+    let mut written = Vec::new();
+    for (path, contents) in files {
+        let path = PathBuf::from(path);
+        write_file(&path, &contents)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
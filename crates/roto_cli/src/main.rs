@@ -0,0 +1,1642 @@
+mod config;
+mod fmt;
+mod graph;
+mod init;
+mod manifest;
+mod rename;
+mod report;
+mod select;
+mod snapshot;
+mod tree;
+mod watch;
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::process::Command;
+use std::time::Instant;
+
+use config::HeaderConfig;
+use config::ProjectConfig;
+use config::TargetConfig;
+use graph::GraphFilter;
+use graph::GraphFormat;
+use manifest::TypeEntry;
+use report::ErrorFormat;
+use report::Verbosity;
+use roto_backends::migration_writer;
+use roto_backends::registry;
+use roto_conformance::Suite;
+use roto_core::annotations::AnnotationRegistry;
+use roto_core::backend::write_file;
+use roto_core::backend::write_files;
+use roto_core::backend::BackendConfig;
+use roto_core::backend::BackendRegistry;
+use roto_core::backend::CodegenBackend;
+use roto_core::backend::HeaderSource;
+use roto_core::backend::OutputFile;
+use roto_core::compat::check_compatibility;
+use roto_core::compat::Compatibility;
+use roto_core::diagnostics::has_errors;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::format::format_program;
+use roto_core::frontend::CollisionPolicy;
+use roto_core::frontend::IRCompiler;
+use roto_core::intern::Atom;
+use roto_core::ir::TypeName;
+use roto_core::lint::Level;
+use roto_core::lint::LintConfig;
+use roto_core::lint::Rule;
+use roto_core::migrate::migrations;
+use roto_core::output::write_all;
+use roto_core::output::Change;
+use roto_core::output::DiskSink;
+use roto_core::output::PreviewSink;
+use roto_schema_registry::CompatibilityMode;
+use roto_schema_registry::Registry;
+use roto_schema_registry::SchemaFormat;
+use roto_sql_import::Dialect as SqlDialect;
+use select::TypeFilter;
+use serde_json::json;
+use sha2::Digest;
+use sha2::Sha256;
+use tracing::debug;
+use tracing::info;
+use tracing::info_span;
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} build <input>... --target <target> [<option>=<value>...] [-o <output_file> | --out-dir <output_dir>] [--watch] [--root <type>...] [--only <pattern>...] [--exclude <pattern>...] [--banner <text>] [--provenance] [--tool-version] [--do-not-edit] [--source-map] [--manifest] [--dry-run | --diff]",
+        program
+    );
+    eprintln!("       {} build [--dry-run | --diff]", program);
+    eprintln!("       {} check <input>... [--target <target>]", program);
+    eprintln!(
+        "       {} conformance <input>... -o <output_dir> [--target <target>...] [--samples <count>] [--seed <seed>]",
+        program
+    );
+    eprintln!(
+        "       {} doc <input>... [--format markdown|html] [-o <output_dir>]",
+        program
+    );
+    eprintln!(
+        "       {} diff <old> <new> [--fail-on breaking|non-breaking|info]",
+        program
+    );
+    eprintln!(
+        "       {} diff <input>... --against git:<revision> [--fail-on breaking|non-breaking|info]",
+        program
+    );
+    eprintln!("       {} fmt [--check] <input>...", program);
+    eprintln!(
+        "       {} graph <input>... [--format dot|mermaid|json] [--root <type>...] [--depth <depth>] [--reverse] [-o <output_file>]",
+        program
+    );
+    eprintln!(
+        "       {} import openapi <spec> [-o <output_file>]",
+        program
+    );
+    eprintln!(
+        "       {} import sql (<ddl_file> | --postgres <url> | --sqlite <db_file>) [--dialect postgres|sqlite|generic] [-o <output_file>]",
+        program
+    );
+    eprintln!(
+        "       {} infer <sample>... [--name <type>] [-o <output_file>]",
+        program
+    );
+    eprintln!("       {} init [<target>...]", program);
+    eprintln!("       {} ir <input>... --type <type> [--resolve]", program);
+    eprintln!(
+        "       {} migrate <old> <new> --target <target> [-o <output_dir>]",
+        program
+    );
+    eprintln!(
+        "       {} migrate <input>... --against git:<revision> --target <target> [-o <output_dir>]",
+        program
+    );
+    eprintln!(
+        "       {} lint <input>... [--target <target>] [--config <config_file>]",
+        program
+    );
+    eprintln!(
+        "       {} publish <input>... --registry <url> [--format avro|json] [--subject <template>] [--root <type>...] [--compatibility none|backward|forward|full] [--against <old> | --against git:<revision>] [--dry-run]",
+        program
+    );
+    eprintln!(
+        "       {} rename type <type> <new_name> <input>...",
+        program
+    );
+    eprintln!(
+        "       {} rename field <type> <field> <new_name> <input>...",
+        program
+    );
+    eprintln!("       {} test [--update]", program);
+    eprintln!("       {} --list", program);
+    eprintln!();
+    eprintln!("An <input> of - reads standard input, an <output_file> of - is standard output.");
+    eprintln!("Diagnostics are JSON objects, one per line, with --error-format json.");
+    eprintln!("-v, -vv and -vvv trace what the compiler does, -q only reports errors.");
+    process::exit(2);
+}
+
+/// The files named by `inputs`, which are paths, glob patterns like `schemas/**/*.roto` or `-`
+/// for standard input. Each file is only listed once, in the order the inputs name them.
+fn expand_inputs(inputs: &[&String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if !is_pattern(input) {
+            files.push(PathBuf::from(input));
+            continue;
+        }
+        let paths = glob::glob(input).map_err(|e| format!("invalid pattern `{}`: {}", input, e))?;
+        let mut matched = paths
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        if matched.is_empty() {
+            return Err(format!("no files match `{}`", input));
+        }
+        matched.sort();
+        files.extend(matched);
+    }
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(file.clone()));
+    Ok(files)
+}
+
+/// The input that stands for standard input, and the output file that stands for standard output.
+const STDIO: &str = "-";
+
+/// The name `file` is reported by.
+fn display_name(file: &Path) -> String {
+    if file == Path::new(STDIO) {
+        "<stdin>".to_string()
+    } else {
+        file.display().to_string()
+    }
+}
+
+/// Reads `file`, or standard input if it is `-`.
+fn read_input(file: &Path) -> Result<String, String> {
+    let mut contents = String::new();
+    let read = if file == Path::new(STDIO) {
+        io::stdin().read_to_string(&mut contents).map(|_| ())
+    } else {
+        fs::read_to_string(file).map(|c| contents = c)
+    };
+    read.map_err(|e| format!("failed to read {}: {}", display_name(file), e))?;
+    Ok(contents)
+}
+
+fn is_pattern(input: &str) -> bool {
+    input.contains(['*', '?', '['])
+}
+
+/// A parsed input file, kept between builds in watch mode so that unchanged files are not
+/// parsed again.
+struct LoadedFile {
+    contents: String,
+    compiler: IRCompiler,
+}
+
+/// The schema files given on the command line, as paths and glob patterns.
+struct Inputs<'a> {
+    patterns: Vec<&'a String>,
+    /// The git revision the files are read at, instead of the working tree.
+    revision: Option<&'a str>,
+    error_format: ErrorFormat,
+    /// Registers annotations that no backend knows, like the constraints `roto conformance`
+    /// generates fixtures with.
+    register_annotations: Option<fn(&mut AnnotationRegistry)>,
+    loaded: HashMap<PathBuf, LoadedFile>,
+}
+
+impl<'a> Inputs<'a> {
+    /// `error_format` is how the warnings of a successful compilation are reported.
+    fn new(patterns: Vec<&'a String>, error_format: ErrorFormat) -> Self {
+        Inputs {
+            patterns,
+            revision: None,
+            error_format,
+            register_annotations: None,
+            loaded: HashMap::new(),
+        }
+    }
+
+    /// The inputs as they are at `revision` of the git repository in the current directory.
+    /// Files are still found in the working tree, and are empty if they do not exist at
+    /// `revision`.
+    fn at_revision(
+        patterns: Vec<&'a String>,
+        revision: &'a str,
+        error_format: ErrorFormat,
+    ) -> Self {
+        Inputs {
+            revision: Some(revision),
+            ..Inputs::new(patterns, error_format)
+        }
+    }
+
+    fn read(&self, file: &Path) -> Result<String, String> {
+        let Some(revision) = self.revision else {
+            return read_input(file);
+        };
+        if file == Path::new(STDIO) {
+            return Err(format!("<stdin> has no revision {}", revision));
+        }
+        // `./` makes the path relative to the current directory instead of the repository root.
+        let output = Command::new("git")
+            .arg("show")
+            .arg(format!("{}:./{}", revision, file.display()))
+            .output()
+            .map_err(|e| format!("failed to run git: {}", e))?;
+        if !output.status.success() {
+            return Ok(String::new());
+        }
+        String::from_utf8(output.stdout)
+            .map_err(|_| format!("{} is not UTF-8 at {}", file.display(), revision))
+    }
+
+    /// Parses `file`, unless it did not change since it was last loaded.
+    fn load(&mut self, file: &Path) -> Result<&IRCompiler, Diagnostic> {
+        let contents = self
+            .read(file)
+            .map_err(|error| Diagnostic::error(error, None))?;
+        let unchanged =
+            matches!(self.loaded.get(file), Some(loaded) if loaded.contents == contents);
+        if unchanged {
+            debug!(file = %file.display(), "unchanged, reusing the parsed file");
+        } else {
+            debug!(file = %file.display(), bytes = contents.len(), "parsing");
+            let file_path = display_name(file);
+            let parsed = roto_core::parse(&contents, &file_path)
+                .map_err(|errors| Diagnostic::from(errors[0].clone()))?;
+            let mut compiler = IRCompiler::new();
+            compiler.register_program(parsed, &file_path, &contents);
+            self.loaded
+                .insert(file.to_path_buf(), LoadedFile { contents, compiler });
+        }
+        Ok(&self.loaded[file].compiler)
+    }
+
+    /// The files of the last compilation, with a hash of their contents.
+    fn sources(&self) -> Vec<HeaderSource> {
+        let mut sources: Vec<_> = self
+            .loaded
+            .iter()
+            .map(|(path, loaded)| HeaderSource {
+                file: display_name(path),
+                hash: format!("sha256:{:x}", Sha256::digest(&loaded.contents)),
+            })
+            .collect();
+        sources.sort_by(|a, b| a.file.cmp(&b.file));
+        sources
+    }
+
+    /// Parses and compiles every input. The annotations of `backends` are known, others are
+    /// reported. The error holds whatever stopped the compilation, after every file had the
+    /// chance to report its problems.
+    fn compile(&mut self, backends: &[&dyn CodegenBackend]) -> Result<IRCompiler, Vec<Diagnostic>> {
+        let files =
+            expand_inputs(&self.patterns).map_err(|error| vec![Diagnostic::error(error, None)])?;
+        self.loaded.retain(|path, _| files.contains(path));
+
+        // Every file is registered on its own and merged, so that a type declared differently
+        // in two files is reported instead of silently replaced. References between files are
+        // only resolved once everything is merged.
+        let mut compiler = IRCompiler::new();
+        for backend in backends {
+            backend.register_annotations(&mut compiler.annotations);
+        }
+        if let Some(register_annotations) = self.register_annotations {
+            register_annotations(&mut compiler.annotations);
+        }
+        let mut errors = Vec::new();
+        for file in &files {
+            match self.load(file) {
+                Ok(file_compiler) => match compiler.merge(file_compiler, CollisionPolicy::Reject) {
+                    Ok(collisions) => errors.extend(collisions.iter().map(|c| {
+                        Diagnostic::error(c.to_string(), c.incoming.clone())
+                            .with_code("duplicate-type")
+                    })),
+                    Err(error) => errors.extend(error.into_diagnostics()),
+                },
+                Err(error) => errors.push(error),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let diagnostics = compiler.validate_annotations();
+        if has_errors(&diagnostics) {
+            return Err(diagnostics);
+        }
+        self.error_format.report(&diagnostics);
+
+        compiler
+            .compile_globals()
+            .map_err(|error| error.into_diagnostics())?;
+        Ok(compiler)
+    }
+}
+
+/// What `build` does with the generated files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputMode {
+    Write,
+    /// Lists the files that would be created, updated or left unchanged.
+    DryRun,
+    /// Prints a unified diff of every file that would change.
+    Diff,
+}
+
+impl OutputMode {
+    fn from_flag(flag: &str) -> Option<OutputMode> {
+        match flag {
+            "--dry-run" => Some(OutputMode::DryRun),
+            "--diff" => Some(OutputMode::Diff),
+            _ => None,
+        }
+    }
+}
+
+/// One `build` invocation, which watch mode runs again whenever an input changes.
+struct Build<'a> {
+    backend: &'a dyn CodegenBackend,
+    /// The backends whose annotations are known, usually just `backend`.
+    known: Vec<&'a dyn CodegenBackend>,
+    target: &'a str,
+    config: BackendConfig,
+    inputs: Inputs<'a>,
+    output_file: Option<&'a String>,
+    output_dir: Option<&'a String>,
+    mode: OutputMode,
+    /// The declared types to generate, every one by default.
+    filter: TypeFilter<'a>,
+    header: HeaderConfig,
+    /// Writes `<file>.map.json` next to every written file that has a source map.
+    source_maps: bool,
+    /// Writes a manifest of the generated types, `roto-manifest.json` in the output directory
+    /// or `<file>.manifest.json` next to the output file.
+    manifest: bool,
+    /// The types of the last generation, for the manifest.
+    types: Vec<TypeEntry>,
+}
+
+impl<'a> Build<'a> {
+    /// Compiles the inputs and writes the output.
+    fn run(&mut self) -> Result<(), Vec<Diagnostic>> {
+        let files = self.generate()?;
+        self.write(&files)
+            .map_err(|error| vec![Diagnostic::error(error, None)])
+    }
+
+    /// Compiles the inputs and runs the backend, without writing anything.
+    fn generate(&mut self) -> Result<Vec<OutputFile>, Vec<Diagnostic>> {
+        let _span = info_span!("build", target = self.target).entered();
+        let started = Instant::now();
+        let mut compiler = self.inputs.compile(&self.known)?;
+        self.filter
+            .apply(&mut compiler)
+            .map_err(|error| vec![Diagnostic::error(error, None)])?;
+        let compiled = Instant::now();
+        info!(types = compiler.iter_types().count(), elapsed = ?compiled - started, "compiled");
+        if self.manifest {
+            self.types = manifest::types(&compiler);
+        }
+        let mut files = self
+            .backend
+            .generate(&compiler, &self.config)
+            .map_err(|error| error.diagnostics)?;
+        self.header
+            .file_header(self.inputs.sources())
+            .apply(&mut files);
+        info!(files = files.len(), elapsed = ?compiled.elapsed(), "generated");
+        Ok(files)
+    }
+
+    /// Reports why the build failed.
+    fn report(&self, diagnostics: &[Diagnostic]) {
+        self.inputs.error_format.report(diagnostics);
+    }
+
+    fn write(&self, files: &[OutputFile]) -> Result<(), String> {
+        let (directory, mut files) = match (self.output_file, self.output_dir) {
+            (Some(output_file), _) => {
+                let [file] = files else {
+                    return Err(format!(
+                        "Target `{}` writes {} files, use --out-dir instead of -o",
+                        self.target,
+                        files.len()
+                    ));
+                };
+                if output_file == STDIO {
+                    print!("{}", file.contents);
+                    return Ok(());
+                }
+                (
+                    Path::new(""),
+                    vec![OutputFile {
+                        path: output_file.clone(),
+                        ..file.clone()
+                    }],
+                )
+            }
+            (None, Some(output_dir)) => (Path::new(output_dir.as_str()), files.to_vec()),
+            (None, None) => {
+                for (i, file) in files.iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    if files.len() > 1 {
+                        println!("// {}", file.path);
+                    }
+                    print!("{}", file.contents);
+                }
+                return Ok(());
+            }
+        };
+        if self.manifest {
+            let path = match self.output_file {
+                Some(output_file) => format!("{}.manifest.json", output_file),
+                None => manifest::MANIFEST_FILE.to_string(),
+            };
+            let manifest = manifest::manifest_file(path, self.target, &self.types, &files);
+            files.push(manifest);
+        }
+        if self.source_maps {
+            let source_maps: Vec<_> = files.iter().filter_map(source_map_file).collect();
+            files.extend(source_maps);
+        }
+        if self.mode == OutputMode::Write {
+            let mut sink = DiskSink::default();
+            write_all(&mut sink, directory, &files)
+                .map_err(|e| format!("Failed to write files: {}", e))?;
+            for path in sink.written {
+                report::status(format_args!("Wrote {}", path.display()));
+            }
+            return Ok(());
+        }
+        let mut preview = PreviewSink::default();
+        write_all(&mut preview, directory, &files).map_err(|e| e.to_string())?;
+        for file in &preview.files {
+            if self.mode == OutputMode::DryRun {
+                println!("{:<9} {}", file.change(), file.path.display());
+            } else if file.change() != Change::Unchanged {
+                print!("{}", snapshot::unified_diff(file));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The source map of `file` as `<file>.map.json`, if its backend recorded one. Every mapping
+/// names a line of the generated file and the span of the schema declaration it came from.
+fn source_map_file(file: &OutputFile) -> Option<OutputFile> {
+    if file.source_map.is_empty() {
+        return None;
+    }
+    let name = Path::new(&file.path)
+        .file_name()
+        .map_or(file.path.clone(), |name| {
+            name.to_string_lossy().into_owned()
+        });
+    let mappings: Vec<_> = file
+        .source_map
+        .iter()
+        .map(|mapping| {
+            json!({
+                "line": mapping.line,
+                "source": mapping.location.file,
+                "span": manifest::span_json(&mapping.location),
+            })
+        })
+        .collect();
+    let source_map = json!({"file": name, "mappings": mappings});
+    Some(OutputFile::new(
+        format!("{}.map.json", file.path),
+        serde_json::to_string_pretty(&source_map).unwrap() + "\n",
+    ))
+}
+
+/// The inputs, `--target` and known annotations of `check` and `lint`: the annotations of
+/// `--target`, or of every backend without one. Other arguments are passed to `option` with the
+/// remaining arguments, and are inputs if it returns false.
+fn parse_check_args<'a>(
+    args: &'a [String],
+    registry: &'a BackendRegistry,
+    mut option: impl FnMut(&'a String, &mut dyn Iterator<Item = &'a String>) -> bool,
+) -> (
+    Vec<&'a String>,
+    Option<&'a String>,
+    Vec<&'a dyn CodegenBackend>,
+) {
+    let mut patterns = Vec::new();
+    let mut target = None;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "--target" {
+            match rest.next() {
+                Some(name) => target = Some(name),
+                None => usage(&args[0]),
+            }
+        } else if !option(arg, &mut rest) {
+            patterns.push(arg);
+        }
+    }
+    if patterns.is_empty() {
+        usage(&args[0]);
+    }
+    let backends = match target {
+        Some(target) => match registry.get(target) {
+            Some(backend) => vec![backend],
+            None => {
+                eprintln!("Unknown target `{}`, see --list", target);
+                process::exit(2);
+            }
+        },
+        None => registry.iter().collect(),
+    };
+    (patterns, target, backends)
+}
+
+/// The lint rules the targets require, see [`CodegenBackend::required_rules`]. The targets are
+/// `target`, or those of the `[targets]` table of `config_file` without it.
+fn required_rules(
+    registry: &BackendRegistry,
+    target: Option<&String>,
+    config_file: &Path,
+    error_format: ErrorFormat,
+) -> BTreeSet<Rule> {
+    let targets = match target {
+        Some(target) => vec![target.clone()],
+        None => config::load_target_names(config_file).unwrap_or_else(|error| {
+            error_format.report(&[Diagnostic::error(error, None)]);
+            process::exit(2);
+        }),
+    };
+    targets
+        .iter()
+        .filter_map(|name| registry.get(name))
+        .flat_map(|backend| backend.required_rules())
+        .copied()
+        .collect()
+}
+
+/// `roto check`, which compiles without generating code. The lint rules `--target` or the
+/// targets of `roto.toml` require are checked as well, like tags for protobuf. Exits with 1 if
+/// the schemas have errors.
+fn check(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let (patterns, target, backends) = parse_check_args(args, registry, |_, _| false);
+    let compiler = match Inputs::new(patterns, error_format).compile(&backends) {
+        Ok(compiler) => compiler,
+        Err(errors) => {
+            error_format.report(&errors);
+            process::exit(1);
+        }
+    };
+    let config_file = Path::new(config::CONFIG_FILE);
+    let rules = required_rules(registry, target, config_file, error_format);
+    let diagnostics = roto_core::lint::lint(&compiler, &LintConfig::deny_only(rules));
+    error_format.report(&diagnostics);
+    if has_errors(&diagnostics) {
+        process::exit(1);
+    }
+}
+
+/// `roto lint`, which checks the schemas with the rules configured in `roto.toml`, and denies
+/// those its targets require. Exits with 1 if the schemas have errors or a denied rule is broken.
+fn lint(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let mut config_file = None;
+    let (patterns, target, backends) = parse_check_args(args, registry, |arg, rest| {
+        if arg != "--config" {
+            return false;
+        }
+        match rest.next() {
+            Some(file) => config_file = Some(file),
+            None => usage(&args[0]),
+        }
+        true
+    });
+    let config_file = config_file.map_or(Path::new(config::CONFIG_FILE), Path::new);
+    let mut config = config::load_lint_config(config_file).unwrap_or_else(|error| {
+        error_format.report(&[Diagnostic::error(error, None)]);
+        process::exit(2);
+    });
+    for rule in required_rules(registry, target, config_file, error_format) {
+        config.set(rule, Level::Deny);
+    }
+    let compiler = match Inputs::new(patterns, error_format).compile(&backends) {
+        Ok(compiler) => compiler,
+        Err(errors) => {
+            error_format.report(&errors);
+            process::exit(1);
+        }
+    };
+    let diagnostics = roto_core::lint::lint(&compiler, &config);
+    error_format.report(&diagnostics);
+    if has_errors(&diagnostics) {
+        process::exit(1);
+    }
+}
+
+/// `roto doc`, which runs the `doc-markdown` or `doc-html` backend. `-o` names the directory the
+/// pages are written to.
+fn doc(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let mut patterns = Vec::new();
+    let mut format = "markdown";
+    let mut output_dir = None;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "--format" {
+            match rest.next().map(String::as_str) {
+                Some(name @ ("markdown" | "html")) => format = name,
+                _ => usage(&args[0]),
+            }
+        } else if arg == "-o" {
+            match rest.next() {
+                Some(dir) => output_dir = Some(dir),
+                None => usage(&args[0]),
+            }
+        } else {
+            patterns.push(arg);
+        }
+    }
+    if patterns.is_empty() {
+        usage(&args[0]);
+    }
+    let target = match format {
+        "html" => "doc-html",
+        _ => "doc-markdown",
+    };
+    // Documentation describes every annotation, whichever backend it is meant for.
+    let mut build = Build {
+        backend: registry.get(target).expect("doc backends are registered"),
+        known: registry.iter().collect(),
+        target,
+        config: BackendConfig::new(),
+        inputs: Inputs::new(patterns, error_format),
+        output_file: None,
+        output_dir,
+        mode: OutputMode::Write,
+        filter: TypeFilter::default(),
+        header: HeaderConfig::default(),
+        source_maps: false,
+        manifest: false,
+        types: Vec::new(),
+    };
+    if let Err(errors) = build.run() {
+        build.report(&errors);
+        process::exit(1);
+    }
+}
+
+/// Compiles the two versions of a schema `roto diff` and `roto migrate` compare: the two files
+/// `patterns` names, or the inputs and what they were at the git revision `against`.
+fn compile_versions(
+    program: &str,
+    patterns: Vec<&String>,
+    against: Option<&str>,
+    registry: &BackendRegistry,
+    error_format: ErrorFormat,
+) -> (IRCompiler, IRCompiler) {
+    let (mut old, mut new) = match against {
+        Some(revision) => {
+            let valid = Command::new("git")
+                .args(["rev-parse", "--quiet", "--verify"])
+                .arg(format!("{}^{{commit}}", revision))
+                .output()
+                .is_ok_and(|output| output.status.success());
+            if !valid {
+                eprintln!("Unknown git revision `{}`", revision);
+                process::exit(2);
+            }
+            let old = Inputs::at_revision(patterns.clone(), revision, error_format);
+            (old, Inputs::new(patterns, error_format))
+        }
+        None => match patterns.as_slice() {
+            [old, new] => (
+                Inputs::new(vec![old], error_format),
+                Inputs::new(vec![new], error_format),
+            ),
+            _ => usage(program),
+        },
+    };
+    if new.patterns.is_empty() {
+        usage(program);
+    }
+
+    let backends: Vec<_> = registry.iter().collect();
+    let compiled = old
+        .compile(&backends)
+        .and_then(|old| Ok((old, new.compile(&backends)?)));
+    compiled.unwrap_or_else(|errors| {
+        error_format.report(&errors);
+        process::exit(1);
+    })
+}
+
+/// `roto diff`, which compares two versions of a schema: two files, or the inputs and what they
+/// were at a git revision. Exits with 1 if a change is at least as severe as `--fail-on`.
+fn diff(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let mut patterns = Vec::new();
+    let mut against = None;
+    let mut fail_on = None;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "--against" {
+            match rest.next().and_then(|a| a.strip_prefix("git:")) {
+                Some(revision) => against = Some(revision),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--fail-on" {
+            match rest.next().and_then(|a| Compatibility::from_name(a)) {
+                Some(compatibility) => fail_on = Some(compatibility),
+                None => usage(&args[0]),
+            }
+        } else {
+            patterns.push(arg);
+        }
+    }
+
+    let (old, new) = compile_versions(&args[0], patterns, against, registry, error_format);
+    let issues = check_compatibility(&old, &new);
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    let failed = fail_on.is_some_and(|fail_on| issues.iter().any(|i| i.compatibility >= fail_on));
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// `roto migrate`, which compares two versions of a schema like `roto diff` and writes the
+/// migrations of the types whose version increased with the migration writer of `--target`, to
+/// `-o` or standard output.
+fn migrate(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let mut patterns = Vec::new();
+    let mut against = None;
+    let mut target = None;
+    let mut output_dir = None;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "--against" {
+            match rest.next().and_then(|a| a.strip_prefix("git:")) {
+                Some(revision) => against = Some(revision),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--target" {
+            match rest.next() {
+                Some(name) => target = Some(name.as_str()),
+                None => usage(&args[0]),
+            }
+        } else if arg == "-o" {
+            match rest.next() {
+                Some(dir) => output_dir = Some(dir),
+                None => usage(&args[0]),
+            }
+        } else {
+            patterns.push(arg);
+        }
+    }
+    let Some(target) = target else {
+        usage(&args[0]);
+    };
+    let Some(writer) = migration_writer(target) else {
+        eprintln!("Target `{}` does not write migrations", target);
+        process::exit(2);
+    };
+
+    let (old, new) = compile_versions(&args[0], patterns, against, registry, error_format);
+    let (migrations, diagnostics) = migrations(&old, &new);
+    error_format.report(&diagnostics);
+    if has_errors(&diagnostics) {
+        process::exit(1);
+    }
+    let files = writer.write_migrations(&migrations);
+    match output_dir {
+        Some(output_dir) => match write_files(Path::new(output_dir), &files) {
+            Ok(paths) => {
+                for path in paths {
+                    report::status(format_args!("Wrote {}", path.display()));
+                }
+            }
+            Err(e) => {
+                error_format.report(&[Diagnostic::error(
+                    format!("Failed to write files: {}", e),
+                    None,
+                )]);
+                process::exit(1);
+            }
+        },
+        None => {
+            for file in &files {
+                print!("{}", file.contents);
+            }
+        }
+    }
+}
+
+/// `roto graph`, which writes the references between the types, optionally only those reachable
+/// from `--root` types.
+fn graph(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let mut patterns = Vec::new();
+    let mut format = GraphFormat::Dot;
+    let mut filter = GraphFilter {
+        roots: Vec::new(),
+        depth: None,
+        reverse: false,
+    };
+    let mut output_file = None;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "--format" {
+            match rest.next().and_then(|a| GraphFormat::from_name(a)) {
+                Some(name) => format = name,
+                None => usage(&args[0]),
+            }
+        } else if arg == "--root" {
+            match rest.next() {
+                Some(root) => filter.roots.push(root),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--depth" {
+            match rest.next().and_then(|a| a.parse().ok()) {
+                Some(depth) => filter.depth = Some(depth),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--reverse" {
+            filter.reverse = true;
+        } else if arg == "-o" {
+            match rest.next() {
+                Some(file) => output_file = Some(file),
+                None => usage(&args[0]),
+            }
+        } else {
+            patterns.push(arg);
+        }
+    }
+    if patterns.is_empty() {
+        usage(&args[0]);
+    }
+
+    let backends: Vec<_> = registry.iter().collect();
+    let compiler = Inputs::new(patterns, error_format)
+        .compile(&backends)
+        .unwrap_or_else(|errors| {
+            error_format.report(&errors);
+            process::exit(1);
+        });
+    let written =
+        graph::write_graph(&compiler, &filter, format).and_then(|graph| match output_file {
+            Some(output_file) => write_file(Path::new(output_file), &graph)
+                .map_err(|e| format!("Failed to write {}: {}", output_file, e)),
+            None => {
+                print!("{}", graph);
+                Ok(())
+            }
+        });
+    if let Err(error) = written {
+        error_format.report(&[Diagnostic::error(error, None)]);
+        process::exit(1);
+    }
+}
+
+/// `roto ir`, which prints the compiled type `--type` as a tree, with the named types it refers
+/// to expanded if `--resolve` is given.
+fn ir(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let mut patterns = Vec::new();
+    let mut type_name = None;
+    let mut resolve = false;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "--type" {
+            match rest.next() {
+                Some(name) => type_name = Some(name),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--resolve" {
+            resolve = true;
+        } else {
+            patterns.push(arg);
+        }
+    }
+    let Some(type_name) = type_name else {
+        usage(&args[0]);
+    };
+    if patterns.is_empty() {
+        usage(&args[0]);
+    }
+
+    let backends: Vec<_> = registry.iter().collect();
+    let compiler = Inputs::new(patterns, error_format)
+        .compile(&backends)
+        .unwrap_or_else(|errors| {
+            error_format.report(&errors);
+            process::exit(1);
+        });
+    match tree::write_tree(&compiler, type_name, resolve) {
+        Ok(tree) => print!("{}", tree),
+        Err(error) => {
+            error_format.report(&[Diagnostic::error(error, None)]);
+            process::exit(1);
+        }
+    }
+}
+
+/// `roto conformance`, which writes a conformance suite to `-o`: fixtures of every declared type,
+/// and the code and a harness of every `--target`, or of every target with a harness. Running the
+/// harnesses checks that the targets agree on how every type is encoded.
+fn conformance(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let mut patterns = Vec::new();
+    let mut targets = Vec::new();
+    let mut suite = Suite::default();
+    let mut output_dir = None;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "--target" {
+            match rest.next() {
+                Some(name) => targets.push(name.as_str()),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--samples" {
+            match rest.next().and_then(|a| a.parse().ok()) {
+                Some(samples) => suite.samples = samples,
+                None => usage(&args[0]),
+            }
+        } else if arg == "--seed" {
+            match rest.next().and_then(|a| a.parse().ok()) {
+                Some(seed) => suite.seed = seed,
+                None => usage(&args[0]),
+            }
+        } else if arg == "-o" {
+            match rest.next() {
+                Some(dir) => output_dir = Some(dir),
+                None => usage(&args[0]),
+            }
+        } else {
+            patterns.push(arg);
+        }
+    }
+    let Some(output_dir) = output_dir else {
+        usage(&args[0]);
+    };
+    if patterns.is_empty() {
+        usage(&args[0]);
+    }
+    if targets.is_empty() {
+        targets = roto_conformance::TARGETS.to_vec();
+    }
+    let backends: Vec<_> = targets
+        .iter()
+        .map(|target| {
+            registry.get(target).unwrap_or_else(|| {
+                eprintln!("Unknown target `{}`, see --list", target);
+                process::exit(2);
+            })
+        })
+        .collect();
+
+    let mut inputs = Inputs::new(patterns, error_format);
+    inputs.register_annotations = Some(roto_conformance::register_annotations);
+    let written = inputs
+        .compile(&backends)
+        .and_then(|compiler| suite.generate(&compiler, &backends))
+        .and_then(|files| {
+            write_files(Path::new(output_dir), &files).map_err(|e| {
+                vec![Diagnostic::error(
+                    format!("Failed to write files: {}", e),
+                    None,
+                )]
+            })
+        });
+    match written {
+        Ok(paths) => {
+            for path in paths {
+                report::status(format_args!("Wrote {}", path.display()));
+            }
+        }
+        Err(errors) => {
+            error_format.report(&errors);
+            process::exit(1);
+        }
+    }
+}
+
+/// `roto import openapi` and `roto import sql`, which write the schemas of an OpenAPI 3.x
+/// document or the tables of a SQL schema as roto declarations. The SQL schema is a file of DDL,
+/// or is read from a database with `--postgres <url>` or `--sqlite <file>`. What cannot be
+/// imported faithfully is reported as warnings.
+fn import(args: &[String], error_format: ErrorFormat) {
+    let mut input = None;
+    let mut database = None;
+    let mut dialect = None;
+    let mut output_file = None;
+    let mut rest = args.iter().skip(3);
+    while let Some(arg) = rest.next() {
+        if arg == "-o" {
+            match rest.next() {
+                Some(file) => output_file = Some(file),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--dialect" {
+            match rest.next().and_then(|name| SqlDialect::from_name(name)) {
+                Some(name) => dialect = Some(name),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--postgres" || arg == "--sqlite" {
+            match rest.next() {
+                Some(location) => database = Some((arg.as_str(), location)),
+                None => usage(&args[0]),
+            }
+        } else if input.is_none() {
+            input = Some(arg);
+        } else {
+            usage(&args[0]);
+        }
+    }
+    let imported = match (args.get(2).map(String::as_str), input, database) {
+        (Some("openapi"), Some(spec), None) => read_input(Path::new(spec))
+            .map_err(|error| Diagnostic::error(error, None))
+            .and_then(|source| roto_openapi_import::import(&source))
+            .map(|imported| (imported.items, imported.diagnostics)),
+        (Some("sql"), Some(file), None) => read_input(Path::new(file))
+            .map_err(|error| Diagnostic::error(error, None))
+            .and_then(|sql| roto_sql_import::import(&sql, dialect.unwrap_or(SqlDialect::Generic)))
+            .map(|imported| (imported.items, imported.diagnostics)),
+        (Some("sql"), None, Some((kind, location))) => {
+            let (dump, default_dialect) = if kind == "--postgres" {
+                (
+                    roto_sql_import::dump_postgres(location),
+                    SqlDialect::Postgres,
+                )
+            } else {
+                (roto_sql_import::dump_sqlite(location), SqlDialect::Sqlite)
+            };
+            dump.map_err(|error| Diagnostic::error(error, None))
+                .and_then(|sql| roto_sql_import::import(&sql, dialect.unwrap_or(default_dialect)))
+                .map(|imported| (imported.items, imported.diagnostics))
+        }
+        _ => usage(&args[0]),
+    };
+    let (items, diagnostics) = imported.unwrap_or_else(|error| {
+        error_format.report(&[error]);
+        process::exit(1);
+    });
+    error_format.report(&diagnostics);
+    let schema = format_program(&items);
+    let written = match output_file {
+        Some(output_file) if output_file != STDIO => write_file(Path::new(output_file), &schema)
+            .map_err(|e| format!("Failed to write {}: {}", output_file, e)),
+        _ => {
+            print!("{}", schema);
+            Ok(())
+        }
+    };
+    if let Err(error) = written {
+        error_format.report(&[Diagnostic::error(error, None)]);
+        process::exit(1);
+    }
+}
+
+/// `roto infer`, which writes a schema inferred from example JSON documents. What cannot be
+/// inferred faithfully is reported as warnings.
+fn infer(args: &[String], error_format: ErrorFormat) {
+    let mut patterns = Vec::new();
+    let mut name = "Root";
+    let mut output_file = None;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "--name" {
+            match rest.next() {
+                Some(type_name) => name = type_name,
+                None => usage(&args[0]),
+            }
+        } else if arg == "-o" {
+            match rest.next() {
+                Some(file) => output_file = Some(file),
+                None => usage(&args[0]),
+            }
+        } else {
+            patterns.push(arg);
+        }
+    }
+    if patterns.is_empty() {
+        usage(&args[0]);
+    }
+    let samples = expand_inputs(&patterns).and_then(|files| {
+        files
+            .iter()
+            .map(|file| {
+                let contents = read_input(file)?;
+                serde_json::from_str(&contents)
+                    .map_err(|e| format!("{} is not valid JSON: {}", display_name(file), e))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    });
+    let samples = samples.unwrap_or_else(|error| {
+        error_format.report(&[Diagnostic::error(error, None)]);
+        process::exit(1);
+    });
+    let inference = roto_infer::infer(name, &samples);
+    error_format.report(&inference.diagnostics);
+    let schema = format_program(&inference.items);
+    let written = match output_file {
+        Some(output_file) if output_file != STDIO => write_file(Path::new(output_file), &schema)
+            .map_err(|e| format!("Failed to write {}: {}", output_file, e)),
+        _ => {
+            print!("{}", schema);
+            Ok(())
+        }
+    };
+    if let Err(error) = written {
+        error_format.report(&[Diagnostic::error(error, None)]);
+        process::exit(1);
+    }
+}
+
+/// `roto publish`, which registers the declared types, or the `--root` types, with a schema
+/// registry and prints the id each was assigned. Subjects are named by `--subject`, where
+/// `{type}` is the name of the type. With `--against`, the types are first compared with their
+/// previous version, and changes that the compatibility mode does not allow stop the publish.
+fn publish(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let mut patterns = Vec::new();
+    let mut url = None;
+    let mut format = SchemaFormat::Avro;
+    let mut subject = "{type}";
+    let mut roots = Vec::new();
+    let mut mode = CompatibilityMode::Backward;
+    let mut against = None;
+    let mut dry_run = false;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "--registry" {
+            match rest.next() {
+                Some(registry_url) => url = Some(registry_url),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--format" {
+            match rest.next().and_then(|name| SchemaFormat::from_name(name)) {
+                Some(name) => format = name,
+                None => usage(&args[0]),
+            }
+        } else if arg == "--subject" {
+            match rest.next() {
+                Some(template) => subject = template,
+                None => usage(&args[0]),
+            }
+        } else if arg == "--root" {
+            match rest.next() {
+                Some(root) => roots.push(root),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--compatibility" {
+            match rest
+                .next()
+                .and_then(|name| CompatibilityMode::from_name(name))
+            {
+                Some(name) => mode = name,
+                None => usage(&args[0]),
+            }
+        } else if arg == "--against" {
+            match rest.next() {
+                Some(old) => against = Some(old),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--dry-run" {
+            dry_run = true;
+        } else {
+            patterns.push(arg);
+        }
+    }
+    let Some(url) = url else { usage(&args[0]) };
+    if patterns.is_empty() {
+        usage(&args[0]);
+    }
+
+    let backends: Vec<_> = registry.iter().collect();
+    let new = Inputs::new(patterns.clone(), error_format)
+        .compile(&backends)
+        .unwrap_or_else(|errors| {
+            error_format.report(&errors);
+            process::exit(1);
+        });
+    if let Some(against) = against {
+        let mut old = match against.strip_prefix("git:") {
+            Some(revision) => Inputs::at_revision(patterns, revision, error_format),
+            None => Inputs::new(vec![against], error_format),
+        };
+        let old = old.compile(&backends).unwrap_or_else(|errors| {
+            error_format.report(&errors);
+            process::exit(1);
+        });
+        let incompatible: Vec<_> = check_compatibility(&old, &new)
+            .into_iter()
+            .filter(|issue| !mode.allows(issue))
+            .map(|issue| {
+                Diagnostic::error(
+                    format!("{} is not allowed with {} compatibility", issue, mode),
+                    None,
+                )
+                .with_code("incompatible")
+            })
+            .collect();
+        if !incompatible.is_empty() {
+            error_format.report(&incompatible);
+            process::exit(1);
+        }
+    }
+
+    let declared: Vec<(usize, Atom)> = new
+        .iter_types()
+        .filter_map(|(id, named_type)| match &named_type.name {
+            TypeName::Variable(name) => Some((*id, *name)),
+            _ => None,
+        })
+        .collect();
+    if let Some(root) = roots
+        .iter()
+        .find(|root| !declared.iter().any(|(_, name)| name == root.as_str()))
+    {
+        eprintln!("Unknown root type `{}`", root);
+        process::exit(2);
+    }
+    let registry = Registry::new(url);
+    for (id, name) in declared {
+        if !roots.is_empty() && !roots.iter().any(|root| name == root.as_str()) {
+            continue;
+        }
+        let subject = subject.replace("{type}", &name);
+        let schema = format.schema(&new, id);
+        if dry_run {
+            println!("{}: {}", subject, schema);
+            continue;
+        }
+        match registry.register(&subject, format, &schema) {
+            Ok(schema_id) => println!("{}: {}", subject, schema_id),
+            Err(error) => {
+                error_format.report(&[Diagnostic::error(error, None)]);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// `roto build` without inputs, which builds every target of `roto.toml` into its `out-dir`.
+/// Exits with 1 if any of them fails.
+fn build_configured(registry: &BackendRegistry, error_format: ErrorFormat, mode: OutputMode) {
+    let project = load_project();
+    let mut failed = false;
+    for target in &project.targets {
+        let mut build = configured_build(registry, &project, target, error_format, mode);
+        if let Err(errors) = build.run() {
+            build.report(&errors);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// The `roto.toml` of the current directory, which has to have targets.
+fn load_project() -> ProjectConfig {
+    let project =
+        config::load_project_config(Path::new(config::CONFIG_FILE)).unwrap_or_else(|error| {
+            eprintln!("{}, run `roto init` to create one", error);
+            process::exit(2);
+        });
+    if project.targets.is_empty() {
+        eprintln!("{} has no targets", config::CONFIG_FILE);
+        process::exit(2);
+    }
+    project
+}
+
+/// The build of one target of `project`, into its `out-dir`.
+fn configured_build<'a>(
+    registry: &'a BackendRegistry,
+    project: &'a ProjectConfig,
+    target: &'a TargetConfig,
+    error_format: ErrorFormat,
+    mode: OutputMode,
+) -> Build<'a> {
+    let Some(backend) = registry.get(&target.name) else {
+        eprintln!("Unknown target `{}`, see --list", target.name);
+        process::exit(2);
+    };
+    Build {
+        backend,
+        known: vec![backend],
+        target: &target.name,
+        config: target.config.clone(),
+        inputs: Inputs::new(project.inputs.iter().collect(), error_format),
+        output_file: None,
+        output_dir: Some(&target.out_dir),
+        mode,
+        filter: TypeFilter::default(),
+        header: project.header.clone(),
+        source_maps: target.source_maps,
+        manifest: target.manifest,
+        types: Vec::new(),
+    }
+}
+
+/// `roto test`, which generates every target of `roto.toml` and compares the output to the
+/// files in its `snapshot-dir`, or its `out-dir` if it has none. Differences are printed as
+/// diffs and fail with 1. With `--update`, the snapshots are written instead.
+fn test(args: &[String], registry: &BackendRegistry, error_format: ErrorFormat) {
+    let update = match &args[2..] {
+        [] => false,
+        [flag] if flag == "--update" => true,
+        _ => usage(&args[0]),
+    };
+    let project = load_project();
+    let mut failed = false;
+    let mut outdated = 0;
+    for target in &project.targets {
+        let mut build =
+            configured_build(registry, &project, target, error_format, OutputMode::Write);
+        let snapshot_dir = target.snapshot_dir.as_ref().unwrap_or(&target.out_dir);
+        build.output_dir = Some(snapshot_dir);
+        let files = match build.generate() {
+            Ok(files) => files,
+            Err(errors) => {
+                build.report(&errors);
+                failed = true;
+                continue;
+            }
+        };
+        let compared = if update {
+            build.write(&files).map(|()| Vec::new())
+        } else {
+            snapshot::compare(Path::new(snapshot_dir), &files)
+        };
+        match compared {
+            Ok(diffs) => {
+                for diff in &diffs {
+                    print!("{}", diff);
+                }
+                outdated += diffs.len();
+            }
+            Err(error) => {
+                build.report(&[Diagnostic::error(error, None)]);
+                failed = true;
+            }
+        }
+    }
+    if outdated > 0 {
+        eprintln!(
+            "{} generated file(s) differ from the snapshots, run `roto test --update` if that is intended",
+            outdated
+        );
+    }
+    if failed || outdated > 0 {
+        process::exit(1);
+    }
+}
+
+/// `roto init`, which creates a project for the targets given as arguments.
+fn init(args: &[String], registry: &BackendRegistry) {
+    let targets: Vec<_> = args[2..].iter().collect();
+    if let Some(target) = targets.iter().find(|t| registry.get(t).is_none()) {
+        eprintln!("Unknown target `{}`, see --list", target);
+        process::exit(2);
+    }
+    match init::init(&targets) {
+        Ok(paths) => {
+            for path in paths {
+                report::status(format_args!("Wrote {}", path.display()));
+            }
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let registry = registry();
+    let mut error_format = ErrorFormat::Human;
+    if let Some(i) = args.iter().position(|arg| arg == "--error-format") {
+        match args
+            .get(i + 1)
+            .and_then(|name| ErrorFormat::from_name(name))
+        {
+            Some(format) => error_format = format,
+            None => usage(&args[0]),
+        }
+        args.drain(i..i + 2);
+    }
+    let mut verbosity = Verbosity::Normal;
+    args.retain(|arg| match Verbosity::from_flag(arg) {
+        Some(flag) => {
+            verbosity = flag;
+            false
+        }
+        None => true,
+    });
+    verbosity.init();
+    if args.len() == 2 && args[1] == "--list" {
+        for name in registry.names() {
+            println!("{}", name);
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "fmt" {
+        let check = args[2..].iter().any(|arg| arg == "--check");
+        let inputs: Vec<_> = args[2..].iter().filter(|arg| *arg != "--check").collect();
+        if inputs.is_empty() {
+            usage(&args[0]);
+        }
+        fmt::fmt(&inputs, check);
+        return;
+    }
+    if args.len() > 1 && args[1] == "check" {
+        check(&args, &registry, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "conformance" {
+        conformance(&args, &registry, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "doc" {
+        doc(&args, &registry, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "diff" {
+        diff(&args, &registry, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "migrate" {
+        migrate(&args, &registry, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "rename" {
+        rename::rename(&args, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "graph" {
+        graph(&args, &registry, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "import" {
+        import(&args, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "infer" {
+        infer(&args, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "ir" {
+        ir(&args, &registry, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "init" {
+        init(&args, &registry);
+        return;
+    }
+    if args.len() > 1 && args[1] == "build" && args.len() <= 3 {
+        let mode = match args.get(2) {
+            None => Some(OutputMode::Write),
+            Some(flag) => OutputMode::from_flag(flag),
+        };
+        if let Some(mode) = mode {
+            build_configured(&registry, error_format, mode);
+            return;
+        }
+    }
+    if args.len() > 1 && args[1] == "publish" {
+        publish(&args, &registry, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "test" {
+        test(&args, &registry, error_format);
+        return;
+    }
+    if args.len() > 1 && args[1] == "lint" {
+        lint(&args, &registry, error_format);
+        return;
+    }
+    if args.len() < 2 || args[1] != "build" {
+        usage(&args[0]);
+    }
+
+    let mut inputs = Vec::new();
+    let mut target = None;
+    let mut config = BackendConfig::new();
+    let mut output_file = None;
+    let mut output_dir = None;
+    let mut watch = false;
+    let mut mode = OutputMode::Write;
+    let mut filter = TypeFilter::default();
+    let mut header = HeaderConfig::default();
+    let mut source_maps = false;
+    let mut manifest = false;
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        if arg == "-o" {
+            match rest.next() {
+                Some(file) => output_file = Some(file),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--out-dir" {
+            match rest.next() {
+                Some(dir) => output_dir = Some(dir),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--watch" {
+            watch = true;
+        } else if let Some(flag) = OutputMode::from_flag(arg) {
+            mode = flag;
+        } else if arg == "--root" {
+            match rest.next() {
+                Some(root) => filter.roots.push(root),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--banner" {
+            match rest.next() {
+                Some(banner) => header.banner = Some(banner.clone()),
+                None => usage(&args[0]),
+            }
+        } else if arg == "--provenance" {
+            header.provenance = true;
+        } else if arg == "--tool-version" {
+            header.tool_version = true;
+        } else if arg == "--do-not-edit" {
+            header.do_not_edit = true;
+        } else if arg == "--source-map" {
+            source_maps = true;
+        } else if arg == "--manifest" {
+            manifest = true;
+        } else if arg == "--only" || arg == "--exclude" {
+            let pattern = match rest.next().map(|p| glob::Pattern::new(p)) {
+                Some(Ok(pattern)) => pattern,
+                Some(Err(error)) => {
+                    eprintln!("Invalid pattern: {}", error);
+                    process::exit(2);
+                }
+                None => usage(&args[0]),
+            };
+            if arg == "--only" {
+                filter.only.push(pattern);
+            } else {
+                filter.exclude.push(pattern);
+            }
+        } else if arg == "--target" {
+            match rest.next() {
+                Some(name) => target = Some(name),
+                None => usage(&args[0]),
+            }
+        } else if target.is_none() {
+            inputs.push(arg);
+        } else {
+            config.set_pair(arg);
+        }
+    }
+    let Some(target) = target else {
+        usage(&args[0]);
+    };
+    if inputs.is_empty() || (output_file.is_some() && output_dir.is_some()) {
+        usage(&args[0]);
+    }
+    let Some(backend) = registry.get(target) else {
+        eprintln!("Unknown target `{}`, see --list", target);
+        process::exit(2);
+    };
+
+    let mut build = Build {
+        backend,
+        known: vec![backend],
+        target,
+        config,
+        inputs: Inputs::new(inputs, error_format),
+        output_file,
+        output_dir,
+        mode,
+        filter,
+        header,
+        source_maps,
+        manifest,
+        types: Vec::new(),
+    };
+    if watch {
+        if build.inputs.patterns.iter().any(|input| *input == STDIO) {
+            eprintln!("Standard input cannot be watched");
+            process::exit(2);
+        }
+        watch::watch(&mut build);
+    } else if let Err(errors) = build.run() {
+        build.report(&errors);
+        process::exit(1);
+    }
+}
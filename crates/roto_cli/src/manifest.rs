@@ -0,0 +1,157 @@
+//! The generation manifest, a JSON file next to the generated code that lists every type of a
+//! build with a fingerprint of what was generated from it, where it was declared and the files
+//! it ended up in. Build systems compare fingerprints between builds to find what changed.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use roto_core::ast::SourceLocation;
+use roto_core::backend::OutputFile;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+use serde_json::json;
+use serde_json::Value;
+use sha2::Digest;
+use sha2::Sha256;
+
+/// The manifest written to an output directory.
+pub const MANIFEST_FILE: &str = "roto-manifest.json";
+
+/// A compiled type, as the manifest lists it.
+#[derive(Debug, Clone)]
+pub struct TypeEntry {
+    pub name: String,
+    /// A hash of the type like `sha256:9f86d0…`, see [`fingerprint`].
+    pub fingerprint: String,
+    pub location: Option<SourceLocation>,
+}
+
+/// Every type of `compiler`, ordered by name.
+pub fn types(compiler: &IRCompiler) -> Vec<TypeEntry> {
+    let mut types: Vec<_> = compiler
+        .iter_types()
+        .map(|(_, t)| TypeEntry {
+            name: t.name.to_string(),
+            fingerprint: fingerprint(compiler, t),
+            location: t.metadata.location.clone(),
+        })
+        .collect();
+    types.sort_by(|a, b| a.name.cmp(&b.name));
+    types
+}
+
+/// A hash of everything a backend sees of `t`: its shape, annotations and comments. References
+/// are hashed as the names of the types they point to, since ids differ between builds, so a
+/// change to a referenced type only changes the fingerprint of that type. Locations are left
+/// out, moving a declaration does not change what is generated from it.
+pub fn fingerprint(compiler: &IRCompiler, t: &NamedIRType) -> String {
+    let name = |t: &PrimitiveType| match t {
+        PrimitiveType::Reference(id) => compiler.named_type(*id).name.to_string(),
+        PrimitiveType::Builtin(builtin) => builtin.to_string(),
+    };
+    let mut text = format!("type {}\n", t.name);
+    if let Some(doc) = &t.metadata.doc {
+        writeln!(text, "doc {:?}", doc).unwrap();
+    }
+    for annotation in &t.metadata.annotations {
+        writeln!(text, "{}", annotation).unwrap();
+    }
+    match &t.type_ {
+        IRType::Struct(s) => {
+            for field in &s.fields {
+                if let Some(comment) = &field.comment {
+                    writeln!(text, "  doc {:?}", comment).unwrap();
+                }
+                for annotation in &field.annotations {
+                    writeln!(text, "  {}", annotation).unwrap();
+                }
+                let marker = if field.optional { "?" } else { "" };
+                writeln!(
+                    text,
+                    "  field {}{}: {}",
+                    field.name,
+                    marker,
+                    name(&field.type_)
+                )
+                .unwrap();
+            }
+        }
+        IRType::Variant(v) => {
+            for option in &v.variants {
+                if let Some(comment) = &option.comment {
+                    writeln!(text, "  doc {:?}", comment).unwrap();
+                }
+                for annotation in &option.annotations {
+                    writeln!(text, "  {}", annotation).unwrap();
+                }
+                writeln!(text, "  option {}({})", option.name, name(&option.type_)).unwrap();
+            }
+        }
+        IRType::Reference(id) => {
+            writeln!(text, "= {}", compiler.named_type(*id).name).unwrap();
+        }
+        IRType::Builtin(builtin) => writeln!(text, "= {}", builtin).unwrap(),
+    }
+    format!("sha256:{:x}", Sha256::digest(&text))
+}
+
+/// The span of `location` as JSON, with 1-based lines and columns.
+pub fn span_json(location: &SourceLocation) -> Value {
+    json!({
+        "start": {"line": location.line, "column": location.column},
+        "end": {"line": location.end_line, "column": location.end_column},
+    })
+}
+
+/// The manifest of `types` generated into `files` by `target`, at `path`. A type is in the
+/// files whose source map maps a line to its declaration. Types that no source map mentions,
+/// like every type of a backend without source maps, are listed in every file.
+pub fn manifest_file(
+    path: String,
+    target: &str,
+    types: &[TypeEntry],
+    files: &[OutputFile],
+) -> OutputFile {
+    let entries: Vec<_> = types
+        .iter()
+        .map(|t| {
+            let mapped: BTreeSet<_> = files
+                .iter()
+                .filter(|file| {
+                    t.location.as_ref().is_some_and(|location| {
+                        file.source_map
+                            .iter()
+                            .any(|mapping| mapping.location == *location)
+                    })
+                })
+                .map(|file| file.path.as_str())
+                .collect();
+            let in_files: Vec<_> = if mapped.is_empty() {
+                files.iter().map(|file| file.path.as_str()).collect()
+            } else {
+                mapped.into_iter().collect()
+            };
+            json!({
+                "name": t.name,
+                "fingerprint": t.fingerprint,
+                "source": t.location.as_ref().map(|location| json!({
+                    "file": location.file,
+                    "span": span_json(location),
+                })),
+                "files": in_files,
+            })
+        })
+        .collect();
+    let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+    let manifest = json!({
+        "target": target,
+        "files": paths,
+        "types": entries,
+    });
+    OutputFile::new(
+        path,
+        serde_json::to_string_pretty(&manifest).unwrap() + "\n",
+    )
+}
@@ -0,0 +1,79 @@
+//! `roto rename`, which renames a type or a field of a schema and every reference to it, in
+//! place.
+
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use roto_core::cst::apply;
+use roto_core::refactor::rename_field;
+use roto_core::refactor::rename_type;
+
+use crate::display_name;
+use crate::expand_inputs;
+use crate::read_input;
+use crate::report;
+use crate::report::ErrorFormat;
+use crate::usage;
+use crate::STDIO;
+
+/// `roto rename type <old> <new> <input>...` and `roto rename field <type> <old> <new>
+/// <input>...`. Names of types are qualified, like `auth.User`. The files that change are
+/// rewritten, standard input is written to standard output.
+pub fn rename(args: &[String], error_format: ErrorFormat) {
+    let (names, inputs) = match args.get(2).map(String::as_str) {
+        Some("type") if args.len() > 5 => (&args[3..5], &args[5..]),
+        Some("field") if args.len() > 6 => (&args[3..6], &args[6..]),
+        _ => usage(&args[0]),
+    };
+    let inputs: Vec<_> = inputs.iter().collect();
+    let files = expand_inputs(&inputs).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+    let mut sources = Vec::new();
+    for file in &files {
+        match read_input(file) {
+            Ok(contents) => sources.push((display_name(file), contents)),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+    }
+
+    let schema: Vec<_> = sources
+        .iter()
+        .map(|(name, contents)| (name.as_str(), contents.as_str()))
+        .collect();
+    let renamed = match names {
+        [old, new] => rename_type(&schema, old, new),
+        [type_name, old, new] => rename_field(&schema, type_name, old, new),
+        _ => unreachable!(),
+    };
+    let edits = renamed.unwrap_or_else(|diagnostics| {
+        error_format.report(&diagnostics);
+        process::exit(1);
+    });
+
+    let mut failed = false;
+    for (file, (name, contents)) in files.iter().zip(&sources) {
+        let renamed = match edits.iter().find(|edits| &edits.file == name) {
+            Some(edits) => apply(contents, &edits.edits),
+            None => contents.clone(),
+        };
+        if file == Path::new(STDIO) {
+            print!("{}", renamed);
+        } else if renamed == *contents {
+            continue;
+        } else if let Err(error) = fs::write(file, renamed) {
+            eprintln!("failed to write {}: {}", file.display(), error);
+            failed = true;
+        } else {
+            report::status(format_args!("Renamed in {}", file.display()));
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
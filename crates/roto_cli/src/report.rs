@@ -0,0 +1,117 @@
+//! What is written to standard error: diagnostics, as text for people or as one JSON object per
+//! line for editors and CI annotators, progress like the files that were written, and with `-v`
+//! the trace of the compiler and backends.
+
+use std::fmt::Display;
+use std::io;
+use std::io::IsTerminal;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use roto_core::diagnostics::Diagnostic;
+use roto_core::diagnostics::Severity;
+use serde_json::json;
+use serde_json::Value;
+use tracing::level_filters::LevelFilter;
+
+/// Set by `-q`, after which only errors are reported.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// How much is reported, from `-q` to `-vvv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    /// `-v`, which traces what was compiled and generated and how long it took.
+    Verbose,
+    /// `-vv`, which traces every file, merge and written output.
+    Debug,
+    /// `-vvv`, which traces every type that is compiled, reused or emitted.
+    Trace,
+}
+
+impl Verbosity {
+    /// Parses `-q`, `-v`, `-vv` and `-vvv`.
+    pub fn from_flag(flag: &str) -> Option<Verbosity> {
+        match flag {
+            "-q" | "--quiet" => Some(Verbosity::Quiet),
+            "-v" => Some(Verbosity::Verbose),
+            "-vv" => Some(Verbosity::Debug),
+            "-vvv" => Some(Verbosity::Trace),
+            _ => None,
+        }
+    }
+
+    /// Sets up reporting for the rest of the process.
+    pub fn init(self) {
+        QUIET.store(self == Verbosity::Quiet, Ordering::Relaxed);
+        let level = match self {
+            Verbosity::Quiet => LevelFilter::OFF,
+            Verbosity::Normal => LevelFilter::WARN,
+            Verbosity::Verbose => LevelFilter::INFO,
+            Verbosity::Debug => LevelFilter::DEBUG,
+            Verbosity::Trace => LevelFilter::TRACE,
+        };
+        tracing_subscriber::fmt()
+            .with_max_level(level)
+            .with_writer(io::stderr)
+            .with_ansi(io::stderr().is_terminal())
+            .without_time()
+            .init();
+    }
+}
+
+/// Reports progress, like a file that was written, unless `-q` was given.
+pub fn status(message: impl Display) {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn from_name(name: &str) -> Option<ErrorFormat> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Writes `diagnostics`, leaving out warnings with `-q`.
+    pub fn report(self, diagnostics: &[Diagnostic]) {
+        let quiet = QUIET.load(Ordering::Relaxed);
+        for diagnostic in diagnostics {
+            if quiet && diagnostic.severity == Severity::Warning {
+                continue;
+            }
+            match self {
+                ErrorFormat::Human => eprintln!("{}", diagnostic),
+                ErrorFormat::Json => eprintln!("{}", to_json(diagnostic)),
+            }
+        }
+    }
+}
+
+/// The fields of `diagnostic`, with a `null` file and span if it has no location.
+fn to_json(diagnostic: &Diagnostic) -> Value {
+    let location = diagnostic.location.as_ref();
+    let span = location.map(|location| {
+        json!({
+            "start": {"line": location.line, "column": location.column},
+            "end": {"line": location.end_line, "column": location.end_column},
+        })
+    });
+    json!({
+        "file": location.map(|location| &location.file),
+        "span": span,
+        "severity": diagnostic.severity.to_string(),
+        "code": diagnostic.code,
+        "message": diagnostic.message,
+    })
+}
@@ -0,0 +1,59 @@
+//! The `--root`, `--only` and `--exclude` options of `roto build`, which limit the generated
+//! types to what an application needs.
+
+use glob::Pattern;
+use roto_core::frontend::IRCompiler;
+use roto_core::intern::Atom;
+use roto_core::ir::TypeName;
+
+/// Which declared types are generated. The types they refer to are always generated as well,
+/// even if they are excluded, since the output would not be complete without them.
+#[derive(Default)]
+pub struct TypeFilter<'a> {
+    /// Only these types and the types they refer to. Without roots every declared type is one.
+    pub roots: Vec<&'a String>,
+    /// Patterns like `auth.*` that the roots have to match, if there are any.
+    pub only: Vec<Pattern>,
+    /// Patterns of roots that are left out.
+    pub exclude: Vec<Pattern>,
+}
+
+impl<'a> TypeFilter<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty() && self.only.is_empty() && self.exclude.is_empty()
+    }
+
+    fn includes(&self, name: &str) -> bool {
+        (self.roots.is_empty() || self.roots.iter().any(|root| *root == name))
+            && (self.only.is_empty() || self.only.iter().any(|p| p.matches(name)))
+            && !self.exclude.iter().any(|p| p.matches(name))
+    }
+
+    /// Removes the compiled types that are not reachable from the selected roots.
+    pub fn apply(&self, compiler: &mut IRCompiler) -> Result<(), String> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let globals: Vec<(usize, Atom)> = compiler
+            .iter_types()
+            .filter_map(|(id, named_type)| match &named_type.name {
+                TypeName::Variable(name) => Some((*id, *name)),
+                _ => None,
+            })
+            .collect();
+        if let Some(root) = self
+            .roots
+            .iter()
+            .find(|root| !globals.iter().any(|(_, name)| name == root.as_str()))
+        {
+            return Err(format!("Unknown root type `{}`", root));
+        }
+        let roots: Vec<usize> = globals
+            .iter()
+            .filter(|(_, name)| self.includes(name))
+            .map(|(id, _)| *id)
+            .collect();
+        compiler.retain_reachable(&roots);
+        Ok(())
+    }
+}
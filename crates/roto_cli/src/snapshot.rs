@@ -0,0 +1,32 @@
+//! `roto test`, which checks that the committed output of every target is what the schemas
+//! generate now, so that accidental changes to the generated code fail in CI.
+
+use std::path::Path;
+
+use roto_core::backend::OutputFile;
+use roto_core::output::{write_all, PendingFile, PreviewSink};
+use similar::TextDiff;
+
+/// A unified diff from what is on disk to what `file` would write.
+pub fn unified_diff(file: &PendingFile) -> String {
+    let name = file.path.display().to_string();
+    TextDiff::from_lines(file.previous.as_deref().unwrap_or(""), &file.contents)
+        .unified_diff()
+        .header(
+            if file.previous.is_some() {
+                &name
+            } else {
+                "/dev/null"
+            },
+            &format!("{} (generated)", name),
+        )
+        .to_string()
+}
+
+/// Compares `files` to the snapshots in `directory`. Returns a unified diff per file that is
+/// missing or differs, from the snapshot to the generated contents.
+pub fn compare(directory: &Path, files: &[OutputFile]) -> Result<Vec<String>, String> {
+    let mut preview = PreviewSink::default();
+    write_all(&mut preview, directory, files).map_err(|error| error.to_string())?;
+    Ok(preview.changed().map(unified_diff).collect())
+}
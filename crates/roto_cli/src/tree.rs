@@ -0,0 +1,141 @@
+//! `roto ir --type <type>`, which prints a compiled type as an indented tree: its fields and
+//! options with their optionality, annotations and comments, and the inline types they contain.
+//! With `--resolve`, the named types it refers to are expanded as well.
+
+use std::fmt::Write;
+
+use roto_core::ast;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+
+/// Writes the tree of the declared type `name`.
+pub fn write_tree(compiler: &IRCompiler, name: &str, resolve: bool) -> Result<String, String> {
+    let id = compiler
+        .iter_types()
+        .find_map(|(id, t)| match &t.name {
+            TypeName::Variable(n) if *n == name => Some(*id),
+            _ => None,
+        })
+        .ok_or_else(|| format!("Unknown type `{}`", name))?;
+    let mut writer = TreeWriter {
+        compiler,
+        resolve,
+        path: vec![id],
+        out: String::new(),
+    };
+    let named_type = compiler.named_type(id);
+    writer.write_comment(0, named_type.metadata.doc.as_deref());
+    write!(writer.out, "{}", name).unwrap();
+    writer.write_annotations(&named_type.metadata.annotations);
+    writer.write_body(&named_type.type_, 0, " = ");
+    Ok(writer.out)
+}
+
+struct TreeWriter<'a> {
+    compiler: &'a IRCompiler,
+    resolve: bool,
+    /// The types being expanded, from the root to the innermost, which are not expanded again.
+    path: Vec<usize>,
+    out: String,
+}
+
+impl TreeWriter<'_> {
+    /// Writes the comment and the annotations of a member on the lines before it, like in a
+    /// schema.
+    fn write_header(
+        &mut self,
+        depth: usize,
+        comment: Option<&str>,
+        annotations: &[ast::Annotation],
+    ) {
+        self.write_comment(depth, comment);
+        for annotation in annotations {
+            writeln!(self.out, "{}{}", "  ".repeat(depth), annotation).unwrap();
+        }
+    }
+
+    fn write_comment(&mut self, depth: usize, comment: Option<&str>) {
+        for line in comment.into_iter().flat_map(str::lines) {
+            writeln!(self.out, "{}// {}", "  ".repeat(depth), line).unwrap();
+        }
+    }
+
+    fn write_annotations(&mut self, annotations: &[ast::Annotation]) {
+        for annotation in annotations {
+            write!(self.out, " {}", annotation).unwrap();
+        }
+    }
+
+    /// Writes what follows the name of a type after `separator`, and the members of the type
+    /// below it at `depth + 1`.
+    fn write_body(&mut self, t: &IRType, depth: usize, separator: &str) {
+        match t {
+            IRType::Struct(s) => {
+                writeln!(self.out, "{}struct", separator).unwrap();
+                for field in &s.fields {
+                    self.write_header(depth + 1, field.comment.as_deref(), &field.annotations);
+                    let marker = if field.optional { "?" } else { "" };
+                    write!(
+                        self.out,
+                        "{}{}{}:",
+                        "  ".repeat(depth + 1),
+                        field.name,
+                        marker
+                    )
+                    .unwrap();
+                    self.write_member_type(&field.type_, depth + 1);
+                }
+            }
+            IRType::Variant(v) => {
+                writeln!(self.out, "{}enum", separator).unwrap();
+                for option in &v.variants {
+                    self.write_header(depth + 1, option.comment.as_deref(), &option.annotations);
+                    write!(self.out, "{}{}", "  ".repeat(depth + 1), option.name).unwrap();
+                    if option.type_ == PrimitiveType::Builtin(ast::Builtin::Unit) {
+                        self.out.push('\n');
+                        continue;
+                    }
+                    self.out.push(':');
+                    self.write_member_type(&option.type_, depth + 1);
+                }
+            }
+            IRType::Reference(id) => {
+                self.out.push_str(separator.trim_end());
+                self.write_member_type(&PrimitiveType::Reference(*id), depth);
+            }
+            IRType::Builtin(builtin) => writeln!(self.out, "{}{}", separator, builtin).unwrap(),
+        }
+    }
+
+    /// Writes the type of a member at `depth`, after its name. Inline types are always expanded,
+    /// named types only with `resolve`, and never while they are being expanded already.
+    fn write_member_type(&mut self, t: &PrimitiveType, depth: usize) {
+        let id = match t {
+            PrimitiveType::Builtin(builtin) => {
+                writeln!(self.out, " {}", builtin).unwrap();
+                return;
+            }
+            PrimitiveType::Reference(id) => *id,
+        };
+        let named_type = self.compiler.named_type(id);
+        let inline = matches!(named_type.name, TypeName::Temporary(..));
+        if !inline {
+            write!(self.out, " {}", named_type.name).unwrap();
+        }
+        if self.path.contains(&id) {
+            self.out.push_str(" (recursive)\n");
+            return;
+        }
+        if !inline && !self.resolve {
+            self.out.push('\n');
+            return;
+        }
+        self.write_annotations(&named_type.metadata.annotations);
+        self.path.push(id);
+        let separator = if inline { " " } else { " = " };
+        self.write_body(&named_type.type_, depth, separator);
+        self.path.pop();
+    }
+}
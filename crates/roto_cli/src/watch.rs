@@ -0,0 +1,110 @@
+//! `roto build --watch`, which builds again whenever one of the inputs changes.
+
+use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use glob::Pattern;
+use notify::EventKind;
+use notify::RecursiveMode;
+use notify::Watcher;
+
+use crate::is_pattern;
+use crate::report;
+use crate::Build;
+
+/// Editors often save a file in several steps, which are built once.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// The directory to watch for `input` and whether its subdirectories are watched as well. Files
+/// are watched through their directory, since editors tend to replace them on save.
+fn watched_directory(input: &str) -> (PathBuf, RecursiveMode) {
+    let path = Path::new(input);
+    if !is_pattern(input) {
+        let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+        return (
+            parent.unwrap_or(Path::new(".")).to_path_buf(),
+            RecursiveMode::NonRecursive,
+        );
+    }
+    let base: PathBuf = path
+        .components()
+        .take_while(|c| !is_pattern(&c.as_os_str().to_string_lossy()))
+        .collect();
+    if base.as_os_str().is_empty() {
+        (PathBuf::from("."), RecursiveMode::Recursive)
+    } else {
+        (base, RecursiveMode::Recursive)
+    }
+}
+
+/// Whether a change to `path` affects the build, so that writing the output next to the inputs
+/// does not trigger another build.
+fn is_input(patterns: &[Pattern], current_dir: &Path, path: &Path) -> bool {
+    let relative = path.strip_prefix(current_dir).unwrap_or(path);
+    patterns
+        .iter()
+        .any(|pattern| pattern.matches_path(path) || pattern.matches_path(relative))
+}
+
+/// Builds, then builds again on every change of the inputs until the process is stopped. Errors
+/// are reported and the next change is waited for.
+pub fn watch(build: &mut Build) {
+    let patterns: Vec<Pattern> = build
+        .inputs
+        .patterns
+        .iter()
+        .map(|input| {
+            if is_pattern(input) {
+                Pattern::new(input)
+            } else {
+                Pattern::new(&Pattern::escape(input))
+            }
+        })
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|e| {
+            eprintln!("invalid pattern: {}", e);
+            process::exit(1);
+        });
+    let current_dir = env::current_dir().expect("Failed to get the current directory");
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).expect("Failed to start watching");
+    for input in &build.inputs.patterns {
+        let (directory, mode) = watched_directory(input);
+        if let Err(error) = watcher.watch(&directory, mode) {
+            eprintln!("Failed to watch {}: {}", directory.display(), error);
+            process::exit(1);
+        }
+    }
+
+    loop {
+        match build.run() {
+            Ok(()) => report::status("Build finished, watching for changes"),
+            Err(errors) => {
+                build.report(&errors);
+                report::status("Build failed, watching for changes");
+            }
+        }
+        loop {
+            let Ok(event) = receiver.recv() else {
+                return;
+            };
+            let Ok(event) = event else {
+                continue;
+            };
+            if !matches!(event.kind, EventKind::Access(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| is_input(&patterns, &current_dir, path))
+            {
+                break;
+            }
+        }
+        while receiver.recv_timeout(SETTLE_TIME).is_ok() {}
+    }
+}
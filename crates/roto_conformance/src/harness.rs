@@ -0,0 +1,253 @@
+//! The test programs that run the generated code of a target against the fixtures. Each one is
+//! written next to the generated code, in the directory of its target, and reads the fixtures
+//! from the `fixtures` directory next to it.
+
+use roto_core::backend::OutputFile;
+
+/// A fixture and the name of its type in the generated code, like `auth.User` and `auth_User`.
+pub struct HarnessType {
+    pub fixture: String,
+    pub identifier: String,
+}
+
+/// The loop shared by the Python harnesses. `roundtrip(type_, line)` returns what is expected
+/// and what the generated code produced for one line of a fixture.
+const PYTHON_MAIN: &str = r#"def main() -> int:
+    failures = 0
+    for name, type_ in TYPES:
+        lines = (FIXTURES / f"{name}.{EXTENSION}").read_text().splitlines()
+        for number, line in enumerate(lines, 1):
+            try:
+                expected, actual = roundtrip(type_, line)
+            except Exception as error:
+                print(f"{name} #{number}: {error!r}")
+                failures += 1
+                continue
+            if actual != expected:
+                print(f"{name} #{number}: expected {expected!r}, got {actual!r}")
+                failures += 1
+    print(f"{failures} failure(s)")
+    return 1 if failures else 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
+"#;
+
+const PYDANTIC_ROUNDTRIP: &str = r#"def roundtrip(type_, line):
+    adapter = TypeAdapter(type_)
+    value = adapter.validate_json(line)
+    encoded = adapter.dump_json(value, by_alias=True, exclude_unset=True)
+    return json.loads(line), json.loads(encoded)
+"#;
+
+const MSGSPEC_ROUNDTRIP: &str = r#"def without_nulls(value):
+    # Optional fields that are not set are written as null, the fixtures leave them out.
+    if isinstance(value, dict):
+        return {k: without_nulls(v) for k, v in value.items() if v is not None}
+    return value
+
+
+def roundtrip(type_, line):
+    value = msgspec.json.decode(line, type=type_)
+    encoded = msgspec.json.encode(value)
+    return without_nulls(json.loads(line)), without_nulls(json.loads(encoded))
+"#;
+
+const WIRE_PYTHON_ROUNDTRIP: &str = r#"def roundtrip(functions, line):
+    decode, encode = functions
+    data = bytes.fromhex(line)
+    value, end = decode(data)
+    if end != len(data):
+        raise ValueError(f"{len(data) - end} trailing bytes")
+    out = bytearray()
+    encode(out, value)
+    return data.hex(), out.hex()
+"#;
+
+/// A Python harness for `target`. `module` is the generated module, `imports` what the
+/// roundtrip needs and `entry` the `TYPES` entry of a type.
+fn python(
+    target: &str,
+    module: &str,
+    imports: &[&str],
+    extension: &str,
+    roundtrip: &str,
+    types: &[HarnessType],
+    entry: impl Fn(&HarnessType) -> String,
+) -> OutputFile {
+    let mut contents = format!(
+        "# Checks that {module}.py decodes every fixture and encodes it back unchanged.\n\
+         # Run with `python {target}/conformance.py`.\n\
+         import importlib.util\nimport json\nimport pathlib\nimport sys\n\n"
+    );
+    for import in imports {
+        contents.push_str(import);
+        contents.push('\n');
+    }
+    contents.push_str(&format!(
+        "\nHERE = pathlib.Path(__file__).resolve().parent\n\
+         FIXTURES = HERE.parent / \"fixtures\"\n\
+         EXTENSION = \"{extension}\"\n\n\
+         # Loaded by path, since the module may be named like a standard module, like `types`.\n\
+         spec = importlib.util.spec_from_file_location(\"generated\", HERE / \"{module}.py\")\n\
+         generated = importlib.util.module_from_spec(spec)\n\
+         sys.modules[\"generated\"] = generated\n\
+         spec.loader.exec_module(generated)\n\nTYPES = [\n"
+    ));
+    for t in types {
+        contents.push_str(&format!("    (\"{}\", {}),\n", t.fixture, entry(t)));
+    }
+    contents.push_str("]\n\n\n");
+    contents.push_str(roundtrip);
+    contents.push_str("\n\n");
+    contents.push_str(PYTHON_MAIN);
+    OutputFile::new(format!("{}/conformance.py", target), contents)
+}
+
+pub fn pydantic(types: &[HarnessType]) -> OutputFile {
+    python(
+        "py-pydantic",
+        "models",
+        &["from pydantic import TypeAdapter"],
+        "json",
+        PYDANTIC_ROUNDTRIP,
+        types,
+        |t| format!("generated.{}", t.identifier),
+    )
+}
+
+pub fn msgspec(types: &[HarnessType]) -> OutputFile {
+    python(
+        "py-msgspec",
+        "types",
+        &["import msgspec"],
+        "json",
+        MSGSPEC_ROUNDTRIP,
+        types,
+        |t| format!("generated.{}", t.identifier),
+    )
+}
+
+pub fn wire_python(types: &[HarnessType]) -> OutputFile {
+    python(
+        "wire-python",
+        "wire",
+        &[],
+        "wire",
+        WIRE_PYTHON_ROUNDTRIP,
+        types,
+        |t| format!("(generated.decode_{0}, generated.encode_{0})", t.identifier),
+    )
+}
+
+const ZOD_MAIN: &str = r#"let failures = 0;
+for (const [name, schema] of TYPES) {
+  const lines = readFileSync(join(FIXTURES, `${name}.json`), "utf8")
+    .split("\n")
+    .filter((line) => line !== "");
+  lines.forEach((line, index) => {
+    const expected = JSON.parse(line);
+    const result = schema.safeParse(expected);
+    if (!result.success) {
+      console.log(`${name} #${index + 1}: ${result.error.message}`);
+      failures++;
+      return;
+    }
+    const actual = JSON.parse(JSON.stringify(result.data));
+    if (!isDeepStrictEqual(actual, expected)) {
+      console.log(`${name} #${index + 1}: expected ${line}, got ${JSON.stringify(actual)}`);
+      failures++;
+    }
+  });
+}
+console.log(`${failures} failure(s)`);
+process.exit(failures > 0 ? 1 : 0);
+"#;
+
+pub fn zod(types: &[HarnessType]) -> OutputFile {
+    let mut contents = String::from(
+        "// Checks that the schemas of schemas.ts parse every fixture and keep it unchanged.\n\
+         // Run with `npx tsx ts-zod/conformance.ts`.\n\
+         import { readFileSync } from \"fs\";\n\
+         import { join } from \"path\";\n\
+         import { isDeepStrictEqual } from \"util\";\n\
+         import type { ZodTypeAny } from \"zod\";\n\
+         import * as schemas from \"./schemas\";\n\n\
+         const FIXTURES = join(__dirname, \"..\", \"fixtures\");\n\n\
+         const TYPES: [string, ZodTypeAny][] = [\n",
+    );
+    for t in types {
+        contents.push_str(&format!(
+            "  [\"{}\", schemas.{}Schema],\n",
+            t.fixture, t.identifier
+        ));
+    }
+    contents.push_str("];\n\n");
+    contents.push_str(ZOD_MAIN);
+    OutputFile::new("ts-zod/conformance.ts", contents)
+}
+
+const WIRE_RUST_CHECK: &str = r#"fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(line: &str) -> Vec<u8> {
+    (0..line.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&line[i..i + 2], 16).expect("fixtures are hex"))
+        .collect()
+}
+
+/// Decodes every line of the fixture `name` as a `T` and encodes it again. Returns the number of
+/// lines that failed.
+fn check<T: Decode + Encode>(name: &str) -> usize {
+    let path = Path::new(file!())
+        .parent()
+        .unwrap()
+        .join("../fixtures")
+        .join(format!("{}.wire", name));
+    let contents = fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("failed to read {}: {}", path.display(), error));
+    let mut failures = 0;
+    for (number, line) in contents.lines().enumerate() {
+        match T::from_bytes(&unhex(line)) {
+            Ok(value) => {
+                let actual = hex(&value.to_bytes());
+                if actual != line {
+                    println!("{} #{}: expected {}, got {}", name, number + 1, line, actual);
+                    failures += 1;
+                }
+            }
+            Err(error) => {
+                println!("{} #{}: {:?}", name, number + 1, error);
+                failures += 1;
+            }
+        }
+    }
+    failures
+}
+"#;
+
+pub fn wire_rust(types: &[HarnessType]) -> OutputFile {
+    let mut contents = String::from(
+        "//! Checks that wire.rs decodes every fixture and encodes it back to the same bytes.\n\
+         //! Run with `rustc --edition 2021 wire-rust/conformance.rs -o wire-conformance && \
+         ./wire-conformance`.\n\n\
+         mod wire;\n\n\
+         use std::fs;\nuse std::path::Path;\nuse std::process;\n\n\
+         use wire::{Decode, Encode};\n\n",
+    );
+    contents.push_str(WIRE_RUST_CHECK);
+    contents.push_str("\nfn main() {\n    let mut failures = 0;\n");
+    for t in types {
+        contents.push_str(&format!(
+            "    failures += check::<wire::{}>(\"{}\");\n",
+            t.identifier, t.fixture
+        ));
+    }
+    contents.push_str(
+        "    println!(\"{} failure(s)\", failures);\n    if failures > 0 {\n        process::exit(1);\n    }\n}\n",
+    );
+    OutputFile::new("wire-rust/conformance.rs", contents)
+}
@@ -0,0 +1,156 @@
+//! Cross-language conformance suites. A suite holds random instances of every declared type as
+//! fixtures, the code each target generates and a harness per target that decodes every fixture
+//! with the generated code and checks that encoding it again gives the same data. When the
+//! harnesses of all targets pass, the targets agree on the representation of every type.
+//!
+//! The fixtures are in `fixtures/`, one line per instance: `<type>.json` holds the adjacently
+//! tagged JSON the JSON targets share, `<type>.wire` the hex of the roto wire format, as roto
+//! encodes it. The code and the harness of a target are in a directory named after it.
+
+mod harness;
+pub mod wire;
+
+use roto_core::annotations::AnnotationRegistry;
+use roto_core::backend::BackendConfig;
+use roto_core::backend::CodegenBackend;
+use roto_core::backend::OutputFile;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::TypeName;
+use roto_fake::FakeGenerator;
+
+use harness::HarnessType;
+
+/// The targets a harness is written for.
+pub const TARGETS: [&str; 5] = [
+    "py-msgspec",
+    "py-pydantic",
+    "ts-zod",
+    "wire-python",
+    "wire-rust",
+];
+
+/// Registers the constraints the fixtures respect, see [`roto_fake::register_annotations`].
+pub fn register_annotations(registry: &mut AnnotationRegistry) {
+    roto_fake::register_annotations(registry);
+}
+
+/// What a suite is generated with.
+#[derive(Debug, Clone)]
+pub struct Suite {
+    /// The number of instances of every type.
+    pub samples: usize,
+    /// The seed of the instances, the same seed gives the same fixtures.
+    pub seed: u64,
+}
+
+impl Default for Suite {
+    fn default() -> Self {
+        Suite {
+            samples: 8,
+            seed: 0,
+        }
+    }
+}
+
+impl Suite {
+    /// The fixtures of the declared types of `compiler`, and the code and harness of every one of
+    /// `backends`, which have to be in [`TARGETS`]. The code is generated with the default
+    /// options of the backend.
+    pub fn generate(
+        &self,
+        compiler: &IRCompiler,
+        backends: &[&dyn CodegenBackend],
+    ) -> Result<Vec<OutputFile>, Vec<Diagnostic>> {
+        let unsupported: Vec<_> = backends
+            .iter()
+            .filter(|backend| !TARGETS.contains(&backend.name()))
+            .map(|backend| {
+                Diagnostic::error(
+                    format!(
+                        "target `{}` has no conformance harness, supported are {}",
+                        backend.name(),
+                        TARGETS.join(", ")
+                    ),
+                    None,
+                )
+            })
+            .collect();
+        if !unsupported.is_empty() {
+            return Err(unsupported);
+        }
+
+        // Generic declarations are only instantiated where they are used, so only types without
+        // parameters get fixtures.
+        let mut declared: Vec<_> = compiler
+            .iter_types()
+            .filter_map(|(id, t)| match t.name {
+                TypeName::Variable(name) => Some((name, *id)),
+                _ => None,
+            })
+            .collect();
+        declared.sort_by_key(|(name, _)| name.as_str());
+
+        let mut files = Vec::new();
+        let mut generator = FakeGenerator::new(compiler, self.seed);
+        let mut diagnostics = Vec::new();
+        for (name, id) in &declared {
+            let mut json = String::new();
+            let mut encoded = String::new();
+            for _ in 0..self.samples {
+                let value = match generator.generate(name) {
+                    Ok(value) => value,
+                    Err(errors) => {
+                        diagnostics.extend(errors);
+                        break;
+                    }
+                };
+                let mut bytes = Vec::new();
+                if let Err(error) = wire::encode(compiler, *id, &value, &mut bytes) {
+                    let location = compiler.named_type(*id).metadata.location.clone();
+                    diagnostics.push(Diagnostic::error(
+                        format!("failed to encode an instance of {}: {}", name, error),
+                        location,
+                    ));
+                    break;
+                }
+                json.push_str(&value.to_string());
+                json.push('\n');
+                encoded.push_str(&wire::hex(&bytes));
+                encoded.push('\n');
+            }
+            files.push(OutputFile::new(format!("fixtures/{}.json", name), json));
+            files.push(OutputFile::new(format!("fixtures/{}.wire", name), encoded));
+        }
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+
+        // Every target names declared types like `auth_User`.
+        let types: Vec<_> = declared
+            .iter()
+            .map(|(name, _)| HarnessType {
+                fixture: name.to_string(),
+                identifier: name.replace('.', "_"),
+            })
+            .collect();
+        let config = BackendConfig::new();
+        for backend in backends {
+            let generated = backend
+                .generate(compiler, &config)
+                .map_err(|error| error.diagnostics)?;
+            files.extend(generated.into_iter().map(|file| OutputFile {
+                path: format!("{}/{}", backend.name(), file.path),
+                ..file
+            }));
+            files.push(match backend.name() {
+                "py-msgspec" => harness::msgspec(&types),
+                "py-pydantic" => harness::pydantic(&types),
+                "ts-zod" => harness::zod(&types),
+                "wire-python" => harness::wire_python(&types),
+                _ => harness::wire_rust(&types),
+            });
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,123 @@
+//! The reference encoding of JSON values in the roto wire format, as described in
+//! `roto_wire_backend`. The wire harnesses decode these bytes with the generated code and check
+//! that encoding the result gives the same bytes.
+
+use roto_core::ast;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::PrimitiveStructField;
+use roto_core::ir::PrimitiveType;
+use serde_json::Value;
+
+/// The key of `field` in JSON, its `@rename` if it has one.
+pub fn json_name(field: &PrimitiveStructField) -> String {
+    match field
+        .annotations
+        .iter()
+        .find(|a| a.name == "rename")
+        .and_then(|a| a.arg("name"))
+    {
+        Some(ast::AnnotationValue::String(name)) => name.clone(),
+        _ => field.name.to_string(),
+    }
+}
+
+/// Encodes `value`, the JSON of an instance of the type `id`, and appends it to `out`.
+pub fn encode(
+    compiler: &IRCompiler,
+    id: usize,
+    value: &Value,
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    match &compiler.named_type(id).type_ {
+        IRType::Struct(struct_type) => {
+            let Value::Object(object) = value else {
+                return Err(format!("expected an object, got {}", value));
+            };
+            for field in struct_type.fields.iter() {
+                let member = object.get(&json_name(field));
+                if field.optional {
+                    out.push(member.is_some() as u8);
+                }
+                match member {
+                    Some(member) => encode_primitive(compiler, &field.type_, member, out)?,
+                    None if field.optional => {}
+                    None => return Err(format!("missing field `{}`", json_name(field))),
+                }
+            }
+            Ok(())
+        }
+        IRType::Variant(variant_type) => {
+            let tag = value.get("type").and_then(Value::as_str);
+            let Some((index, option)) = variant_type
+                .variants
+                .iter()
+                .enumerate()
+                .find(|(_, option)| Some(option.name.as_str()) == tag)
+            else {
+                return Err(format!("expected an option of the variant, got {}", value));
+            };
+            write_varint(out, index as u64);
+            let payload = value.get("value").unwrap_or(&Value::Null);
+            encode_primitive(compiler, &option.type_, payload, out)
+        }
+        IRType::Reference(target) => encode(compiler, *target, value, out),
+        IRType::Builtin(builtin) => encode_builtin(compiler, builtin, value, out),
+    }
+}
+
+fn encode_primitive(
+    compiler: &IRCompiler,
+    t: &PrimitiveType,
+    value: &Value,
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    match t {
+        PrimitiveType::Reference(id) => encode(compiler, *id, value, out),
+        PrimitiveType::Builtin(builtin) => encode_builtin(compiler, builtin, value, out),
+    }
+}
+
+fn encode_builtin(
+    compiler: &IRCompiler,
+    builtin: &ast::Builtin,
+    value: &Value,
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    let mismatch = || format!("expected {}, got {}", builtin, value);
+    match builtin {
+        ast::Builtin::Int => {
+            let value = value.as_i64().ok_or_else(mismatch)?;
+            write_varint(out, ((value << 1) ^ (value >> 63)) as u64);
+        }
+        ast::Builtin::Float => {
+            let value = value.as_f64().ok_or_else(mismatch)?;
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        ast::Builtin::Bool => out.push(value.as_bool().ok_or_else(mismatch)? as u8),
+        ast::Builtin::String => {
+            let value = value.as_str().ok_or_else(mismatch)?;
+            write_varint(out, value.len() as u64);
+            out.extend_from_slice(value.as_bytes());
+        }
+        ast::Builtin::Unit => {}
+        ast::Builtin::Custom(_) => {
+            let representation = compiler.builtins.representation(builtin);
+            return encode_builtin(compiler, &representation, value, out);
+        }
+    }
+    Ok(())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// `bytes` as lowercase hex, the way the fixtures store them.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
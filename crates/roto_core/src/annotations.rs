@@ -0,0 +1,278 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    ast::{Annotation, AnnotationValue, SourceLocation},
+    diagnostics::Diagnostic,
+};
+
+/// Regular expressions of the common string formats of `@format`, in the names of JSON Schema,
+/// for backends that can only check strings against a pattern.
+const FORMAT_PATTERNS: [(&str, &str); 7] = [
+    ("date", r"^\d{4}-\d{2}-\d{2}$"),
+    (
+        "date-time",
+        r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:\d{2})$",
+    ),
+    ("email", r"^[^@\s]+@[^@\s]+\.[^@\s]+$"),
+    ("ipv4", r"^(\d{1,3}\.){3}\d{1,3}$"),
+    ("time", r"^\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:\d{2})?$"),
+    ("uri", r"^[A-Za-z][A-Za-z0-9+.-]*:\S*$"),
+    (
+        "uuid",
+        r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$",
+    ),
+];
+
+/// The regular expression that strings in `format` match, if it is one of the common formats.
+/// Other formats are only hints for documentation and schemas.
+pub fn format_pattern(format: &str) -> Option<&'static str> {
+    FORMAT_PATTERNS
+        .iter()
+        .find(|(name, _)| *name == format)
+        .map(|(_, pattern)| *pattern)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentKind {
+    Ident,
+    Int,
+    String,
+    Any,
+}
+
+impl ArgumentKind {
+    fn accepts(&self, value: &AnnotationValue) -> bool {
+        matches!(
+            (self, value),
+            (ArgumentKind::Any, _)
+                | (ArgumentKind::Ident, AnnotationValue::Ident(_))
+                | (ArgumentKind::Int, AnnotationValue::Int(_))
+                | (ArgumentKind::String, AnnotationValue::String(_))
+        )
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            ArgumentKind::Ident => "an identifier",
+            ArgumentKind::Int => "an integer",
+            ArgumentKind::String => "a string",
+            ArgumentKind::Any => "a value",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArgumentSpec {
+    pub name: String,
+    pub kind: ArgumentKind,
+    pub required: bool,
+}
+
+/// Declares an annotation and the arguments it accepts.
+#[derive(Debug, Clone)]
+pub struct AnnotationSpec {
+    pub name: String,
+    pub args: Vec<ArgumentSpec>,
+    /// Whether a declaration or member can have the annotation more than once.
+    pub repeatable: bool,
+    /// Annotations that contradict this one on the same declaration or member.
+    pub conflicts: Vec<String>,
+}
+
+impl AnnotationSpec {
+    pub fn new(name: &str) -> Self {
+        AnnotationSpec {
+            name: name.to_string(),
+            args: Vec::new(),
+            repeatable: false,
+            conflicts: Vec::new(),
+        }
+    }
+
+    pub fn arg(mut self, name: &str, kind: ArgumentKind) -> Self {
+        self.args.push(ArgumentSpec {
+            name: name.to_string(),
+            kind,
+            required: true,
+        });
+        self
+    }
+
+    pub fn optional_arg(mut self, name: &str, kind: ArgumentKind) -> Self {
+        self.args.push(ArgumentSpec {
+            name: name.to_string(),
+            kind,
+            required: false,
+        });
+        self
+    }
+
+    pub fn repeatable(mut self) -> Self {
+        self.repeatable = true;
+        self
+    }
+
+    /// Declares that `name` contradicts this annotation. Conflicts are symmetric, declaring them
+    /// on either annotation is enough.
+    pub fn conflicts_with(mut self, name: &str) -> Self {
+        self.conflicts.push(name.to_string());
+        self
+    }
+}
+
+/// The set of annotations known to the frontend and the active backends. Unknown annotations are
+/// reported as warnings, malformed uses of known annotations as errors.
+#[derive(Debug, Clone)]
+pub struct AnnotationRegistry {
+    specs: BTreeMap<String, AnnotationSpec>,
+}
+
+impl Default for AnnotationRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnnotationRegistry {
+    pub fn new() -> Self {
+        AnnotationRegistry {
+            specs: BTreeMap::new(),
+        }
+    }
+
+    /// A registry containing the annotations that are understood by all backends.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(AnnotationSpec::new("rename").arg("name", ArgumentKind::String));
+        registry.register(
+            AnnotationSpec::new("deprecated").optional_arg("reason", ArgumentKind::String),
+        );
+        registry.register(AnnotationSpec::new("tag").arg("value", ArgumentKind::Int));
+        // Whether an alias is written where it is used, or as a distinct type where the target
+        // language has them.
+        registry.register(AnnotationSpec::new("inline"));
+        registry.register(AnnotationSpec::new("opaque").conflicts_with("inline"));
+        // The format of the values of an alias, like `email`, see `format_pattern`. Inlined
+        // aliases have no declaration to keep it.
+        registry.register(
+            AnnotationSpec::new("format")
+                .arg("value", ArgumentKind::String)
+                .conflicts_with("inline"),
+        );
+        // The version of a type, see `migrate`.
+        registry.register(AnnotationSpec::new("version").arg("value", ArgumentKind::Int));
+        registry
+    }
+
+    pub fn register(&mut self, spec: AnnotationSpec) {
+        self.specs.insert(spec.name.clone(), spec);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AnnotationSpec> {
+        self.specs.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AnnotationSpec> {
+        self.specs.values()
+    }
+
+    pub fn validate(
+        &self,
+        annotation: &Annotation,
+        location: Option<&SourceLocation>,
+    ) -> Vec<Diagnostic> {
+        let Some(spec) = self.get(&annotation.name) else {
+            return vec![Diagnostic::warning(
+                format!("unknown annotation @{}", annotation.name),
+                location.cloned(),
+            )
+            .with_code("unknown-annotation")];
+        };
+
+        let mut diagnostics = Vec::new();
+        for (name, value) in &annotation.args {
+            match spec.args.iter().find(|a| &a.name == name) {
+                None => diagnostics.push(
+                    Diagnostic::error(
+                        format!("@{} has no argument named {}", spec.name, name),
+                        location.cloned(),
+                    )
+                    .with_code("unknown-argument"),
+                ),
+                Some(arg) if !arg.kind.accepts(value) => diagnostics.push(
+                    Diagnostic::error(
+                        format!(
+                            "argument {} of @{} must be {}, found {}",
+                            name,
+                            spec.name,
+                            arg.kind.describe(),
+                            value
+                        ),
+                        location.cloned(),
+                    )
+                    .with_code("invalid-argument"),
+                ),
+                Some(_) => {}
+            }
+        }
+        for arg in spec.args.iter().filter(|a| a.required) {
+            if annotation.arg(&arg.name).is_none() {
+                diagnostics.push(
+                    Diagnostic::error(
+                        format!("@{} is missing the argument {}", spec.name, arg.name),
+                        location.cloned(),
+                    )
+                    .with_code("missing-argument"),
+                );
+            }
+        }
+        diagnostics
+    }
+
+    /// Checks the combination of `annotations` on the same declaration or member, `what`, like
+    /// `User` or `User.name`: known annotations that are not repeatable may only appear once, and
+    /// conflicting annotations not together.
+    pub fn validate_combination(
+        &self,
+        what: &str,
+        annotations: &[Annotation],
+        location: Option<&SourceLocation>,
+    ) -> Vec<Diagnostic> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for annotation in annotations {
+            *counts.entry(&annotation.name).or_default() += 1;
+        }
+        let mut diagnostics = Vec::new();
+        for (name, count) in &counts {
+            let repeatable = self.get(name).is_none_or(|spec| spec.repeatable);
+            if *count > 1 && !repeatable {
+                diagnostics.push(
+                    Diagnostic::error(
+                        format!("@{} is repeated on {}", name, what),
+                        location.cloned(),
+                    )
+                    .with_code("duplicate-annotation"),
+                );
+            }
+        }
+        let names: Vec<_> = counts.into_keys().collect();
+        for (i, a) in names.iter().enumerate() {
+            for b in &names[i + 1..] {
+                let declared = |x: &str, y: &str| {
+                    self.get(x)
+                        .is_some_and(|spec| spec.conflicts.iter().any(|c| c == y))
+                };
+                if declared(a, b) || declared(b, a) {
+                    diagnostics.push(
+                        Diagnostic::error(
+                            format!("{} cannot be both @{} and @{}", what, a, b),
+                            location.cloned(),
+                        )
+                        .with_code("conflicting-annotations"),
+                    );
+                }
+            }
+        }
+        diagnostics
+    }
+}
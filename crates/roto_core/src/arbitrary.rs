@@ -0,0 +1,385 @@
+//! Strategies for property based testing of the compiler and the backends, behind the `proptest`
+//! feature.
+//!
+//! The `Arbitrary` implementations produce syntax trees that survive formatting and parsing, but
+//! may refer to undeclared types. [`program`] produces programs that also compile: every
+//! reference points at an earlier declaration and every member is tagged, so that each backend
+//! can generate code for them.
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use crate::ast::{
+    Annotation, AnnotationValue, Builtin, ConditionalTypeExpression, Item, ModuleDeclaration, Span,
+    StructField, StructTypeExpression, TypeAliasDeclaration, TypeExpression, UseDeclaration,
+    VariantOption, VariantTypeExpression, Visibility,
+};
+use crate::intern::Atom;
+use crate::syntax::KEYWORDS;
+
+/// A lowercase identifier that is not a keyword.
+pub fn identifier() -> impl Strategy<Value = String> {
+    "[a-z][a-zA-Z0-9_]{0,7}".prop_filter("keywords are not identifiers", |name| {
+        !KEYWORDS.contains(&name.as_str())
+    })
+}
+
+/// A dotted path of one to three identifiers, like `auth.User`.
+pub fn path() -> impl Strategy<Value = String> {
+    prop::collection::vec(identifier(), 1..=3).prop_map(|segments| segments.join("."))
+}
+
+/// A comment of one or two lines. Comments are trimmed when they are parsed, so the lines are
+/// words separated by single spaces.
+pub fn comment() -> impl Strategy<Value = String> {
+    prop::collection::vec("[a-zA-Z0-9]{1,8}( [a-zA-Z0-9]{1,8}){0,4}", 1..=2)
+        .prop_map(|lines| lines.join("\n"))
+}
+
+fn visibility() -> impl Strategy<Value = Visibility> {
+    prop_oneof![Just(Visibility::Private), Just(Visibility::Public)]
+}
+
+impl Arbitrary for Builtin {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Builtin>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            Just(Builtin::Int),
+            Just(Builtin::Float),
+            Just(Builtin::String),
+            Just(Builtin::Bool),
+            Just(Builtin::Unit),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for AnnotationValue {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<AnnotationValue>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            identifier().prop_map(AnnotationValue::Ident),
+            any::<i64>().prop_map(AnnotationValue::Int),
+            "[ -~]{0,12}".prop_map(AnnotationValue::String),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Annotation {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Annotation>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            identifier(),
+            prop::collection::vec((identifier(), any::<AnnotationValue>()), 0..3),
+        )
+            .prop_map(|(name, args)| Annotation { name, args })
+            .boxed()
+    }
+}
+
+fn annotations() -> impl Strategy<Value = Vec<Annotation>> {
+    prop::collection::vec(any::<Annotation>(), 0..2)
+}
+
+/// Names the members `prefix0`, `prefix1` and so on, as duplicate names are rejected.
+fn member_names(prefix: &str) -> impl Iterator<Item = String> + '_ {
+    (0..).map(move |i| format!("{}{}", prefix, i))
+}
+
+/// Type expressions that need no grouping, the only ones the grammar accepts to the right of
+/// `&`, `|` and `else`.
+fn atom(inner: BoxedStrategy<TypeExpression>) -> BoxedStrategy<TypeExpression> {
+    let field = (
+        inner.clone(),
+        any::<bool>(),
+        annotations(),
+        prop::option::of(comment()),
+    );
+    let option = (inner.clone(), annotations(), prop::option::of(comment()));
+    prop_oneof![
+        prop::collection::vec(field, 1..4).prop_map(|fields| {
+            TypeExpression::Struct(StructTypeExpression {
+                fields: fields
+                    .into_iter()
+                    .zip(member_names("field"))
+                    .map(
+                        |((type_, optional, annotations, comment), name)| StructField {
+                            name: name.into(),
+                            type_,
+                            optional,
+                            annotations,
+                            comment,
+                            location: None,
+                        },
+                    )
+                    .collect(),
+            })
+        }),
+        prop::collection::vec(option, 1..4).prop_map(|options| {
+            TypeExpression::Variant(VariantTypeExpression {
+                variants: options
+                    .into_iter()
+                    .zip(member_names("option"))
+                    .map(|((type_, annotations, comment), name)| VariantOption {
+                        name: name.into(),
+                        type_,
+                        annotations,
+                        comment,
+                        location: None,
+                    })
+                    .collect(),
+            })
+        }),
+        (
+            path(),
+            prop::collection::btree_map(identifier().prop_map(Atom::from), inner.clone(), 1..3)
+        )
+            .prop_map(|(name, args)| TypeExpression::Generic(name.into(), args)),
+        (inner.clone(), inner.clone(), inner, leaf().boxed()).prop_map(
+            |(left, right, then, otherwise)| {
+                TypeExpression::Conditional(ConditionalTypeExpression {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    then: Box::new(then),
+                    otherwise: Box::new(otherwise),
+                })
+            }
+        ),
+    ]
+    .boxed()
+}
+
+fn leaf() -> impl Strategy<Value = TypeExpression> {
+    prop_oneof![
+        any::<Builtin>().prop_map(TypeExpression::Builtin),
+        path().prop_map(|path| TypeExpression::Variable(path.into())),
+    ]
+}
+
+impl Arbitrary for TypeExpression {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<TypeExpression>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        leaf()
+            .prop_recursive(4, 32, 3, |inner| {
+                let atom = atom(inner.clone());
+                prop_oneof![
+                    2 => atom.clone(),
+                    1 => (inner.clone(), prop_oneof![leaf().boxed(), atom.clone()])
+                        .prop_map(|(left, right)| {
+                            TypeExpression::Intersection(Box::new(left), Box::new(right))
+                        }),
+                    1 => (inner, prop_oneof![leaf().boxed(), atom])
+                        .prop_map(|(left, right)| {
+                            TypeExpression::Union(Box::new(left), Box::new(right))
+                        }),
+                ]
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for TypeAliasDeclaration {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<TypeAliasDeclaration>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            prop::option::of(comment()),
+            annotations(),
+            visibility(),
+            identifier(),
+            prop::collection::vec(identifier(), 0..3),
+            any::<TypeExpression>(),
+        )
+            .prop_map(
+                |(doc, annotations, visibility, name, params, type_)| TypeAliasDeclaration {
+                    doc,
+                    annotations,
+                    visibility,
+                    name,
+                    params,
+                    type_,
+                    span: Span::default(),
+                },
+            )
+            .boxed()
+    }
+}
+
+impl Arbitrary for UseDeclaration {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<UseDeclaration>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            prop::option::of(comment()),
+            annotations(),
+            visibility(),
+            path(),
+        )
+            .prop_map(|(doc, annotations, visibility, path)| UseDeclaration {
+                doc,
+                annotations,
+                visibility,
+                path,
+                span: Span::default(),
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Item {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Item>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            3 => any::<TypeAliasDeclaration>().prop_map(Item::Type),
+            1 => any::<UseDeclaration>().prop_map(Item::Use),
+        ]
+        .prop_recursive(2, 16, 4, |inner| {
+            (
+                prop::option::of(comment()),
+                annotations(),
+                visibility(),
+                identifier(),
+                prop::collection::vec(inner, 0..4),
+            )
+                .prop_map(|(doc, annotations, visibility, name, items)| {
+                    Item::Module(ModuleDeclaration {
+                        doc,
+                        annotations,
+                        visibility,
+                        name,
+                        items,
+                        span: Span::default(),
+                    })
+                })
+        })
+        .boxed()
+    }
+}
+
+/// The structure of a declaration in [`program`], before references are resolved against the
+/// declarations before it. Shrinking a shape shrinks the type it becomes.
+#[derive(Debug, Clone)]
+enum Shape {
+    Builtin(Builtin),
+    Reference(Index),
+    Struct(Vec<(Shape, bool)>),
+    Variant(Vec<Shape>),
+}
+
+fn shape() -> impl Strategy<Value = Shape> {
+    prop_oneof![
+        any::<Builtin>().prop_map(Shape::Builtin),
+        any::<Index>().prop_map(Shape::Reference),
+    ]
+    .prop_recursive(3, 16, 3, |inner| {
+        prop_oneof![
+            prop::collection::vec((inner.clone(), any::<bool>()), 1..4).prop_map(Shape::Struct),
+            prop::collection::vec(inner, 1..4).prop_map(Shape::Variant),
+        ]
+    })
+}
+
+/// A declared type is always a struct or a variant, aliases of builtins are not supported by
+/// every backend.
+fn declaration_shape() -> impl Strategy<Value = Shape> {
+    prop_oneof![
+        prop::collection::vec((shape(), any::<bool>()), 1..5).prop_map(Shape::Struct),
+        prop::collection::vec(shape(), 1..5).prop_map(Shape::Variant),
+    ]
+}
+
+fn tag(value: usize) -> Vec<Annotation> {
+    vec![Annotation {
+        name: "tag".to_string(),
+        args: vec![("value".to_string(), AnnotationValue::Int(value as i64 + 1))],
+    }]
+}
+
+/// The type expression of `shape` in the declaration at `position`. References pick one of the
+/// declarations before it, or `int` in the first one.
+fn resolve(shape: Shape, position: usize) -> TypeExpression {
+    match shape {
+        Shape::Builtin(builtin) => TypeExpression::Builtin(builtin),
+        Shape::Reference(_) if position == 0 => TypeExpression::Builtin(Builtin::Int),
+        Shape::Reference(index) => {
+            TypeExpression::Variable(declaration_name(index.index(position)).into())
+        }
+        Shape::Struct(fields) => TypeExpression::Struct(StructTypeExpression {
+            fields: fields
+                .into_iter()
+                .zip(member_names("field"))
+                .enumerate()
+                .map(|(i, ((shape, optional), name))| StructField {
+                    name: name.into(),
+                    type_: resolve(shape, position),
+                    optional,
+                    annotations: tag(i),
+                    comment: None,
+                    location: None,
+                })
+                .collect(),
+        }),
+        Shape::Variant(options) => TypeExpression::Variant(VariantTypeExpression {
+            variants: options
+                .into_iter()
+                .zip(member_names("option"))
+                .enumerate()
+                .map(|(i, (shape, name))| VariantOption {
+                    name: name.into(),
+                    type_: resolve(shape, position),
+                    annotations: tag(i),
+                    comment: None,
+                    location: None,
+                })
+                .collect(),
+        }),
+    }
+}
+
+fn declaration_name(position: usize) -> String {
+    format!("Type{}", position)
+}
+
+/// Programs of one to `max_declarations` declarations, `Type0`, `Type1` and so on, that compile
+/// and that every backend generates code for.
+pub fn program_with(max_declarations: usize) -> impl Strategy<Value = Vec<Item>> {
+    prop::collection::vec(
+        (prop::option::of(comment()), declaration_shape()),
+        1..=max_declarations.max(1),
+    )
+    .prop_map(|declarations| {
+        declarations
+            .into_iter()
+            .enumerate()
+            .map(|(position, (doc, shape))| {
+                Item::Type(TypeAliasDeclaration {
+                    doc,
+                    annotations: Vec::new(),
+                    visibility: Visibility::Private,
+                    name: declaration_name(position),
+                    params: Vec::new(),
+                    type_: resolve(shape, position),
+                    span: Span::default(),
+                })
+            })
+            .collect()
+    })
+}
+
+/// Programs of up to eight declarations, see [`program_with`].
+pub fn program() -> impl Strategy<Value = Vec<Item>> {
+    program_with(8)
+}
@@ -0,0 +1,147 @@
+//! Hash-consed type expressions. An [`ExpressionArena`] stores every distinct type expression
+//! once, with its subexpressions replaced by the [`ExpressionId`]s they were interned as. Equal
+//! expressions get the same id, so expressions can be compared and hashed by id instead of by
+//! walking the whole tree.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    ast::{Annotation, Builtin, TypeExpression},
+    intern::Atom,
+};
+
+/// The id of a type expression in an [`ExpressionArena`]. Ids are only meaningful for the arena
+/// that handed them out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExpressionId(u32);
+
+/// A struct field whose type is interned.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FieldNode {
+    name: Atom,
+    type_: ExpressionId,
+    optional: bool,
+    annotations: Vec<Annotation>,
+    comment: Option<String>,
+}
+
+/// A variant option whose type is interned.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct OptionNode {
+    name: Atom,
+    type_: ExpressionId,
+    annotations: Vec<Annotation>,
+    comment: Option<String>,
+}
+
+/// A [`TypeExpression`] one level deep, its subexpressions are ids.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ExpressionNode {
+    Variable(Atom),
+    Builtin(Builtin),
+    Generic(Atom, Vec<(Atom, ExpressionId)>),
+    Struct(Vec<FieldNode>),
+    Variant(Vec<OptionNode>),
+    Intersection(ExpressionId, ExpressionId),
+    Union(ExpressionId, ExpressionId),
+    Conditional([ExpressionId; 4]),
+}
+
+/// Interns type expressions, see the [module documentation](self).
+#[derive(Debug, Default)]
+pub struct ExpressionArena {
+    ids: HashMap<ExpressionNode, ExpressionId>,
+    /// The interned expressions, by id.
+    nodes: Vec<ExpressionNode>,
+}
+
+impl ExpressionArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The id of `t`, the same one for every expression equal to `t`.
+    pub fn intern(&mut self, t: &TypeExpression) -> ExpressionId {
+        let node = match t {
+            TypeExpression::Variable(name) => ExpressionNode::Variable(*name),
+            TypeExpression::Builtin(builtin) => ExpressionNode::Builtin(builtin.clone()),
+            TypeExpression::Generic(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|(param, arg)| (*param, self.intern(arg)))
+                    .collect();
+                return self.intern_generic(*name, args);
+            }
+            TypeExpression::Struct(s) => ExpressionNode::Struct(
+                s.fields
+                    .iter()
+                    .map(|field| FieldNode {
+                        name: field.name,
+                        type_: self.intern(&field.type_),
+                        optional: field.optional,
+                        annotations: field.annotations.clone(),
+                        comment: field.comment.clone(),
+                    })
+                    .collect(),
+            ),
+            TypeExpression::Variant(v) => ExpressionNode::Variant(
+                v.variants
+                    .iter()
+                    .map(|option| OptionNode {
+                        name: option.name,
+                        type_: self.intern(&option.type_),
+                        annotations: option.annotations.clone(),
+                        comment: option.comment.clone(),
+                    })
+                    .collect(),
+            ),
+            TypeExpression::Intersection(left, right) => {
+                ExpressionNode::Intersection(self.intern(left), self.intern(right))
+            }
+            TypeExpression::Union(left, right) => {
+                ExpressionNode::Union(self.intern(left), self.intern(right))
+            }
+            TypeExpression::Conditional(c) => ExpressionNode::Conditional([
+                self.intern(&c.left),
+                self.intern(&c.right),
+                self.intern(&c.then),
+                self.intern(&c.otherwise),
+            ]),
+        };
+        self.insert(node)
+    }
+
+    /// The id of the instantiation of `name` with the interned `args`, ordered by parameter, the
+    /// same one [`ExpressionArena::intern`] gives it. Its arguments are not walked again.
+    pub fn intern_generic(&mut self, name: Atom, args: Vec<(Atom, ExpressionId)>) -> ExpressionId {
+        self.insert(ExpressionNode::Generic(name, args))
+    }
+
+    /// The ids of the arguments of the instantiation with `id`, by parameter. Other expressions
+    /// have none.
+    pub fn arguments(&self, id: ExpressionId) -> BTreeMap<Atom, ExpressionId> {
+        match &self.nodes[id.0 as usize] {
+            ExpressionNode::Generic(_, args) => args.iter().copied().collect(),
+            _ => BTreeMap::new(),
+        }
+    }
+
+    fn insert(&mut self, node: ExpressionNode) -> ExpressionId {
+        if let Some(id) = self.ids.get(&node) {
+            return *id;
+        }
+        let id = ExpressionId(self.nodes.len() as u32);
+        self.nodes.push(node.clone());
+        self.ids.insert(node, id);
+        id
+    }
+
+    /// The number of distinct expressions interned so far.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
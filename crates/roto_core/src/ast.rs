@@ -2,45 +2,268 @@ use core::fmt;
 use std::{
     collections::BTreeMap,
     fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
 };
 
+use crate::builtins::BuiltinId;
+use crate::format::format_type;
+use crate::intern::Atom;
+
+/// A byte range in a source file.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A human readable range in a named source file. Lines and columns are 1-based, the end is
+/// exclusive.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// The 1-based line and column of a byte offset into `source`.
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    (line, column)
+}
+
+impl SourceLocation {
+    /// Converts a byte offset into `source` into an empty range at that position.
+    pub fn from_offset(file: &str, source: &str, offset: usize) -> SourceLocation {
+        SourceLocation::from_span(
+            file,
+            source,
+            Span {
+                start: offset,
+                end: offset,
+            },
+        )
+    }
+
+    /// Converts a byte range of `source` into lines and columns.
+    pub fn from_span(file: &str, source: &str, span: Span) -> SourceLocation {
+        let (line, column) = line_and_column(source, span.start);
+        let (end_line, end_column) = line_and_column(source, span.end);
+        SourceLocation {
+            file: file.to_string(),
+            line,
+            column,
+            end_line,
+            end_column,
+        }
+    }
+}
+
+/// The byte offset of every line of a source file, to convert many offsets into lines and
+/// columns without scanning the source from its start for each of them.
+pub struct LineIndex<'a> {
+    file: &'a str,
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(file: &'a str, source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex {
+            file,
+            source,
+            starts,
+        }
+    }
+
+    fn line_and_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.starts.partition_point(|&start| start <= offset);
+        (line, offset - self.starts[line - 1] + 1)
+    }
+
+    /// Like [`SourceLocation::from_span`], for a byte range of the indexed source.
+    pub fn location(&self, span: Span) -> SourceLocation {
+        let (line, column) = self.line_and_column(span.start);
+        let (end_line, end_column) = self.line_and_column(span.end);
+        SourceLocation {
+            file: self.file.to_string(),
+            line,
+            column,
+            end_line,
+            end_column,
+        }
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnnotationValue {
+    Ident(String),
+    Int(i64),
+    String(String),
+}
+
+impl Display for AnnotationValue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            AnnotationValue::Ident(ident) => write!(f, "{}", ident),
+            AnnotationValue::Int(value) => write!(f, "{}", value),
+            AnnotationValue::String(value) => {
+                write!(
+                    f,
+                    "\"{}\"",
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Annotation {
     pub name: String,
-    pub args: Vec<(String, String)>
+    pub args: Vec<(String, AnnotationValue)>,
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+impl Annotation {
+    pub fn arg(&self, name: &str) -> Option<&AnnotationValue> {
+        self.args.iter().find(|(k, _)| k == name).map(|(_, v)| v)
+    }
+}
+
+impl Display for Annotation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "@{}", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, "(")?;
+            for (i, (k, v)) in self.args.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}={}", k, v)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// A field of a struct. Its location is where it was declared, not part of the field, and is not
+/// compared or hashed, so that equal types declared in different places are still equal.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StructField {
-    pub name: String,
+    pub name: Atom,
     pub type_: TypeExpression,
+    pub optional: bool,
+    pub annotations: Vec<Annotation>,
     pub comment: Option<String>,
+    pub location: Option<SourceLocation>,
+}
+
+impl PartialEq for StructField {
+    fn eq(&self, other: &StructField) -> bool {
+        self.name == other.name
+            && self.type_ == other.type_
+            && self.optional == other.optional
+            && self.annotations == other.annotations
+            && self.comment == other.comment
+    }
+}
+
+impl Eq for StructField {}
+
+impl Hash for StructField {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.type_.hash(state);
+        self.optional.hash(state);
+        self.annotations.hash(state);
+        self.comment.hash(state);
+    }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StructTypeExpression {
     pub fields: Vec<StructField>,
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+/// An option of a variant. Like the location of a [`StructField`], its location is not compared
+/// or hashed.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VariantOption {
-    pub name: String,
+    pub name: Atom,
     pub type_: TypeExpression,
+    pub annotations: Vec<Annotation>,
     pub comment: Option<String>,
+    pub location: Option<SourceLocation>,
+}
+
+impl PartialEq for VariantOption {
+    fn eq(&self, other: &VariantOption) -> bool {
+        self.name == other.name
+            && self.type_ == other.type_
+            && self.annotations == other.annotations
+            && self.comment == other.comment
+    }
+}
+
+impl Eq for VariantOption {}
+
+impl Hash for VariantOption {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.type_.hash(state);
+        self.annotations.hash(state);
+        self.comment.hash(state);
+    }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VariantTypeExpression {
     pub variants: Vec<VariantOption>,
 }
 
+/// `if left == right then then else otherwise`, evaluated when the type is compiled.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConditionalTypeExpression {
+    pub left: Box<TypeExpression>,
+    pub right: Box<TypeExpression>,
+    pub then: Box<TypeExpression>,
+    pub otherwise: Box<TypeExpression>,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Builtin {
     Int,
     Float,
     String,
     Bool,
     Unit,
+    /// A builtin registered by an embedder, see [`crate::builtins`]. The parser never produces
+    /// it, names of registered builtins are resolved to it with the other names.
+    Custom(BuiltinId),
 }
 
 impl Display for Builtin {
@@ -51,53 +274,60 @@ impl Display for Builtin {
             Builtin::String => write!(f, "string"),
             Builtin::Bool => write!(f, "bool"),
             Builtin::Unit => write!(f, "unit"),
+            Builtin::Custom(id) => write!(f, "{}", id),
         }
     }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypeExpression {
-    Variable(String),
+    Variable(Atom),
     Builtin(Builtin),
-    Generic(String, BTreeMap<String, TypeExpression>),
+    Generic(Atom, BTreeMap<Atom, TypeExpression>),
     Struct(StructTypeExpression),
     Variant(VariantTypeExpression),
     Intersection(Box<TypeExpression>, Box<TypeExpression>),
+    Union(Box<TypeExpression>, Box<TypeExpression>),
+    Conditional(ConditionalTypeExpression),
 }
 
 impl StructField {
-    pub fn unify(&self, mapping: &BTreeMap<String, TypeExpression>) -> StructField {
+    pub fn unify(&self, mapping: &BTreeMap<Atom, TypeExpression>) -> StructField {
         StructField {
-            name: self.name.clone(),
+            name: self.name,
             type_: self.type_.unify(mapping),
+            optional: self.optional,
+            annotations: self.annotations.clone(),
             comment: self.comment.clone(),
+            location: self.location.clone(),
         }
     }
 }
 
 impl VariantOption {
-    pub fn unify(&self, mapping: &BTreeMap<String, TypeExpression>) -> VariantOption {
+    pub fn unify(&self, mapping: &BTreeMap<Atom, TypeExpression>) -> VariantOption {
         VariantOption {
-            name: self.name.clone(),
+            name: self.name,
             type_: self.type_.unify(mapping),
+            annotations: self.annotations.clone(),
             comment: self.comment.clone(),
+            location: self.location.clone(),
         }
     }
 }
 
 impl TypeExpression {
-    pub fn unify(&self, mapping: &BTreeMap<String, TypeExpression>) -> TypeExpression {
+    pub fn unify(&self, mapping: &BTreeMap<Atom, TypeExpression>) -> TypeExpression {
         match self {
             TypeExpression::Variable(name) => mapping
                 .get(name)
                 .cloned()
-                .unwrap_or_else(|| TypeExpression::Variable(name.clone())),
+                .unwrap_or(TypeExpression::Variable(*name)),
             TypeExpression::Builtin(_) => self.clone(),
             TypeExpression::Generic(name, args) => TypeExpression::Generic(
-                name.clone(),
-                args.iter()
-                    .map(|(k, v)| (k.clone(), v.unify(mapping)))
-                    .collect(),
+                *name,
+                args.iter().map(|(k, v)| (*k, v.unify(mapping))).collect(),
             ),
             TypeExpression::Struct(struct_type) => TypeExpression::Struct(StructTypeExpression {
                 fields: struct_type
@@ -118,14 +348,190 @@ impl TypeExpression {
             TypeExpression::Intersection(a, b) => {
                 TypeExpression::Intersection(Box::new(a.unify(mapping)), Box::new(b.unify(mapping)))
             }
+            TypeExpression::Union(a, b) => {
+                TypeExpression::Union(Box::new(a.unify(mapping)), Box::new(b.unify(mapping)))
+            }
+            TypeExpression::Conditional(c) => {
+                TypeExpression::Conditional(ConditionalTypeExpression {
+                    left: Box::new(c.left.unify(mapping)),
+                    right: Box::new(c.right.unify(mapping)),
+                    then: Box::new(c.then.unify(mapping)),
+                    otherwise: Box::new(c.otherwise.unify(mapping)),
+                })
+            }
+        }
+    }
+}
+
+/// The canonical spelling of `t`: nested intersections and unions are flattened, the operands of
+/// unions are ordered by how they are formatted and identical members of a union are kept once,
+/// so equivalent spellings of a union compile to the same IR. The operands of intersections keep
+/// their order, which is the order of the fields and options of the intersected type, and so of
+/// their position in encodings that do not name them.
+///
+/// References are compared by how they are spelled, so `t` is expected to be qualified and
+/// instantiated, see [`crate::frontend::IRCompiler`].
+pub fn normalize(t: &TypeExpression) -> TypeExpression {
+    match t {
+        TypeExpression::Variable(_) | TypeExpression::Builtin(_) => t.clone(),
+        TypeExpression::Generic(name, args) => TypeExpression::Generic(
+            *name,
+            args.iter().map(|(k, v)| (*k, normalize(v))).collect(),
+        ),
+        TypeExpression::Struct(struct_type) => TypeExpression::Struct(StructTypeExpression {
+            fields: struct_type
+                .fields
+                .iter()
+                .map(|field| StructField {
+                    type_: normalize(&field.type_),
+                    ..field.clone()
+                })
+                .collect(),
+        }),
+        TypeExpression::Variant(variant_type) => TypeExpression::Variant(VariantTypeExpression {
+            variants: variant_type
+                .variants
+                .iter()
+                .map(|option| VariantOption {
+                    type_: normalize(&option.type_),
+                    ..option.clone()
+                })
+                .collect(),
+        }),
+        TypeExpression::Intersection(..) => {
+            let mut operands = Vec::new();
+            collect_operands(t, false, &mut operands);
+            rebuild(operands, TypeExpression::Intersection)
+        }
+        TypeExpression::Union(..) => {
+            let mut operands = Vec::new();
+            collect_operands(t, true, &mut operands);
+            operands.sort_by(|a, b| a.0.cmp(&b.0));
+            operands.dedup_by(|a, b| a.0 == b.0);
+            rebuild(operands, TypeExpression::Union)
+        }
+        TypeExpression::Conditional(c) => TypeExpression::Conditional(ConditionalTypeExpression {
+            left: Box::new(normalize(&c.left)),
+            right: Box::new(normalize(&c.right)),
+            then: Box::new(normalize(&c.then)),
+            otherwise: Box::new(normalize(&c.otherwise)),
+        }),
+    }
+}
+
+/// Collects the normalized operands of the chain of unions, or of intersections, `t`, together
+/// with their formatting.
+fn collect_operands(t: &TypeExpression, union: bool, out: &mut Vec<(String, TypeExpression)>) {
+    match t {
+        TypeExpression::Intersection(a, b) if !union => {
+            collect_operands(a, union, out);
+            collect_operands(b, union, out);
+        }
+        TypeExpression::Union(a, b) if union => {
+            collect_operands(a, union, out);
+            collect_operands(b, union, out);
+        }
+        _ => {
+            let t = normalize(t);
+            out.push((format_type(&t), t));
         }
     }
 }
 
+/// Joins `operands` from the left with `operator`.
+fn rebuild(
+    operands: Vec<(String, TypeExpression)>,
+    operator: fn(Box<TypeExpression>, Box<TypeExpression>) -> TypeExpression,
+) -> TypeExpression {
+    operands
+        .into_iter()
+        .map(|(_, t)| t)
+        .reduce(|a, b| operator(Box::new(a), Box::new(b)))
+        .expect("operators have operands")
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Visibility {
+    /// Visible in the declaring module and its submodules.
+    #[default]
+    Private,
+    Public,
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeAliasDeclaration {
+    pub doc: Option<String>,
     pub annotations: Vec<Annotation>,
+    pub visibility: Visibility,
     pub name: String,
     pub params: Vec<String>,
     pub type_: TypeExpression,
+    pub span: Span,
+}
+
+/// `use auth.User;` makes `auth.User` available as `User` in the declaring module. With `pub`,
+/// other modules can refer to it through the declaring module as well.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UseDeclaration {
+    pub doc: Option<String>,
+    pub annotations: Vec<Annotation>,
+    pub visibility: Visibility,
+    pub path: String,
+    pub span: Span,
+}
+
+impl UseDeclaration {
+    /// The name under which the used symbol is available.
+    pub fn name(&self) -> &str {
+        self.path.rsplit('.').next().unwrap_or(&self.path)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleDeclaration {
+    pub doc: Option<String>,
+    pub annotations: Vec<Annotation>,
+    pub visibility: Visibility,
+    pub name: String,
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Item {
+    Type(TypeAliasDeclaration),
+    Use(UseDeclaration),
+    Module(ModuleDeclaration),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The type of the only declaration in `source`.
+    fn type_of(source: &str) -> TypeExpression {
+        match crate::parse(source, "test.roto").unwrap().pop() {
+            Some(Item::Type(decl)) => decl.type_,
+            item => panic!("expected a type declaration, found {:?}", item),
+        }
+    }
+
+    #[test]
+    fn unions_are_ordered_and_deduplicated() {
+        assert_eq!(
+            normalize(&type_of("type T = c | a | b | a;")),
+            normalize(&type_of("type T = a | b | c;")),
+        );
+    }
+
+    #[test]
+    fn intersections_are_flattened_in_order() {
+        let t = normalize(&type_of("type T = c & a & b;"));
+        assert_eq!(format_type(&t), "c & a & b");
+    }
 }
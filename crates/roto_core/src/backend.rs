@@ -0,0 +1,1456 @@
+use core::fmt;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, trace};
+
+use crate::ast;
+use crate::intern;
+use crate::ir::{IRType, NamedIRType, PrimitiveType, TypeName};
+use crate::output::{write_all, DiskSink};
+use crate::{
+    annotations::AnnotationRegistry, diagnostics::Diagnostic, frontend::IRCompiler, lint::Rule,
+};
+
+/// The schema can not be represented in the target language.
+#[derive(Debug, Clone)]
+pub struct BackendError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl BackendError {
+    pub fn new(diagnostics: Vec<Diagnostic>) -> Self {
+        BackendError { diagnostics }
+    }
+}
+
+impl Display for BackendError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+/// A generated file, with a path relative to the output directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFile {
+    pub path: String,
+    pub contents: String,
+    /// The declarations some lines of `contents` were generated from, ordered by line. Only
+    /// backends that build their output with [`MappedText`] record them.
+    pub source_map: Vec<SourceMapping>,
+}
+
+impl OutputFile {
+    pub fn new(path: impl Into<String>, contents: impl Into<String>) -> Self {
+        OutputFile {
+            path: path.into(),
+            contents: contents.into(),
+            source_map: Vec::new(),
+        }
+    }
+}
+
+/// A line of a generated file and the declaration in a schema it was generated from, so that
+/// errors in generated code can be traced back to the schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapping {
+    /// The 1-based line in the generated file.
+    pub line: usize,
+    pub location: ast::SourceLocation,
+}
+
+/// Generated text that remembers which of its lines were generated from which declaration, for
+/// backends that assemble a file from pieces. Mappings move along when pieces are appended or
+/// joined.
+#[derive(Debug, Clone, Default)]
+pub struct MappedText {
+    text: String,
+    source_map: Vec<SourceMapping>,
+    /// The number of line breaks in `text`.
+    line_breaks: usize,
+}
+
+impl MappedText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.line_breaks += text.matches('\n').count();
+        self.text.push_str(text);
+    }
+
+    /// Appends `text`, of which the line it starts on was generated from `location`.
+    pub fn push_mapped(&mut self, text: &str, location: Option<&ast::SourceLocation>) {
+        if let Some(location) = location {
+            self.source_map.push(SourceMapping {
+                line: self.line_breaks + 1,
+                location: location.clone(),
+            });
+        }
+        self.push_str(text);
+    }
+
+    /// Appends `other`, which continues the current line.
+    pub fn append(&mut self, other: MappedText) {
+        let offset = self.line_breaks;
+        self.source_map
+            .extend(other.source_map.into_iter().map(|mapping| SourceMapping {
+                line: mapping.line + offset,
+                ..mapping
+            }));
+        self.text.push_str(&other.text);
+        self.line_breaks += other.line_breaks;
+    }
+
+    /// Like [`slice::join`], for text with mappings.
+    pub fn join(pieces: Vec<MappedText>, separator: &str) -> MappedText {
+        let mut result = MappedText::new();
+        for (i, piece) in pieces.into_iter().enumerate() {
+            if i > 0 {
+                result.push_str(separator);
+            }
+            result.append(piece);
+        }
+        result
+    }
+
+    pub fn into_file(self, path: impl Into<String>) -> OutputFile {
+        OutputFile {
+            path: path.into(),
+            contents: self.text,
+            source_map: self.source_map,
+        }
+    }
+}
+
+impl From<&str> for MappedText {
+    fn from(text: &str) -> Self {
+        let mut result = MappedText::new();
+        result.push_str(text);
+        result
+    }
+}
+
+impl From<String> for MappedText {
+    fn from(text: String) -> Self {
+        MappedText::from(text.as_str())
+    }
+}
+
+/// Writes `files` below `directory`, creating missing directories on the way. Returns the paths
+/// that were written.
+pub fn write_files(directory: &Path, files: &[OutputFile]) -> io::Result<Vec<PathBuf>> {
+    let mut sink = DiskSink::default();
+    write_all(&mut sink, directory, files)?;
+    Ok(sink.written)
+}
+
+/// Writes `contents` to `path`, creating missing directories on the way.
+pub fn write_file(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)
+}
+
+/// A schema a file was generated from, identified by a hash of its contents like
+/// `sha256:9f86d0…`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderSource {
+    pub file: String,
+    pub hash: String,
+}
+
+/// The comment generated files start with, to show where they come from and that they should
+/// not be edited by hand.
+#[derive(Debug, Clone, Default)]
+pub struct FileHeader {
+    /// Free text, like a license notice. Every line becomes a comment line.
+    pub banner: Option<String>,
+    pub sources: Vec<HeaderSource>,
+    pub tool_version: bool,
+    /// The `Code generated … DO NOT EDIT.` line that editors and review tools recognize.
+    pub do_not_edit: bool,
+}
+
+/// The start and end of a line comment in the file at `path`, by extension. JSON has no
+/// comments.
+fn comment_syntax(path: &str) -> Option<(&'static str, &'static str)> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    match extension {
+        "py" | "yaml" | "yml" | "toml" => Some(("# ", "")),
+        "ts" | "rs" | "proto" | "thrift" | "cs" | "h" | "hpp" | "cpp" | "cc" | "dot" => {
+            Some(("// ", ""))
+        }
+        "puml" => Some(("' ", "")),
+        "md" | "html" => Some(("<!-- ", " -->")),
+        _ => None,
+    }
+}
+
+impl FileHeader {
+    pub fn is_empty(&self) -> bool {
+        self.banner.is_none() && self.sources.is_empty() && !self.tool_version && !self.do_not_edit
+    }
+
+    /// The text of the header, without comment markers.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.do_not_edit {
+            lines.push("Code generated by roto. DO NOT EDIT.".to_string());
+        }
+        if let Some(banner) = &self.banner {
+            lines.extend(banner.lines().map(str::to_string));
+        }
+        if self.tool_version {
+            lines.push(format!("roto {}", env!("CARGO_PKG_VERSION")));
+        }
+        for source in &self.sources {
+            lines.push(format!("source: {} ({})", source.file, source.hash));
+        }
+        lines
+    }
+
+    /// Starts every file of `files` with the header as comments. Files of formats without
+    /// comments, like JSON, are left as they are.
+    pub fn apply(&self, files: &mut [OutputFile]) {
+        if self.is_empty() {
+            return;
+        }
+        let lines = self.lines();
+        for file in files {
+            let Some((start, end)) = comment_syntax(&file.path) else {
+                debug!(
+                    path = file.path,
+                    "no comment syntax, leaving out the header"
+                );
+                continue;
+            };
+            let mut header = String::new();
+            for line in &lines {
+                let line = format!("{}{}{}", start, line, end);
+                header.push_str(line.trim_end());
+                header.push('\n');
+            }
+            header.push('\n');
+            file.contents.insert_str(0, &header);
+            for mapping in &mut file.source_map {
+                mapping.line += lines.len() + 1;
+            }
+        }
+    }
+}
+
+/// A code generator that turns compiled IR into source code of a target language.
+pub trait Backend {
+    /// Registers the annotations the backend understands, so they are not reported as unknown.
+    fn register_annotations(&self, _registry: &mut AnnotationRegistry) {}
+
+    /// The lint rules schemas have to follow for the backend to generate code, like
+    /// [`Rule::MissingTag`] for binary formats. `roto check` denies them when the backend is a
+    /// target, so they are reported before generating.
+    fn required_rules(&self) -> &'static [Rule] {
+        &[]
+    }
+
+    /// Generates code for all types that are reachable from the compiled globals. Most backends
+    /// produce a single file, but some split their output, e.g. by module.
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError>;
+
+    /// Like [`Backend::generate`], with the names of the generated types following `naming`.
+    /// Backends that name types with a [`TypeNameAllocator`] override this, the others fail
+    /// unless `naming` leaves names as they are.
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        if !naming.is_default() {
+            return Err(BackendError::new(vec![Diagnostic::error(
+                "this backend does not support the type naming options".to_string(),
+                None,
+            )
+            .with_code("unknown-option")]));
+        }
+        self.generate(compiler)
+    }
+}
+
+/// A part of the IR that not every target language can represent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Feature {
+    /// A builtin used as a field type or option payload, like `name: string`.
+    Builtin(ast::Builtin),
+    /// A named type that is nothing but a builtin, like `type Nothing = unit`.
+    BuiltinAlias(ast::Builtin),
+}
+
+/// The global `type_name` belongs to, which temporaries share with their owner.
+fn global_name(type_name: &TypeName) -> &str {
+    match type_name {
+        TypeName::Variable(name) | TypeName::Generic(name, _) => name,
+        TypeName::Temporary(owner, _) => global_name(owner),
+    }
+}
+
+/// Checks that every compiled type only uses features `supports` accepts, so backends fail with
+/// the offending type and its location instead of writing something that does not mean the same.
+/// `target` names the backend in the diagnostics.
+pub fn check_features(
+    compiler: &IRCompiler,
+    target: &str,
+    supports: impl Fn(&Feature) -> bool,
+) -> Result<(), BackendError> {
+    let mut diagnostics = Vec::new();
+    for (_id, named_type) in compiler.iter_types() {
+        let location = named_type.metadata.location.clone().or_else(|| {
+            compiler
+                .symbols()
+                .get_type(global_name(&named_type.name))
+                .and_then(|prototype| prototype.location.clone())
+        });
+        let mut unsupported = |subject: String, feature: Feature| {
+            if !supports(&feature) {
+                let (verb, builtin) = match &feature {
+                    Feature::Builtin(builtin) => ("uses", builtin),
+                    Feature::BuiltinAlias(builtin) => ("is an alias of", builtin),
+                };
+                diagnostics.push(
+                    Diagnostic::error(
+                        format!(
+                            "{} {} `{}`, which the {} backend does not support",
+                            subject, verb, builtin, target
+                        ),
+                        location.clone(),
+                    )
+                    .with_code("unsupported-feature"),
+                );
+            }
+        };
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                for field in &struct_type.fields {
+                    if let PrimitiveType::Builtin(builtin) = &field.type_ {
+                        let subject = format!("{}.{}", named_type.name, field.name);
+                        unsupported(subject, Feature::Builtin(builtin.clone()));
+                    }
+                }
+            }
+            IRType::Variant(variant_type) => {
+                for option in &variant_type.variants {
+                    if let PrimitiveType::Builtin(builtin) = &option.type_ {
+                        let subject = format!("{}.{}", named_type.name, option.name);
+                        unsupported(subject, Feature::Builtin(builtin.clone()));
+                    }
+                }
+            }
+            IRType::Builtin(builtin) => {
+                let subject = named_type.name.to_string();
+                unsupported(subject, Feature::BuiltinAlias(builtin.clone()));
+            }
+            IRType::Reference(_) => {}
+        }
+    }
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(BackendError::new(diagnostics))
+    }
+}
+
+/// The options a [`CodegenBackend`] is run with, given as `key=value` pairs like `frozen=true`.
+#[derive(Debug, Clone, Default)]
+pub struct BackendConfig {
+    options: BTreeMap<String, String>,
+}
+
+impl BackendConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.options.insert(key.into(), value.into());
+    }
+
+    /// Parses a `key=value` pair. A bare `key` is short for `key=true`.
+    pub fn set_pair(&mut self, pair: &str) {
+        match pair.split_once('=') {
+            Some((key, value)) => self.set(key, value),
+            None => self.set(pair, "true"),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    /// The value of a `true`/`false` option, which is false if it is not given.
+    pub fn flag(&self, key: &str) -> Result<bool, BackendError> {
+        match self.get(key) {
+            None | Some("false") => Ok(false),
+            Some("true") => Ok(true),
+            Some(value) => Err(Self::invalid(key, value, "true or false")),
+        }
+    }
+
+    /// The value of an option that is one of `choices`, if it is given.
+    pub fn choice<'c>(
+        &self,
+        key: &str,
+        choices: &[&'c str],
+    ) -> Result<Option<&'c str>, BackendError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(value) => match choices.iter().find(|choice| **choice == value) {
+                Some(choice) => Ok(Some(choice)),
+                None => Err(Self::invalid(key, value, &choices.join(", "))),
+            },
+        }
+    }
+
+    /// Fails if an option is not one of `known`, which catches typos.
+    pub fn expect_keys(&self, known: &[&str]) -> Result<(), BackendError> {
+        let unknown: Vec<_> = self
+            .options
+            .keys()
+            .filter(|key| !known.contains(&key.as_str()))
+            .map(|key| {
+                Diagnostic::error(format!("unknown backend option `{}`", key), None)
+                    .with_code("unknown-option")
+            })
+            .collect();
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(BackendError::new(unknown))
+        }
+    }
+
+    fn invalid(key: &str, value: &str, expected: &str) -> BackendError {
+        BackendError::new(vec![Diagnostic::error(
+            format!(
+                "invalid value `{}` for backend option `{}`, expected {}",
+                value, key, expected
+            ),
+            None,
+        )
+        .with_code("invalid-option")])
+    }
+}
+
+/// A backend that can be selected by name and configured with a [`BackendConfig`], like the
+/// targets of a command line tool.
+pub trait CodegenBackend {
+    /// The name the backend is selected by, like `py-msgspec`.
+    fn name(&self) -> &'static str;
+
+    /// Registers the annotations the backend understands, so they are not reported as unknown.
+    fn register_annotations(&self, _registry: &mut AnnotationRegistry) {}
+
+    /// See [`Backend::required_rules`].
+    fn required_rules(&self) -> &'static [Rule] {
+        &[]
+    }
+
+    fn generate(
+        &self,
+        compiler: &IRCompiler,
+        config: &BackendConfig,
+    ) -> Result<Vec<OutputFile>, BackendError>;
+}
+
+/// A [`Backend`] selected by `name`, whose only options are those of [`TypeNaming`]. Any other
+/// option is an error.
+pub struct Unconfigured<B: Backend> {
+    pub name: &'static str,
+    pub backend: B,
+}
+
+impl<B: Backend> CodegenBackend for Unconfigured<B> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn register_annotations(&self, registry: &mut AnnotationRegistry) {
+        self.backend.register_annotations(registry);
+    }
+
+    fn required_rules(&self) -> &'static [Rule] {
+        self.backend.required_rules()
+    }
+
+    fn generate(
+        &self,
+        compiler: &IRCompiler,
+        config: &BackendConfig,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        config.expect_keys(&TypeNaming::OPTIONS)?;
+        let naming = TypeNaming::from_config(config)?;
+        self.backend.generate_named(compiler, &naming)
+    }
+}
+
+/// The backends that can be selected by name.
+#[derive(Default)]
+pub struct BackendRegistry {
+    backends: BTreeMap<&'static str, Box<dyn CodegenBackend>>,
+}
+
+impl BackendRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `backend`, replacing a backend of the same name.
+    pub fn register(&mut self, backend: Box<dyn CodegenBackend>) {
+        self.backends.insert(backend.name(), backend);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn CodegenBackend> {
+        self.backends.get(name).map(|backend| backend.as_ref())
+    }
+
+    /// Every backend, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn CodegenBackend> {
+        self.backends.values().map(|backend| backend.as_ref())
+    }
+
+    /// The names of every backend, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.backends.keys().copied()
+    }
+}
+
+/// How the names of generated types are cased, see [`TypeNaming`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeCase {
+    /// `ApiAuthUser`
+    Pascal,
+    /// `apiAuthUser`
+    Camel,
+    /// `api_auth_user`
+    Snake,
+}
+
+impl TypeCase {
+    fn join(self, words: &[&str]) -> String {
+        let capitalize = |word: &str| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        };
+        match self {
+            TypeCase::Pascal => words.iter().map(|word| capitalize(word)).collect(),
+            TypeCase::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| match i {
+                    0 => word.to_lowercase(),
+                    _ => capitalize(word),
+                })
+                .collect(),
+            TypeCase::Snake => words
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+        }
+    }
+}
+
+/// The words of a name like `auth.HTTPRequest_body`: `auth`, `HTTP`, `Request` and `body`.
+/// Words are separated by anything but letters and digits, and by changes from lower to upper
+/// case.
+fn words(name: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    for part in name.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<(usize, char)> = part.char_indices().collect();
+        let mut start = 0;
+        for (i, &(index, c)) in chars.iter().enumerate().skip(1) {
+            let previous = chars[i - 1].1;
+            let next_is_lower = chars
+                .get(i + 1)
+                .is_some_and(|(_, next)| next.is_lowercase());
+            let starts_word = c.is_uppercase()
+                && (previous.is_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_uppercase() && next_is_lower));
+            if starts_word {
+                words.push(&part[start..index]);
+                start = index;
+            }
+        }
+        words.push(&part[start..]);
+    }
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+/// The conventions generated type names follow: a prefix like `Api`, a suffix like `Dto` and a
+/// casing. Backends take them as the options `type-prefix`, `type-suffix` and `type-case`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeNaming {
+    pub prefix: String,
+    pub suffix: String,
+    /// Keeps names as they are if `None`.
+    pub case: Option<TypeCase>,
+}
+
+impl TypeNaming {
+    /// The backend options a naming is read from.
+    pub const OPTIONS: [&'static str; 3] = ["type-prefix", "type-suffix", "type-case"];
+
+    pub fn from_config(config: &BackendConfig) -> Result<Self, BackendError> {
+        let case = config
+            .choice("type-case", &["pascal", "camel", "snake"])?
+            .map(|case| match case {
+                "pascal" => TypeCase::Pascal,
+                "camel" => TypeCase::Camel,
+                _ => TypeCase::Snake,
+            });
+        Ok(TypeNaming {
+            prefix: config.get("type-prefix").unwrap_or_default().to_string(),
+            suffix: config.get("type-suffix").unwrap_or_default().to_string(),
+            case,
+        })
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `name` between the prefix and the suffix, all in the casing if there is one. The prefix
+    /// and the suffix are always separate words, `Api` and `auth.User` are `ApiAuthUser`.
+    pub fn apply(&self, name: &str) -> String {
+        match self.case {
+            Some(case) => {
+                let mut all = words(&self.prefix);
+                all.extend(words(name));
+                all.extend(words(&self.suffix));
+                case.join(&all)
+            }
+            None => format!("{}{}{}", self.prefix, name, self.suffix),
+        }
+    }
+}
+
+/// A language backends generate code in, for the words its identifiers can not be. Backends
+/// pass the names of types through [`Language::escape`], and the names of fields and options
+/// through a [`Scope`], instead of keeping their own lists, so a name that is fine in one target
+/// does not break another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    Python,
+    /// Also the names of the primitive types, which types can not be named.
+    TypeScript,
+    Rust,
+    CSharp,
+    Cpp,
+    Thrift,
+    Go,
+    /// Also the words that can be variable names but not the names of types, like `var`.
+    Java,
+}
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+const TYPESCRIPT_KEYWORDS: &[&str] = &[
+    "any",
+    "bigint",
+    "boolean",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "never",
+    "new",
+    "null",
+    "number",
+    "object",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "string",
+    "super",
+    "switch",
+    "symbol",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "unknown",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Rust keywords that can not be written as raw identifiers.
+const RUST_NOT_RAW: &[&str] = &["Self", "crate", "self", "super"];
+
+const CSHARP_KEYWORDS: &[&str] = &[
+    "abstract",
+    "as",
+    "base",
+    "bool",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "checked",
+    "class",
+    "const",
+    "continue",
+    "decimal",
+    "default",
+    "delegate",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "event",
+    "explicit",
+    "extern",
+    "false",
+    "finally",
+    "fixed",
+    "float",
+    "for",
+    "foreach",
+    "goto",
+    "if",
+    "implicit",
+    "in",
+    "int",
+    "interface",
+    "internal",
+    "is",
+    "lock",
+    "long",
+    "namespace",
+    "new",
+    "null",
+    "object",
+    "operator",
+    "out",
+    "override",
+    "params",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "ref",
+    "return",
+    "sbyte",
+    "sealed",
+    "short",
+    "sizeof",
+    "stackalloc",
+    "static",
+    "string",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "uint",
+    "ulong",
+    "unchecked",
+    "unsafe",
+    "ushort",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+const CPP_KEYWORDS: &[&str] = &[
+    "alignas",
+    "alignof",
+    "and",
+    "and_eq",
+    "asm",
+    "auto",
+    "bitand",
+    "bitor",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "char16_t",
+    "char32_t",
+    "char8_t",
+    "class",
+    "co_await",
+    "co_return",
+    "co_yield",
+    "compl",
+    "concept",
+    "const",
+    "const_cast",
+    "consteval",
+    "constexpr",
+    "constinit",
+    "continue",
+    "decltype",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "dynamic_cast",
+    "else",
+    "enum",
+    "explicit",
+    "export",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "friend",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "not",
+    "not_eq",
+    "nullptr",
+    "operator",
+    "or",
+    "or_eq",
+    "private",
+    "protected",
+    "public",
+    "register",
+    "reinterpret_cast",
+    "requires",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "static_assert",
+    "static_cast",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "thread_local",
+    "throw",
+    "true",
+    "try",
+    "typedef",
+    "typeid",
+    "typename",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "wchar_t",
+    "while",
+    "xor",
+    "xor_eq",
+];
+
+/// The keywords of the Thrift IDL, and the words it reserves because they are keywords of a
+/// language Thrift generates code in.
+const THRIFT_KEYWORDS: &[&str] = &[
+    "BEGIN",
+    "END",
+    "__CLASS__",
+    "__DIR__",
+    "__FILE__",
+    "__FUNCTION__",
+    "__LINE__",
+    "__METHOD__",
+    "__NAMESPACE__",
+    "abstract",
+    "alias",
+    "and",
+    "args",
+    "as",
+    "assert",
+    "async",
+    "begin",
+    "binary",
+    "bool",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "class",
+    "clone",
+    "const",
+    "continue",
+    "cpp_include",
+    "cpp_type",
+    "declare",
+    "def",
+    "default",
+    "del",
+    "delete",
+    "do",
+    "double",
+    "dynamic",
+    "elif",
+    "else",
+    "elseif",
+    "elsif",
+    "end",
+    "enddeclare",
+    "endfor",
+    "endforeach",
+    "endif",
+    "endswitch",
+    "endwhile",
+    "ensure",
+    "enum",
+    "except",
+    "exception",
+    "exec",
+    "extends",
+    "false",
+    "finally",
+    "float",
+    "for",
+    "foreach",
+    "from",
+    "function",
+    "global",
+    "goto",
+    "i16",
+    "i32",
+    "i64",
+    "i8",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "include",
+    "inline",
+    "instanceof",
+    "interface",
+    "is",
+    "lambda",
+    "list",
+    "map",
+    "module",
+    "namespace",
+    "native",
+    "new",
+    "next",
+    "nil",
+    "not",
+    "oneway",
+    "optional",
+    "or",
+    "package",
+    "pass",
+    "print",
+    "private",
+    "protected",
+    "public",
+    "raise",
+    "redo",
+    "register",
+    "required",
+    "rescue",
+    "retry",
+    "return",
+    "self",
+    "senum",
+    "service",
+    "set",
+    "sizeof",
+    "slist",
+    "static",
+    "string",
+    "struct",
+    "super",
+    "switch",
+    "synchronized",
+    "then",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "true",
+    "try",
+    "typedef",
+    "undef",
+    "union",
+    "unless",
+    "unsigned",
+    "until",
+    "use",
+    "uuid",
+    "var",
+    "virtual",
+    "void",
+    "volatile",
+    "when",
+    "while",
+    "with",
+    "xor",
+    "yield",
+];
+
+const GO_KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+];
+
+const JAVA_KEYWORDS: &[&str] = &[
+    "_",
+    "abstract",
+    "assert",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "null",
+    "package",
+    "permits",
+    "private",
+    "protected",
+    "public",
+    "record",
+    "return",
+    "sealed",
+    "short",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "true",
+    "try",
+    "var",
+    "void",
+    "volatile",
+    "while",
+    "yield",
+];
+
+impl Language {
+    /// The words that are not identifiers of the language.
+    pub fn reserved_words(self) -> &'static [&'static str] {
+        match self {
+            Language::Python => PYTHON_KEYWORDS,
+            Language::TypeScript => TYPESCRIPT_KEYWORDS,
+            Language::Rust => RUST_KEYWORDS,
+            Language::CSharp => CSHARP_KEYWORDS,
+            Language::Cpp => CPP_KEYWORDS,
+            Language::Thrift => THRIFT_KEYWORDS,
+            Language::Go => GO_KEYWORDS,
+            Language::Java => JAVA_KEYWORDS,
+        }
+    }
+
+    pub fn is_reserved(self, name: &str) -> bool {
+        self.reserved_words().contains(&name)
+    }
+
+    /// `name` as an identifier of the language: unchanged unless it is reserved, and otherwise
+    /// written the way the language allows, like `r#type` in Rust and `@class` in C#, or with a
+    /// trailing underscore like `class_` where there is no such way.
+    pub fn escape(self, name: &str) -> String {
+        if !self.is_reserved(name) {
+            return name.to_string();
+        }
+        match self {
+            Language::Rust if !RUST_NOT_RAW.contains(&name) => format!("r#{}", name),
+            Language::CSharp => format!("@{}", name),
+            _ => format!("{}_", name),
+        }
+    }
+}
+
+/// The identifiers of one scope of generated code, like the fields of a class or the options of
+/// an enum. Names are escaped for the language, and a name that an earlier identifier of the scope
+/// already has, like `class_` after `class` was escaped to it, gets underscores appended until it
+/// is unique.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    language: Language,
+    used: HashSet<String>,
+}
+
+impl Scope {
+    pub fn new(language: Language) -> Self {
+        Scope {
+            language,
+            used: HashSet::new(),
+        }
+    }
+
+    /// Takes `name` without escaping it, for identifiers the backend chooses itself, like the name
+    /// of the class the scope belongs to where its members can not have that name.
+    pub fn reserve(&mut self, name: &str) {
+        self.used.insert(name.to_string());
+    }
+
+    /// `name` as an identifier of the language that no other identifier of the scope has.
+    pub fn identifier(&mut self, name: &str) -> String {
+        self.unique(self.language.escape(name))
+    }
+
+    /// Like [`Scope::identifier`], for names that are not escaped because they can not be
+    /// reserved words, like names with a prefix.
+    pub fn unique(&mut self, mut name: String) -> String {
+        while self.used.contains(&name) {
+            name.push('_');
+        }
+        self.used.insert(name.clone());
+        name
+    }
+}
+
+/// Names compiled types in the target language. Qualified names like `auth.User` become
+/// `auth_User`, instantiations of generics are numbered in the order they are first named, like
+/// `Page0`, and temporaries are named after their owner and path, like `User_address`. A
+/// [`TypeNaming`] is applied to the whole name, and escaped like a declared type afterwards.
+pub struct TypeNameAllocator {
+    next_id: usize,
+    /// The names of the instantiations and temporaries named so far.
+    names: HashMap<TypeName, String>,
+    /// Every name handed out, and the names of the declared types, which no other type may have.
+    used: HashSet<String>,
+    variable_name: fn(&str) -> String,
+    naming: TypeNaming,
+}
+
+impl Default for TypeNameAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypeNameAllocator {
+    pub fn new() -> Self {
+        Self::with_variable_name(|name| name.replace('.', "_"))
+    }
+
+    /// Names declared types with `variable_name` instead, for targets that need to escape names.
+    /// Temporaries are named after the escaped name of their owner.
+    pub fn with_variable_name(variable_name: fn(&str) -> String) -> Self {
+        Self {
+            next_id: 0,
+            names: HashMap::new(),
+            used: HashSet::new(),
+            variable_name,
+            naming: TypeNaming::default(),
+        }
+    }
+
+    /// Keeps instantiations and temporaries from being named like a declared type of `compiler`:
+    /// with a declared type `User_address`, the temporary `User.address` is named
+    /// `User_address_`.
+    pub fn with_declared(mut self, compiler: &IRCompiler) -> Self {
+        for (_, named_type) in compiler.iter_types() {
+            if let TypeName::Variable(name) = named_type.name {
+                self.used.insert((self.variable_name)(&name));
+            }
+        }
+        self
+    }
+
+    pub fn with_naming(mut self, naming: TypeNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    pub fn allocate_name(&mut self, type_name: &TypeName) -> String {
+        let name = self.declared_name(type_name);
+        if self.naming.is_default() {
+            return name;
+        }
+        (self.variable_name)(&self.naming.apply(&name))
+    }
+
+    /// The name of `type_name` before the naming is applied. Instantiations and temporaries get
+    /// underscores appended until no other type has their name, like `User_a_b_` for `User.a.b`
+    /// when `User.a_b` is named `User_a_b` already.
+    fn declared_name(&mut self, type_name: &TypeName) -> String {
+        if let Some(name) = self.names.get(type_name) {
+            return name.clone();
+        }
+        let mut name = match type_name {
+            TypeName::Variable(name) => {
+                let name = (self.variable_name)(name);
+                self.used.insert(name.clone());
+                return name;
+            }
+            TypeName::Generic(name, _) => {
+                let id = self.next_id;
+                self.next_id += 1;
+                format!("{}{}", name.replace('.', "_"), id)
+            }
+            TypeName::Temporary(owner, path) => {
+                format!("{}_{}", self.declared_name(owner), intern::join(path, "_"))
+            }
+        };
+        while !self.used.insert(name.clone()) {
+            name.push('_');
+        }
+        self.names.insert(type_name.clone(), name.clone());
+        name
+    }
+}
+
+/// The named types a backend still has to write. It starts out with the declared types, writers
+/// push the types they reference, and every type is popped once.
+#[derive(Debug, Default)]
+pub struct Worklist {
+    stack: VecDeque<NamedIRType>,
+    compiled: HashSet<TypeName>,
+}
+
+impl Worklist {
+    /// A worklist of every declared type that does not take generic parameters.
+    pub fn new(compiler: &IRCompiler) -> Self {
+        let mut worklist = Worklist::default();
+        for (_id, named_type) in compiler.iter_types() {
+            if let TypeName::Variable(_) = named_type.name {
+                worklist.push_back(named_type);
+            }
+        }
+        worklist
+    }
+
+    /// Adds `named_type` to the end, unless it was written already.
+    pub fn push_back(&mut self, named_type: &NamedIRType) {
+        if !self.compiled.contains(&named_type.name) {
+            self.stack.push_back(named_type.clone());
+        }
+    }
+
+    /// Adds `named_type` to the front, so it is written next, unless it was written already.
+    pub fn push_front(&mut self, named_type: &NamedIRType) {
+        if !self.compiled.contains(&named_type.name) {
+            self.stack.push_front(named_type.clone());
+        }
+    }
+
+    /// The next type that has not been written yet, which counts as written from now on.
+    pub fn pop(&mut self) -> Option<NamedIRType> {
+        while let Some(named_type) = self.stack.pop_front() {
+            if self.compiled.insert(named_type.name.clone()) {
+                trace!(name = %named_type.name, "emitting type");
+                return Some(named_type);
+            }
+        }
+        None
+    }
+
+    pub fn is_compiled(&self, name: &TypeName) -> bool {
+        self.compiled.contains(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intern::Atom;
+
+    #[test]
+    fn scopes_keep_escaped_names_unique() {
+        let mut scope = Scope::new(Language::Python);
+        assert_eq!(scope.identifier("class"), "class_");
+        assert_eq!(scope.identifier("class_"), "class__");
+        assert_eq!(scope.identifier("name"), "name");
+        assert_eq!(scope.identifier("name"), "name_");
+
+        let mut scope = Scope::new(Language::CSharp);
+        scope.reserve("User");
+        assert_eq!(scope.identifier("User"), "User_");
+        assert_eq!(scope.identifier("object"), "@object");
+    }
+
+    #[test]
+    fn temporaries_are_not_named_like_declared_types() {
+        let source = "type User = struct { address: struct { x: int, }, };
+                      type User_address = struct { y: int, };";
+        let items = crate::parse(source, "test.roto").expect("the source parses");
+        let mut compiler = IRCompiler::new();
+        compiler.register_program(items, "test.roto", source);
+        compiler.compile_globals().unwrap();
+        let mut names = TypeNameAllocator::new().with_declared(&compiler);
+        let user = TypeName::Variable(Atom::from("User"));
+        let address = TypeName::Temporary(Box::new(user), vec![Atom::from("address")]);
+        assert_eq!(names.allocate_name(&address), "User_address_");
+        let declared = TypeName::Variable(Atom::from("User_address"));
+        assert_eq!(names.allocate_name(&declared), "User_address");
+    }
+
+    #[test]
+    fn temporaries_and_instantiations_have_names_of_their_own() {
+        let source = "type User = struct {
+                          a_b: struct { x: int, },
+                          a: struct { b: struct { y: string, }, },
+                      };
+                      type Page<T> = struct { items: T, };
+                      type Page0 = int;
+                      type Users = Page<T=User>;";
+        let items = crate::parse(source, "test.roto").expect("the source parses");
+        let mut compiler = IRCompiler::new();
+        compiler.register_program(items, "test.roto", source);
+        compiler.compile_globals().unwrap();
+        let mut names = TypeNameAllocator::new().with_declared(&compiler);
+        let user = TypeName::Variable(Atom::from("User"));
+        let temporary = |path: &[&str]| {
+            TypeName::Temporary(
+                Box::new(user.clone()),
+                path.iter().map(|name| Atom::from(*name)).collect(),
+            )
+        };
+        assert_eq!(names.allocate_name(&temporary(&["a_b"])), "User_a_b");
+        assert_eq!(names.allocate_name(&temporary(&["a", "b"])), "User_a_b_");
+        assert_eq!(names.allocate_name(&temporary(&["a_b"])), "User_a_b");
+        assert_eq!(names.allocate_name(&temporary(&["a", "b"])), "User_a_b_");
+        let (_, page) = compiler
+            .iter_types()
+            .find(|(_, t)| matches!(t.name, TypeName::Generic(..)))
+            .unwrap();
+        assert_eq!(names.allocate_name(&page.name), "Page0_");
+    }
+
+    #[test]
+    fn go_and_java_keywords_get_a_trailing_underscore() {
+        assert_eq!(Language::Go.escape("type"), "type_");
+        assert_eq!(Language::Go.escape("string"), "string");
+        assert_eq!(Language::Java.escape("class"), "class_");
+        assert_eq!(Language::Java.escape("record"), "record_");
+        assert_eq!(Language::Java.escape("name"), "name");
+    }
+
+    #[test]
+    fn thrift_reserves_the_words_of_generated_languages() {
+        for word in ["class", "self", "async", "struct"] {
+            assert_eq!(Language::Thrift.escape(word), format!("{}_", word));
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Builtins that embedders add to the language, like `money` or `geojson`. Schemas refer to them
+//! by name like to any type, and they are compiled to [`Builtin::Custom`]. Every custom builtin
+//! is represented as one of the language's own builtins, which backends generate unless they
+//! have a lowering for it, like `Decimal` for `money` in Python.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ast::Builtin;
+use crate::intern::Atom;
+
+/// A builtin added by an embedder, registered with [`BuiltinRegistry::register`].
+pub trait BuiltinExtension: Send + Sync {
+    /// The name schemas refer to it by, like `money`.
+    fn name(&self) -> &str;
+
+    /// The builtin its values are made of, like `string` for `money`. Must not be `unit`, which
+    /// has no values to speak of, or a custom builtin.
+    fn representation(&self) -> Builtin;
+
+    /// The type `target` generates for it, like `Decimal` for `py-pydantic`, in the syntax of
+    /// the target. `None` generates the representation. Backends write the lowering as it is,
+    /// so it can only refer to what the generated code has in scope.
+    fn lower(&self, target: &str) -> Option<String> {
+        let _ = target;
+        None
+    }
+}
+
+/// A registered custom builtin. Ids are only meaningful to the registry that handed them out,
+/// and to registries where a builtin of the same name is registered.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuiltinId(Atom);
+
+impl BuiltinId {
+    pub fn name(self) -> &'static str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Display for BuiltinId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The custom builtins of a compiler. Names that are declared as types in a schema refer to the
+/// type, so registering a builtin never changes the meaning of a schema that compiled before.
+#[derive(Clone, Default)]
+pub struct BuiltinRegistry {
+    extensions: BTreeMap<BuiltinId, Arc<dyn BuiltinExtension>>,
+}
+
+impl fmt::Debug for BuiltinRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.extensions.keys()).finish()
+    }
+}
+
+impl BuiltinRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `extension`, replacing a builtin of the same name.
+    ///
+    /// # Panics
+    ///
+    /// If `extension` is represented as `unit` or a custom builtin.
+    pub fn register(&mut self, extension: impl BuiltinExtension + 'static) -> BuiltinId {
+        let representation = extension.representation();
+        assert!(
+            !matches!(representation, Builtin::Unit | Builtin::Custom(_)),
+            "builtin {} can not be represented as {}",
+            extension.name(),
+            representation
+        );
+        let id = BuiltinId(Atom::intern(extension.name()));
+        self.extensions.insert(id, Arc::new(extension));
+        id
+    }
+
+    /// Registers the builtins of `other` that are not registered here.
+    pub fn extend(&mut self, other: &BuiltinRegistry) {
+        for (id, extension) in &other.extensions {
+            self.extensions
+                .entry(*id)
+                .or_insert_with(|| extension.clone());
+        }
+    }
+
+    /// The builtin called `name`, if one is registered.
+    pub fn lookup(&self, name: &str) -> Option<BuiltinId> {
+        let id = BuiltinId(Atom::intern(name));
+        self.extensions.contains_key(&id).then_some(id)
+    }
+
+    pub fn get(&self, id: BuiltinId) -> Option<&dyn BuiltinExtension> {
+        self.extensions.get(&id).map(|extension| &**extension)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BuiltinId, &dyn BuiltinExtension)> {
+        self.extensions
+            .iter()
+            .map(|(id, extension)| (*id, &**extension))
+    }
+
+    /// The builtin `builtin` is made of: itself, or the representation of a custom builtin.
+    ///
+    /// # Panics
+    ///
+    /// If `builtin` is a custom builtin that is not registered, which compiled types never refer
+    /// to.
+    pub fn representation(&self, builtin: &Builtin) -> Builtin {
+        match builtin {
+            Builtin::Custom(id) => self
+                .get(*id)
+                .unwrap_or_else(|| panic!("builtin {} is not registered", id))
+                .representation(),
+            builtin => builtin.clone(),
+        }
+    }
+
+    /// What `target` generates for the custom builtin `id`, if it has a lowering for it.
+    pub fn lower(&self, id: BuiltinId, target: &str) -> Option<String> {
+        self.get(id).and_then(|extension| extension.lower(target))
+    }
+}
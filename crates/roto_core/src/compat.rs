@@ -0,0 +1,179 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    diff::{diff, SchemaChange},
+    frontend::IRCompiler,
+};
+
+/// How a difference between two schema versions affects existing producers and consumers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Compatibility {
+    /// The difference has no effect on the wire format, e.g. a changed comment.
+    Informational,
+    /// Data written with one version can still be read with the other.
+    NonBreaking,
+    /// Existing data or existing readers are no longer compatible.
+    Breaking,
+}
+
+impl Display for Compatibility {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Compatibility::Informational => write!(f, "info"),
+            Compatibility::NonBreaking => write!(f, "non-breaking"),
+            Compatibility::Breaking => write!(f, "breaking"),
+        }
+    }
+}
+
+impl Compatibility {
+    /// Parses the name a compatibility is displayed with, like `breaking`.
+    pub fn from_name(name: &str) -> Option<Compatibility> {
+        match name {
+            "info" => Some(Compatibility::Informational),
+            "non-breaking" => Some(Compatibility::NonBreaking),
+            "breaking" => Some(Compatibility::Breaking),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatibilityIssue {
+    pub compatibility: Compatibility,
+    pub change: SchemaChange,
+}
+
+impl Display for CompatibilityIssue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "[{}] {}", self.compatibility, self.change)
+    }
+}
+
+/// Classifies a single schema change.
+pub fn classify(change: &SchemaChange) -> Compatibility {
+    match change {
+        SchemaChange::TypeAdded { .. } => Compatibility::NonBreaking,
+        SchemaChange::FieldAdded { optional: true, .. } => Compatibility::NonBreaking,
+        // What an annotation means is up to the backends, which the checker does not know.
+        SchemaChange::ReferenceChanged { .. }
+        | SchemaChange::CommentChanged { .. }
+        | SchemaChange::AnnotationsChanged { .. } => Compatibility::Informational,
+        // Readers built against the old schema cannot decode new variant options either.
+        SchemaChange::TypeRemoved { .. }
+        | SchemaChange::Retyped { .. }
+        | SchemaChange::FieldAdded {
+            optional: false, ..
+        }
+        | SchemaChange::FieldRemoved { .. }
+        | SchemaChange::FieldRenamed { .. }
+        | SchemaChange::FieldOptionalityChanged { .. }
+        | SchemaChange::VariantAdded { .. }
+        | SchemaChange::VariantRemoved { .. } => Compatibility::Breaking,
+    }
+}
+
+/// Compares the global types of two compiled schemas and reports every difference together with
+/// its compatibility classification. Types are matched by their global name.
+pub fn check_compatibility(old: &IRCompiler, new: &IRCompiler) -> Vec<CompatibilityIssue> {
+    diff(old, new)
+        .into_iter()
+        .map(|change| CompatibilityIssue {
+            compatibility: classify(&change),
+            change,
+        })
+        .collect()
+}
+
+/// Returns true if any of the issues is a breaking change.
+pub fn is_breaking(issues: &[CompatibilityIssue]) -> bool {
+    issues
+        .iter()
+        .any(|issue| issue.compatibility == Compatibility::Breaking)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str) -> IRCompiler {
+        let items = crate::parse(source, "test.roto").expect("the source parses");
+        let mut compiler = IRCompiler::new();
+        compiler.register_program(items, "test.roto", source);
+        compiler.compile_globals().unwrap();
+        compiler
+    }
+
+    /// The issues from `old` to `new`, as they are displayed.
+    fn issues(old: &str, new: &str) -> Vec<String> {
+        check_compatibility(&compile(old), &compile(new))
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn additions_of_types_and_optional_fields_are_not_breaking() {
+        let old = "type User = struct { name: string, };";
+        let new = "type User = struct { name: string, email?: string, }; type Team = int;";
+        assert_eq!(
+            issues(old, new),
+            [
+                "[non-breaking] User.email: optional field added",
+                "[non-breaking] Team: type added",
+            ]
+        );
+        assert!(!is_breaking(&check_compatibility(
+            &compile(old),
+            &compile(new)
+        )));
+    }
+
+    #[test]
+    fn removals_retypes_and_required_fields_are_breaking() {
+        let old = "type User = struct { name: string, age: int, };
+                   type Status = enum { active(unit), };";
+        let new = "type User = struct { name: int, id: string, };
+                   type Status = enum { active(unit), pending(unit), };";
+        assert_eq!(
+            issues(old, new),
+            [
+                "[breaking] Status.pending: variant option added",
+                "[breaking] User.name: type changed from string to int",
+                "[breaking] User.id: required field added",
+                "[breaking] User.age: field removed",
+            ]
+        );
+        assert!(is_breaking(&check_compatibility(
+            &compile(old),
+            &compile(new)
+        )));
+    }
+
+    #[test]
+    fn comments_are_informational() {
+        assert_eq!(
+            issues(
+                "type User = struct {\n    // The name.\n    name: string,\n};",
+                "type User = struct {\n    name: string,\n};",
+            ),
+            ["[info] User.name: comment changed"]
+        );
+    }
+
+    #[test]
+    fn compatibilities_are_parsed_by_their_names() {
+        for compatibility in [
+            Compatibility::Informational,
+            Compatibility::NonBreaking,
+            Compatibility::Breaking,
+        ] {
+            assert_eq!(
+                Compatibility::from_name(&compatibility.to_string()),
+                Some(compatibility)
+            );
+        }
+        assert_eq!(Compatibility::from_name("minor"), None);
+    }
+}
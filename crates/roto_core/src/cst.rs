@@ -0,0 +1,967 @@
+//! A lossless concrete syntax tree of schema source, for tools that rewrite schemas without
+//! reformatting them, like renaming a type. Every byte of the source is in exactly one token or
+//! one piece of trivia, so the tree gives back the source it was parsed from, and [`apply`]ing
+//! edits to the spans of some tokens leaves the rest of the file as it was.
+//!
+//! Comments are trivia here. The grammar of [`crate::parse`] reads the comments before a
+//! declaration, field or option as its documentation and rejects comments anywhere else, the
+//! tree keeps every comment where it is.
+//!
+//! The tree is parsed by hand rather than by that grammar, so the tests parse the examples and
+//! every construct with both parsers and check that they agree. A change to the grammar needs a
+//! change here as well.
+
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::ast::{LineIndex, Span};
+use crate::syntax::{ParseDiagnostic, KEYWORDS};
+
+const BUILTINS: [&str; 5] = ["bool", "float", "int", "string", "unit"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident,
+    /// A keyword, including the names of builtins like `string`.
+    Keyword,
+    /// Punctuation like `{` or `==`.
+    Punct,
+    String,
+    Int,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    /// A `//` comment, without the line break that ends it.
+    Comment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+/// A token and the trivia around it. The trivia up to the end of the line of the token trails
+/// it, the trivia on the following lines leads the next token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Program,
+    /// `type Name<T> = ...;`, with the annotations before it.
+    TypeDeclaration,
+    UseDeclaration,
+    ModuleDeclaration,
+    Annotation,
+    /// The `<T, U>` of a type declaration.
+    GenericParameters,
+    Struct,
+    Field,
+    Variant,
+    VariantOption,
+    /// A reference to a named type, like `auth.User` or `Page<T=User>`.
+    TypeReference,
+    /// A dotted name, in a type reference or a `use`.
+    Path,
+    /// The `<T=User>` of a type reference.
+    GenericArguments,
+    GenericArgument,
+    Builtin,
+    Intersection,
+    Union,
+    Conditional,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Node(Node),
+    Token(Token),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+impl Node {
+    /// The nodes directly in this one.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        })
+    }
+
+    /// This node and every node in it, in source order.
+    pub fn descendants(&self) -> Vec<&Node> {
+        let mut nodes = vec![self];
+        for child in self.child_nodes() {
+            nodes.extend(child.descendants());
+        }
+        nodes
+    }
+
+    /// The tokens of this node, in source order.
+    pub fn tokens(&self) -> Vec<&Token> {
+        let mut tokens = Vec::new();
+        for child in &self.children {
+            match child {
+                Element::Node(node) => tokens.extend(node.tokens()),
+                Element::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// The identifier a declaration, field, option, annotation, generic parameter or argument
+    /// is named by: the first identifier directly in the node.
+    pub fn name(&self) -> Option<&Token> {
+        self.children.iter().find_map(|child| match child {
+            Element::Token(token) if token.kind == TokenKind::Ident => Some(token),
+            _ => None,
+        })
+    }
+
+    /// The span from the start of the first token to the end of the last, without trivia.
+    /// `None` for an empty program.
+    pub fn span(&self) -> Option<Span> {
+        let tokens = self.tokens();
+        Some(Span {
+            start: tokens.first()?.span.start,
+            end: tokens.last()?.span.end,
+        })
+    }
+}
+
+/// The concrete syntax tree of a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxTree {
+    pub source: String,
+    pub root: Node,
+    /// The trivia after the last token.
+    pub end: Vec<Trivia>,
+}
+
+impl SyntaxTree {
+    /// The source text of `span`, like the text of a token.
+    pub fn text(&self, span: Span) -> &str {
+        &self.source[span.start..span.end]
+    }
+}
+
+/// Writes the tree back as source, which is the source it was parsed from.
+impl Display for SyntaxTree {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let trivia = |f: &mut Formatter, trivia: &[Trivia]| {
+            trivia
+                .iter()
+                .try_for_each(|t| f.write_str(self.text(t.span)))
+        };
+        for token in self.root.tokens() {
+            trivia(f, &token.leading)?;
+            f.write_str(self.text(token.span))?;
+            trivia(f, &token.trailing)?;
+        }
+        trivia(f, &self.end)
+    }
+}
+
+/// Replaces the source in `span` with `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub span: Span,
+    pub text: String,
+}
+
+/// `source` with `edits` applied, in any order. Edits must not overlap.
+pub fn apply(source: &str, edits: &[Edit]) -> String {
+    let mut edits: Vec<_> = edits.iter().collect();
+    edits.sort_by_key(|edit| edit.span.start);
+    let mut result = String::with_capacity(source.len());
+    let mut position = 0;
+    for edit in edits {
+        result.push_str(&source[position..edit.span.start]);
+        result.push_str(&edit.text);
+        position = edit.span.end;
+    }
+    result.push_str(&source[position..]);
+    result
+}
+
+/// Parses `source`, the contents of the file `file_name`, into its concrete syntax tree. The
+/// source has to be valid for [`crate::parse`], except that comments can be anywhere.
+pub fn parse(source: &str, file_name: &str) -> Result<SyntaxTree, Vec<ParseDiagnostic>> {
+    let error = |message: String, span: Span, expected: Vec<String>| {
+        vec![ParseDiagnostic {
+            message,
+            expected,
+            span,
+            location: LineIndex::new(file_name, source).location(span),
+        }]
+    };
+    let (tokens, end) = lex(source).map_err(|span| {
+        let found = source[span.start..].chars().next().unwrap_or_default();
+        error(
+            format!("unexpected character `{}`", found),
+            span,
+            Vec::new(),
+        )
+    })?;
+    let mut parser = Parser {
+        source,
+        tokens: tokens.into_iter().peekable(),
+    };
+    match parser.program() {
+        Ok(children) => Ok(SyntaxTree {
+            source: source.to_string(),
+            root: Node {
+                kind: NodeKind::Program,
+                children,
+            },
+            end,
+        }),
+        Err(Unexpected { span, expected }) => {
+            let message = if span.start == source.len() {
+                "unexpected end of file".to_string()
+            } else {
+                format!("unexpected `{}`", &source[span.start..span.end])
+            };
+            let expected = expected.iter().map(|e| e.to_string()).collect();
+            Err(error(message, span, expected))
+        }
+    }
+}
+
+/// Splits `source` into tokens, and the trivia after the last one. Fails with the span of a
+/// character that starts no token.
+fn lex(source: &str) -> Result<(Vec<Token>, Vec<Trivia>), Span> {
+    let bytes = source.as_bytes();
+    let mut position = 0;
+    // Whitespace without line breaks and comments, or any whitespace and comments.
+    let trivia = |position: &mut usize, multiline: bool| {
+        let mut trivia = Vec::new();
+        loop {
+            let start = *position;
+            let is_space = |b: u8| b.is_ascii_whitespace() && (multiline || b != b'\n');
+            while *position < bytes.len() && is_space(bytes[*position]) {
+                *position += 1;
+            }
+            if *position > start {
+                trivia.push(Trivia {
+                    kind: TriviaKind::Whitespace,
+                    span: Span {
+                        start,
+                        end: *position,
+                    },
+                });
+            } else if source[start..].starts_with("//") {
+                *position = source[start..]
+                    .find('\n')
+                    .map_or(source.len(), |i| start + i);
+                trivia.push(Trivia {
+                    kind: TriviaKind::Comment,
+                    span: Span {
+                        start,
+                        end: *position,
+                    },
+                });
+            } else {
+                return trivia;
+            }
+        }
+    };
+
+    let mut tokens = Vec::new();
+    loop {
+        let leading = trivia(&mut position, true);
+        let start = position;
+        let rest = &source[start..];
+        let Some(first) = rest.chars().next() else {
+            return Ok((tokens, leading));
+        };
+        let length =
+            |predicate: fn(char) -> bool| rest.find(|c: char| !predicate(c)).unwrap_or(rest.len());
+        let (kind, end) = if first.is_ascii_alphabetic() || first == '_' {
+            let end = start + length(|c| c.is_ascii_alphanumeric() || c == '_');
+            let kind = if KEYWORDS.contains(&&source[start..end]) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Ident
+            };
+            (kind, end)
+        } else if first.is_ascii_digit()
+            || (first == '-' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let digits = rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len() - 1);
+            (TokenKind::Int, start + 1 + digits)
+        } else if first == '"' {
+            let mut chars = rest.char_indices().skip(1);
+            let mut end = None;
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => {
+                        end = Some(start + i + 1);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            match end {
+                Some(end) => (TokenKind::String, end),
+                None => {
+                    return Err(Span {
+                        start,
+                        end: start + 1,
+                    })
+                }
+            }
+        } else if rest.starts_with("==") {
+            (TokenKind::Punct, start + 2)
+        } else if "{}()<>,;:?=&|@.".contains(first) {
+            (TokenKind::Punct, start + 1)
+        } else {
+            return Err(Span {
+                start,
+                end: start + first.len_utf8(),
+            });
+        };
+        position = end;
+        let trailing = trivia(&mut position, false);
+        tokens.push(Token {
+            kind,
+            span: Span { start, end },
+            leading,
+            trailing,
+        });
+    }
+}
+
+/// A token the parser did not expect, and what it would have accepted instead.
+struct Unexpected {
+    span: Span,
+    expected: Vec<&'static str>,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser<'_> {
+    fn peek_text(&mut self) -> Option<&str> {
+        let source = self.source;
+        self.tokens
+            .peek()
+            .map(|token| &source[token.span.start..token.span.end])
+    }
+
+    fn at(&mut self, text: &str) -> bool {
+        self.peek_text() == Some(text)
+    }
+
+    fn unexpected(&mut self, expected: Vec<&'static str>) -> Unexpected {
+        let span = match self.tokens.peek() {
+            Some(token) => token.span,
+            None => Span {
+                start: self.source.len(),
+                end: self.source.len(),
+            },
+        };
+        Unexpected { span, expected }
+    }
+
+    fn bump(&mut self, children: &mut Vec<Element>) {
+        if let Some(token) = self.tokens.next() {
+            children.push(Element::Token(token));
+        }
+    }
+
+    /// Moves the next token to `children` if its text is `text`, `described` as in errors.
+    fn expect(
+        &mut self,
+        text: &str,
+        described: &'static str,
+        children: &mut Vec<Element>,
+    ) -> Result<(), Unexpected> {
+        if !self.at(text) {
+            return Err(self.unexpected(vec![described]));
+        }
+        self.bump(children);
+        Ok(())
+    }
+
+    fn expect_ident(&mut self, children: &mut Vec<Element>) -> Result<(), Unexpected> {
+        match self.tokens.peek() {
+            Some(token) if token.kind == TokenKind::Ident => {
+                self.bump(children);
+                Ok(())
+            }
+            _ => Err(self.unexpected(vec!["identifier"])),
+        }
+    }
+
+    fn program(&mut self) -> Result<Vec<Element>, Unexpected> {
+        let mut items = Vec::new();
+        while self.tokens.peek().is_some() {
+            items.push(Element::Node(self.item()?));
+        }
+        Ok(items)
+    }
+
+    fn item(&mut self) -> Result<Node, Unexpected> {
+        let mut children = Vec::new();
+        while self.at("@") {
+            children.push(Element::Node(self.annotation()?));
+        }
+        if self.at("pub") {
+            self.bump(&mut children);
+        }
+        let kind = match self.peek_text() {
+            Some("type") => {
+                self.bump(&mut children);
+                self.expect_ident(&mut children)?;
+                if self.at("<") {
+                    children.push(Element::Node(self.generic_parameters()?));
+                }
+                self.expect("=", "`=`", &mut children)?;
+                children.push(Element::Node(self.type_expression()?));
+                self.expect(";", "`;`", &mut children)?;
+                NodeKind::TypeDeclaration
+            }
+            Some("use") => {
+                self.bump(&mut children);
+                children.push(Element::Node(self.path()?));
+                self.expect(";", "`;`", &mut children)?;
+                NodeKind::UseDeclaration
+            }
+            Some("mod") => {
+                self.bump(&mut children);
+                self.expect_ident(&mut children)?;
+                self.expect("{", "`{`", &mut children)?;
+                while self.tokens.peek().is_some() && !self.at("}") {
+                    children.push(Element::Node(self.item()?));
+                }
+                self.expect("}", "`}`", &mut children)?;
+                NodeKind::ModuleDeclaration
+            }
+            _ => {
+                return Err(self.unexpected(vec!["`@`", "`mod`", "`pub`", "`type`", "`use`"]));
+            }
+        };
+        Ok(Node { kind, children })
+    }
+
+    fn annotation(&mut self) -> Result<Node, Unexpected> {
+        let mut children = Vec::new();
+        self.expect("@", "`@`", &mut children)?;
+        self.expect_ident(&mut children)?;
+        if self.at("(") {
+            self.bump(&mut children);
+            while !self.at(")") {
+                self.expect_ident(&mut children)?;
+                self.expect("=", "`=`", &mut children)?;
+                match self.tokens.peek() {
+                    Some(token)
+                        if matches!(
+                            token.kind,
+                            TokenKind::Ident | TokenKind::Int | TokenKind::String
+                        ) =>
+                    {
+                        self.bump(&mut children)
+                    }
+                    _ => return Err(self.unexpected(vec!["identifier", "integer", "string"])),
+                }
+                if !self.at(",") {
+                    break;
+                }
+                self.bump(&mut children);
+            }
+            self.expect(")", "`)`", &mut children)?;
+        }
+        Ok(Node {
+            kind: NodeKind::Annotation,
+            children,
+        })
+    }
+
+    fn generic_parameters(&mut self) -> Result<Node, Unexpected> {
+        let mut children = Vec::new();
+        self.expect("<", "`<`", &mut children)?;
+        while !self.at(">") {
+            self.expect_ident(&mut children)?;
+            if !self.at(",") {
+                break;
+            }
+            self.bump(&mut children);
+        }
+        self.expect(">", "`>`", &mut children)?;
+        Ok(Node {
+            kind: NodeKind::GenericParameters,
+            children,
+        })
+    }
+
+    fn path(&mut self) -> Result<Node, Unexpected> {
+        let mut children = Vec::new();
+        self.expect_ident(&mut children)?;
+        while self.at(".") {
+            self.bump(&mut children);
+            self.expect_ident(&mut children)?;
+        }
+        Ok(Node {
+            kind: NodeKind::Path,
+            children,
+        })
+    }
+
+    /// Intersections and unions associate to the left, and only take atoms on their right.
+    fn type_expression(&mut self) -> Result<Node, Unexpected> {
+        let mut left = self.type_atom()?;
+        loop {
+            let kind = match self.peek_text() {
+                Some("&") => NodeKind::Intersection,
+                Some("|") => NodeKind::Union,
+                _ => return Ok(left),
+            };
+            let mut children = vec![Element::Node(left)];
+            self.bump(&mut children);
+            children.push(Element::Node(self.type_atom()?));
+            left = Node { kind, children };
+        }
+    }
+
+    fn type_atom(&mut self) -> Result<Node, Unexpected> {
+        let mut children = Vec::new();
+        let kind = match self.peek_text() {
+            Some("struct") => {
+                self.bump(&mut children);
+                self.expect("{", "`{`", &mut children)?;
+                loop {
+                    children.push(Element::Node(self.member(NodeKind::Field)?));
+                    if self.at("}") {
+                        break;
+                    }
+                }
+                self.bump(&mut children);
+                NodeKind::Struct
+            }
+            Some("enum") => {
+                self.bump(&mut children);
+                self.expect("{", "`{`", &mut children)?;
+                loop {
+                    children.push(Element::Node(self.member(NodeKind::VariantOption)?));
+                    if self.at("}") {
+                        break;
+                    }
+                }
+                self.bump(&mut children);
+                NodeKind::Variant
+            }
+            Some("if") => {
+                self.bump(&mut children);
+                children.push(Element::Node(self.type_expression()?));
+                self.expect("==", "`==`", &mut children)?;
+                children.push(Element::Node(self.type_expression()?));
+                self.expect("then", "`then`", &mut children)?;
+                children.push(Element::Node(self.type_expression()?));
+                self.expect("else", "`else`", &mut children)?;
+                children.push(Element::Node(self.type_atom()?));
+                NodeKind::Conditional
+            }
+            Some(text) if BUILTINS.contains(&text) => {
+                self.bump(&mut children);
+                NodeKind::Builtin
+            }
+            _ => {
+                children.push(Element::Node(self.path()?));
+                if self.at("<") {
+                    children.push(Element::Node(self.generic_arguments()?));
+                }
+                NodeKind::TypeReference
+            }
+        };
+        Ok(Node { kind, children })
+    }
+
+    /// A field like `name?: string,` or an option like `Active(unit),`.
+    fn member(&mut self, kind: NodeKind) -> Result<Node, Unexpected> {
+        let mut children = Vec::new();
+        while self.at("@") {
+            children.push(Element::Node(self.annotation()?));
+        }
+        self.expect_ident(&mut children)?;
+        if kind == NodeKind::Field {
+            if self.at("?") {
+                self.bump(&mut children);
+            }
+            self.expect(":", "`:`", &mut children)?;
+            children.push(Element::Node(self.type_expression()?));
+        } else {
+            self.expect("(", "`(`", &mut children)?;
+            children.push(Element::Node(self.type_expression()?));
+            self.expect(")", "`)`", &mut children)?;
+        }
+        self.expect(",", "`,`", &mut children)?;
+        Ok(Node { kind, children })
+    }
+
+    fn generic_arguments(&mut self) -> Result<Node, Unexpected> {
+        let mut children = Vec::new();
+        self.expect("<", "`<`", &mut children)?;
+        while !self.at(">") {
+            let mut argument = Vec::new();
+            self.expect_ident(&mut argument)?;
+            self.expect("=", "`=`", &mut argument)?;
+            argument.push(Element::Node(self.type_expression()?));
+            children.push(Element::Node(Node {
+                kind: NodeKind::GenericArgument,
+                children: argument,
+            }));
+            if !self.at(",") {
+                break;
+            }
+            self.bump(&mut children);
+        }
+        self.expect(">", "`>`", &mut children)?;
+        Ok(Node {
+            kind: NodeKind::GenericArguments,
+            children,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+    use crate::ast::{Item, TypeExpression, Visibility};
+
+    /// `@name(arg=value, ...)`, like the `Display` of [`crate::ast::Annotation`].
+    fn annotation(tree: &SyntaxTree, node: &Node) -> String {
+        let texts: Vec<&str> = node.tokens().iter().map(|t| tree.text(t.span)).collect();
+        let mut result = format!("@{}", texts[1]);
+        if texts.len() > 2 {
+            let args: Vec<String> = texts[3..texts.len() - 1]
+                .split(|text| *text == ",")
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| arg.concat())
+                .collect();
+            result.push_str(&format!("({})", args.join(", ")));
+        }
+        result
+    }
+
+    /// The annotations and the name of a declaration or member, like `@tag(value=1) name`.
+    fn head(tree: &SyntaxTree, node: &Node) -> String {
+        let mut parts: Vec<String> = node
+            .child_nodes()
+            .filter(|child| child.kind == NodeKind::Annotation)
+            .map(|child| annotation(tree, child))
+            .collect();
+        parts.push(tree.text(node.name().unwrap().span).to_string());
+        parts.join(" ")
+    }
+
+    fn node_outline(tree: &SyntaxTree, node: &Node) -> String {
+        let children: Vec<&Node> = node.child_nodes().collect();
+        let text =
+            |node: &Node| -> String { node.tokens().iter().map(|t| tree.text(t.span)).collect() };
+        match node.kind {
+            NodeKind::Builtin | NodeKind::Path => text(node),
+            NodeKind::TypeReference => match children.as_slice() {
+                [path] => text(path),
+                [path, arguments] => {
+                    let mut arguments: Vec<String> = arguments
+                        .child_nodes()
+                        .map(|argument| {
+                            let name = tree.text(argument.name().unwrap().span);
+                            let type_ = argument.child_nodes().next().unwrap();
+                            format!("{}={}", name, node_outline(tree, type_))
+                        })
+                        .collect();
+                    arguments.sort();
+                    format!("{}<{}>", text(path), arguments.join(","))
+                }
+                _ => unreachable!(),
+            },
+            NodeKind::Struct | NodeKind::Variant => {
+                let members: Vec<String> = children
+                    .iter()
+                    .map(|member| {
+                        let type_ = member.child_nodes().last().unwrap();
+                        let optional = member.tokens().iter().any(|t| tree.text(t.span) == "?");
+                        format!(
+                            "{}{}:{}",
+                            head(tree, member),
+                            if optional { "?" } else { "" },
+                            node_outline(tree, type_)
+                        )
+                    })
+                    .collect();
+                let keyword = if node.kind == NodeKind::Struct {
+                    "struct"
+                } else {
+                    "enum"
+                };
+                format!("{}{{{}}}", keyword, members.join(","))
+            }
+            NodeKind::Intersection | NodeKind::Union => {
+                let operator = if node.kind == NodeKind::Intersection {
+                    "&"
+                } else {
+                    "|"
+                };
+                format!(
+                    "({}{}{})",
+                    node_outline(tree, children[0]),
+                    operator,
+                    node_outline(tree, children[1])
+                )
+            }
+            NodeKind::Conditional => {
+                let [left, right, then, otherwise] = children.as_slice() else {
+                    unreachable!()
+                };
+                format!(
+                    "if({}=={}?{}:{})",
+                    node_outline(tree, left),
+                    node_outline(tree, right),
+                    node_outline(tree, then),
+                    node_outline(tree, otherwise)
+                )
+            }
+            NodeKind::TypeDeclaration | NodeKind::UseDeclaration | NodeKind::ModuleDeclaration => {
+                let visibility = if node.tokens().iter().any(|t| tree.text(t.span) == "pub") {
+                    "pub "
+                } else {
+                    ""
+                };
+                let annotations: Vec<String> = children
+                    .iter()
+                    .filter(|child| child.kind == NodeKind::Annotation)
+                    .map(|child| annotation(tree, child) + " ")
+                    .collect();
+                let annotations = annotations.concat();
+                match node.kind {
+                    NodeKind::TypeDeclaration => {
+                        let params = children
+                            .iter()
+                            .find(|child| child.kind == NodeKind::GenericParameters)
+                            .map(|params| {
+                                let names: Vec<&str> = params
+                                    .tokens()
+                                    .iter()
+                                    .filter(|t| t.kind == TokenKind::Ident)
+                                    .map(|t| tree.text(t.span))
+                                    .collect();
+                                format!("<{}>", names.join(","))
+                            })
+                            .unwrap_or_default();
+                        format!(
+                            "{}{}type {}{}={}",
+                            annotations,
+                            visibility,
+                            tree.text(node.name().unwrap().span),
+                            params,
+                            node_outline(tree, children.last().unwrap())
+                        )
+                    }
+                    NodeKind::UseDeclaration => format!(
+                        "{}{}use {}",
+                        annotations,
+                        visibility,
+                        text(children.last().unwrap())
+                    ),
+                    _ => {
+                        let items: Vec<String> = children
+                            .iter()
+                            .filter(|child| child.kind != NodeKind::Annotation)
+                            .map(|child| node_outline(tree, child))
+                            .collect();
+                        format!(
+                            "{}{}mod {}{{{}}}",
+                            annotations,
+                            visibility,
+                            tree.text(node.name().unwrap().span),
+                            items.join(";")
+                        )
+                    }
+                }
+            }
+            _ => unreachable!("{:?} is part of another node", node.kind),
+        }
+    }
+
+    /// The same outline as [`node_outline`], from the syntax tree of [`crate::parse`].
+    fn type_outline(t: &TypeExpression) -> String {
+        match t {
+            TypeExpression::Variable(name) => name.to_string(),
+            TypeExpression::Builtin(builtin) => builtin.to_string(),
+            TypeExpression::Generic(name, args) => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|(name, t)| format!("{}={}", name, type_outline(t)))
+                    .collect();
+                format!("{}<{}>", name, args.join(","))
+            }
+            TypeExpression::Struct(s) => {
+                let fields: Vec<String> = s
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        format!(
+                            "{}{}{}:{}",
+                            annotations(&field.annotations),
+                            field.name,
+                            if field.optional { "?" } else { "" },
+                            type_outline(&field.type_)
+                        )
+                    })
+                    .collect();
+                format!("struct{{{}}}", fields.join(","))
+            }
+            TypeExpression::Variant(v) => {
+                let options: Vec<String> = v
+                    .variants
+                    .iter()
+                    .map(|option| {
+                        format!(
+                            "{}{}:{}",
+                            annotations(&option.annotations),
+                            option.name,
+                            type_outline(&option.type_)
+                        )
+                    })
+                    .collect();
+                format!("enum{{{}}}", options.join(","))
+            }
+            TypeExpression::Intersection(a, b) => {
+                format!("({}&{})", type_outline(a), type_outline(b))
+            }
+            TypeExpression::Union(a, b) => format!("({}|{})", type_outline(a), type_outline(b)),
+            TypeExpression::Conditional(c) => format!(
+                "if({}=={}?{}:{})",
+                type_outline(&c.left),
+                type_outline(&c.right),
+                type_outline(&c.then),
+                type_outline(&c.otherwise)
+            ),
+        }
+    }
+
+    fn annotations(annotations: &[crate::ast::Annotation]) -> String {
+        annotations.iter().map(|a| format!("{} ", a)).collect()
+    }
+
+    fn item_outline(item: &Item) -> String {
+        let visibility = |visibility: &Visibility| match visibility {
+            Visibility::Public => "pub ",
+            Visibility::Private => "",
+        };
+        match item {
+            Item::Type(declaration) => {
+                let params = if declaration.params.is_empty() {
+                    String::new()
+                } else {
+                    format!("<{}>", declaration.params.join(","))
+                };
+                format!(
+                    "{}{}type {}{}={}",
+                    annotations(&declaration.annotations),
+                    visibility(&declaration.visibility),
+                    declaration.name,
+                    params,
+                    type_outline(&declaration.type_)
+                )
+            }
+            Item::Use(declaration) => format!(
+                "{}{}use {}",
+                annotations(&declaration.annotations),
+                visibility(&declaration.visibility),
+                declaration.path
+            ),
+            Item::Module(declaration) => {
+                let items: Vec<String> = declaration.items.iter().map(item_outline).collect();
+                format!(
+                    "{}{}mod {}{{{}}}",
+                    annotations(&declaration.annotations),
+                    visibility(&declaration.visibility),
+                    declaration.name,
+                    items.join(";")
+                )
+            }
+        }
+    }
+
+    /// Parses `source` with both parsers, and checks that the trees agree and that the concrete
+    /// one gives back the source.
+    fn assert_parsers_agree(source: &str, file_name: &str) {
+        let items = crate::parse(source, file_name).unwrap();
+        let tree = parse(source, file_name).unwrap();
+        assert_eq!(tree.to_string(), source, "{}", file_name);
+        let expected: Vec<String> = items.iter().map(item_outline).collect();
+        let actual: Vec<String> = tree
+            .root
+            .child_nodes()
+            .map(|node| node_outline(&tree, node))
+            .collect();
+        assert_eq!(actual, expected, "{}", file_name);
+    }
+
+    #[test]
+    fn examples_parse_the_same_with_both_parsers() {
+        let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+        let mut parsed = 0;
+        for entry in fs::read_dir(examples).unwrap() {
+            let path = entry.unwrap().path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "roto")
+            {
+                let source = fs::read_to_string(&path).unwrap();
+                assert_parsers_agree(&source, &path.display().to_string());
+                parsed += 1;
+            }
+        }
+        assert!(parsed > 0);
+    }
+
+    #[test]
+    fn every_construct_parses_the_same_with_both_parsers() {
+        let source = r#"// A comment.
+@deprecated(reason="use \"B\"")
+pub type A<T, U> = struct {
+    @tag(value=1) @rename(name=first) first?: T,
+    second: Page<U=int, T=auth.User>,
+} & B | C;
+
+pub mod auth {
+    /// A user.
+    type User = enum {
+        Active(unit),
+        Inactive(if string == T then bool else float),
+    };
+    pub use other.Thing;
+}
+
+use auth.User;
+"#;
+        assert_parsers_agree(source, "test.roto");
+    }
+}
@@ -0,0 +1,74 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::ast::SourceLocation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in a schema that does not necessarily stop compilation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub location: Option<SourceLocation>,
+    /// Identifies the kind of problem for tools, like `unknown-annotation`.
+    pub code: Option<&'static str>,
+}
+
+impl Diagnostic {
+    pub fn warning(message: String, location: Option<SourceLocation>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            message,
+            location,
+            code: None,
+        }
+    }
+
+    pub fn error(message: String, location: Option<SourceLocation>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message,
+            location,
+            code: None,
+        }
+    }
+
+    pub fn with_code(self, code: &'static str) -> Self {
+        Diagnostic {
+            code: Some(code),
+            ..self
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{}: ", location)?;
+        }
+        write!(f, "{}: {}", self.severity, self.message)?;
+        if let Some(code) = self.code {
+            write!(f, " [{}]", code)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if any of the diagnostics is an error.
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
@@ -0,0 +1,538 @@
+use core::fmt;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    ast::Annotation,
+    frontend::IRCompiler,
+    intern::Atom,
+    ir::{
+        IRType, PrimitiveStruct, PrimitiveStructField, PrimitiveType, PrimitiveVariant,
+        ResolvedIRType, TypeName,
+    },
+};
+
+/// A single semantic difference between two versions of a compiled schema. Paths are dotted and
+/// start at the global type name, e.g. `User.status` for the `status` field of `User`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaChange {
+    TypeAdded {
+        name: String,
+    },
+    TypeRemoved {
+        name: String,
+    },
+    /// The shape at `path` changed, e.g. from `string` to `int` or from a struct to an enum.
+    Retyped {
+        path: String,
+        old: String,
+        new: String,
+    },
+    /// The reference at `path` now points to a different named type. Structural differences
+    /// between the two types are reported as separate changes.
+    ReferenceChanged {
+        path: String,
+        old: String,
+        new: String,
+    },
+    FieldAdded {
+        path: String,
+        optional: bool,
+    },
+    FieldRemoved {
+        path: String,
+    },
+    /// A field at the same position with the same type but a different name. `path` points to
+    /// the struct containing the field.
+    FieldRenamed {
+        path: String,
+        old_name: String,
+        new_name: String,
+    },
+    FieldOptionalityChanged {
+        path: String,
+        optional: bool,
+    },
+    VariantAdded {
+        path: String,
+    },
+    VariantRemoved {
+        path: String,
+    },
+    CommentChanged {
+        path: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    /// The annotations of the global type `path` changed. They are written as in the source.
+    AnnotationsChanged {
+        path: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+}
+
+impl SchemaChange {
+    pub fn path(&self) -> &str {
+        match self {
+            SchemaChange::TypeAdded { name } | SchemaChange::TypeRemoved { name } => name,
+            SchemaChange::Retyped { path, .. }
+            | SchemaChange::ReferenceChanged { path, .. }
+            | SchemaChange::FieldAdded { path, .. }
+            | SchemaChange::FieldRemoved { path }
+            | SchemaChange::FieldRenamed { path, .. }
+            | SchemaChange::FieldOptionalityChanged { path, .. }
+            | SchemaChange::VariantAdded { path }
+            | SchemaChange::VariantRemoved { path }
+            | SchemaChange::CommentChanged { path, .. }
+            | SchemaChange::AnnotationsChanged { path, .. } => path,
+        }
+    }
+
+    /// A human readable description of the change, without the path.
+    pub fn description(&self) -> String {
+        match self {
+            SchemaChange::TypeAdded { .. } => "type added".to_string(),
+            SchemaChange::TypeRemoved { .. } => "type removed".to_string(),
+            SchemaChange::Retyped { old, new, .. } => {
+                format!("type changed from {} to {}", old, new)
+            }
+            SchemaChange::ReferenceChanged { old, new, .. } => {
+                format!("reference changed from {} to {}", old, new)
+            }
+            SchemaChange::FieldAdded { optional: true, .. } => "optional field added".to_string(),
+            SchemaChange::FieldAdded {
+                optional: false, ..
+            } => "required field added".to_string(),
+            SchemaChange::FieldRemoved { .. } => "field removed".to_string(),
+            SchemaChange::FieldRenamed {
+                old_name, new_name, ..
+            } => format!("field {} renamed to {}", old_name, new_name),
+            SchemaChange::FieldOptionalityChanged { optional: true, .. } => {
+                "field became optional".to_string()
+            }
+            SchemaChange::FieldOptionalityChanged {
+                optional: false, ..
+            } => "field became required".to_string(),
+            SchemaChange::VariantAdded { .. } => "variant option added".to_string(),
+            SchemaChange::VariantRemoved { .. } => "variant option removed".to_string(),
+            SchemaChange::CommentChanged { .. } => "comment changed".to_string(),
+            SchemaChange::AnnotationsChanged { old, new, .. } => {
+                let list = |annotations: &[String]| match annotations.is_empty() {
+                    true => "none".to_string(),
+                    false => annotations.join(" "),
+                };
+                format!("annotations changed from {} to {}", list(old), list(new))
+            }
+        }
+    }
+}
+
+impl Display for SchemaChange {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path(), self.description())
+    }
+}
+
+/// Computes the semantic differences between the global types of two compiled schemas. Types are
+/// matched by their global name, fields and variant options by their name.
+pub fn diff(old: &IRCompiler, new: &IRCompiler) -> Vec<SchemaChange> {
+    let mut differ = SchemaDiffer {
+        old,
+        new,
+        visited: HashSet::new(),
+        changes: Vec::new(),
+    };
+    differ.diff();
+    differ.changes
+}
+
+fn global_types(compiler: &IRCompiler) -> BTreeMap<Atom, usize> {
+    compiler
+        .iter_types()
+        .filter_map(|(id, t)| match &t.name {
+            TypeName::Variable(name) => Some((*name, *id)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn alias_target(compiler: &IRCompiler, id: usize) -> Option<Atom> {
+    match &compiler.named_type(id).type_ {
+        IRType::Reference(target) => match &compiler.named_type(*target).name {
+            TypeName::Variable(name) => Some(*name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn describe_resolved(t: &ResolvedIRType) -> String {
+    match t {
+        ResolvedIRType::Struct(_) => "struct".to_string(),
+        ResolvedIRType::Variant(_) => "enum".to_string(),
+        ResolvedIRType::Builtin(builtin) => builtin.to_string(),
+    }
+}
+
+fn global_name(compiler: &IRCompiler, t: &PrimitiveType) -> Option<Atom> {
+    match t {
+        PrimitiveType::Reference(id) => match &compiler.named_type(*id).name {
+            TypeName::Variable(name) => Some(*name),
+            _ => None,
+        },
+        PrimitiveType::Builtin(_) => None,
+    }
+}
+
+struct SchemaDiffer<'a> {
+    old: &'a IRCompiler,
+    new: &'a IRCompiler,
+    visited: HashSet<(usize, usize)>,
+    changes: Vec<SchemaChange>,
+}
+
+impl<'a> SchemaDiffer<'a> {
+    fn diff(&mut self) {
+        let old_globals = global_types(self.old);
+        let new_globals = global_types(self.new);
+
+        for (name, old_id) in &old_globals {
+            match new_globals.get(name) {
+                Some(new_id) => {
+                    self.compare_metadata(name, *old_id, *new_id);
+                    // Aliases of the same global are covered when that global is compared.
+                    let old_target = alias_target(self.old, *old_id);
+                    if old_target.is_some() && old_target == alias_target(self.new, *new_id) {
+                        continue;
+                    }
+                    self.compare_ids(name, *old_id, *new_id)
+                }
+                None => self.changes.push(SchemaChange::TypeRemoved {
+                    name: name.to_string(),
+                }),
+            }
+        }
+        for name in new_globals.keys() {
+            if !old_globals.contains_key(name) {
+                self.changes.push(SchemaChange::TypeAdded {
+                    name: name.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Compares the doc comment and the annotations of the global type `name`.
+    fn compare_metadata(&mut self, name: &str, old_id: usize, new_id: usize) {
+        let old = &self.old.named_type(old_id).metadata;
+        let new = &self.new.named_type(new_id).metadata;
+        if old.doc != new.doc {
+            self.changes.push(SchemaChange::CommentChanged {
+                path: name.to_string(),
+                old: old.doc.clone(),
+                new: new.doc.clone(),
+            });
+        }
+        if old.annotations != new.annotations {
+            let written =
+                |annotations: &[Annotation]| annotations.iter().map(|a| a.to_string()).collect();
+            self.changes.push(SchemaChange::AnnotationsChanged {
+                path: name.to_string(),
+                old: written(&old.annotations),
+                new: written(&new.annotations),
+            });
+        }
+    }
+
+    fn compare_ids(&mut self, path: &str, old_id: usize, new_id: usize) {
+        if !self.visited.insert((old_id, new_id)) {
+            return;
+        }
+        let old = self.old.resolve_ir_type(&IRType::Reference(old_id));
+        let new = self.new.resolve_ir_type(&IRType::Reference(new_id));
+        self.compare_resolved(path, &old, &new);
+    }
+
+    fn compare_resolved(&mut self, path: &str, old: &ResolvedIRType, new: &ResolvedIRType) {
+        match (old, new) {
+            (ResolvedIRType::Struct(a), ResolvedIRType::Struct(b)) => {
+                self.compare_structs(path, a, b)
+            }
+            (ResolvedIRType::Variant(a), ResolvedIRType::Variant(b)) => {
+                self.compare_variants(path, a, b)
+            }
+            (ResolvedIRType::Builtin(a), ResolvedIRType::Builtin(b)) if a == b => {}
+            _ => self.changes.push(SchemaChange::Retyped {
+                path: path.to_string(),
+                old: describe_resolved(old),
+                new: describe_resolved(new),
+            }),
+        }
+    }
+
+    fn compare_primitive(&mut self, path: &str, old: &PrimitiveType, new: &PrimitiveType) {
+        match (old, new) {
+            (PrimitiveType::Builtin(a), PrimitiveType::Builtin(b)) => {
+                if a != b {
+                    self.changes.push(SchemaChange::Retyped {
+                        path: path.to_string(),
+                        old: a.to_string(),
+                        new: b.to_string(),
+                    });
+                }
+            }
+            (PrimitiveType::Reference(a), PrimitiveType::Reference(b)) => {
+                match (global_name(self.old, old), global_name(self.new, new)) {
+                    // Named globals that exist in both versions are compared on their own.
+                    (Some(x), Some(y)) if x == y => return,
+                    (Some(x), Some(y)) => self.changes.push(SchemaChange::ReferenceChanged {
+                        path: path.to_string(),
+                        old: x.to_string(),
+                        new: y.to_string(),
+                    }),
+                    _ => {}
+                }
+                self.compare_ids(path, *a, *b);
+            }
+            _ => {
+                let old = self.old.resolve_ir_type(&old.clone().into());
+                let new = self.new.resolve_ir_type(&new.clone().into());
+                self.compare_resolved(path, &old, &new);
+            }
+        }
+    }
+
+    /// Two fields are considered the same field under a different name if they have the same
+    /// position, optionality and a trivially equal type.
+    fn is_rename(
+        &self,
+        old: &PrimitiveStructField,
+        old_index: usize,
+        new: &PrimitiveStructField,
+        new_index: usize,
+    ) -> bool {
+        if old_index != new_index || old.optional != new.optional {
+            return false;
+        }
+        match (&old.type_, &new.type_) {
+            (PrimitiveType::Builtin(a), PrimitiveType::Builtin(b)) => a == b,
+            (PrimitiveType::Reference(_), PrimitiveType::Reference(_)) => {
+                let old_name = global_name(self.old, &old.type_);
+                old_name.is_some() && old_name == global_name(self.new, &new.type_)
+            }
+            _ => false,
+        }
+    }
+
+    fn compare_structs(&mut self, path: &str, old: &PrimitiveStruct, new: &PrimitiveStruct) {
+        let mut removed = Vec::new();
+        for (index, old_field) in old.fields.iter().enumerate() {
+            let field_path = format!("{}.{}", path, old_field.name);
+            let Some(new_field) = new.fields.iter().find(|f| f.name == old_field.name) else {
+                removed.push((index, old_field));
+                continue;
+            };
+
+            if old_field.optional != new_field.optional {
+                self.changes.push(SchemaChange::FieldOptionalityChanged {
+                    path: field_path.clone(),
+                    optional: new_field.optional,
+                });
+            }
+            if old_field.comment != new_field.comment {
+                self.changes.push(SchemaChange::CommentChanged {
+                    path: field_path.clone(),
+                    old: old_field.comment.clone(),
+                    new: new_field.comment.clone(),
+                });
+            }
+            self.compare_primitive(&field_path, &old_field.type_, &new_field.type_);
+        }
+
+        for (index, new_field) in new.fields.iter().enumerate() {
+            if old.fields.iter().any(|f| f.name == new_field.name) {
+                continue;
+            }
+            let renamed = removed.iter().position(|(old_index, old_field)| {
+                self.is_rename(old_field, *old_index, new_field, index)
+            });
+            match renamed {
+                Some(position) => {
+                    let (_, old_field) = removed.remove(position);
+                    self.changes.push(SchemaChange::FieldRenamed {
+                        path: path.to_string(),
+                        old_name: old_field.name.to_string(),
+                        new_name: new_field.name.to_string(),
+                    });
+                }
+                None => self.changes.push(SchemaChange::FieldAdded {
+                    path: format!("{}.{}", path, new_field.name),
+                    optional: new_field.optional,
+                }),
+            }
+        }
+
+        for (_, old_field) in removed {
+            self.changes.push(SchemaChange::FieldRemoved {
+                path: format!("{}.{}", path, old_field.name),
+            });
+        }
+    }
+
+    fn compare_variants(&mut self, path: &str, old: &PrimitiveVariant, new: &PrimitiveVariant) {
+        for old_option in &old.variants {
+            let option_path = format!("{}.{}", path, old_option.name);
+            let Some(new_option) = new.variants.iter().find(|v| v.name == old_option.name) else {
+                self.changes
+                    .push(SchemaChange::VariantRemoved { path: option_path });
+                continue;
+            };
+
+            if old_option.comment != new_option.comment {
+                self.changes.push(SchemaChange::CommentChanged {
+                    path: option_path.clone(),
+                    old: old_option.comment.clone(),
+                    new: new_option.comment.clone(),
+                });
+            }
+            self.compare_primitive(&option_path, &old_option.type_, &new_option.type_);
+        }
+
+        for new_option in &new.variants {
+            if !old.variants.iter().any(|v| v.name == new_option.name) {
+                self.changes.push(SchemaChange::VariantAdded {
+                    path: format!("{}.{}", path, new_option.name),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str) -> IRCompiler {
+        let items = crate::parse(source, "test.roto").expect("the source parses");
+        let mut compiler = IRCompiler::new();
+        compiler.register_program(items, "test.roto", source);
+        compiler.compile_globals().unwrap();
+        compiler
+    }
+
+    /// The changes from `old` to `new`, as they are displayed.
+    fn changes(old: &str, new: &str) -> Vec<String> {
+        diff(&compile(old), &compile(new))
+            .iter()
+            .map(|change| change.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn equal_schemas_have_no_changes() {
+        let source = "type User = struct { name: string, status: Status, };
+                      type Status = enum { active(unit), };";
+        assert!(changes(source, source).is_empty());
+    }
+
+    #[test]
+    fn types_are_matched_by_name() {
+        assert_eq!(
+            changes("type A = int; type B = int;", "type B = int; type C = int;"),
+            ["A: type removed", "C: type added"]
+        );
+    }
+
+    #[test]
+    fn fields_are_added_removed_and_retyped() {
+        assert_eq!(
+            changes(
+                "type User = struct { name: string, age: int, email?: string, };",
+                "type User = struct { name: int, email: string, phone?: string, id: string, };",
+            ),
+            [
+                "User.name: type changed from string to int",
+                "User.email: field became required",
+                "User.phone: optional field added",
+                "User.id: required field added",
+                "User.age: field removed",
+            ]
+        );
+    }
+
+    #[test]
+    fn fields_at_the_same_position_with_the_same_type_are_renamed() {
+        assert_eq!(
+            changes(
+                "type User = struct { name: string, age: int, };",
+                "type User = struct { full_name: string, years: string, };",
+            ),
+            [
+                "User: field name renamed to full_name",
+                "User.years: required field added",
+                "User.age: field removed",
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_and_variant_changes_have_paths() {
+        assert_eq!(
+            changes(
+                "type Status = enum { active(unit), inactive(unit), };
+                 type User = struct { status: Status, address: struct { city: string, }, };",
+                "type Status = enum { active(int), pending(unit), };
+                 type User = struct { status: Status, address: struct { city: int, }, };",
+            ),
+            [
+                "Status.active: type changed from unit to int",
+                "Status.inactive: variant option removed",
+                "Status.pending: variant option added",
+                "User.address.city: type changed from string to int",
+            ]
+        );
+    }
+
+    #[test]
+    fn references_to_other_types_are_reported() {
+        assert_eq!(
+            changes(
+                "type A = struct { x: int, }; type B = struct { x: int, };
+                 type User = struct { a: A, };",
+                "type A = struct { x: int, }; type B = struct { x: int, };
+                 type User = struct { a: B, };",
+            ),
+            ["User.a: reference changed from A to B"]
+        );
+    }
+
+    #[test]
+    fn comments_are_compared() {
+        assert_eq!(
+            changes(
+                "type User = struct {\n    // The name.\n    name: string,\n};",
+                "type User = struct {\n    // The full name.\n    name: string,\n};",
+            ),
+            ["User.name: comment changed"]
+        );
+    }
+
+    #[test]
+    fn docs_and_annotations_of_types_are_compared() {
+        assert_eq!(
+            changes(
+                "// A user.\n@deprecated\ntype User = struct { name: string, };\n\
+                 type Id = string;",
+                "// A person.\ntype User = struct { name: string, };\n\
+                 @format(value=\"uuid\")\ntype Id = string;",
+            ),
+            [
+                "Id: annotations changed from none to @format(value=\"uuid\")",
+                "User: comment changed",
+                "User: annotations changed from @deprecated to none",
+            ]
+        );
+    }
+}
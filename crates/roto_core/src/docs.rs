@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    ast,
+    frontend::IRCompiler,
+    ir::{IRType, PrimitiveType, TypeName},
+};
+
+/// A reference from a documented member to its type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeRef {
+    Builtin(ast::Builtin),
+    /// A link to another documented type, identified by its id in the [`DocModel`].
+    Type {
+        id: usize,
+        name: String,
+    },
+}
+
+/// A documented struct field or variant option.
+#[derive(Debug, Clone)]
+pub struct MemberDoc {
+    pub name: String,
+    pub type_: TypeRef,
+    pub optional: bool,
+    pub description: Option<String>,
+    pub annotations: Vec<ast::Annotation>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeDocKind {
+    Struct { fields: Vec<MemberDoc> },
+    Variant { options: Vec<MemberDoc> },
+    Alias { target: TypeRef },
+    Builtin(ast::Builtin),
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeDoc {
+    pub id: usize,
+    pub name: TypeName,
+    pub description: Option<String>,
+    pub annotations: Vec<ast::Annotation>,
+    /// The format of the values of an alias, like `email`, from `@format`.
+    pub format: Option<String>,
+    pub location: Option<ast::SourceLocation>,
+    pub kind: TypeDocKind,
+    /// Ids of the types that mention this type in one of their members or as alias target.
+    pub referenced_by: Vec<usize>,
+}
+
+impl TypeDoc {
+    /// Global types are the ones declared without parameters in the source.
+    pub fn is_global(&self) -> bool {
+        matches!(self.name, TypeName::Variable(_))
+    }
+
+    pub fn references(&self) -> Vec<usize> {
+        let refs: Vec<&TypeRef> = match &self.kind {
+            TypeDocKind::Struct { fields: members } | TypeDocKind::Variant { options: members } => {
+                members.iter().map(|m| &m.type_).collect()
+            }
+            TypeDocKind::Alias { target } => vec![target],
+            TypeDocKind::Builtin(_) => vec![],
+        };
+        let mut ids = Vec::new();
+        for r in refs {
+            if let TypeRef::Type { id, .. } = r {
+                if !ids.contains(id) {
+                    ids.push(*id);
+                }
+            }
+        }
+        ids
+    }
+}
+
+/// A backend independent description of all types of a compiled schema, meant to be rendered by
+/// documentation generators.
+#[derive(Debug, Clone)]
+pub struct DocModel {
+    pub types: BTreeMap<usize, TypeDoc>,
+}
+
+impl DocModel {
+    pub fn get(&self, id: usize) -> Option<&TypeDoc> {
+        self.types.get(&id)
+    }
+
+    pub fn iter_globals(&self) -> impl Iterator<Item = &TypeDoc> {
+        self.types.values().filter(|t| t.is_global())
+    }
+}
+
+fn type_ref(compiler: &IRCompiler, t: &PrimitiveType) -> TypeRef {
+    match t {
+        PrimitiveType::Builtin(builtin) => TypeRef::Builtin(builtin.clone()),
+        PrimitiveType::Reference(id) => TypeRef::Type {
+            id: *id,
+            name: compiler.named_type(*id).name.to_string(),
+        },
+    }
+}
+
+/// Walks the IR of `compiler` and collects the documentation of every allocated type.
+pub fn build_doc_model(compiler: &IRCompiler) -> DocModel {
+    let mut types = BTreeMap::new();
+    for (id, t) in compiler.iter_types() {
+        let kind = match &t.type_ {
+            IRType::Struct(s) => TypeDocKind::Struct {
+                fields: s
+                    .fields
+                    .iter()
+                    .map(|f| MemberDoc {
+                        name: f.name.to_string(),
+                        type_: type_ref(compiler, &f.type_),
+                        optional: f.optional,
+                        description: f.comment.clone(),
+                        annotations: f.annotations.clone(),
+                    })
+                    .collect(),
+            },
+            IRType::Variant(v) => TypeDocKind::Variant {
+                options: v
+                    .variants
+                    .iter()
+                    .map(|o| MemberDoc {
+                        name: o.name.to_string(),
+                        type_: type_ref(compiler, &o.type_),
+                        optional: false,
+                        description: o.comment.clone(),
+                        annotations: o.annotations.clone(),
+                    })
+                    .collect(),
+            },
+            IRType::Reference(target) => TypeDocKind::Alias {
+                target: type_ref(compiler, &PrimitiveType::Reference(*target)),
+            },
+            IRType::Builtin(builtin) => TypeDocKind::Builtin(builtin.clone()),
+        };
+        types.insert(
+            *id,
+            TypeDoc {
+                id: *id,
+                name: t.name.clone(),
+                description: t.metadata.doc.clone(),
+                annotations: t.metadata.annotations.clone(),
+                format: t.metadata.format().map(str::to_string),
+                location: t.metadata.location.clone(),
+                kind,
+                referenced_by: Vec::new(),
+            },
+        );
+    }
+
+    let edges: Vec<(usize, usize)> = types
+        .values()
+        .flat_map(|t| t.references().into_iter().map(move |target| (t.id, target)))
+        .collect();
+    for (source, target) in edges {
+        if let Some(target) = types.get_mut(&target) {
+            target.referenced_by.push(source);
+        }
+    }
+
+    DocModel { types }
+}
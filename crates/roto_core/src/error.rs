@@ -0,0 +1,217 @@
+//! What can go wrong between reading a schema and writing the generated code, for library users
+//! that need to tell the failures apart.
+
+use std::fmt::Display;
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::ast::SourceLocation;
+use crate::backend::BackendError;
+use crate::diagnostics::Diagnostic;
+use crate::symbols::LookupError;
+use crate::syntax::ParseDiagnostic;
+
+fn lines<T: Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Arguments like `<K, V>`, or `none` if there are none.
+fn arguments(names: &[String], none: &str) -> String {
+    if names.is_empty() {
+        none.to_string()
+    } else {
+        format!("<{}>", names.join(", "))
+    }
+}
+
+/// A generic type used with arguments that are not its parameters, or a type without parameters
+/// used with arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "type {name} expects {}, found {}",
+    arguments(.expected, "no arguments"),
+    arguments(.provided, "none")
+)]
+pub struct ArgumentMismatch {
+    pub name: String,
+    pub expected: Vec<String>,
+    pub provided: Vec<String>,
+    /// Where the type is used.
+    pub location: Option<SourceLocation>,
+}
+
+/// `, also declared at {location}`, if the other declaration of a member is known.
+fn also_declared(other: &Option<SourceLocation>) -> String {
+    other
+        .as_ref()
+        .map(|other| format!(", also declared at {}", other))
+        .unwrap_or_default()
+}
+
+/// A member that two operands of an intersection or union both have, where they can not be
+/// merged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberConflict {
+    pub name: String,
+    /// Where the member of the right operand is declared.
+    pub location: Option<SourceLocation>,
+    /// Where the member of the left operand is declared.
+    pub other: Option<SourceLocation>,
+}
+
+/// A reference in a declaration that does not resolve, because the type is not declared or not
+/// visible from the module of the declaration.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{error}")]
+pub struct Unresolved {
+    pub error: LookupError,
+    /// The declaration the reference is in.
+    pub location: Option<SourceLocation>,
+}
+
+/// Why the registered types could not be compiled.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CompileError {
+    #[error(transparent)]
+    Lookup(#[from] LookupError),
+    #[error(transparent)]
+    Unresolved(Box<Unresolved>),
+    #[error("{name} is a module, not a type")]
+    NotAType { name: String },
+    #[error(transparent)]
+    ArgumentMismatch(Box<ArgumentMismatch>),
+    /// Only structs can be intersected with structs, and variants with variants. Located at the
+    /// declaration or member whose type the intersection is.
+    #[error("intersection of incompatible types")]
+    IncompatibleIntersection { location: Option<SourceLocation> },
+    /// Only variants can be united.
+    #[error("union of incompatible types")]
+    IncompatibleUnion { location: Option<SourceLocation> },
+    #[error(
+        "intersection of types that both have the member {}{}",
+        .0.name,
+        also_declared(&.0.other)
+    )]
+    OverlappingMember(Box<MemberConflict>),
+    #[error(
+        "union of variants with option {} that has different payloads{}",
+        .0.name,
+        also_declared(&.0.other)
+    )]
+    ConflictingOption(Box<MemberConflict>),
+    /// An operand of an intersection or union that refers back to the type being defined, which
+    /// has no members to merge yet.
+    #[error("{name} is an operand of an intersection or union in its own definition")]
+    CyclicType {
+        name: String,
+        location: Option<SourceLocation>,
+    },
+    /// A conditional type that compares a type whose definition depends on the outcome of the
+    /// comparison.
+    #[error("{name} is compared by a conditional type in its own definition")]
+    CyclicConditional {
+        name: String,
+        location: Option<SourceLocation>,
+    },
+    /// Aliases annotated with `@inline` that refer to each other, which leaves nothing to inline
+    /// them as.
+    #[error("{name} is an @inline alias of itself")]
+    CyclicInlineAlias {
+        name: String,
+        location: Option<SourceLocation>,
+    },
+    /// An inline struct or variant compiled on its own, rather than as part of a named type that
+    /// its temporary could be named after.
+    #[error("inline struct or enum outside of a named type")]
+    UnnamedInlineType,
+    /// The annotations are invalid. The diagnostics include the warnings.
+    #[error("{}", lines(.0))]
+    Annotations(Vec<Diagnostic>),
+}
+
+impl CompileError {
+    /// The error as diagnostics, like the command line reports it.
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        match self {
+            CompileError::Annotations(diagnostics) => diagnostics,
+            CompileError::ArgumentMismatch(mismatch) => {
+                let location = mismatch.location.clone();
+                vec![Diagnostic::error(mismatch.to_string(), location)
+                    .with_code("generic-arguments")]
+            }
+            CompileError::Unresolved(unresolved) => {
+                let code = match unresolved.error {
+                    LookupError::NotFound { .. } => "unknown-type",
+                    LookupError::Private { .. } => "private-type",
+                    LookupError::Cycle { .. } => "reexport-cycle",
+                };
+                let location = unresolved.location.clone();
+                vec![Diagnostic::error(unresolved.to_string(), location).with_code(code)]
+            }
+            CompileError::IncompatibleIntersection { ref location } => {
+                let location = location.clone();
+                vec![Diagnostic::error(self.to_string(), location)
+                    .with_code("incompatible-intersection")]
+            }
+            CompileError::IncompatibleUnion { ref location } => {
+                let location = location.clone();
+                vec![Diagnostic::error(self.to_string(), location).with_code("incompatible-union")]
+            }
+            CompileError::OverlappingMember(ref conflict) => {
+                let location = conflict.location.clone();
+                vec![Diagnostic::error(self.to_string(), location).with_code("overlapping-member")]
+            }
+            CompileError::ConflictingOption(ref conflict) => {
+                let location = conflict.location.clone();
+                vec![Diagnostic::error(self.to_string(), location).with_code("conflicting-option")]
+            }
+            CompileError::CyclicType { ref location, .. } => {
+                let location = location.clone();
+                vec![Diagnostic::error(self.to_string(), location).with_code("cyclic-type")]
+            }
+            CompileError::CyclicConditional { ref location, .. } => {
+                let location = location.clone();
+                vec![Diagnostic::error(self.to_string(), location).with_code("cyclic-conditional")]
+            }
+            CompileError::CyclicInlineAlias { ref location, .. } => {
+                let location = location.clone();
+                vec![Diagnostic::error(self.to_string(), location).with_code("cyclic-inline-alias")]
+            }
+            error => vec![Diagnostic::error(error.to_string(), None)],
+        }
+    }
+}
+
+/// Any failure of turning a schema into code.
+#[derive(Debug, Error)]
+pub enum RotoError {
+    #[error("{}", lines(.0))]
+    Parse(Vec<ParseDiagnostic>),
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+    #[error(transparent)]
+    Codegen(#[from] BackendError),
+    #[error("{}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl RotoError {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        RotoError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl From<Vec<ParseDiagnostic>> for RotoError {
+    fn from(errors: Vec<ParseDiagnostic>) -> Self {
+        RotoError::Parse(errors)
+    }
+}
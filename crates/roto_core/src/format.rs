@@ -0,0 +1,175 @@
+//! The canonical layout of a schema, as written by `roto fmt`. Comments and annotations are part
+//! of the AST, so formatting a parsed program keeps them.
+
+use crate::ast::{Annotation, Item, StructField, TypeExpression, VariantOption, Visibility};
+
+const INDENT: &str = "    ";
+
+/// Formats `items` as a schema file: one declaration per paragraph, four spaces of indentation
+/// and a trailing comma after every field and option. Consecutive `use` declarations are kept
+/// together.
+pub fn format_program(items: &[Item]) -> String {
+    let mut result = String::new();
+    write_items(&mut result, items, "");
+    result
+}
+
+/// Formats a type expression as it is written in a declaration, like `Array<T=User>`.
+pub fn format_type(t: &TypeExpression) -> String {
+    let mut result = String::new();
+    write_type(&mut result, t, "");
+    result
+}
+
+fn write_items(result: &mut String, items: &[Item], indent: &str) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 && !matches!((&items[i - 1], item), (Item::Use(_), Item::Use(_))) {
+            result.push('\n');
+        }
+        write_item(result, item, indent);
+    }
+}
+
+fn write_comment(result: &mut String, comment: &Option<String>, indent: &str) {
+    let Some(comment) = comment else {
+        return;
+    };
+    for line in comment.lines() {
+        if line.is_empty() {
+            result.push_str(&format!("{}//\n", indent));
+        } else {
+            result.push_str(&format!("{}// {}\n", indent, line));
+        }
+    }
+}
+
+fn write_annotations(result: &mut String, annotations: &[Annotation], indent: &str) {
+    for annotation in annotations {
+        result.push_str(&format!("{}{}\n", indent, annotation));
+    }
+}
+
+fn visibility(visibility: Visibility) -> &'static str {
+    match visibility {
+        Visibility::Public => "pub ",
+        Visibility::Private => "",
+    }
+}
+
+fn write_item(result: &mut String, item: &Item, indent: &str) {
+    match item {
+        Item::Type(decl) => {
+            write_comment(result, &decl.doc, indent);
+            write_annotations(result, &decl.annotations, indent);
+            result.push_str(&format!(
+                "{}{}type {}",
+                indent,
+                visibility(decl.visibility),
+                decl.name
+            ));
+            if !decl.params.is_empty() {
+                result.push_str(&format!("<{}>", decl.params.join(", ")));
+            }
+            result.push_str(" = ");
+            write_type(result, &decl.type_, indent);
+            result.push_str(";\n");
+        }
+        Item::Use(decl) => {
+            write_comment(result, &decl.doc, indent);
+            write_annotations(result, &decl.annotations, indent);
+            result.push_str(&format!(
+                "{}{}use {};\n",
+                indent,
+                visibility(decl.visibility),
+                decl.path
+            ));
+        }
+        Item::Module(decl) => {
+            write_comment(result, &decl.doc, indent);
+            write_annotations(result, &decl.annotations, indent);
+            result.push_str(&format!(
+                "{}{}mod {} {{\n",
+                indent,
+                visibility(decl.visibility),
+                decl.name
+            ));
+            write_items(result, &decl.items, &format!("{}{}", indent, INDENT));
+            result.push_str(&format!("{}}}\n", indent));
+        }
+    }
+}
+
+fn write_field(result: &mut String, field: &StructField, indent: &str) {
+    write_comment(result, &field.comment, indent);
+    write_annotations(result, &field.annotations, indent);
+    let optional = if field.optional { "?" } else { "" };
+    result.push_str(&format!("{}{}{}: ", indent, field.name, optional));
+    write_type(result, &field.type_, indent);
+    result.push_str(",\n");
+}
+
+fn write_option(result: &mut String, option: &VariantOption, indent: &str) {
+    write_comment(result, &option.comment, indent);
+    write_annotations(result, &option.annotations, indent);
+    result.push_str(&format!("{}{}(", indent, option.name));
+    write_type(result, &option.type_, indent);
+    result.push_str("),\n");
+}
+
+/// Writes `t` where a line indented by `indent` left off. Inline structs and variants continue
+/// on the following lines and end with a line at `indent`.
+fn write_type(result: &mut String, t: &TypeExpression, indent: &str) {
+    let inner = format!("{}{}", indent, INDENT);
+    match t {
+        TypeExpression::Variable(name) => result.push_str(name),
+        TypeExpression::Builtin(builtin) => result.push_str(&builtin.to_string()),
+        TypeExpression::Generic(name, args) => {
+            result.push_str(name);
+            result.push('<');
+            for (i, (param, arg)) in args.iter().enumerate() {
+                if i > 0 {
+                    result.push_str(", ");
+                }
+                result.push_str(&format!("{}=", param));
+                write_type(result, arg, indent);
+            }
+            result.push('>');
+        }
+        TypeExpression::Struct(struct_type) => {
+            result.push_str("struct {\n");
+            for field in &struct_type.fields {
+                write_field(result, field, &inner);
+            }
+            result.push_str(&format!("{}}}", indent));
+        }
+        TypeExpression::Variant(variant_type) => {
+            result.push_str("enum {\n");
+            for option in &variant_type.variants {
+                write_option(result, option, &inner);
+            }
+            result.push_str(&format!("{}}}", indent));
+        }
+        // The right operand is always an atom, so a chain like `A & B | C` groups to the left
+        // without parentheses.
+        TypeExpression::Intersection(left, right) => {
+            write_type(result, left, indent);
+            result.push_str(" & ");
+            write_type(result, right, indent);
+        }
+        TypeExpression::Union(left, right) => {
+            write_type(result, left, indent);
+            result.push_str(" | ");
+            write_type(result, right, indent);
+        }
+        TypeExpression::Conditional(conditional) => {
+            result.push_str("if ");
+            write_type(result, &conditional.left, indent);
+            result.push_str(" == ");
+            write_type(result, &conditional.right, indent);
+            result.push_str(" then ");
+            write_type(result, &conditional.then, indent);
+            result.push_str(" else ");
+            write_type(result, &conditional.otherwise, indent);
+        }
+    }
+}
@@ -1,17 +1,47 @@
-use std::collections::{BTreeMap, HashMap};
+use core::fmt;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Formatter},
+    rc::Rc,
+};
+
+use tracing::{debug, debug_span, trace};
 
 use crate::{
+    annotations::AnnotationRegistry,
+    arena::{ExpressionArena, ExpressionId},
     ast,
+    builtins::BuiltinRegistry,
+    diagnostics::{has_errors, Diagnostic},
+    error::{ArgumentMismatch, CompileError, Unresolved},
+    intern::Atom,
     ir::{
         IRType, Intersectable, NamedIRType, PrimitiveStruct, PrimitiveStructField, PrimitiveType,
-        PrimitiveVariant, PrimitiveVariantOption, ResolvedIRType, TypeName,
+        PrimitiveVariant, PrimitiveVariantOption, ResolvedIRType, TypeMetadata, TypeName,
+        Unionable,
     },
+    symbols::{qualify_name, split_qualified_name, LookupError, SymbolKind, SymbolTable},
 };
 
+/// The compiled types by id. Ids are only handed out by the compiler, backends look types up
+/// through [`TypeAllocator::get`] and [`TypeAllocator::iter`].
 pub struct TypeAllocator {
-    pub next_id: usize,
-    pub types: BTreeMap<usize, NamedIRType>,
-    pub named_types: HashMap<ast::TypeExpression, usize>,
+    next_id: usize,
+    pub(crate) types: BTreeMap<usize, NamedIRType>,
+    /// The id of each type expression that was compiled by name, so that it is compiled once.
+    /// Keyed by the expression's id in `expressions`, so that no expression is stored twice.
+    pub(crate) named_types: HashMap<ExpressionId, usize>,
+    expressions: ExpressionArena,
+    /// The keys of the expressions in the instantiated bodies that are being compiled, by
+    /// address, innermost last, see [`TypeAllocator::remember_keys`].
+    known_keys: Vec<HashMap<usize, ExpressionId>>,
+}
+
+impl Default for TypeAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TypeAllocator {
@@ -20,82 +50,671 @@ impl TypeAllocator {
             next_id: 0,
             types: BTreeMap::new(),
             named_types: HashMap::new(),
+            expressions: ExpressionArena::new(),
+            known_keys: Vec::new(),
         }
     }
 
+    /// The compiled type with `id`, if it was not removed by
+    /// [`IRCompiler::retain_reachable`].
+    pub fn get(&self, id: usize) -> Option<&NamedIRType> {
+        self.types.get(&id)
+    }
+
+    /// The compiled types, ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = (&usize, &NamedIRType)> {
+        self.types.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
     fn alloc_unnamed(&mut self) -> usize {
         let id = self.next_id;
         self.next_id += 1;
         id
     }
 
-    fn alloc(&mut self, t: &ast::TypeExpression) -> (usize, bool) {
-        if let Some(&id) = self.named_types.get(t) {
+    /// The key the type expression `t` is allocated under when it is compiled by name. The keys
+    /// of the instantiations in the body that is being instantiated are known already.
+    fn key(&mut self, t: &ast::TypeExpression) -> ExpressionId {
+        let known = self
+            .known_keys
+            .last()
+            .and_then(|known| known.get(&address(t)));
+        match known {
+            Some(id) => *id,
+            None => self.expressions.intern(t),
+        }
+    }
+
+    /// Remembers the keys of the instantiations in `body`, the `prototype` with the arguments
+    /// whose keys are `args` substituted, until [`TypeAllocator::forget_keys`]. Their keys are
+    /// built from those of the arguments, so an argument is not interned again wherever it is
+    /// substituted, and nested instantiations are not interned once for every level.
+    fn remember_keys(
+        &mut self,
+        prototype: &ast::TypeExpression,
+        body: &ast::TypeExpression,
+        args: &BTreeMap<Atom, ExpressionId>,
+    ) {
+        let mut known = HashMap::new();
+        self.collect_keys(prototype, body, args, &mut known);
+        self.known_keys.push(known);
+    }
+
+    fn forget_keys(&mut self) {
+        self.known_keys.pop();
+    }
+
+    /// The key of `body`, the `prototype` with `args` substituted, if it is an argument or an
+    /// instantiation, after remembering it and those in `body` in `known`. Parts of `body` that
+    /// were reordered by [`ast::normalize`], like the operands of unions, are interned when
+    /// they are compiled instead.
+    fn collect_keys(
+        &mut self,
+        prototype: &ast::TypeExpression,
+        body: &ast::TypeExpression,
+        args: &BTreeMap<Atom, ExpressionId>,
+        known: &mut HashMap<usize, ExpressionId>,
+    ) -> Option<ExpressionId> {
+        use ast::TypeExpression as T;
+
+        let id = match (prototype, body) {
+            (T::Variable(param), _) if args.contains_key(param) => {
+                let id = args[param];
+                debug_assert_eq!(id, self.expressions.intern(body), "{} is normalized", param);
+                id
+            }
+            (T::Generic(_, prototype_args), T::Generic(name, body_args))
+                if prototype_args.keys().eq(body_args.keys()) =>
+            {
+                let mut ids = Vec::new();
+                for ((param, p), b) in prototype_args.iter().zip(body_args.values()) {
+                    let id = match self.collect_keys(p, b, args, known) {
+                        Some(id) => id,
+                        None => self.expressions.intern(b),
+                    };
+                    known.insert(address(b), id);
+                    ids.push((*param, id));
+                }
+                self.expressions.intern_generic(*name, ids)
+            }
+            (T::Struct(p), T::Struct(b)) if p.fields.len() == b.fields.len() => {
+                for (p, b) in p.fields.iter().zip(&b.fields) {
+                    self.collect_keys(&p.type_, &b.type_, args, known);
+                }
+                return None;
+            }
+            (T::Variant(p), T::Variant(b)) if p.variants.len() == b.variants.len() => {
+                for (p, b) in p.variants.iter().zip(&b.variants) {
+                    self.collect_keys(&p.type_, &b.type_, args, known);
+                }
+                return None;
+            }
+            (T::Conditional(p), T::Conditional(b)) => {
+                self.collect_keys(&p.left, &b.left, args, known);
+                self.collect_keys(&p.right, &b.right, args, known);
+                self.collect_keys(&p.then, &b.then, args, known);
+                self.collect_keys(&p.otherwise, &b.otherwise, args, known);
+                return None;
+            }
+            _ => return None,
+        };
+        known.insert(address(body), id);
+        Some(id)
+    }
+
+    /// The id of the type expression with `key`, if it was compiled, or is being compiled.
+    fn allocated(&self, key: ExpressionId) -> Option<usize> {
+        self.named_types.get(&key).copied()
+    }
+
+    fn alloc(&mut self, key: ExpressionId) -> (usize, bool) {
+        if let Some(&id) = self.named_types.get(&key) {
             (id, false)
         } else {
             let id = self.next_id;
             self.next_id += 1;
-            self.named_types.insert(t.clone(), id);
+            self.named_types.insert(key, id);
             (id, true)
         }
     }
 
-    fn set(&mut self, id: usize, name: TypeName, t: IRType) {
-        self.types.insert(id, NamedIRType { name: name, type_: t });
+    fn set(&mut self, id: usize, name: TypeName, t: IRType, metadata: TypeMetadata) {
+        self.types.insert(
+            id,
+            NamedIRType {
+                name,
+                type_: t,
+                metadata,
+            },
+        );
     }
 }
 
+/// Where `t` is, which identifies it while it is borrowed, see [`TypeAllocator::key`].
+fn address(t: &ast::TypeExpression) -> usize {
+    t as *const ast::TypeExpression as usize
+}
+
+#[derive(Debug, Clone)]
 pub struct TypePrototype {
-    pub params: Vec<String>,
+    pub params: Vec<Atom>,
     pub type_: ast::TypeExpression,
+    pub doc: Option<String>,
+    pub annotations: Vec<ast::Annotation>,
+    /// Where the prototype was declared, if it comes from a source file.
+    pub location: Option<ast::SourceLocation>,
 }
 
 impl TypePrototype {
-    pub fn unify(&self, args: &BTreeMap<String, ast::TypeExpression>) -> ast::TypeExpression {
-        for param in &self.params {
-            args.get(param)
-                .expect("Type parameter not found in arguments");
+    /// Substitutes `args` into the body of the prototype `name`. Every parameter needs an
+    /// argument, and every argument a parameter, otherwise the mismatch is reported at
+    /// `location`, where the prototype is used.
+    pub fn unify(
+        &self,
+        name: &str,
+        args: &BTreeMap<Atom, ast::TypeExpression>,
+        location: Option<&ast::SourceLocation>,
+    ) -> Result<ast::TypeExpression, CompileError> {
+        let matches = self.params.len() == args.len()
+            && self.params.iter().all(|param| args.contains_key(param));
+        if !matches {
+            return Err(CompileError::ArgumentMismatch(Box::new(ArgumentMismatch {
+                name: name.to_string(),
+                expected: self.params.iter().map(|p| p.to_string()).collect(),
+                provided: args.keys().map(|k| k.to_string()).collect(),
+                location: location.cloned(),
+            })));
         }
-        for (k, _v) in args {
-            if !self.params.contains(k) {
-                panic!("Type parameter {} not found in type prototype", k);
-            }
+        Ok(self.type_.unify(args))
+    }
+
+    pub fn metadata(&self) -> TypeMetadata {
+        TypeMetadata {
+            annotations: self.annotations.clone(),
+            doc: self.doc.clone(),
+            location: self.location.clone(),
         }
-        self.type_.unify(args)
+    }
+}
+
+/// Decides what happens when two merged compilers both declare a global type with the same name
+/// but a different definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Do not merge anything if there is at least one collision.
+    Reject,
+    /// Keep the declaration of the compiler that is merged into.
+    KeepExisting,
+    /// Replace the existing declaration with the incoming one.
+    Replace,
+}
+
+#[derive(Debug, Clone)]
+pub struct NameCollision {
+    pub name: String,
+    pub existing: Option<ast::SourceLocation>,
+    pub incoming: Option<ast::SourceLocation>,
+}
+
+impl Display for NameCollision {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "type {} is declared twice", self.name)?;
+        match (&self.existing, &self.incoming) {
+            (Some(existing), Some(incoming)) => write!(f, " (at {} and {})", existing, incoming),
+            (Some(location), None) | (None, Some(location)) => write!(f, " (at {})", location),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Notified while an [`IRCompiler`] registers and compiles types, for embedders that collect
+/// metrics, check their own policies or report progress. Every method does nothing by default.
+pub trait CompilationObserver {
+    /// The declared type with the qualified `name` was registered, from a parsed file or by
+    /// merging another compiler.
+    fn on_type_registered(&mut self, _name: &str, _prototype: &TypePrototype) {}
+
+    /// The named type `id` was compiled. Types that are referenced again reuse the compiled type
+    /// and are only reported once.
+    fn on_type_compiled(&mut self, _id: usize, _named_type: &NamedIRType) {}
+
+    /// A struct or variant written inline, like the type of a field, was emitted as the
+    /// temporary `id`.
+    fn on_temporary_emitted(&mut self, _id: usize, _named_type: &NamedIRType) {}
+}
+
+/// Shares an observer with the embedder, which reads what it collected once compiling is done.
+impl<T: CompilationObserver> CompilationObserver for Rc<RefCell<T>> {
+    fn on_type_registered(&mut self, name: &str, prototype: &TypePrototype) {
+        self.borrow_mut().on_type_registered(name, prototype);
+    }
+
+    fn on_type_compiled(&mut self, id: usize, named_type: &NamedIRType) {
+        self.borrow_mut().on_type_compiled(id, named_type);
+    }
+
+    fn on_temporary_emitted(&mut self, id: usize, named_type: &NamedIRType) {
+        self.borrow_mut().on_temporary_emitted(id, named_type);
     }
 }
 
 pub struct IRCompiler {
-    pub allocator: TypeAllocator,
-    type_env: HashMap<String, TypePrototype>,
-    next_temporary_id: usize,
+    pub(crate) allocator: TypeAllocator,
+    /// Annotations that are valid in the compiled schema. Backends register their own
+    /// annotations here before [`IRCompiler::validate_annotations`] is called.
+    pub annotations: AnnotationRegistry,
+    /// Builtins added by the embedder. Their names resolve to them in schemas compiled after
+    /// they are registered.
+    pub builtins: BuiltinRegistry,
+    symbols: SymbolTable,
+    /// The named types that are currently being compiled by id, together with the position
+    /// inside of them. Temporaries are named after the innermost entry.
+    scopes: Vec<(usize, TypeName, Vec<Atom>)>,
+    /// The declaration of the innermost member or named type that is currently compiled, where
+    /// errors about the type expression being compiled are reported.
+    site: Option<ast::SourceLocation>,
+    observers: Vec<Box<dyn CompilationObserver>>,
+}
+
+/// `@inline`, `@opaque` or `@format` on the declaration `name` that is not an alias. That they are not
+/// both used is checked like every other conflict between annotations.
+fn alias_annotation_diagnostics(name: &str, prototype: &TypePrototype) -> Vec<Diagnostic> {
+    let has = |annotation: &str| prototype.annotations.iter().any(|a| a.name == annotation);
+    let location = prototype.location.clone();
+    let mut diagnostics = Vec::new();
+    let is_alias = !matches!(
+        prototype.type_,
+        ast::TypeExpression::Struct(_)
+            | ast::TypeExpression::Variant(_)
+            | ast::TypeExpression::Intersection(..)
+            | ast::TypeExpression::Union(..)
+    );
+    for annotation in ["inline", "opaque", "format"] {
+        if has(annotation) && !is_alias {
+            diagnostics.push(
+                Diagnostic::warning(
+                    format!(
+                        "@{} only applies to aliases, {} declares a new type",
+                        annotation, name
+                    ),
+                    location.clone(),
+                )
+                .with_code("misplaced-annotation"),
+            );
+        }
+    }
+    diagnostics
+}
+
+/// The annotations of a declaration or member, with what it is, like `User.name`, and where it
+/// was declared.
+type Annotated<'a> = (
+    String,
+    &'a [ast::Annotation],
+    Option<&'a ast::SourceLocation>,
+);
+
+/// Collects the annotations of the members of `t`, which is declared as `path`.
+fn collect_member_annotations<'a>(
+    path: &str,
+    t: &'a ast::TypeExpression,
+    out: &mut Vec<Annotated<'a>>,
+) {
+    match t {
+        ast::TypeExpression::Variable(_) | ast::TypeExpression::Builtin(_) => {}
+        ast::TypeExpression::Generic(_, args) => {
+            for arg in args.values() {
+                collect_member_annotations(path, arg, out);
+            }
+        }
+        ast::TypeExpression::Struct(s) => {
+            for field in &s.fields {
+                let field_path = format!("{}.{}", path, field.name);
+                out.push((
+                    field_path.clone(),
+                    &field.annotations,
+                    field.location.as_ref(),
+                ));
+                collect_member_annotations(&field_path, &field.type_, out);
+            }
+        }
+        ast::TypeExpression::Variant(v) => {
+            for option in &v.variants {
+                let option_path = format!("{}.{}", path, option.name);
+                out.push((
+                    option_path.clone(),
+                    &option.annotations,
+                    option.location.as_ref(),
+                ));
+                collect_member_annotations(&option_path, &option.type_, out);
+            }
+        }
+        ast::TypeExpression::Intersection(a, b) | ast::TypeExpression::Union(a, b) => {
+            collect_member_annotations(path, a, out);
+            collect_member_annotations(path, b, out);
+        }
+        ast::TypeExpression::Conditional(c) => {
+            collect_member_annotations(path, &c.then, out);
+            collect_member_annotations(path, &c.otherwise, out);
+        }
+    }
+}
+
+impl Default for IRCompiler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl IRCompiler {
     pub fn new() -> Self {
         IRCompiler {
             allocator: TypeAllocator::new(),
-            type_env: HashMap::new(),
-            next_temporary_id: 0,
+            annotations: AnnotationRegistry::with_builtins(),
+            builtins: BuiltinRegistry::new(),
+            symbols: SymbolTable::new(),
+            scopes: Vec::new(),
+            site: None,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Adds an observer that is notified of every type registered or compiled from now on.
+    pub fn add_observer(&mut self, observer: Box<dyn CompilationObserver>) {
+        self.observers.push(observer);
+    }
+
+    fn notify_registered(&mut self, name: &str, prototype: &TypePrototype) {
+        for observer in &mut self.observers {
+            observer.on_type_registered(name, prototype);
         }
     }
 
+    /// Registers a public type in the root module.
     pub fn register_global_type(&mut self, name: String, t: TypePrototype) {
-        self.type_env.insert(name, t);
+        let t = TypePrototype {
+            type_: ast::normalize(&t.type_),
+            ..t
+        };
+        self.notify_registered(&name, &t);
+        self.symbols
+            .declare("", &name, ast::Visibility::Public, SymbolKind::Type(t));
+    }
+
+    /// Registers all items of a parsed source file, including the items of nested modules.
+    pub fn register_program(&mut self, items: Vec<ast::Item>, file: &str, source: &str) {
+        debug!(file, items = items.len(), "registering program");
+        self.register_items("", items, &ast::LineIndex::new(file, source));
+    }
+
+    fn register_items(&mut self, module: &str, items: Vec<ast::Item>, lines: &ast::LineIndex) {
+        for item in items {
+            match item {
+                ast::Item::Type(decl) => {
+                    let prototype = TypePrototype {
+                        params: decl.params.iter().map(Atom::from).collect(),
+                        type_: ast::normalize(&decl.type_),
+                        doc: decl.doc,
+                        annotations: decl.annotations,
+                        location: Some(lines.location(decl.span)),
+                    };
+                    self.notify_registered(&qualify_name(module, &decl.name), &prototype);
+                    self.symbols.declare(
+                        module,
+                        &decl.name,
+                        decl.visibility,
+                        SymbolKind::Type(prototype),
+                    );
+                }
+                ast::Item::Use(decl) => {
+                    self.symbols.declare(
+                        module,
+                        decl.name(),
+                        decl.visibility,
+                        SymbolKind::Reexport(decl.path.clone()),
+                    );
+                }
+                ast::Item::Module(decl) => {
+                    let path = qualify_name(module, &decl.name);
+                    self.symbols.declare_module(&path, decl.visibility);
+                    self.register_items(&path, decl.items, lines);
+                }
+            }
+        }
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Checks every annotation of the registered types against the annotation registry.
+    pub fn validate_annotations(&self) -> Vec<Diagnostic> {
+        let mut types = self.symbols.iter_types().collect::<Vec<_>>();
+        types.sort_by_key(|(name, _)| *name);
+
+        let mut diagnostics = Vec::new();
+        for (name, prototype) in types {
+            diagnostics.extend(alias_annotation_diagnostics(name, prototype));
+            let mut annotated = vec![(
+                name.clone(),
+                &prototype.annotations[..],
+                prototype.location.as_ref(),
+            )];
+            collect_member_annotations(name, &prototype.type_, &mut annotated);
+            for (what, annotations, location) in annotated {
+                // Members without a location are reported at the declaration they are part of.
+                let location = location.or(prototype.location.as_ref());
+                for annotation in annotations {
+                    diagnostics.extend(self.annotations.validate(annotation, location));
+                }
+                diagnostics.extend(self.annotations.validate_combination(
+                    &what,
+                    annotations,
+                    location,
+                ));
+            }
+        }
+        debug!(diagnostics = diagnostics.len(), "validated annotations");
+        diagnostics
+    }
+
+    /// Like [`IRCompiler::validate_annotations`], but an error if any annotation is invalid.
+    /// Returns the warnings otherwise.
+    pub fn check_annotations(&self) -> Result<Vec<Diagnostic>, CompileError> {
+        let diagnostics = self.validate_annotations();
+        if has_errors(&diagnostics) {
+            return Err(CompileError::Annotations(diagnostics));
+        }
+        Ok(diagnostics)
+    }
+
+    /// Merges the global types of `other` into this compiler and recompiles every global that
+    /// was compiled in either of them, pruned like [`IRCompiler::compile_globals`] prunes them.
+    /// Type ids of this compiler are not preserved.
+    ///
+    /// Returns the names that are declared with different definitions in both compilers. With
+    /// [`CollisionPolicy::Reject`], nothing is merged if any collision is found.
+    pub fn merge(
+        &mut self,
+        other: &IRCompiler,
+        policy: CollisionPolicy,
+    ) -> Result<Vec<NameCollision>, CompileError> {
+        let mut collisions = Vec::new();
+        for (name, incoming) in other.iter_globals() {
+            let Some(existing) = self.symbols.get_type(name) else {
+                continue;
+            };
+            if existing.params != incoming.params || existing.type_ != incoming.type_ {
+                collisions.push(NameCollision {
+                    name: name.clone(),
+                    existing: existing.location.clone(),
+                    incoming: incoming.location.clone(),
+                });
+            }
+        }
+        collisions.sort_by(|a, b| a.name.cmp(&b.name));
+        debug!(
+            types = other.symbols.iter_types().count(),
+            collisions = collisions.len(),
+            "merging"
+        );
+
+        if policy == CollisionPolicy::Reject && !collisions.is_empty() {
+            return Ok(collisions);
+        }
+
+        let mut compiled = Vec::new();
+        for compiler in [&*self, other] {
+            for (_, t) in compiler.iter_types() {
+                if let TypeName::Variable(name) = &t.name {
+                    if !compiled.contains(name) {
+                        compiled.push(*name);
+                    }
+                }
+            }
+        }
+
+        // The merged types may use builtins that were only registered with `other`.
+        self.builtins.extend(&other.builtins);
+        for (name, incoming) in other.symbols.iter() {
+            if policy == CollisionPolicy::KeepExisting && self.symbols.contains(name) {
+                continue;
+            }
+            if let SymbolKind::Type(prototype) = &incoming.kind {
+                self.notify_registered(name, prototype);
+            }
+            self.symbols.insert(name.clone(), incoming.clone());
+        }
+        for (module, visibility) in other.symbols.iter_modules() {
+            self.symbols.declare_module(module, visibility);
+        }
+
+        self.allocator = TypeAllocator::new();
+        for name in compiled {
+            let t = self.symbols.get_type(&name).unwrap().type_.clone();
+            self.compile_global(name.to_string(), &t)?;
+        }
+        self.prune()?;
+
+        Ok(collisions)
     }
 
     pub fn iter_types(&self) -> impl Iterator<Item = (&usize, &NamedIRType)> {
-        self.allocator.types.iter()
+        self.allocator.iter()
+    }
+
+    /// The compiled types, for backends that keep a reference to them while writing.
+    pub fn allocator(&self) -> &TypeAllocator {
+        &self.allocator
     }
 
+    /// The compiled type with `id`.
+    ///
+    /// # Panics
+    ///
+    /// If no type has `id`. Ids found in compiled types always refer to a compiled type.
+    pub fn named_type(&self, id: usize) -> &NamedIRType {
+        self.allocator
+            .get(id)
+            .unwrap_or_else(|| panic!("Type {} not found in allocator", id))
+    }
+
+    /// Compiles every declared type that does not take generic parameters, and inlines the
+    /// aliases annotated with `@inline`, see [`IRCompiler::inline_aliases`]. Private types of
+    /// modules are checked like the others, but only kept if an exported type refers to them,
+    /// see [`IRCompiler::retain_exported`]. Stops at the first error, after which the compiled
+    /// types are incomplete.
+    pub fn compile_globals(&mut self) -> Result<(), CompileError> {
+        let globals = self
+            .iter_globals()
+            .filter(|(_, t)| t.params.is_empty())
+            .map(|(name, t)| (name.clone(), t.type_.clone()))
+            .collect::<Vec<_>>();
+        let _span = debug_span!("compile_globals", globals = globals.len()).entered();
+        for (name, expr) in globals {
+            self.compile_global(name, &expr)?;
+        }
+        self.prune()?;
+        debug!(types = self.allocator.len(), "compiled");
+        Ok(())
+    }
+
+    /// Iterates over all declared types by their qualified name.
     pub fn iter_globals(&self) -> impl Iterator<Item = (&String, &TypePrototype)> {
-        self.type_env.iter()
+        self.symbols.iter_types()
+    }
+
+    /// Iterates over the declared types that take generic parameters, by their qualified name.
+    /// They are only compiled where they are instantiated, see [`IRCompiler::instantiations_of`].
+    pub fn iter_generic_prototypes(&self) -> impl Iterator<Item = (&String, &TypePrototype)> {
+        self.iter_globals().filter(|(_, t)| !t.params.is_empty())
+    }
+
+    /// The compiled instantiations of the prototype with the qualified `name`: their qualified
+    /// arguments and the id they were allocated, ordered by id.
+    pub fn instantiations_of(
+        &self,
+        name: &str,
+    ) -> Vec<(&BTreeMap<Atom, ast::TypeExpression>, usize)> {
+        let mut instantiations: Vec<_> = self
+            .iter_types()
+            .filter_map(|(id, t)| match &t.name {
+                TypeName::Generic(prototype, args) if *prototype == name => Some((args, *id)),
+                _ => None,
+            })
+            .collect();
+        instantiations.sort_by_key(|(_, id)| *id);
+        instantiations
+    }
+
+    /// Looks up the prototype with the qualified `name` and substitutes `args` into its body,
+    /// after qualifying the references in the body relative to the prototype's module. Returns
+    /// the qualified body before and after the substitution.
+    fn instantiate(
+        &self,
+        name: &str,
+        args: &BTreeMap<Atom, ast::TypeExpression>,
+    ) -> Result<(ast::TypeExpression, ast::TypeExpression, TypeMetadata), CompileError> {
+        let symbol = self
+            .symbols
+            .get(name)
+            .ok_or_else(|| LookupError::NotFound {
+                path: name.to_string(),
+                module: String::new(),
+            })?;
+        let SymbolKind::Type(prototype) = &symbol.kind else {
+            return Err(CompileError::NotAType {
+                name: name.to_string(),
+            });
+        };
+        let body = self.symbols.qualify(
+            &symbol.module,
+            &prototype.type_,
+            &prototype.params,
+            &self.builtins,
+        )?;
+        let qualified = TypePrototype {
+            type_: body,
+            ..prototype.clone()
+        };
+        // Arguments can be substituted into unions, whose operands are only in their canonical
+        // order once they are known.
+        let body = ast::normalize(&qualified.unify(name, args, self.site.as_ref())?);
+        Ok((qualified.type_, body, prototype.metadata()))
     }
 
     pub fn resolve_ir_type(&self, t: &IRType) -> ResolvedIRType {
         match t {
             IRType::Reference(id) => {
-                let named_type = self.allocator.types.get(id).unwrap();
+                let named_type = self.named_type(*id);
                 self.resolve_ir_type(&named_type.type_)
             }
             IRType::Builtin(builtin) => ResolvedIRType::Builtin(builtin.clone()),
@@ -104,92 +723,213 @@ impl IRCompiler {
         }
     }
 
+    /// Resolves `t`, an operand of an intersection or union. A reference to a type that is still
+    /// being compiled, because the operand is part of its own definition, is an error, since
+    /// there are no members to merge yet.
+    fn resolve_operand(&self, t: &IRType) -> Result<ResolvedIRType, CompileError> {
+        let IRType::Reference(id) = t else {
+            return Ok(self.resolve_ir_type(t));
+        };
+        match self.allocator.get(*id) {
+            Some(named_type) => self.resolve_operand(&named_type.type_),
+            None => Err(CompileError::CyclicType {
+                name: self.compiling_name(*id),
+                location: self.site.clone(),
+            }),
+        }
+    }
+
+    /// The name of `id`, a type that is still being compiled.
+    fn compiling_name(&self, id: usize) -> String {
+        self.scopes
+            .iter()
+            .find(|(scope, _, _)| *scope == id)
+            .map(|(_, name, _)| name.to_string())
+            .unwrap_or_else(|| id.to_string())
+    }
+
     pub fn compile_force_allocation(
         &mut self,
         name: TypeName,
         type_var: &ast::TypeExpression,
         t: &ast::TypeExpression,
-    ) -> (usize, bool) {
-        let (alloc_id, new) = self.allocator.alloc(type_var);
+        metadata: TypeMetadata,
+    ) -> Result<(usize, bool), CompileError> {
+        let key = self.allocator.key(type_var);
+        self.compile_allocation(key, name, t, metadata)
+    }
+
+    /// Like [`IRCompiler::compile_force_allocation`], for a type expression that was already
+    /// turned into its allocation `key`.
+    fn compile_allocation(
+        &mut self,
+        key: ExpressionId,
+        name: TypeName,
+        t: &ast::TypeExpression,
+        metadata: TypeMetadata,
+    ) -> Result<(usize, bool), CompileError> {
+        let (alloc_id, new) = self.allocator.alloc(key);
         if new {
+            trace!(%name, id = alloc_id, "compiling type");
+            self.scopes.push((alloc_id, name.clone(), Vec::new()));
+            let site = metadata.location.clone().or_else(|| self.site.clone());
+            let outer = std::mem::replace(&mut self.site, site);
             let inner_primitive = self.compile_to_ir_type(t);
-            self.allocator.set(alloc_id, name, inner_primitive);
+            self.site = outer;
+            self.scopes.pop();
+            let inner_primitive = inner_primitive?;
+            self.allocator
+                .set(alloc_id, name, inner_primitive, metadata);
+            for observer in &mut self.observers {
+                observer.on_type_compiled(alloc_id, &self.allocator.types[&alloc_id]);
+            }
+        } else {
+            trace!(%name, id = alloc_id, "reusing compiled type");
         }
-        return (alloc_id, new);
+        Ok((alloc_id, new))
     }
 
-    pub fn eager_emit_temporary(&mut self, p: IRType) -> usize {
+    /// Compiles `t`, the prototype `name` instantiated with `args`, unless the same instantiation
+    /// was compiled before. Instantiations are keyed by the prototype and its arguments, which are
+    /// qualified and ordered by parameter, so the body of a prototype is only substituted once for
+    /// each distinct list of arguments, however often the instantiation is referred to.
+    fn compile_instantiation(
+        &mut self,
+        t: &ast::TypeExpression,
+        name: &str,
+        args: &BTreeMap<Atom, ast::TypeExpression>,
+        type_name: impl FnOnce() -> TypeName,
+    ) -> Result<usize, CompileError> {
+        let key = self.allocator.key(t);
+        if let Some(alloc_id) = self.allocator.allocated(key) {
+            trace!(name, id = alloc_id, "reusing instantiation");
+            return Ok(alloc_id);
+        }
+        let (prototype, inner_type, metadata) = self.instantiate(name, args)?;
+        let arg_keys = self.allocator.expressions.arguments(key);
+        self.allocator
+            .remember_keys(&prototype, &inner_type, &arg_keys);
+        let compiled = self.compile_allocation(key, type_name(), &inner_type, metadata);
+        self.allocator.forget_keys();
+        let (alloc_id, _new) = compiled?;
+        Ok(alloc_id)
+    }
+
+    /// Allocates the inline struct or variant `p` as a temporary of the named type being
+    /// compiled, named after the path to it.
+    pub(crate) fn eager_emit_temporary(&mut self, p: IRType) -> Result<usize, CompileError> {
+        let (_, owner, path) = self.scopes.last().ok_or(CompileError::UnnamedInlineType)?;
+        let name = TypeName::Temporary(Box::new(owner.clone()), path.clone());
         let alloc_id = self.allocator.alloc_unnamed();
 
         self.allocator
-            .set(alloc_id, TypeName::Temporary(self.next_temporary_id), p);
-        self.next_temporary_id += 1;
+            .set(alloc_id, name, p, TypeMetadata::default());
+        for observer in &mut self.observers {
+            observer.on_temporary_emitted(alloc_id, &self.allocator.types[&alloc_id]);
+        }
 
-        return alloc_id;
+        Ok(alloc_id)
     }
 
-    pub fn compile_global(&mut self, name: String, t: &ast::TypeExpression) -> (usize, bool) {
-        let var_expression = ast::TypeExpression::Variable(name.clone());
-        self.compile_force_allocation(TypeName::Variable(name.clone()), &var_expression, &t)
+    /// Compiles the expression `t` as the global type with the qualified `name`. References in
+    /// `t` are resolved relative to the module of `name`.
+    pub fn compile_global(
+        &mut self,
+        name: String,
+        t: &ast::TypeExpression,
+    ) -> Result<(usize, bool), CompileError> {
+        let symbol = Atom::from(&name);
+        let var_expression = ast::TypeExpression::Variable(symbol);
+        let metadata = self
+            .symbols
+            .get_type(&name)
+            .map(|prototype| prototype.metadata())
+            .unwrap_or_default();
+        let module = split_qualified_name(&name).0;
+        let t = self
+            .symbols
+            .qualify(module, t, &[], &self.builtins)
+            .map_err(|error| {
+                CompileError::Unresolved(Box::new(Unresolved {
+                    error,
+                    location: metadata.location.clone(),
+                }))
+            })?;
+        let t = ast::normalize(&t);
+        self.compile_force_allocation(TypeName::Variable(symbol), &var_expression, &t, metadata)
     }
 
-    pub fn compile_to_primitive_type(&mut self, t: &ast::TypeExpression) -> PrimitiveType {
-        let ir_type = self.compile_to_ir_type(t);
-        match ir_type {
+    /// Compiles the type of the field or option `member` of the type that is currently compiled,
+    /// declared at `location`.
+    fn compile_member(
+        &mut self,
+        member: Atom,
+        location: Option<&ast::SourceLocation>,
+        t: &ast::TypeExpression,
+    ) -> Result<PrimitiveType, CompileError> {
+        if let Some((_, _, path)) = self.scopes.last_mut() {
+            path.push(member);
+        }
+        let site = location.cloned().or_else(|| self.site.clone());
+        let outer = std::mem::replace(&mut self.site, site);
+        let primitive = self.compile_to_primitive_type(t);
+        self.site = outer;
+        if let Some((_, _, path)) = self.scopes.last_mut() {
+            path.pop();
+        }
+        primitive
+    }
+
+    pub fn compile_to_primitive_type(
+        &mut self,
+        t: &ast::TypeExpression,
+    ) -> Result<PrimitiveType, CompileError> {
+        let ir_type = self.compile_to_ir_type(t)?;
+        Ok(match ir_type {
             IRType::Reference(id) => PrimitiveType::Reference(id),
             IRType::Builtin(builtin) => PrimitiveType::Builtin(builtin),
             IRType::Struct(fields) => {
-                let alloc_id = self.eager_emit_temporary(IRType::Struct(fields.clone()));
+                let alloc_id = self.eager_emit_temporary(IRType::Struct(fields.clone()))?;
                 PrimitiveType::Reference(alloc_id)
             }
             IRType::Variant(variants) => {
-                let alloc_id = self.eager_emit_temporary(IRType::Variant(variants.clone()));
+                let alloc_id = self.eager_emit_temporary(IRType::Variant(variants.clone()))?;
                 PrimitiveType::Reference(alloc_id)
             }
-        }
+        })
     }
 
     // primitive type, resolved primitive type
-    pub fn compile_to_ir_type(&mut self, t: &ast::TypeExpression) -> IRType {
-        match t {
+    // All type references in `t` are expected to be qualified.
+    pub fn compile_to_ir_type(&mut self, t: &ast::TypeExpression) -> Result<IRType, CompileError> {
+        Ok(match t {
             ast::TypeExpression::Variable(name) => {
-                let inner_type = self
-                    .type_env
-                    .get(name)
-                    .expect(
-                        format!("Type variable {} not found in type environment", name).as_str(),
-                    )
-                    .unify(&BTreeMap::new());
-
-                let (alloc_id, _new) =
-                    self.compile_force_allocation(TypeName::Variable(name.clone()), t, &inner_type);
+                let alloc_id = self.compile_instantiation(t, name, &BTreeMap::new(), || {
+                    TypeName::Variable(*name)
+                })?;
                 IRType::Reference(alloc_id)
             }
             ast::TypeExpression::Builtin(name) => IRType::Builtin(name.clone()),
             ast::TypeExpression::Generic(name, args) => {
-                let inner_type = self
-                    .type_env
-                    .get(name)
-                    .expect(
-                        format!("Type variable {} not found in type environment", name).as_str(),
-                    )
-                    .unify(args);
-                let (alloc_id, _new) = self.compile_force_allocation(
-                    TypeName::Generic(name.clone(), args.clone()),
-                    t,
-                    &inner_type,
-                );
+                let alloc_id = self.compile_instantiation(t, name, args, || {
+                    TypeName::Generic(*name, args.clone())
+                })?;
                 IRType::Reference(alloc_id)
             }
             ast::TypeExpression::Struct(ast::StructTypeExpression { fields }) => {
                 let primitive_fields = fields
                     .iter()
-                    .map(|v| PrimitiveStructField {
-                        name: v.name.clone(),
-                        type_: self.compile_to_primitive_type(&v.type_),
-                        comment: v.comment.clone(),
+                    .map(|v| {
+                        Ok(PrimitiveStructField {
+                            name: v.name,
+                            type_: self.compile_member(v.name, v.location.as_ref(), &v.type_)?,
+                            optional: v.optional,
+                            annotations: v.annotations.clone(),
+                            comment: v.comment.clone(),
+                            location: v.location.clone(),
+                        })
                     })
-                    .collect();
+                    .collect::<Result<_, CompileError>>()?;
 
                 IRType::Struct(PrimitiveStruct {
                     fields: primitive_fields,
@@ -198,34 +938,355 @@ impl IRCompiler {
             ast::TypeExpression::Variant(ast::VariantTypeExpression { variants }) => {
                 let primitive_variants = variants
                     .iter()
-                    .map(|v| PrimitiveVariantOption {
-                        name: v.name.clone(),
-                        type_: self.compile_to_primitive_type(&v.type_),
-                        comment: v.comment.clone(),
+                    .map(|v| {
+                        Ok(PrimitiveVariantOption {
+                            name: v.name,
+                            type_: self.compile_member(v.name, v.location.as_ref(), &v.type_)?,
+                            annotations: v.annotations.clone(),
+                            comment: v.comment.clone(),
+                            location: v.location.clone(),
+                        })
                     })
-                    .collect();
+                    .collect::<Result<_, CompileError>>()?;
 
                 IRType::Variant(PrimitiveVariant {
                     variants: primitive_variants,
                 })
             }
             ast::TypeExpression::Intersection(a, b) => {
-                let ax = self.compile_to_ir_type(&a);
-                let a = self.resolve_ir_type(&ax);
-                let bx = self.compile_to_ir_type(&b);
-                let b = self.resolve_ir_type(&bx);
+                let ax = self.compile_to_ir_type(a)?;
+                let a = self.resolve_operand(&ax)?;
+                let bx = self.compile_to_ir_type(b)?;
+                let b = self.resolve_operand(&bx)?;
                 match (a, b) {
                     (ResolvedIRType::Struct(a), ResolvedIRType::Struct(b)) => {
-                        let merged: PrimitiveStruct = a.intersect(&b);
+                        let merged: PrimitiveStruct = a.intersect(&b)?;
                         IRType::Struct(merged)
                     }
                     (ResolvedIRType::Variant(a), ResolvedIRType::Variant(b)) => {
-                        let merged: PrimitiveVariant = a.intersect(&b);
+                        let merged: PrimitiveVariant = a.intersect(&b)?;
+                        IRType::Variant(merged)
+                    }
+                    _ => {
+                        return Err(CompileError::IncompatibleIntersection {
+                            location: self.site.clone(),
+                        })
+                    }
+                }
+            }
+            ast::TypeExpression::Union(a, b) => {
+                let ax = self.compile_to_ir_type(a)?;
+                let a = self.resolve_operand(&ax)?;
+                let bx = self.compile_to_ir_type(b)?;
+                let b = self.resolve_operand(&bx)?;
+                match (a, b) {
+                    (ResolvedIRType::Variant(a), ResolvedIRType::Variant(mut b)) => {
+                        // Inline payloads on both sides are compiled to distinct temporaries, so
+                        // identical options are found structurally before merging.
+                        let mut options = Vec::with_capacity(b.variants.len());
+                        for option in b.variants {
+                            let mut duplicate = false;
+                            for existing in a.variants.iter().filter(|e| e.name == option.name) {
+                                duplicate |= self
+                                    .try_types_equal(
+                                        &existing.type_.clone().into(),
+                                        &option.type_.clone().into(),
+                                    )
+                                    .map_err(|id| CompileError::CyclicType {
+                                        name: self.compiling_name(id),
+                                        location: self.site.clone(),
+                                    })?;
+                            }
+                            if !duplicate {
+                                options.push(option);
+                            }
+                        }
+                        b.variants = options;
+                        let merged: PrimitiveVariant = a.union(&b)?;
                         IRType::Variant(merged)
                     }
-                    _ => panic!("Intersection of incompatible types"),
+                    _ => {
+                        return Err(CompileError::IncompatibleUnion {
+                            location: self.site.clone(),
+                        })
+                    }
                 }
             }
+            ast::TypeExpression::Conditional(c) => {
+                let first = self.allocator.next_id;
+                let left = self.compile_to_ir_type(&c.left)?;
+                let right = self.compile_to_ir_type(&c.right)?;
+                let equal = self.try_types_equal(&left, &right).map_err(|id| {
+                    CompileError::CyclicConditional {
+                        name: self.compiling_name(id),
+                        location: self.site.clone(),
+                    }
+                })?;
+                // The operands are only compared, their members are not types of the program.
+                self.drop_unreferenced_temporaries(first);
+                if equal {
+                    self.compile_to_ir_type(&c.then)?
+                } else {
+                    self.compile_to_ir_type(&c.otherwise)?
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str) -> Result<IRCompiler, CompileError> {
+        let items = crate::parse(source, "test.roto").expect("the source parses");
+        let mut compiler = IRCompiler::new();
+        compiler.register_program(items, "test.roto", source);
+        compiler.compile_globals()?;
+        Ok(compiler)
+    }
+
+    /// The names of the fields or options of the global type `name`.
+    fn members(compiler: &IRCompiler, name: &str) -> Vec<String> {
+        let (_, t) = compiler
+            .iter_types()
+            .find(|(_, t)| t.name == TypeName::Variable(Atom::from(name)))
+            .unwrap_or_else(|| panic!("{} is compiled", name));
+        match compiler.resolve_ir_type(&t.type_) {
+            ResolvedIRType::Struct(s) => s.fields.iter().map(|f| f.name.to_string()).collect(),
+            ResolvedIRType::Variant(v) => v.variants.iter().map(|o| o.name.to_string()).collect(),
+            ResolvedIRType::Builtin(builtin) => panic!("{} is the builtin {}", name, builtin),
         }
     }
+
+    #[test]
+    fn intersections_keep_the_written_order_through_generics() {
+        let compiler = compile(
+            "type zed = struct { z: int, };
+             type P<T> = struct { x: int, } & T;
+             type A = struct { x: int, } & zed;
+             type B = P<T=zed>;
+             type C = zed & struct { x: int, };",
+        )
+        .unwrap();
+        assert_eq!(members(&compiler, "A"), ["x", "z"]);
+        assert_eq!(members(&compiler, "B"), ["x", "z"]);
+        assert_eq!(members(&compiler, "C"), ["z", "x"]);
+    }
+
+    #[test]
+    fn equivalent_unions_compile_the_same() {
+        let compiler = compile(
+            "type a = enum { a(unit), };
+             type b = enum { b(unit), };
+             type U<T> = T | a;
+             type C = a | b;
+             type D = U<T=b>;
+             type E = b | a | b;",
+        )
+        .unwrap();
+        assert_eq!(members(&compiler, "C"), ["a", "b"]);
+        assert_eq!(members(&compiler, "D"), ["a", "b"]);
+        assert_eq!(members(&compiler, "E"), ["a", "b"]);
+    }
+
+    #[test]
+    fn registered_globals_are_normalized() {
+        let source = "type a = enum { a(unit), }; type b = enum { b(unit), };";
+        let mut compiler = IRCompiler::new();
+        compiler.register_program(
+            crate::parse(source, "test.roto").unwrap(),
+            "test.roto",
+            source,
+        );
+        let union = |l: &str, r: &str| {
+            ast::TypeExpression::Union(
+                Box::new(ast::TypeExpression::Variable(Atom::from(l))),
+                Box::new(ast::TypeExpression::Variable(Atom::from(r))),
+            )
+        };
+        for (name, type_) in [("C", union("a", "b")), ("D", union("b", "a"))] {
+            compiler.register_global_type(
+                name.to_string(),
+                TypePrototype {
+                    params: Vec::new(),
+                    type_,
+                    doc: None,
+                    annotations: Vec::new(),
+                    location: None,
+                },
+            );
+        }
+        compiler.compile_globals().unwrap();
+        assert_eq!(members(&compiler, "C"), members(&compiler, "D"));
+    }
+
+    /// The line and column of a location.
+    fn position(location: &Option<ast::SourceLocation>) -> (usize, usize) {
+        let location = location.as_ref().expect("the error is located");
+        (location.line, location.column)
+    }
+
+    #[test]
+    fn inlined_aliases_of_each_other_are_errors() {
+        let error =
+            compile("@inline type B = A;\n@inline type A = B;\ntype C = struct { x: A, };").err();
+        let Some(CompileError::CyclicInlineAlias { name, location }) = error else {
+            panic!("{:?}", error);
+        };
+        assert_eq!(name, "A");
+        assert_eq!(position(&location), (2, 1));
+    }
+
+    #[test]
+    fn merged_compilers_inline_aliases() {
+        let mut compiler = compile("type Team = struct { name: string, };").unwrap();
+        let other = compile("@inline type Id = string;\ntype User = struct { id: Id, };").unwrap();
+        compiler.merge(&other, CollisionPolicy::Reject).unwrap();
+        let names: Vec<String> = compiler
+            .iter_types()
+            .map(|(_, t)| t.name.to_string())
+            .collect();
+        assert!(!names.contains(&"Id".to_string()), "{:?}", names);
+        let (_, user) = compiler
+            .iter_types()
+            .find(|(_, t)| t.name == TypeName::Variable(Atom::from("User")))
+            .unwrap();
+        let IRType::Struct(user) = &user.type_ else {
+            panic!("{:?}", user);
+        };
+        assert_eq!(
+            user.fields[0].type_,
+            PrimitiveType::Builtin(ast::Builtin::String)
+        );
+    }
+
+    #[test]
+    fn inline_types_outside_of_named_types_are_errors() {
+        let mut compiler = IRCompiler::new();
+        let t = ast::TypeExpression::Struct(ast::StructTypeExpression { fields: Vec::new() });
+        assert!(matches!(
+            compiler.compile_to_primitive_type(&t),
+            Err(CompileError::UnnamedInlineType)
+        ));
+    }
+
+    #[test]
+    fn self_referential_intersections_are_errors() {
+        let error = compile("type B = int;\ntype A = struct { x: int, } & A;").err();
+        let Some(CompileError::CyclicType { name, location }) = error else {
+            panic!("expected a cyclic type, found {:?}", error);
+        };
+        assert_eq!(name, "A");
+        assert_eq!(position(&location), (2, 1));
+
+        let error = compile("type A = B;\ntype B = enum { b(unit), } | A;").err();
+        assert!(
+            matches!(&error, Some(CompileError::CyclicType { name, .. }) if name == "A"),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn overlapping_members_are_located() {
+        let error = compile("type A = struct {\n  x: int,\n} & struct {\n  x: string,\n};").err();
+        let Some(CompileError::OverlappingMember(conflict)) = error else {
+            panic!("expected overlapping members, found {:?}", error);
+        };
+        assert_eq!(conflict.name, "x");
+        assert_eq!(position(&conflict.location), (4, 3));
+        assert_eq!(position(&conflict.other), (2, 3));
+    }
+
+    #[test]
+    fn incompatible_operands_are_located() {
+        let error = compile("type A = int;\ntype B = struct { x: A & int, };").err();
+        let Some(CompileError::IncompatibleIntersection { location }) = error else {
+            panic!("expected an incompatible intersection, found {:?}", error);
+        };
+        assert_eq!(position(&location), (2, 19));
+    }
+
+    #[test]
+    fn self_referential_conditionals_are_errors() {
+        let error = compile("type A = if A == unit then int else string;").err();
+        let Some(CompileError::CyclicConditional { name, location }) = error else {
+            panic!("expected a cyclic conditional, found {:?}", error);
+        };
+        assert_eq!(name, "A");
+        assert_eq!(position(&location), (1, 1));
+
+        let error = compile(
+            "type A = struct { b: B, };\n\
+             type B = if A == struct { b: int, } then int else string;",
+        )
+        .err();
+        assert!(
+            matches!(&error, Some(CompileError::CyclicConditional { name, .. }) if name == "A"),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn substituted_instantiations_are_the_written_ones() {
+        let compiler = compile(
+            "type W<T> = struct { v: T, };\n\
+             type X = struct { a: W<T=W<T=W<T=int>>>, b: W<T=W<T=int>>, c: W<T=int>, };",
+        )
+        .unwrap();
+        let field = |id: usize, name: &str| match compiler.resolve_ir_type(&IRType::Reference(id)) {
+            ResolvedIRType::Struct(s) => match s.fields.iter().find(|f| f.name == name) {
+                Some(PrimitiveStructField {
+                    type_: PrimitiveType::Reference(id),
+                    ..
+                }) => *id,
+                _ => panic!("{} is not a reference", name),
+            },
+            _ => panic!("{} is not a struct", id),
+        };
+        let (&x, _) = compiler
+            .iter_types()
+            .find(|(_, t)| t.name == TypeName::Variable(Atom::from("X")))
+            .unwrap();
+        assert_eq!(field(field(x, "a"), "v"), field(x, "b"));
+        assert_eq!(field(field(x, "b"), "v"), field(x, "c"));
+        assert_eq!(compiler.instantiations_of("W").len(), 3);
+    }
+
+    #[test]
+    fn conditionals_keep_no_temporaries_of_their_operands() {
+        let source = "type A = struct { x: int, };\n\
+             type B = if A == struct { x: int, y: struct { z: int, }, } then int else string;";
+        let mut compiler = IRCompiler::new();
+        compiler.register_program(
+            crate::parse(source, "test.roto").unwrap(),
+            "test.roto",
+            source,
+        );
+        // Without `compile_globals`, which would remove them along with every other unused type.
+        let expr = compiler.symbols().get_type("B").unwrap().type_.clone();
+        compiler.compile_global("B".to_string(), &expr).unwrap();
+        assert!(
+            compiler
+                .iter_types()
+                .all(|(_, t)| !matches!(t.name, TypeName::Temporary(..))),
+            "{:?}",
+            compiler
+                .iter_types()
+                .map(|(_, t)| &t.name)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn conditionals_of_compiled_types_are_evaluated() {
+        let compiler = compile(
+            "type A = struct { x: int, };\n\
+             type B = struct { c: if A == struct { x: int, } then enum { yes(unit), } else int, };",
+        )
+        .unwrap();
+        assert_eq!(members(&compiler, "B"), ["c"]);
+    }
 }
@@ -0,0 +1,186 @@
+//! Interned names. The names of types and of their members are compared and hashed everywhere in
+//! the compiler, so they are [`Atom`]s: pointers to the one copy of each name in a table of the
+//! process. Copying, hashing and testing atoms for equality compares pointers, and reading or
+//! ordering them reads the name directly, without touching the table. Only interning a name looks
+//! it up, behind a `RwLock`.
+//!
+//! The table is global and its names are leaked, never freed. That is fine for a compiler run,
+//! but a long-running process that embeds the compiler, like a language server or a watch loop,
+//! keeps every name it has ever seen, and threads that intern new names contend for the write
+//! lock.
+
+use core::fmt;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{OnceLock, RwLock};
+
+/// An interned name, like `User` or `auth.User`. Atoms order by their names, so that maps keyed
+/// by them iterate in the same order as maps keyed by strings.
+#[derive(Clone, Copy)]
+pub struct Atom(&'static str);
+
+fn interner() -> &'static RwLock<HashSet<&'static str>> {
+    static INTERNER: OnceLock<RwLock<HashSet<&'static str>>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Atom {
+    /// The atom of `name`, the same one every time.
+    pub fn intern(name: &str) -> Atom {
+        if let Some(name) = interner().read().unwrap().get(name) {
+            return Atom(name);
+        }
+        let mut interner = interner().write().unwrap();
+        if let Some(name) = interner.get(name) {
+            return Atom(name);
+        }
+        let name: &'static str = Box::leak(name.into());
+        interner.insert(name);
+        Atom(name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+impl PartialEq for Atom {
+    fn eq(&self, other: &Atom) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Atom {}
+
+impl Hash for Atom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.0, state)
+    }
+}
+
+impl Deref for Atom {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Atom {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Atom {
+    fn from(name: &str) -> Atom {
+        Atom::intern(name)
+    }
+}
+
+impl From<&String> for Atom {
+    fn from(name: &String) -> Atom {
+        Atom::intern(name)
+    }
+}
+
+impl From<String> for Atom {
+    fn from(name: String) -> Atom {
+        Atom::intern(&name)
+    }
+}
+
+impl From<Atom> for String {
+    fn from(atom: Atom) -> String {
+        atom.as_str().to_string()
+    }
+}
+
+impl PartialEq<str> for Atom {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Atom {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Atom {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialOrd for Atom {
+    fn partial_cmp(&self, other: &Atom) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Atom {
+    fn cmp(&self, other: &Atom) -> Ordering {
+        if self == other {
+            return Ordering::Equal;
+        }
+        self.0.cmp(other.0)
+    }
+}
+
+impl Display for Atom {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl Debug for Atom {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Atom {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Atom {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Atom, D::Error> {
+        let name = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        Ok(Atom::intern(&name))
+    }
+}
+
+/// The names of `atoms` joined by `separator`, like the path of a temporary type.
+pub fn join(atoms: &[Atom], separator: &str) -> String {
+    let names: Vec<&str> = atoms.iter().map(|atom| atom.as_str()).collect();
+    names.join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atoms_are_equal_when_their_names_are() {
+        let name = String::from("User");
+        assert_eq!(Atom::intern("User"), Atom::intern(&name));
+        assert_ne!(Atom::intern("User"), Atom::intern("Users"));
+        assert_eq!(Atom::intern("User"), "User");
+    }
+
+    #[test]
+    fn atoms_order_by_their_names() {
+        let mut atoms = vec![Atom::intern("b"), Atom::intern("c"), Atom::intern("a")];
+        atoms.sort();
+        assert_eq!(atoms, ["a", "b", "c"].map(Atom::intern));
+    }
+}
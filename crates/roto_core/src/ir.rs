@@ -1,59 +1,92 @@
 use core::fmt;
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::BTreeMap,
     fmt::{Display, Formatter},
 };
 
-use crate::ast;
+use crate::{
+    ast,
+    error::{CompileError, MemberConflict},
+    format::format_type,
+    intern::{self, Atom},
+};
 
 pub trait Intersectable<A, B> {
-    fn intersect(&self, other: &B) -> A;
+    fn intersect(&self, other: &B) -> Result<A, CompileError>;
+}
+
+pub trait Unionable<A, B> {
+    fn union(&self, other: &B) -> Result<A, CompileError>;
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypeName {
-    Variable(String),
-    Generic(String, BTreeMap<String, ast::TypeExpression>),
-    Temporary(usize),
+    Variable(Atom),
+    Generic(Atom, BTreeMap<Atom, ast::TypeExpression>),
+    /// An inline struct or variant, named after the type it is declared in and the path of
+    /// field and option names leading to it.
+    Temporary(Box<TypeName>, Vec<Atom>),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PrimitiveStructField {
-    pub name: String,
+    pub name: Atom,
     pub type_: PrimitiveType,
+    pub optional: bool,
+    pub annotations: Vec<ast::Annotation>,
     pub comment: Option<String>,
+    /// Where the field was declared, if it comes from a source file.
+    pub location: Option<ast::SourceLocation>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PrimitiveStruct {
     pub fields: Vec<PrimitiveStructField>,
 }
 
+impl Default for PrimitiveStruct {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PrimitiveStruct {
     pub fn new() -> Self {
         PrimitiveStruct { fields: Vec::new() }
     }
-    pub fn add_field(&mut self, name: String, type_: PrimitiveType, comment: Option<String>) {
+    pub fn add_field(
+        &mut self,
+        name: Atom,
+        type_: PrimitiveType,
+        optional: bool,
+        annotations: Vec<ast::Annotation>,
+        comment: Option<String>,
+    ) {
         self.fields.push(PrimitiveStructField {
             name,
             type_,
+            optional,
+            annotations,
             comment,
+            location: None,
         });
     }
 }
 
 impl Intersectable<PrimitiveStruct, PrimitiveStruct> for PrimitiveStruct {
-    fn intersect(&self, other: &PrimitiveStruct) -> PrimitiveStruct {
+    fn intersect(&self, other: &PrimitiveStruct) -> Result<PrimitiveStruct, CompileError> {
         let mut out = PrimitiveStruct::new();
-        let b_fields_names = other
-            .fields
-            .iter()
-            .map(|f| f.name.clone())
-            .collect::<HashSet<_>>();
 
         for f in &self.fields {
-            if b_fields_names.contains(&f.name) {
-                panic!("Intersection of structs with overlapping fields");
+            if let Some(other) = other.fields.iter().find(|other| other.name == f.name) {
+                return Err(CompileError::OverlappingMember(Box::new(MemberConflict {
+                    name: f.name.to_string(),
+                    location: other.location.clone(),
+                    other: f.location.clone(),
+                })));
             }
 
             out.fields.push(f.clone());
@@ -63,34 +96,37 @@ impl Intersectable<PrimitiveStruct, PrimitiveStruct> for PrimitiveStruct {
             out.fields.push(f.clone());
         }
 
-        out
+        Ok(out)
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PrimitiveVariantOption {
-    pub name: String,
+    pub name: Atom,
     pub type_: PrimitiveType,
+    pub annotations: Vec<ast::Annotation>,
     pub comment: Option<String>,
+    /// Where the option was declared, if it comes from a source file.
+    pub location: Option<ast::SourceLocation>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PrimitiveVariant {
     pub variants: Vec<PrimitiveVariantOption>,
 }
 
 impl Intersectable<PrimitiveVariant, PrimitiveVariant> for PrimitiveVariant {
-    fn intersect(&self, other: &PrimitiveVariant) -> PrimitiveVariant {
+    fn intersect(&self, other: &PrimitiveVariant) -> Result<PrimitiveVariant, CompileError> {
         let mut out = PrimitiveVariant::new();
-        let b_variants_names = other
-            .variants
-            .iter()
-            .map(|v| v.name.clone())
-            .collect::<HashSet<_>>();
-
         for v in &self.variants {
-            if b_variants_names.contains(&v.name) {
-                panic!("Intersection of variants with overlapping fields");
+            if let Some(other) = other.variants.iter().find(|other| other.name == v.name) {
+                return Err(CompileError::OverlappingMember(Box::new(MemberConflict {
+                    name: v.name.to_string(),
+                    location: other.location.clone(),
+                    other: v.location.clone(),
+                })));
             }
 
             out.variants.push(v.clone());
@@ -100,7 +136,44 @@ impl Intersectable<PrimitiveVariant, PrimitiveVariant> for PrimitiveVariant {
             out.variants.push(v.clone());
         }
 
-        out
+        Ok(out)
+    }
+}
+
+/// Options that appear on both sides with the same payload are kept once. Options with the same
+/// name but a different payload are an error.
+impl Unionable<PrimitiveVariant, PrimitiveVariant> for PrimitiveVariant {
+    fn union(&self, other: &PrimitiveVariant) -> Result<PrimitiveVariant, CompileError> {
+        let mut out = PrimitiveVariant::new();
+        for v in &self.variants {
+            out.variants.push(v.clone());
+        }
+
+        for v in &other.variants {
+            match self
+                .variants
+                .iter()
+                .find(|existing| existing.name == v.name)
+            {
+                Some(existing) if existing.type_ == v.type_ => {}
+                Some(existing) => {
+                    return Err(CompileError::ConflictingOption(Box::new(MemberConflict {
+                        name: v.name.to_string(),
+                        location: v.location.clone(),
+                        other: existing.location.clone(),
+                    })))
+                }
+                None => out.variants.push(v.clone()),
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+impl Default for PrimitiveVariant {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -110,11 +183,19 @@ impl PrimitiveVariant {
             variants: Vec::new(),
         }
     }
-    pub fn add_variant(&mut self, name: String, type_: PrimitiveType, comment: Option<String>) {
+    pub fn add_variant(
+        &mut self,
+        name: Atom,
+        type_: PrimitiveType,
+        annotations: Vec<ast::Annotation>,
+        comment: Option<String>,
+    ) {
         self.variants.push(PrimitiveVariantOption {
             name,
             type_,
+            annotations,
             comment,
+            location: None,
         });
     }
 }
@@ -122,6 +203,7 @@ impl PrimitiveVariant {
 /// IRType is the most generate type of type - it can represent any type that can be used in the
 /// IR. This includes structs, variants, references, and builtins.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IRType {
     Struct(PrimitiveStruct),
     Variant(PrimitiveVariant),
@@ -132,7 +214,8 @@ pub enum IRType {
 /// A primitive type is a type that is "constant" in size, i.e. it does not have any direct
 /// nesting, but can reference other types. This is used to represent the type of values in the
 /// ir.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PrimitiveType {
     Reference(usize),
     Builtin(ast::Builtin),
@@ -146,6 +229,8 @@ pub enum ResolvedIRType {
     Builtin(ast::Builtin),
 }
 
+/// The name as it is written in a schema, like `Page<T=User>`, or, for temporaries, with the path
+/// to them from the declared type, like `User.address`.
 impl Display for TypeName {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
@@ -156,19 +241,44 @@ impl Display for TypeName {
                     if i > 0 {
                         write!(f, ", ")?;
                     }
-                    write!(f, "{}={:?}", k, v)?;
+                    write!(f, "{}={}", k, format_type(v))?;
                 }
                 write!(f, ">")
             }
-            TypeName::Temporary(id) => write!(f, "T{}", id),
+            TypeName::Temporary(owner, path) => write!(f, "{}.{}", owner, intern::join(path, ".")),
+        }
+    }
+}
+
+/// Source level information about a named type that does not influence its structure.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeMetadata {
+    pub annotations: Vec<ast::Annotation>,
+    pub doc: Option<String>,
+    pub location: Option<ast::SourceLocation>,
+}
+
+impl TypeMetadata {
+    pub fn annotation(&self, name: &str) -> Option<&ast::Annotation> {
+        self.annotations.iter().find(|a| a.name == name)
+    }
+
+    /// The format of the values of an alias, like `email`, from `@format`.
+    pub fn format(&self) -> Option<&str> {
+        match self.annotation("format")?.arg("value")? {
+            ast::AnnotationValue::String(format) => Some(format),
+            _ => None,
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NamedIRType {
     pub name: TypeName,
     pub type_: IRType,
+    pub metadata: TypeMetadata,
 }
 
 impl Display for IRType {
@@ -179,7 +289,10 @@ impl Display for IRType {
                 for PrimitiveStructField {
                     name: k,
                     type_: v,
+                    optional,
+                    annotations,
                     comment,
+                    ..
                 } in fields.iter()
                 {
                     if let Some(comment) = comment {
@@ -187,7 +300,11 @@ impl Display for IRType {
                             write!(f, "\n  // {}", line)?;
                         }
                     }
-                    write!(f, "\n  {}: {},", k, v)?;
+                    for annotation in annotations {
+                        write!(f, "\n  {}", annotation)?;
+                    }
+                    let marker = if *optional { "?" } else { "" };
+                    write!(f, "\n  {}{}: {},", k, marker, v)?;
                 }
                 write!(f, "\n}}")
             }
@@ -196,7 +313,9 @@ impl Display for IRType {
                 for PrimitiveVariantOption {
                     name: k,
                     type_: v,
+                    annotations,
                     comment,
+                    ..
                 } in variants.iter()
                 {
                     if let Some(comment) = comment {
@@ -204,6 +323,9 @@ impl Display for IRType {
                             write!(f, "\n  // {}", line)?;
                         }
                     }
+                    for annotation in annotations {
+                        write!(f, "\n  {}", annotation)?;
+                    }
                     write!(f, "\n  {}({}),", k, v)?;
                 }
                 write!(f, "\n}}")
@@ -223,18 +345,18 @@ impl Display for PrimitiveType {
     }
 }
 
-impl Into<IRType> for PrimitiveType {
-    fn into(self) -> IRType {
-        match self {
+impl From<PrimitiveType> for IRType {
+    fn from(t: PrimitiveType) -> IRType {
+        match t {
             PrimitiveType::Reference(id) => IRType::Reference(id),
             PrimitiveType::Builtin(builtin) => IRType::Builtin(builtin),
         }
     }
 }
 
-impl Into<IRType> for ResolvedIRType {
-    fn into(self) -> IRType {
-        match self {
+impl From<ResolvedIRType> for IRType {
+    fn from(t: ResolvedIRType) -> IRType {
+        match t {
             ResolvedIRType::Struct(fields) => IRType::Struct(fields),
             ResolvedIRType::Variant(variants) => IRType::Variant(variants),
             ResolvedIRType::Builtin(builtin) => IRType::Builtin(builtin),
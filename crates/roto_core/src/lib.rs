@@ -1,4 +1,31 @@
+pub mod annotations;
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+pub mod arena;
 pub mod ast;
+pub mod backend;
+pub mod builtins;
+pub mod compat;
+pub mod cst;
+pub mod diagnostics;
+pub mod diff;
+pub mod docs;
+pub mod error;
+pub mod format;
 pub mod frontend;
+pub mod intern;
 pub mod ir;
-pub mod parser;
\ No newline at end of file
+pub mod lint;
+pub mod migrate;
+pub mod output;
+#[allow(clippy::all)]
+pub mod parser;
+pub mod prelude;
+pub mod prune;
+pub mod refactor;
+pub mod relations;
+pub mod symbols;
+pub mod syntax;
+
+pub use error::{CompileError, RotoError};
+pub use syntax::{parse, ParseDiagnostic};
@@ -0,0 +1,298 @@
+//! Style checks on the declared types. Every rule reports at a level that can be configured,
+//! e.g. from the `[lint]` table of `roto.toml`.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+    ast::{self, TypeExpression, Visibility},
+    diagnostics::Diagnostic,
+    frontend::{IRCompiler, TypePrototype},
+    symbols::split_qualified_name,
+};
+
+/// Inline structs and variants nested deeper than this are reported by [`Rule::DeepNesting`].
+pub const MAX_NESTING: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rule {
+    /// Type names are PascalCase, like `UserStatus`.
+    TypeNaming,
+    /// Field and option names are snake_case, like `display_name`.
+    FieldNaming,
+    /// Public types have a doc comment. Types of the root module count as public.
+    MissingDoc,
+    /// Every field has a `@tag`, as the protobuf and thrift backends require.
+    MissingTag,
+    /// Every variant option has a `@tag`, so it keeps its ordinal in binary encodings when
+    /// options are added, removed or reordered.
+    MissingOrdinal,
+    /// Inline types are nested at most [`MAX_NESTING`] deep.
+    DeepNesting,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::TypeNaming,
+        Rule::FieldNaming,
+        Rule::MissingDoc,
+        Rule::MissingTag,
+        Rule::MissingOrdinal,
+        Rule::DeepNesting,
+    ];
+
+    /// The name the rule is configured by, like `type-naming`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::TypeNaming => "type-naming",
+            Rule::FieldNaming => "field-naming",
+            Rule::MissingDoc => "missing-doc",
+            Rule::MissingTag => "missing-tag",
+            Rule::MissingOrdinal => "missing-ordinal",
+            Rule::DeepNesting => "deep-nesting",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    /// Tags only matter for some targets, so those rules have to be turned on.
+    fn default_level(self) -> Level {
+        match self {
+            Rule::MissingTag | Rule::MissingOrdinal => Level::Allow,
+            _ => Level::Warn,
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    /// The rule is not checked.
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// The level of every rule, which is the rule's default unless it is set.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    levels: BTreeMap<Rule, Level>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, rule: Rule, level: Level) {
+        self.levels.insert(rule, level);
+    }
+
+    /// A configuration that only checks `rules`, and reports them as errors.
+    pub fn deny_only(rules: impl IntoIterator<Item = Rule>) -> Self {
+        let mut config = Self::new();
+        for rule in Rule::ALL {
+            config.set(rule, Level::Allow);
+        }
+        for rule in rules {
+            config.set(rule, Level::Deny);
+        }
+        config
+    }
+
+    pub fn level(&self, rule: Rule) -> Level {
+        self.levels
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_level())
+    }
+}
+
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && !name.contains('_')
+}
+
+fn is_snake_case(name: &str) -> bool {
+    name.chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    diagnostics: Vec<Diagnostic>,
+    location: Option<ast::SourceLocation>,
+}
+
+impl<'a> Linter<'a> {
+    fn report(&mut self, rule: Rule, message: String) {
+        let location = self.location.clone();
+        let diagnostic = match self.config.level(rule) {
+            Level::Allow => return,
+            Level::Warn => Diagnostic::warning(message, location),
+            Level::Deny => Diagnostic::error(message, location),
+        };
+        self.diagnostics.push(diagnostic.with_code(rule.name()));
+    }
+
+    /// Checks the field or option `name` of `path`, which is an option if `option`.
+    fn lint_member(
+        &mut self,
+        path: &str,
+        name: &str,
+        annotations: &[ast::Annotation],
+        option: bool,
+    ) {
+        if !is_snake_case(name) {
+            self.report(
+                Rule::FieldNaming,
+                format!("`{}.{}` should be snake_case", path, name),
+            );
+        }
+        if annotations.iter().any(|a| a.name == "tag") {
+            return;
+        }
+        if option {
+            self.report(
+                Rule::MissingOrdinal,
+                format!(
+                    "`{}.{}` has no @tag annotation, so its ordinal is not stable",
+                    path, name
+                ),
+            );
+        } else {
+            self.report(
+                Rule::MissingTag,
+                format!("`{}.{}` has no @tag annotation", path, name),
+            );
+        }
+    }
+
+    /// Checks the members of `t`, which is nested `depth` inline types deep and found at `path`.
+    fn lint_type(&mut self, t: &TypeExpression, path: &str, depth: usize) {
+        match t {
+            // Only the outermost type that is too deep is reported.
+            TypeExpression::Struct(_) | TypeExpression::Variant(_) if depth == MAX_NESTING + 1 => {
+                self.report(
+                    Rule::DeepNesting,
+                    format!(
+                        "`{}` is an inline type nested more than {} deep",
+                        path, MAX_NESTING
+                    ),
+                );
+            }
+            _ => {}
+        }
+        match t {
+            TypeExpression::Struct(struct_type) => {
+                for field in &struct_type.fields {
+                    self.lint_member(path, &field.name, &field.annotations, false);
+                    let path = format!("{}.{}", path, field.name);
+                    self.lint_type(&field.type_, &path, depth + 1);
+                }
+            }
+            TypeExpression::Variant(variant_type) => {
+                for option in &variant_type.variants {
+                    self.lint_member(path, &option.name, &option.annotations, true);
+                    let path = format!("{}.{}", path, option.name);
+                    self.lint_type(&option.type_, &path, depth + 1);
+                }
+            }
+            TypeExpression::Intersection(a, b) | TypeExpression::Union(a, b) => {
+                self.lint_type(a, path, depth);
+                self.lint_type(b, path, depth);
+            }
+            TypeExpression::Conditional(c) => {
+                self.lint_type(&c.then, path, depth);
+                self.lint_type(&c.otherwise, path, depth);
+            }
+            TypeExpression::Variable(_)
+            | TypeExpression::Builtin(_)
+            | TypeExpression::Generic(_, _) => {}
+        }
+    }
+
+    fn lint_prototype(&mut self, name: &str, prototype: &TypePrototype, public: bool) {
+        self.location = prototype.location.clone();
+        let (_, local) = split_qualified_name(name);
+        if !is_pascal_case(local) {
+            self.report(
+                Rule::TypeNaming,
+                format!("type `{}` should be PascalCase", name),
+            );
+        }
+        if public && prototype.doc.is_none() {
+            self.report(
+                Rule::MissingDoc,
+                format!("public type `{}` has no doc comment", name),
+            );
+        }
+        // The declared type itself is not nested, but inline types in its members are.
+        self.lint_type(&prototype.type_, name, 0);
+    }
+}
+
+/// Checks every declared type against the rules of `config`. Allowed rules report nothing,
+/// denied rules report errors.
+pub fn lint(compiler: &IRCompiler, config: &LintConfig) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        config,
+        diagnostics: Vec::new(),
+        location: None,
+    };
+    for (name, prototype) in compiler.iter_globals() {
+        let public = match compiler.symbols().get(name) {
+            Some(symbol) => symbol.module.is_empty() || symbol.visibility == Visibility::Public,
+            None => false,
+        };
+        linter.lint_prototype(name, prototype, public);
+    }
+    linter.diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_rules_name_members_as_written() {
+        let source = "type Optional<T> = enum { some(T), @tag(value=2) none(unit), };
+                      type Node<T> = struct { value: T, next: Optional<T=Node<T=T>>, };
+                      type Root = struct { @tag(value=1) head: Node<T=int>, };";
+        let items = crate::parse(source, "test.roto").expect("the source parses");
+        let mut compiler = IRCompiler::new();
+        compiler.register_program(items, "test.roto", source);
+        compiler.compile_globals().unwrap();
+        let config = LintConfig::deny_only([Rule::MissingTag, Rule::MissingOrdinal]);
+        let mut messages: Vec<_> = lint(&compiler, &config)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        messages.sort();
+        assert_eq!(
+            messages,
+            [
+                "`Node.next` has no @tag annotation",
+                "`Node.value` has no @tag annotation",
+                "`Optional.some` has no @tag annotation, so its ordinal is not stable",
+            ]
+        );
+    }
+}
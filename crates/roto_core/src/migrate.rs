@@ -0,0 +1,208 @@
+//! Migrations between two versions of a schema. Types declare their version with
+//! `@version(value=N)`, a type without one is at version 1. A type that changes between two
+//! versions of a schema has to declare a higher version, and for every type that does,
+//! [`migrations`] lists what a payload of the old version needs to become one of the new
+//! version. Backends implementing [`MigrationWriter`] turn these into functions of the target
+//! language.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    ast,
+    backend::OutputFile,
+    compat::{classify, Compatibility},
+    diagnostics::Diagnostic,
+    diff::{diff, SchemaChange},
+    frontend::IRCompiler,
+    intern::Atom,
+    ir::{IRType, PrimitiveStructField, ResolvedIRType, TypeName},
+};
+
+/// The version of a type with `annotations`, 1 unless it has a `@version`.
+pub fn version(annotations: &[ast::Annotation]) -> i64 {
+    annotations
+        .iter()
+        .find(|a| a.name == "version")
+        .and_then(|a| match a.arg("value") {
+            Some(ast::AnnotationValue::Int(value)) => Some(*value),
+            _ => None,
+        })
+        .unwrap_or(1)
+}
+
+/// What a migration does to a payload. Keys are the JSON keys of fields, their `@rename` if they
+/// have one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationStep {
+    /// The field `old` of the type is now called `new`.
+    RenameField { old: String, new: String },
+    /// The field `key` of the type was removed and is dropped.
+    RemoveField { key: String },
+    /// A change that can not be migrated without knowing what the data means, like a new
+    /// required field or a changed type. Backends leave a TODO for it.
+    Manual(SchemaChange),
+}
+
+/// The steps that turn a payload of `type_name` at version `from` into one at version `to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// The global name of the type, like `auth.User`.
+    pub type_name: String,
+    pub from: i64,
+    pub to: i64,
+    pub steps: Vec<MigrationStep>,
+}
+
+/// A backend that writes migrations as code of its target language.
+pub trait MigrationWriter {
+    fn write_migrations(&self, migrations: &[Migration]) -> Vec<OutputFile>;
+}
+
+/// The migrations of the types whose version increased from `old` to `new`, ordered by name.
+/// Types that changed without a higher version are reported as warnings, types whose version
+/// decreased as errors.
+pub fn migrations(old: &IRCompiler, new: &IRCompiler) -> (Vec<Migration>, Vec<Diagnostic>) {
+    let old_globals = global_types(old);
+    let new_globals = global_types(new);
+    let mut changes: BTreeMap<Atom, Vec<SchemaChange>> = BTreeMap::new();
+    for change in diff(old, new) {
+        // Qualified names contain dots too, so a change belongs to the longest global name its
+        // path starts with.
+        let path = change.path();
+        let owner = new_globals
+            .keys()
+            .filter(|name| old_globals.contains_key(*name))
+            .filter(|name| {
+                let name = name.as_str();
+                path == name
+                    || path
+                        .strip_prefix(name)
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .max_by_key(|name| name.as_str().len());
+        if let Some(owner) = owner {
+            changes.entry(*owner).or_default().push(change);
+        }
+    }
+
+    let mut migrations = Vec::new();
+    let mut diagnostics = Vec::new();
+    for (name, new_id) in &new_globals {
+        let Some(old_id) = old_globals.get(name) else {
+            continue;
+        };
+        let from = version(&old.named_type(*old_id).metadata.annotations);
+        let metadata = &new.named_type(*new_id).metadata;
+        let to = version(&metadata.annotations);
+        let changes = changes.remove(name).unwrap_or_default();
+        if to < from {
+            diagnostics.push(
+                Diagnostic::error(
+                    format!("the version of {} decreased from {} to {}", name, from, to),
+                    metadata.location.clone(),
+                )
+                .with_code("version-decreased"),
+            );
+            continue;
+        }
+        if to == from {
+            if changes
+                .iter()
+                .any(|change| classify(change) != Compatibility::Informational)
+            {
+                diagnostics.push(
+                    Diagnostic::warning(
+                        format!("{} changed but is still at version {}", name, to),
+                        metadata.location.clone(),
+                    )
+                    .with_code("unversioned-change"),
+                );
+            }
+            continue;
+        }
+        let steps = changes
+            .into_iter()
+            .filter_map(|change| step(old, *old_id, new, *new_id, name.as_str(), change))
+            .collect();
+        migrations.push(Migration {
+            type_name: name.to_string(),
+            from,
+            to,
+            steps,
+        });
+    }
+    (migrations, diagnostics)
+}
+
+fn global_types(compiler: &IRCompiler) -> BTreeMap<Atom, usize> {
+    compiler
+        .iter_types()
+        .filter_map(|(id, t)| match &t.name {
+            TypeName::Variable(name) => Some((*name, *id)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The step for `change` of the type `name`, if it needs one. Only fields of the type itself
+/// are renamed and removed mechanically, fields of the structs it contains are left to a TODO.
+fn step(
+    old: &IRCompiler,
+    old_id: usize,
+    new: &IRCompiler,
+    new_id: usize,
+    name: &str,
+    change: SchemaChange,
+) -> Option<MigrationStep> {
+    let field = |path: &str| {
+        path.strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .filter(|field| !field.contains('.'))
+            .map(str::to_string)
+    };
+    match &change {
+        // Old payloads are still valid for these.
+        SchemaChange::ReferenceChanged { .. }
+        | SchemaChange::CommentChanged { .. }
+        | SchemaChange::AnnotationsChanged { .. }
+        | SchemaChange::FieldAdded { optional: true, .. }
+        | SchemaChange::FieldOptionalityChanged { optional: true, .. }
+        | SchemaChange::VariantAdded { .. } => None,
+        SchemaChange::FieldRenamed {
+            path,
+            old_name,
+            new_name,
+        } if path == name => {
+            let old_key = json_key(old, old_id, old_name)?;
+            let new_key = json_key(new, new_id, new_name)?;
+            (old_key != new_key).then_some(MigrationStep::RenameField {
+                old: old_key,
+                new: new_key,
+            })
+        }
+        SchemaChange::FieldRemoved { path } => match field(path) {
+            Some(field) => Some(MigrationStep::RemoveField {
+                key: json_key(old, old_id, &field)?,
+            }),
+            None => Some(MigrationStep::Manual(change)),
+        },
+        _ => Some(MigrationStep::Manual(change)),
+    }
+}
+
+/// The JSON key of the field `name` of the struct `id`, its `@rename` if it has one.
+fn json_key(compiler: &IRCompiler, id: usize, name: &str) -> Option<String> {
+    let ResolvedIRType::Struct(s) = compiler.resolve_ir_type(&IRType::Reference(id)) else {
+        return None;
+    };
+    let field: &PrimitiveStructField = s.fields.iter().find(|f| f.name == name)?;
+    match field
+        .annotations
+        .iter()
+        .find(|a| a.name == "rename")
+        .and_then(|a| a.arg("name"))
+    {
+        Some(ast::AnnotationValue::String(key)) => Some(key.clone()),
+        _ => Some(field.name.to_string()),
+    }
+}
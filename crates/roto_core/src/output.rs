@@ -0,0 +1,133 @@
+//! Where generated files go. Tools hand the files of a backend to an [`OutputSink`], so that the
+//! same build can write to disk, be captured in memory or only be previewed.
+
+use core::fmt;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::backend::{write_file, OutputFile};
+
+/// Receives generated files.
+pub trait OutputSink {
+    /// Receives the generated `contents` of the file at `path`.
+    fn write(&mut self, path: &Path, contents: &str) -> io::Result<()>;
+}
+
+/// Writes files to disk, creating missing directories on the way.
+#[derive(Debug, Default)]
+pub struct DiskSink {
+    /// The paths that were written, in order.
+    pub written: Vec<PathBuf>,
+}
+
+impl OutputSink for DiskSink {
+    fn write(&mut self, path: &Path, contents: &str) -> io::Result<()> {
+        debug!(path = %path.display(), bytes = contents.len(), "writing");
+        write_file(path, contents)?;
+        self.written.push(path.to_path_buf());
+        Ok(())
+    }
+}
+
+/// Keeps files in memory, for tools that check or post-process the output.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    pub files: BTreeMap<PathBuf, String>,
+}
+
+impl OutputSink for MemorySink {
+    fn write(&mut self, path: &Path, contents: &str) -> io::Result<()> {
+        self.files.insert(path.to_path_buf(), contents.to_string());
+        Ok(())
+    }
+}
+
+/// How writing a file would change what is on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Create,
+    Update,
+    Unchanged,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.pad(match self {
+            Change::Create => "create",
+            Change::Update => "update",
+            Change::Unchanged => "unchanged",
+        })
+    }
+}
+
+/// A file that would be written, with what is on disk now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingFile {
+    pub path: PathBuf,
+    /// The current contents, `None` if the file does not exist.
+    pub previous: Option<String>,
+    pub contents: String,
+}
+
+impl PendingFile {
+    pub fn change(&self) -> Change {
+        match &self.previous {
+            None => Change::Create,
+            Some(previous) if *previous == self.contents => Change::Unchanged,
+            Some(_) => Change::Update,
+        }
+    }
+}
+
+/// Writes nothing, but reads what each file would replace, for dry runs and diff previews.
+#[derive(Debug, Default)]
+pub struct PreviewSink {
+    pub files: Vec<PendingFile>,
+}
+
+impl PreviewSink {
+    /// The files that would be created or would change.
+    pub fn changed(&self) -> impl Iterator<Item = &PendingFile> {
+        self.files
+            .iter()
+            .filter(|file| file.change() != Change::Unchanged)
+    }
+}
+
+impl OutputSink for PreviewSink {
+    fn write(&mut self, path: &Path, contents: &str) -> io::Result<()> {
+        let previous = match fs::read_to_string(path) {
+            Ok(previous) => Some(previous),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => {
+                return Err(io::Error::new(
+                    error.kind(),
+                    format!("failed to read {}: {}", path.display(), error),
+                ))
+            }
+        };
+        self.files.push(PendingFile {
+            path: path.to_path_buf(),
+            previous,
+            contents: contents.to_string(),
+        });
+        Ok(())
+    }
+}
+
+/// Hands `files` to `sink`, with their paths below `directory`.
+pub fn write_all(
+    sink: &mut dyn OutputSink,
+    directory: &Path,
+    files: &[OutputFile],
+) -> io::Result<()> {
+    for file in files {
+        sink.write(&directory.join(&file.path), &file.contents)?;
+    }
+    Ok(())
+}
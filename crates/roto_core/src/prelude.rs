@@ -0,0 +1,21 @@
+//! The items most backends and tools need, for a single `use roto_core::prelude::*;`.
+//!
+//! Everything exported here is part of the stable API: it only changes in a breaking release.
+//! Modules not re-exported here, like `parser` and `symbols`, are implementation details.
+
+pub use crate::annotations::{AnnotationRegistry, AnnotationSpec, ArgumentKind, ArgumentSpec};
+pub use crate::ast::{Annotation, AnnotationValue, Builtin};
+pub use crate::backend::{
+    Backend, BackendConfig, BackendError, BackendRegistry, CodegenBackend, Feature, FileHeader,
+    OutputFile, TypeNameAllocator, Worklist,
+};
+pub use crate::diagnostics::{has_errors, Diagnostic, Severity};
+pub use crate::error::{CompileError, RotoError};
+pub use crate::frontend::{IRCompiler, TypeAllocator};
+pub use crate::intern::Atom;
+pub use crate::ir::{
+    IRType, NamedIRType, PrimitiveStruct, PrimitiveStructField, PrimitiveType, PrimitiveVariant,
+    PrimitiveVariantOption, ResolvedIRType, TypeMetadata, TypeName,
+};
+pub use crate::output::{write_all, DiskSink, MemorySink, OutputSink, PreviewSink};
+pub use crate::syntax::{parse, ParseDiagnostic};
@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    error::CompileError,
+    frontend::IRCompiler,
+    ir::{IRType, PrimitiveType, TypeName},
+};
+
+impl IRType {
+    /// The ids of the types this type refers to directly, in member order.
+    pub fn references(&self) -> Vec<usize> {
+        let members: Vec<&PrimitiveType> = match self {
+            IRType::Struct(s) => s.fields.iter().map(|f| &f.type_).collect(),
+            IRType::Variant(v) => v.variants.iter().map(|o| &o.type_).collect(),
+            IRType::Reference(id) => return vec![*id],
+            IRType::Builtin(_) => return Vec::new(),
+        };
+        members
+            .into_iter()
+            .filter_map(|t| match t {
+                PrimitiveType::Reference(id) => Some(*id),
+                PrimitiveType::Builtin(_) => None,
+            })
+            .collect()
+    }
+}
+
+impl IRCompiler {
+    /// The ids of `roots` and every compiled type they refer to, directly or not.
+    pub fn reachable_from(&self, roots: &[usize]) -> BTreeSet<usize> {
+        let mut reachable = BTreeSet::new();
+        let mut stack = roots.to_vec();
+        while let Some(id) = stack.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            if let Some(named_type) = self.allocator.get(id) {
+                stack.extend(named_type.type_.references());
+            }
+        }
+        reachable
+    }
+
+    /// Removes every compiled type that is not reachable from `roots`, so that backends only
+    /// generate those. Declarations are kept, only their compiled types are removed.
+    pub fn retain_reachable(&mut self, roots: &[usize]) {
+        let reachable = self.reachable_from(roots);
+        self.allocator.types.retain(|id, _| reachable.contains(id));
+        self.allocator
+            .named_types
+            .retain(|_, id| reachable.contains(id));
+    }
+
+    /// Removes the temporaries with an id from `first` on that no other compiled type refers
+    /// to, like the members of the operands of a conditional type.
+    pub(crate) fn drop_unreferenced_temporaries(&mut self, first: usize) {
+        let is_new_temporary =
+            |id: usize, name: &TypeName| id >= first && matches!(name, TypeName::Temporary(..));
+        let roots: Vec<usize> = self
+            .iter_types()
+            .filter(|(id, t)| !is_new_temporary(**id, &t.name))
+            .map(|(id, _)| *id)
+            .collect();
+        let reachable = self.reachable_from(&roots);
+        self.allocator
+            .types
+            .retain(|id, t| !is_new_temporary(*id, &t.name) || reachable.contains(id));
+    }
+
+    /// Removes the compiled private types of modules that no exported type refers to, so that
+    /// backends do not generate the helpers of a module, see
+    /// [`crate::symbols::SymbolTable::is_exported`].
+    pub fn retain_exported(&mut self) {
+        let roots: Vec<usize> = self
+            .iter_types()
+            .filter_map(|(id, t)| match &t.name {
+                TypeName::Variable(name) if self.symbols().is_exported(name.as_str()) => Some(*id),
+                _ => None,
+            })
+            .collect();
+        self.retain_reachable(&roots);
+    }
+
+    /// Inlines the aliases annotated with `@inline` and removes the types no exported type
+    /// refers to, see [`IRCompiler::inline_aliases`] and [`IRCompiler::retain_exported`]. Runs
+    /// once the globals are compiled, and again after compilers are merged.
+    pub(crate) fn prune(&mut self) -> Result<(), CompileError> {
+        self.inline_aliases()?;
+        self.retain_exported();
+        Ok(())
+    }
+
+    /// Replaces every reference to an alias annotated with `@inline` by what the alias refers
+    /// to, and removes the alias, so that backends write its target wherever it is used.
+    /// Inlined aliases of inlined aliases are followed to the end. Types that are not aliases
+    /// of another type or a builtin are never inlined. Inlined aliases of each other have no
+    /// end to follow, and are an error.
+    pub fn inline_aliases(&mut self) -> Result<(), CompileError> {
+        let inlined: BTreeMap<usize, PrimitiveType> = self
+            .allocator
+            .types
+            .iter()
+            .filter(|(_, t)| t.metadata.annotation("inline").is_some())
+            .filter_map(|(id, t)| match &t.type_ {
+                IRType::Reference(target) => Some((*id, PrimitiveType::Reference(*target))),
+                IRType::Builtin(builtin) => Some((*id, PrimitiveType::Builtin(builtin.clone()))),
+                _ => None,
+            })
+            .collect();
+        if inlined.is_empty() {
+            return Ok(());
+        }
+        if let Some(id) = self.inline_cycle(&inlined) {
+            let named_type = &self.allocator.types[&id];
+            return Err(CompileError::CyclicInlineAlias {
+                name: named_type.name.to_string(),
+                location: named_type.metadata.location.clone(),
+            });
+        }
+        let target = |t: &PrimitiveType| {
+            let mut t = t.clone();
+            while let PrimitiveType::Reference(id) = &t {
+                match inlined.get(id) {
+                    Some(target) => t = target.clone(),
+                    None => break,
+                }
+            }
+            t
+        };
+        self.allocator
+            .types
+            .retain(|id, _| !inlined.contains_key(id));
+        self.allocator
+            .named_types
+            .retain(|_, id| !inlined.contains_key(id));
+        for named_type in self.allocator.types.values_mut() {
+            match &mut named_type.type_ {
+                IRType::Struct(s) => {
+                    for field in s.fields.iter_mut() {
+                        field.type_ = target(&field.type_);
+                    }
+                }
+                IRType::Variant(v) => {
+                    for option in v.variants.iter_mut() {
+                        option.type_ = target(&option.type_);
+                    }
+                }
+                IRType::Reference(id) => {
+                    named_type.type_ = target(&PrimitiveType::Reference(*id)).into();
+                }
+                IRType::Builtin(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// An alias of `inlined` that refers back to itself through other inlined aliases, the one
+    /// with the first name if there are several.
+    fn inline_cycle(&self, inlined: &BTreeMap<usize, PrimitiveType>) -> Option<usize> {
+        inlined
+            .keys()
+            .filter(|start| {
+                let mut seen = BTreeSet::new();
+                let mut id = **start;
+                while let Some(PrimitiveType::Reference(target)) = inlined.get(&id) {
+                    if *target == **start {
+                        return true;
+                    }
+                    if !seen.insert(*target) {
+                        return false;
+                    }
+                    id = *target;
+                }
+                false
+            })
+            .min_by_key(|id| self.allocator.types[id].name.to_string())
+            .copied()
+    }
+}
@@ -0,0 +1,326 @@
+//! Renames across the files of a schema. The declarations and every reference to them are
+//! found in the [concrete syntax trees](crate::cst) of the files, so a rename only changes the
+//! names it is about and leaves the layout and the comments of the files as they were.
+//!
+//! References are resolved like the compiler resolves them, relative to their module. A `use`
+//! of a renamed type declares the new name instead of the old one, so the references that go
+//! through it are renamed as well.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    ast::Span,
+    cst::{self, Edit, Node, NodeKind, SyntaxTree, Token, TokenKind},
+    diagnostics::Diagnostic,
+    frontend::IRCompiler,
+    symbols::{qualify_name, split_qualified_name, SymbolKind, SymbolTable},
+    syntax::identifier,
+};
+
+/// The edits a refactoring makes to the file `file`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEdits {
+    pub file: String,
+    pub edits: Vec<Edit>,
+}
+
+/// A schema of several files, parsed for refactoring.
+struct Schema {
+    trees: Vec<(String, SyntaxTree)>,
+    symbols: SymbolTable,
+}
+
+impl Schema {
+    /// Parses `files` of a file name and its contents.
+    fn parse(files: &[(&str, &str)]) -> Result<Schema, Vec<Diagnostic>> {
+        let mut compiler = IRCompiler::new();
+        let mut trees = Vec::new();
+        let mut diagnostics = Vec::new();
+        for (name, source) in files {
+            // The tree accepts comments anywhere, the compiler needs them to be documentation.
+            let parsed =
+                crate::parse(source, name).and_then(|items| Ok((items, cst::parse(source, name)?)));
+            match parsed {
+                Ok((items, tree)) => {
+                    compiler.register_program(items, name, source);
+                    trees.push((name.to_string(), tree));
+                }
+                Err(errors) => diagnostics.extend(errors.into_iter().map(Diagnostic::from)),
+            }
+        }
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        Ok(Schema {
+            trees,
+            symbols: compiler.symbols().clone(),
+        })
+    }
+
+    /// The qualified name of the symbol `path` names in `module`: the first one that is
+    /// declared, walking outwards to the root. Unlike [`SymbolTable::lookup`], re-exports are
+    /// not followed, a `use` is a symbol of its own.
+    fn declared(&self, module: &str, path: &str) -> Option<String> {
+        let mut scope = Some(module);
+        while let Some(s) = scope {
+            let candidate = qualify_name(s, path);
+            if self.symbols.contains(&candidate) {
+                return Some(candidate);
+            }
+            scope = (!s.is_empty()).then(|| split_qualified_name(s).0);
+        }
+        None
+    }
+
+    /// `renamed` and every `use` that re-exports one of them, directly or through other uses.
+    fn with_reexports(&self, mut renamed: BTreeSet<String>) -> BTreeSet<String> {
+        loop {
+            let reexports: Vec<_> = self
+                .symbols
+                .iter()
+                .filter(|(name, symbol)| {
+                    !renamed.contains(*name)
+                        && matches!(&symbol.kind, SymbolKind::Reexport(target)
+                            if self.declared(&symbol.module, target)
+                                .is_some_and(|target| renamed.contains(&target)))
+                })
+                .map(|(name, _)| name.clone())
+                .collect();
+            if reexports.is_empty() {
+                return renamed;
+            }
+            renamed.extend(reexports);
+        }
+    }
+}
+
+/// Whether `name` can be written as an identifier.
+fn check_identifier(name: &str) -> Result<(), Vec<Diagnostic>> {
+    if identifier(name) != name {
+        return Err(vec![Diagnostic::error(
+            format!("`{}` is not a valid name", name),
+            None,
+        )]);
+    }
+    Ok(())
+}
+
+/// Renames the type with the qualified name `old`, like `auth.User`, to `new` in its module,
+/// and every reference to it in `files`, of a file name and its contents. Returns the edits of
+/// the files that change, and fails if the files do not parse, the type is not declared or the
+/// new name is taken.
+pub fn rename_type(
+    files: &[(&str, &str)],
+    old: &str,
+    new: &str,
+) -> Result<Vec<FileEdits>, Vec<Diagnostic>> {
+    check_identifier(new)?;
+    let schema = Schema::parse(files)?;
+    if schema.symbols.get_type(old).is_none() {
+        return Err(vec![Diagnostic::error(
+            format!("type {} not found", old),
+            None,
+        )]);
+    }
+    let renamed = schema.with_reexports(BTreeSet::from([old.to_string()]));
+    let taken: Vec<_> = renamed
+        .iter()
+        .map(|name| qualify_name(split_qualified_name(name).0, new))
+        .filter(|name| !renamed.contains(name) && schema.symbols.contains(name))
+        .map(|name| Diagnostic::error(format!("{} is already declared", name), None))
+        .collect();
+    if !taken.is_empty() {
+        return Err(taken);
+    }
+
+    let mut result = Vec::new();
+    for (file, tree) in &schema.trees {
+        let mut renamer = TypeRenamer {
+            schema: &schema,
+            tree,
+            renamed: &renamed,
+            new,
+            edits: Vec::new(),
+        };
+        renamer.items(&tree.root, "");
+        if !renamer.edits.is_empty() {
+            result.push(FileEdits {
+                file: file.clone(),
+                edits: renamer.edits,
+            });
+        }
+    }
+    Ok(result)
+}
+
+struct TypeRenamer<'a> {
+    schema: &'a Schema,
+    tree: &'a SyntaxTree,
+    renamed: &'a BTreeSet<String>,
+    new: &'a str,
+    edits: Vec<Edit>,
+}
+
+impl TypeRenamer<'_> {
+    fn text(&self, token: &Token) -> &str {
+        self.tree.text(token.span)
+    }
+
+    fn rename(&mut self, span: Span) {
+        self.edits.push(Edit {
+            span,
+            text: self.new.to_string(),
+        });
+    }
+
+    /// The items of a program or of a module, declared in `module`.
+    fn items(&mut self, node: &Node, module: &str) {
+        for item in node.child_nodes() {
+            match item.kind {
+                NodeKind::TypeDeclaration => {
+                    let Some(name) = item.name() else { continue };
+                    if self
+                        .renamed
+                        .contains(&qualify_name(module, self.text(name)))
+                    {
+                        self.rename(name.span);
+                    }
+                    let params: Vec<_> = item
+                        .child_nodes()
+                        .filter(|n| n.kind == NodeKind::GenericParameters)
+                        .flat_map(|n| n.tokens())
+                        .filter(|t| t.kind == TokenKind::Ident)
+                        .map(|t| self.text(t).to_string())
+                        .collect();
+                    for child in item.child_nodes() {
+                        self.references(child, module, &params);
+                    }
+                }
+                NodeKind::UseDeclaration => {
+                    if let Some(path) = item.child_nodes().find(|n| n.kind == NodeKind::Path) {
+                        self.path(path, module);
+                    }
+                }
+                NodeKind::ModuleDeclaration => {
+                    if let Some(name) = item.name() {
+                        let module = qualify_name(module, self.text(name));
+                        self.items(item, &module);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The references to types in `node`, written in `module` where `params` are generic
+    /// parameters.
+    fn references(&mut self, node: &Node, module: &str, params: &[String]) {
+        if node.kind == NodeKind::TypeReference {
+            if let Some(path) = node.child_nodes().find(|n| n.kind == NodeKind::Path) {
+                let is_param = matches!(path.tokens().as_slice(),
+                    [token] if params.iter().any(|p| p == self.text(token)));
+                if !is_param {
+                    self.path(path, module);
+                }
+            }
+        }
+        // Annotations have no references, but the names of their arguments are identifiers.
+        if node.kind == NodeKind::Annotation {
+            return;
+        }
+        for child in node.child_nodes() {
+            self.references(child, module, params);
+        }
+    }
+
+    /// Renames the last segment of `path`, written in `module`, if it names a renamed symbol.
+    fn path(&mut self, path: &Node, module: &str) {
+        let tokens = path.tokens();
+        let text: String = tokens.iter().map(|t| self.text(t)).collect();
+        let names_renamed = self
+            .schema
+            .declared(module, &text)
+            .is_some_and(|name| self.renamed.contains(&name));
+        if let (true, Some(last)) = (names_renamed, tokens.last()) {
+            self.rename(last.span);
+        }
+    }
+}
+
+/// Renames the field `old` of the struct declared as `type_name`, like `auth.User`, to `new`.
+/// Only fields declared in the struct itself can be renamed, not those it gets from an
+/// intersection with another named type. Fields are not referred to by name, so only the
+/// declaration changes.
+pub fn rename_field(
+    files: &[(&str, &str)],
+    type_name: &str,
+    old: &str,
+    new: &str,
+) -> Result<Vec<FileEdits>, Vec<Diagnostic>> {
+    check_identifier(new)?;
+    let schema = Schema::parse(files)?;
+    for (file, tree) in &schema.trees {
+        let Some(declaration) = find_declaration(tree, &tree.root, "", type_name) else {
+            continue;
+        };
+        // The fields of the structs the declaration is made of, not of the types of its fields.
+        let mut fields = Vec::new();
+        let mut operands = vec![declaration];
+        while let Some(node) = operands.pop() {
+            match node.kind {
+                NodeKind::Struct => fields.extend(node.child_nodes()),
+                NodeKind::TypeDeclaration | NodeKind::Intersection | NodeKind::Union => {
+                    operands.extend(node.child_nodes())
+                }
+                _ => {}
+            }
+        }
+        let named = |name: &str| {
+            fields
+                .iter()
+                .filter_map(|field| field.name())
+                .find(|token| tree.text(token.span) == name)
+        };
+        if named(new).is_some() {
+            return Err(vec![Diagnostic::error(
+                format!("{} already has a field {}", type_name, new),
+                None,
+            )]);
+        }
+        return match named(old) {
+            Some(token) => Ok(vec![FileEdits {
+                file: file.clone(),
+                edits: vec![Edit {
+                    span: token.span,
+                    text: new.to_string(),
+                }],
+            }]),
+            None => Err(vec![Diagnostic::error(
+                format!("{} declares no field {}", type_name, old),
+                None,
+            )]),
+        };
+    }
+    Err(vec![Diagnostic::error(
+        format!("type {} not found", type_name),
+        None,
+    )])
+}
+
+/// The declaration of the type with the qualified `name` among the items of `node`, which are
+/// declared in `module`.
+fn find_declaration<'a>(
+    tree: &SyntaxTree,
+    node: &'a Node,
+    module: &str,
+    name: &str,
+) -> Option<&'a Node> {
+    node.child_nodes().find_map(|item| {
+        let declared = qualify_name(module, tree.text(item.name()?.span));
+        match item.kind {
+            NodeKind::TypeDeclaration if declared == name => Some(item),
+            NodeKind::ModuleDeclaration => find_declaration(tree, item, &declared, name),
+            _ => None,
+        }
+    })
+}
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use crate::{
+    frontend::IRCompiler,
+    ir::{IRType, PrimitiveStruct, PrimitiveType, PrimitiveVariant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Relation {
+    Equal,
+    Subtype,
+}
+
+impl IRCompiler {
+    /// Returns true if `a` and `b` describe the same structure. References are followed, so two
+    /// differently named types with the same shape are equal. Field order, annotations and
+    /// comments are not taken into account.
+    ///
+    /// # Panics
+    ///
+    /// If `a` or `b` refers to a type that is still being compiled, see
+    /// [`IRCompiler::try_types_equal`].
+    pub fn types_equal(&self, a: &IRType, b: &IRType) -> bool {
+        self.try_types_equal(a, b)
+            .unwrap_or_else(|id| panic!("Type {} not found in allocator", id))
+    }
+
+    /// Like [`IRCompiler::types_equal`], for types that are compared while compiling. Returns
+    /// the id of a type that is still being compiled, and so can not be compared yet, if the
+    /// comparison reaches one.
+    pub(crate) fn try_types_equal(&self, a: &IRType, b: &IRType) -> Result<bool, usize> {
+        self.relate(a, b, Relation::Equal, &mut HashSet::new())
+    }
+
+    /// Returns true if every value of `a` is also a valid value of `b`. A struct is a subtype of
+    /// another struct if it has at least its fields, and every field that is required in `b` is
+    /// required in `a` as well. A variant is a subtype of another variant if its options are a
+    /// subset of the options of `b`.
+    pub fn is_subtype(&self, a: &IRType, b: &IRType) -> bool {
+        self.relate(a, b, Relation::Subtype, &mut HashSet::new())
+            .unwrap_or_else(|id| panic!("Type {} not found in allocator", id))
+    }
+
+    /// Pairs of references in `assumed` are considered related, which makes recursive types
+    /// terminate. Fails with the id of a referenced type that is not compiled yet.
+    fn relate(
+        &self,
+        a: &IRType,
+        b: &IRType,
+        relation: Relation,
+        assumed: &mut HashSet<(usize, usize)>,
+    ) -> Result<bool, usize> {
+        Ok(match (a, b) {
+            (IRType::Reference(x), IRType::Reference(y)) => {
+                if x == y || !assumed.insert((*x, *y)) {
+                    return Ok(true);
+                }
+                self.relate(
+                    self.dereference(*x)?,
+                    self.dereference(*y)?,
+                    relation,
+                    assumed,
+                )?
+            }
+            (IRType::Reference(x), _) => {
+                self.relate(self.dereference(*x)?, b, relation, assumed)?
+            }
+            (_, IRType::Reference(y)) => {
+                self.relate(a, self.dereference(*y)?, relation, assumed)?
+            }
+            (IRType::Builtin(a), IRType::Builtin(b)) => a == b,
+            (IRType::Struct(a), IRType::Struct(b)) => {
+                self.relate_structs(a, b, relation, assumed)?
+            }
+            (IRType::Variant(a), IRType::Variant(b)) => {
+                self.relate_variants(a, b, relation, assumed)?
+            }
+            _ => false,
+        })
+    }
+
+    fn relate_structs(
+        &self,
+        a: &PrimitiveStruct,
+        b: &PrimitiveStruct,
+        relation: Relation,
+        assumed: &mut HashSet<(usize, usize)>,
+    ) -> Result<bool, usize> {
+        if relation == Relation::Equal && a.fields.len() != b.fields.len() {
+            return Ok(false);
+        }
+        for field in &b.fields {
+            match a.fields.iter().find(|f| f.name == field.name) {
+                Some(other) => {
+                    let optionality_ok = match relation {
+                        Relation::Equal => other.optional == field.optional,
+                        Relation::Subtype => field.optional || !other.optional,
+                    };
+                    if !optionality_ok
+                        || !self.relate_primitive(&other.type_, &field.type_, relation, assumed)?
+                    {
+                        return Ok(false);
+                    }
+                }
+                None if relation == Relation::Subtype && field.optional => {}
+                None => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    fn relate_variants(
+        &self,
+        a: &PrimitiveVariant,
+        b: &PrimitiveVariant,
+        relation: Relation,
+        assumed: &mut HashSet<(usize, usize)>,
+    ) -> Result<bool, usize> {
+        if relation == Relation::Equal && a.variants.len() != b.variants.len() {
+            return Ok(false);
+        }
+        for option in &a.variants {
+            let Some(other) = b.variants.iter().find(|o| o.name == option.name) else {
+                return Ok(false);
+            };
+            if !self.relate_primitive(&option.type_, &other.type_, relation, assumed)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn relate_primitive(
+        &self,
+        a: &PrimitiveType,
+        b: &PrimitiveType,
+        relation: Relation,
+        assumed: &mut HashSet<(usize, usize)>,
+    ) -> Result<bool, usize> {
+        self.relate(&a.clone().into(), &b.clone().into(), relation, assumed)
+    }
+
+    /// The type `id` refers to, unless it is still being compiled.
+    fn dereference(&self, id: usize) -> Result<&IRType, usize> {
+        self.allocator
+            .get(id)
+            .map(|named_type| &named_type.type_)
+            .ok_or(id)
+    }
+}
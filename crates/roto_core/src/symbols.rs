@@ -0,0 +1,321 @@
+use core::fmt;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    ast::{self, Visibility},
+    builtins::BuiltinRegistry,
+    frontend::TypePrototype,
+    intern,
+};
+
+/// Re-exports are followed at most this many times before a lookup is considered cyclic.
+const MAX_REEXPORT_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum SymbolKind {
+    Type(TypePrototype),
+    /// A `use` declaration, pointing to a path that is resolved from the declaring module.
+    Reexport(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    /// The qualified name of the module the symbol is declared in, empty for the root module.
+    pub module: String,
+    pub visibility: Visibility,
+    pub kind: SymbolKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupError {
+    NotFound { path: String, module: String },
+    Private { path: String, module: String },
+    Cycle { path: String },
+}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let describe = |module: &str| {
+            if module.is_empty() {
+                "the root module".to_string()
+            } else {
+                format!("module {}", module)
+            }
+        };
+        match self {
+            LookupError::NotFound { path, module } => {
+                write!(f, "type {} not found from {}", path, describe(module))
+            }
+            LookupError::Private { path, module } => {
+                write!(
+                    f,
+                    "type {} is private and not visible from {}",
+                    path,
+                    describe(module)
+                )
+            }
+            LookupError::Cycle { path } => write!(f, "re-exports of {} form a cycle", path),
+        }
+    }
+}
+
+impl std::error::Error for LookupError {}
+
+/// Joins a module path and a name into a qualified name.
+pub fn qualify_name(module: &str, name: &str) -> String {
+    if module.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", module, name)
+    }
+}
+
+/// Splits a qualified name into its module path and its last segment.
+pub fn split_qualified_name(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(index) => (&name[..index], &name[index + 1..]),
+        None => ("", name),
+    }
+}
+
+fn parent_module(module: &str) -> Option<&str> {
+    if module.is_empty() {
+        None
+    } else {
+        Some(split_qualified_name(module).0)
+    }
+}
+
+/// Returns true if `module` is `ancestor` or one of its submodules.
+fn is_within(module: &str, ancestor: &str) -> bool {
+    ancestor.is_empty()
+        || module == ancestor
+        || (module.starts_with(ancestor) && module[ancestor.len()..].starts_with('.'))
+}
+
+/// The namespaced type environment of a compilation. Symbols are stored under their qualified
+/// name (`auth.User`), in name order, and looked up relative to a module, walking outwards to
+/// the root.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: BTreeMap<String, Symbol>,
+    modules: HashMap<String, Visibility>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable {
+            symbols: BTreeMap::new(),
+            modules: HashMap::new(),
+        }
+    }
+
+    pub fn declare_module(&mut self, module: &str, visibility: Visibility) {
+        self.modules.insert(module.to_string(), visibility);
+    }
+
+    /// Declares `name` in `module` and returns the symbol it replaces, if any.
+    pub fn declare(
+        &mut self,
+        module: &str,
+        name: &str,
+        visibility: Visibility,
+        kind: SymbolKind,
+    ) -> Option<Symbol> {
+        self.symbols.insert(
+            qualify_name(module, name),
+            Symbol {
+                module: module.to_string(),
+                visibility,
+                kind,
+            },
+        )
+    }
+
+    pub fn insert(&mut self, qualified_name: String, symbol: Symbol) -> Option<Symbol> {
+        self.symbols.insert(qualified_name, symbol)
+    }
+
+    pub fn get(&self, qualified_name: &str) -> Option<&Symbol> {
+        self.symbols.get(qualified_name)
+    }
+
+    pub fn contains(&self, qualified_name: &str) -> bool {
+        self.symbols.contains_key(qualified_name)
+    }
+
+    pub fn get_type(&self, qualified_name: &str) -> Option<&TypePrototype> {
+        match &self.symbols.get(qualified_name)?.kind {
+            SymbolKind::Type(prototype) => Some(prototype),
+            SymbolKind::Reexport(_) => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Symbol)> {
+        self.symbols.iter()
+    }
+
+    pub fn iter_modules(&self) -> impl Iterator<Item = (&String, Visibility)> {
+        self.modules
+            .iter()
+            .map(|(module, visibility)| (module, *visibility))
+    }
+
+    pub fn iter_types(&self) -> impl Iterator<Item = (&String, &TypePrototype)> {
+        self.symbols
+            .iter()
+            .filter_map(|(name, symbol)| match &symbol.kind {
+                SymbolKind::Type(prototype) => Some((name, prototype)),
+                SymbolKind::Reexport(_) => None,
+            })
+    }
+
+    /// Whether the type `qualified_name` is part of what a schema exports to backends: it is
+    /// declared in the root module, or declared `pub`. Private types of other modules are
+    /// helpers, which are only generated where an exported type refers to them.
+    pub fn is_exported(&self, qualified_name: &str) -> bool {
+        self.symbols.get(qualified_name).is_some_and(|symbol| {
+            matches!(symbol.kind, SymbolKind::Type(_))
+                && (symbol.module.is_empty() || symbol.visibility == Visibility::Public)
+        })
+    }
+
+    fn is_visible(&self, from: &str, module: &str, visibility: Visibility) -> bool {
+        if visibility == Visibility::Private && !is_within(from, module) {
+            return false;
+        }
+        // Every enclosing module has to be visible as well.
+        let mut current = Some(module);
+        while let Some(m) = current.filter(|m| !m.is_empty()) {
+            let parent = parent_module(m).unwrap_or("");
+            let module_visibility = self.modules.get(m).copied().unwrap_or(Visibility::Public);
+            if module_visibility == Visibility::Private && !is_within(from, parent) {
+                return false;
+            }
+            current = Some(parent);
+        }
+        true
+    }
+
+    /// Resolves `path` as written in `module` to the qualified name of the type it refers to,
+    /// following re-exports and enforcing visibility.
+    pub fn lookup(&self, module: &str, path: &str) -> Result<String, LookupError> {
+        self.lookup_with_depth(module, path, 0)
+    }
+
+    fn lookup_with_depth(
+        &self,
+        module: &str,
+        path: &str,
+        depth: usize,
+    ) -> Result<String, LookupError> {
+        if depth > MAX_REEXPORT_DEPTH {
+            return Err(LookupError::Cycle {
+                path: path.to_string(),
+            });
+        }
+
+        let mut scope = Some(module);
+        while let Some(s) = scope {
+            let candidate = qualify_name(s, path);
+            if let Some(symbol) = self.symbols.get(&candidate) {
+                if !self.is_visible(module, &symbol.module, symbol.visibility) {
+                    return Err(LookupError::Private {
+                        path: path.to_string(),
+                        module: module.to_string(),
+                    });
+                }
+                return match &symbol.kind {
+                    SymbolKind::Type(_) => Ok(candidate),
+                    SymbolKind::Reexport(target) => {
+                        self.lookup_with_depth(&symbol.module, target, depth + 1)
+                    }
+                };
+            }
+            scope = parent_module(s);
+        }
+
+        Err(LookupError::NotFound {
+            path: path.to_string(),
+            module: module.to_string(),
+        })
+    }
+
+    /// Rewrites every type reference in `t` to its qualified name, as seen from `module`. Names
+    /// in `params` are generic parameters and stay untouched. Names that are not declared but
+    /// registered in `builtins` become custom builtins.
+    pub fn qualify(
+        &self,
+        module: &str,
+        t: &ast::TypeExpression,
+        params: &[intern::Atom],
+        builtins: &BuiltinRegistry,
+    ) -> Result<ast::TypeExpression, LookupError> {
+        Ok(match t {
+            ast::TypeExpression::Variable(name) if params.contains(name) => t.clone(),
+            ast::TypeExpression::Variable(name) => {
+                match (self.lookup(module, name), builtins.lookup(name)) {
+                    (Ok(qualified), _) => ast::TypeExpression::Variable(qualified.into()),
+                    (Err(LookupError::NotFound { .. }), Some(id)) => {
+                        ast::TypeExpression::Builtin(ast::Builtin::Custom(id))
+                    }
+                    (Err(error), _) => return Err(error),
+                }
+            }
+            ast::TypeExpression::Builtin(_) => t.clone(),
+            ast::TypeExpression::Generic(name, args) => ast::TypeExpression::Generic(
+                self.lookup(module, name)?.into(),
+                args.iter()
+                    .map(|(k, v)| Ok((*k, self.qualify(module, v, params, builtins)?)))
+                    .collect::<Result<_, LookupError>>()?,
+            ),
+            ast::TypeExpression::Struct(s) => {
+                ast::TypeExpression::Struct(ast::StructTypeExpression {
+                    fields: s
+                        .fields
+                        .iter()
+                        .map(|f| {
+                            Ok(ast::StructField {
+                                type_: self.qualify(module, &f.type_, params, builtins)?,
+                                ..f.clone()
+                            })
+                        })
+                        .collect::<Result<_, LookupError>>()?,
+                })
+            }
+            ast::TypeExpression::Variant(v) => {
+                ast::TypeExpression::Variant(ast::VariantTypeExpression {
+                    variants: v
+                        .variants
+                        .iter()
+                        .map(|o| {
+                            Ok(ast::VariantOption {
+                                type_: self.qualify(module, &o.type_, params, builtins)?,
+                                ..o.clone()
+                            })
+                        })
+                        .collect::<Result<_, LookupError>>()?,
+                })
+            }
+            ast::TypeExpression::Intersection(a, b) => ast::TypeExpression::Intersection(
+                Box::new(self.qualify(module, a, params, builtins)?),
+                Box::new(self.qualify(module, b, params, builtins)?),
+            ),
+            ast::TypeExpression::Union(a, b) => ast::TypeExpression::Union(
+                Box::new(self.qualify(module, a, params, builtins)?),
+                Box::new(self.qualify(module, b, params, builtins)?),
+            ),
+            ast::TypeExpression::Conditional(c) => {
+                ast::TypeExpression::Conditional(ast::ConditionalTypeExpression {
+                    left: Box::new(self.qualify(module, &c.left, params, builtins)?),
+                    right: Box::new(self.qualify(module, &c.right, params, builtins)?),
+                    then: Box::new(self.qualify(module, &c.then, params, builtins)?),
+                    otherwise: Box::new(self.qualify(module, &c.otherwise, params, builtins)?),
+                })
+            }
+        })
+    }
+}
@@ -0,0 +1,175 @@
+//! Parsing of schema source, without exposing the types of the generated parser.
+
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use lalrpop_util::ParseError;
+
+use crate::{
+    ast::{Item, LineIndex, SourceLocation, Span},
+    diagnostics::Diagnostic,
+    parser::ProgramParser,
+};
+
+/// A syntax error, located by its byte range in the source as well as by line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDiagnostic {
+    pub message: String,
+    /// What the parser would have accepted instead, like `identifier` or `` `struct` ``.
+    pub expected: Vec<String>,
+    pub span: Span,
+    pub location: SourceLocation,
+}
+
+impl From<ParseDiagnostic> for Diagnostic {
+    fn from(error: ParseDiagnostic) -> Self {
+        let message = match error.expected.as_slice() {
+            [] => error.message,
+            [expected] => format!("{}, expected {}", error.message, expected),
+            expected => format!("{}, expected one of {}", error.message, expected.join(", ")),
+        };
+        Diagnostic::error(message, Some(error.location)).with_code("syntax-error")
+    }
+}
+
+impl Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", Diagnostic::from(self.clone()))
+    }
+}
+
+impl std::error::Error for ParseDiagnostic {}
+
+/// An error found by an action of the grammar once a rule matched, like an integer literal that
+/// does not fit in 64 bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarError {
+    pub message: String,
+    pub span: Span,
+}
+
+/// Describes a terminal of the grammar, as lalrpop lists them in its errors: keywords and
+/// punctuation are quoted, the regular expressions for tokens are named.
+fn describe_terminal(terminal: &str) -> String {
+    match terminal.strip_prefix("r#\"") {
+        Some(regex) if regex.starts_with("[a-zA-Z_]") => "identifier".to_string(),
+        Some(regex) if regex.starts_with("//") => "comment".to_string(),
+        Some(regex) if regex.starts_with('"') => "string".to_string(),
+        Some(regex) => format!("`{}`", regex.trim_end_matches("\"#")),
+        None => format!("`{}`", terminal.trim_matches('"')),
+    }
+}
+
+/// Parses `source`, the contents of the file `file_name`, into its items.
+pub fn parse(source: &str, file_name: &str) -> Result<Vec<Item>, Vec<ParseDiagnostic>> {
+    let lines = LineIndex::new(file_name, source);
+    ProgramParser::new().parse(&lines, source).map_err(|error| {
+        let (message, span, expected) = match error {
+            ParseError::InvalidToken { location } => {
+                let found = source[location..].chars().next().unwrap_or_default();
+                let span = Span {
+                    start: location,
+                    end: location + found.len_utf8(),
+                };
+                (
+                    format!("unexpected character `{}`", found),
+                    span,
+                    Vec::new(),
+                )
+            }
+            ParseError::UnrecognizedEof { location, expected } => (
+                "unexpected end of file".to_string(),
+                Span {
+                    start: location,
+                    end: location,
+                },
+                expected,
+            ),
+            ParseError::UnrecognizedToken {
+                token: (start, _, end),
+                expected,
+            } => (
+                format!("unexpected `{}`", &source[start..end]),
+                Span { start, end },
+                expected,
+            ),
+            ParseError::ExtraToken {
+                token: (start, _, end),
+            } => (
+                format!("unexpected `{}` after the last item", &source[start..end]),
+                Span { start, end },
+                Vec::new(),
+            ),
+            ParseError::User { error } => (error.message, error.span, Vec::new()),
+        };
+        let mut described: Vec<String> = Vec::new();
+        for terminal in &expected {
+            let terminal = describe_terminal(terminal);
+            if !described.contains(&terminal) {
+                described.push(terminal);
+            }
+        }
+        vec![ParseDiagnostic {
+            message,
+            expected: described,
+            span,
+            location: lines.location(span),
+        }]
+    })
+}
+
+/// The words that cannot be used as identifiers.
+pub const KEYWORDS: [&str; 14] = [
+    "bool", "else", "enum", "float", "if", "int", "mod", "pub", "string", "struct", "then", "type",
+    "unit", "use",
+];
+
+/// `name` as an identifier, for tools that generate schemas from names found elsewhere: other
+/// characters become underscores, a leading digit gets an underscore before it and keywords get
+/// one after them.
+pub fn identifier(name: &str) -> String {
+    let mut result: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        result.insert(0, '_');
+    }
+    if KEYWORDS.contains(&result.as_str()) {
+        result.push('_');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overflowing_integer_literals_are_reported() {
+        let source = "@tag(value=99999999999999999999)\ntype A = int;\n";
+        let errors = parse(source, "test.roto").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "integer literal `99999999999999999999` does not fit in 64 bits"
+        );
+        assert_eq!(
+            &source[errors[0].span.start..errors[0].span.end],
+            "99999999999999999999"
+        );
+        assert_eq!(
+            (errors[0].location.line, errors[0].location.column),
+            (1, 12)
+        );
+    }
+
+    #[test]
+    fn the_smallest_integer_literal_parses() {
+        assert!(parse(
+            "@tag(value=-9223372036854775808)\ntype A = int;\n",
+            "test.roto"
+        )
+        .is_ok());
+    }
+}
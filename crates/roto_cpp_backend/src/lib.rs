@@ -0,0 +1,598 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::Language;
+use roto_core::backend::OutputFile;
+use roto_core::backend::Scope;
+use roto_core::backend::TypeNaming;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::frontend::IRCompiler;
+use roto_core::intern;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveStructField;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+use roto_core::symbols::split_qualified_name;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// Converts a roto option name like `not_found` to a C++ type name like `NotFound`.
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn cpp_string(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn json_name(field: &PrimitiveStructField) -> String {
+    match field
+        .annotations
+        .iter()
+        .find(|a| a.name == "rename")
+        .and_then(|a| a.arg("name"))
+    {
+        Some(ast::AnnotationValue::String(rename)) => rename.clone(),
+        _ => field.name.to_string(),
+    }
+}
+
+fn write_comment(result: &mut String, indent: &str, comment: &Option<String>) {
+    if let Some(comment) = comment {
+        for line in comment.lines() {
+            result.push_str(&format!("{}/// {}\n", indent, line));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    InProgress,
+    Done,
+}
+
+/// How a member refers to its type. Types that are still being defined when they are referenced
+/// are part of a cycle and are held through a `std::shared_ptr`.
+enum CppType {
+    /// Never a custom builtin, see [`HeaderWriter::builtin_type`].
+    Builtin(ast::Builtin),
+    Direct(String),
+    Indirect(String),
+}
+
+impl CppType {
+    fn name(&self) -> String {
+        match self {
+            CppType::Builtin(ast::Builtin::Int) => "std::int64_t".to_string(),
+            CppType::Builtin(ast::Builtin::Float) => "double".to_string(),
+            CppType::Builtin(ast::Builtin::String) => "std::string".to_string(),
+            CppType::Builtin(ast::Builtin::Bool) => "bool".to_string(),
+            CppType::Builtin(ast::Builtin::Unit) => "std::monostate".to_string(),
+            CppType::Builtin(ast::Builtin::Custom(id)) => {
+                unreachable!("custom builtin {} was not lowered", id)
+            }
+            CppType::Direct(name) => name.clone(),
+            CppType::Indirect(name) => format!("std::shared_ptr<{}>", name),
+        }
+    }
+
+    /// The JSON value of `value`, an expression of this type.
+    fn json_value(&self, value: &str) -> String {
+        match self {
+            CppType::Builtin(ast::Builtin::Unit) => "nullptr".to_string(),
+            CppType::Indirect(_) => format!("*{}", value),
+            _ => value.to_string(),
+        }
+    }
+
+    /// A statement that assigns the JSON value `json` to `target`.
+    fn assign_from_json(&self, target: &str, json: &str) -> String {
+        match self {
+            CppType::Builtin(ast::Builtin::Unit) => format!("{} = std::monostate{{}};", target),
+            CppType::Indirect(name) => format!(
+                "{} = std::make_shared<{}>({}.get<{}>());",
+                target, name, json, name
+            ),
+            t => format!("{} = {}.get<{}>();", target, json, t.name()),
+        }
+    }
+}
+
+/// The declarations of one module, which end up in one header.
+#[derive(Default)]
+struct Header {
+    includes: BTreeSet<String>,
+    forward_declarations: BTreeSet<String>,
+    definitions: Vec<String>,
+    prototypes: Vec<String>,
+    functions: Vec<String>,
+}
+
+/// Writes definitions in dependency order, so every type is complete before it is used by value.
+/// Structs become plain structs, variants become a struct holding a `std::variant` of one nested
+/// struct per option, and aliases become `using` declarations.
+struct HeaderWriter<'a> {
+    compiler: &'a IRCompiler,
+    namespace: Option<&'a str>,
+    name_allocator: TypeNameAllocator,
+    states: HashMap<usize, State>,
+    headers: BTreeMap<String, Header>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> HeaderWriter<'a> {
+    /// Returns the module a type is declared in and its unqualified C++ name.
+    fn local_name(&mut self, type_name: &TypeName) -> (String, String) {
+        match type_name {
+            TypeName::Variable(name) => {
+                let (module, name) = split_qualified_name(name);
+                (module.to_string(), Language::Cpp.escape(name))
+            }
+            TypeName::Generic(name, _) => {
+                let (module, _) = split_qualified_name(name);
+                let name = self.name_allocator.allocate_name(type_name);
+                // The allocated name starts with the module path, joined by underscores.
+                let prefix = if module.is_empty() {
+                    0
+                } else {
+                    module.len() + 1
+                };
+                (module.to_string(), name[prefix..].to_string())
+            }
+            TypeName::Temporary(owner, path) => {
+                let (module, name) = self.local_name(owner);
+                (module, format!("{}_{}", name, intern::join(path, "_")))
+            }
+        }
+    }
+
+    fn cpp_namespace(&self, module: &str) -> String {
+        let mut segments: Vec<&str> = self.namespace.into_iter().collect();
+        segments.extend(module.split('.').filter(|s| !s.is_empty()));
+        segments.join("::")
+    }
+
+    fn qualified_name(&mut self, id: usize) -> (String, String) {
+        let compiler = self.compiler;
+        let (module, name) = self.local_name(&compiler.named_type(id).name);
+        let namespace = self.cpp_namespace(&module);
+        if namespace.is_empty() {
+            (module, format!("::{}", name))
+        } else {
+            (module, format!("::{}::{}", namespace, name))
+        }
+    }
+
+    /// Records that `module` refers to the type `id`, either by including its header or by
+    /// declaring it ahead if it is not complete yet.
+    fn use_type(&mut self, module: &str, id: usize, owner: &NamedIRType) {
+        let compiler = self.compiler;
+        let (target_module, target_name) = self.local_name(&compiler.named_type(id).name);
+        let complete = self.states.get(&id) == Some(&State::Done);
+        if target_module == module {
+            if !complete {
+                let header = self.headers.entry(module.to_string()).or_default();
+                header.forward_declarations.insert(target_name);
+            }
+        } else if complete {
+            let header = self.headers.entry(module.to_string()).or_default();
+            header.includes.insert(target_module);
+        } else {
+            self.diagnostics.push(Diagnostic::error(
+                format!(
+                    "{} refers to {} recursively across modules",
+                    owner.name,
+                    compiler.named_type(id).name
+                ),
+                owner.metadata.location.clone(),
+            ));
+        }
+    }
+
+    /// Converts the type of a member. Aliases are resolved to the type they name.
+    /// A custom builtin is its lowering, which has to convert from and to JSON, or else its
+    /// representation.
+    fn builtin_type(&self, builtin: &ast::Builtin) -> CppType {
+        let builtins = &self.compiler.builtins;
+        match builtin {
+            ast::Builtin::Custom(id) => match builtins.lower(*id, "cpp") {
+                Some(lowered) => CppType::Direct(lowered),
+                None => CppType::Builtin(builtins.representation(builtin)),
+            },
+            builtin => CppType::Builtin(builtin.clone()),
+        }
+    }
+
+    fn convert_member_type(
+        &mut self,
+        module: &str,
+        t: &PrimitiveType,
+        owner: &NamedIRType,
+    ) -> CppType {
+        let mut id = match t {
+            PrimitiveType::Builtin(builtin) => return self.builtin_type(builtin),
+            PrimitiveType::Reference(id) => *id,
+        };
+        loop {
+            match &self.compiler.named_type(id).type_ {
+                IRType::Reference(target) => id = *target,
+                IRType::Builtin(builtin) => return self.builtin_type(builtin),
+                IRType::Struct(_) | IRType::Variant(_) => break,
+            }
+        }
+        self.use_type(module, id, owner);
+        let (_, name) = self.qualified_name(id);
+        if self.states.get(&id) == Some(&State::Done) {
+            CppType::Direct(name)
+        } else {
+            CppType::Indirect(name)
+        }
+    }
+
+    fn dependencies(t: &IRType) -> Vec<usize> {
+        let reference = |t: &PrimitiveType| match t {
+            PrimitiveType::Reference(id) => Some(*id),
+            PrimitiveType::Builtin(_) => None,
+        };
+        match t {
+            IRType::Struct(s) => s
+                .fields
+                .iter()
+                .filter_map(|f| reference(&f.type_))
+                .collect(),
+            IRType::Variant(v) => v
+                .variants
+                .iter()
+                .filter_map(|o| reference(&o.type_))
+                .collect(),
+            IRType::Reference(id) => vec![*id],
+            IRType::Builtin(_) => Vec::new(),
+        }
+    }
+
+    fn visit(&mut self, id: usize) {
+        if self.states.contains_key(&id) {
+            return;
+        }
+        self.states.insert(id, State::InProgress);
+
+        let compiler = self.compiler;
+        let named_type = compiler.named_type(id);
+        for dependency in Self::dependencies(&named_type.type_) {
+            self.visit(dependency);
+        }
+
+        let (module, name) = self.local_name(&named_type.name);
+        self.convert_named_ir_type(&module, &name, named_type);
+        self.states.insert(id, State::Done);
+    }
+
+    fn write_functions(&mut self, module: &str, name: &str, to_json: String, from_json: String) {
+        let header = self.headers.entry(module.to_string()).or_default();
+        let to_json_signature =
+            format!("inline void to_json(nlohmann::json& j, const {}& v)", name);
+        let from_json_signature = format!(
+            "inline void from_json(const nlohmann::json& j, {}& v)",
+            name
+        );
+        header.prototypes.push(format!(
+            "{};\n{};\n",
+            to_json_signature, from_json_signature
+        ));
+        header.functions.push(format!(
+            "{} {{\n{}}}\n\n{} {{\n{}}}\n",
+            to_json_signature, to_json, from_json_signature, from_json
+        ));
+    }
+
+    fn convert_named_ir_type(&mut self, module: &str, name: &str, named_type: &NamedIRType) {
+        let mut definition = String::new();
+        write_comment(&mut definition, "", &named_type.metadata.doc);
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                let mut to_json = "    j = nlohmann::json::object();\n".to_string();
+                let mut from_json = String::new();
+                definition.push_str(&format!("struct {} {{\n", name));
+                let mut scope = Scope::new(Language::Cpp);
+                for field in struct_type.fields.iter() {
+                    write_comment(&mut definition, "    ", &field.comment);
+                    let field_type = self.convert_member_type(module, &field.type_, named_type);
+                    let member_name = scope.identifier(&field.name);
+                    let member = format!("v.{}", member_name);
+                    let key = cpp_string(&json_name(field));
+                    if field.optional {
+                        definition.push_str(&format!(
+                            "    std::optional<{}> {};\n",
+                            field_type.name(),
+                            member_name
+                        ));
+                        to_json.push_str(&format!(
+                            "    if ({}) {{\n        j[{}] = {};\n    }}\n",
+                            member,
+                            key,
+                            field_type.json_value(&format!("*{}", member))
+                        ));
+                        from_json.push_str(&format!(
+                            "    if (auto it = j.find({}); it != j.end() && !it->is_null()) {{\n        {}\n    }}\n",
+                            key,
+                            field_type.assign_from_json(&member, "(*it)")
+                        ));
+                    } else {
+                        definition.push_str(&format!(
+                            "    {} {}{{}};\n",
+                            field_type.name(),
+                            member_name
+                        ));
+                        to_json.push_str(&format!(
+                            "    j[{}] = {};\n",
+                            key,
+                            field_type.json_value(&member)
+                        ));
+                        from_json.push_str(&format!(
+                            "    {}\n",
+                            field_type.assign_from_json(&member, &format!("j.at({})", key))
+                        ));
+                    }
+                }
+                definition.push_str("};\n");
+                if from_json.is_empty() {
+                    from_json.push_str("    (void)j;\n    (void)v;\n");
+                }
+                self.write_functions(module, name, to_json, from_json);
+            }
+            // Variants are adjacently tagged: `{ "type": "option", "value": payload }`, where the
+            // value is left out for unit options.
+            IRType::Variant(variant_type) => {
+                if variant_type.variants.is_empty() {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("{} has no options", named_type.name),
+                        named_type.metadata.location.clone(),
+                    ));
+                    return;
+                }
+                let mut to_json = String::new();
+                let mut from_json =
+                    "    const auto type = j.at(\"type\").get<std::string>();\n".to_string();
+                let mut option_names = Vec::new();
+                definition.push_str(&format!("struct {} {{\n", name));
+                // Nested types can not be named like the struct they are in, or its `value`.
+                let mut scope = Scope::new(Language::Cpp);
+                scope.reserve(name);
+                scope.reserve("value");
+                for (i, option) in variant_type.variants.iter().enumerate() {
+                    write_comment(&mut definition, "    ", &option.comment);
+                    let option_name = scope.identifier(&pascal_case(&option.name));
+                    let qualified_option = format!("{}::{}", name, option_name);
+                    let tag = cpp_string(&option.name);
+                    let branch = if i == 0 { "    if" } else { " else if" };
+                    match &option.type_ {
+                        PrimitiveType::Builtin(ast::Builtin::Unit) => {
+                            definition.push_str(&format!("    struct {} {{}};\n", option_name));
+                            to_json.push_str(&format!(
+                                "{} (std::holds_alternative<{}>(v.value)) {{\n        j = {{{{\"type\", {}}}}};\n    }}",
+                                branch, qualified_option, tag
+                            ));
+                            from_json.push_str(&format!(
+                                "{} (type == {}) {{\n        v.value = {}{{}};\n    }}",
+                                branch, tag, qualified_option
+                            ));
+                        }
+                        payload => {
+                            let payload = self.convert_member_type(module, payload, named_type);
+                            // No default member initializer here: the nested struct would not
+                            // count as default constructible inside the incomplete outer struct,
+                            // which `std::variant` needs.
+                            definition.push_str(&format!(
+                                "    struct {} {{\n        {} value;\n    }};\n",
+                                option_name,
+                                payload.name()
+                            ));
+                            to_json.push_str(&format!(
+                                "{} (const auto* option = std::get_if<{}>(&v.value)) {{\n        j = {{{{\"type\", {}}}, {{\"value\", {}}}}};\n    }}",
+                                branch,
+                                qualified_option,
+                                tag,
+                                payload.json_value("option->value")
+                            ));
+                            from_json.push_str(&format!(
+                                "{} (type == {}) {{\n        {} option{{}};\n        {}\n        v.value = std::move(option);\n    }}",
+                                branch,
+                                tag,
+                                qualified_option,
+                                payload.assign_from_json("option.value", "j.at(\"value\")")
+                            ));
+                        }
+                    }
+                    option_names.push(option_name);
+                }
+                definition.push_str(&format!(
+                    "    std::variant<{}> value;\n}};\n",
+                    option_names.join(", ")
+                ));
+                to_json.push('\n');
+                from_json.push_str(&format!(
+                    " else {{\n        throw std::invalid_argument(\"unknown {} option: \" + type);\n    }}\n",
+                    name
+                ));
+                self.write_functions(module, name, to_json, from_json);
+            }
+            IRType::Reference(id) => {
+                self.use_type(module, *id, named_type);
+                let (_, target) = self.qualified_name(*id);
+                definition.push_str(&format!("using {} = {};\n", name, target));
+            }
+            IRType::Builtin(builtin) => {
+                let target = self.builtin_type(builtin).name();
+                definition.push_str(&format!("using {} = {};\n", name, target));
+            }
+        }
+        let header = self.headers.entry(module.to_string()).or_default();
+        header.definitions.push(definition);
+    }
+
+    /// Reports modules whose headers would include each other.
+    fn check_include_cycles(&mut self) {
+        fn visit(
+            module: &str,
+            headers: &BTreeMap<String, Header>,
+            states: &mut HashMap<String, State>,
+            path: &mut Vec<String>,
+        ) -> Option<Vec<String>> {
+            match states.get(module) {
+                Some(State::Done) => return None,
+                Some(State::InProgress) => {
+                    let start = path.iter().position(|m| m == module).unwrap();
+                    let mut cycle = path[start..].to_vec();
+                    cycle.push(module.to_string());
+                    return Some(cycle);
+                }
+                None => {}
+            }
+            states.insert(module.to_string(), State::InProgress);
+            path.push(module.to_string());
+            if let Some(header) = headers.get(module) {
+                for include in &header.includes {
+                    if let Some(cycle) = visit(include, headers, states, path) {
+                        return Some(cycle);
+                    }
+                }
+            }
+            path.pop();
+            states.insert(module.to_string(), State::Done);
+            None
+        }
+
+        let mut states = HashMap::new();
+        for module in self.headers.keys() {
+            if let Some(cycle) = visit(module, &self.headers, &mut states, &mut Vec::new()) {
+                let cycle: Vec<&str> = cycle
+                    .iter()
+                    .map(|m| if m.is_empty() { "<root>" } else { m.as_str() })
+                    .collect();
+                self.diagnostics.push(Diagnostic::error(
+                    format!("modules depend on each other: {}", cycle.join(" -> ")),
+                    None,
+                ));
+                return;
+            }
+        }
+    }
+
+    fn render(&self, module: &str, header: &Header) -> String {
+        let mut sections = vec![[
+            "#pragma once\n\n",
+            "#include <cstdint>\n",
+            "#include <memory>\n",
+            "#include <optional>\n",
+            "#include <stdexcept>\n",
+            "#include <string>\n",
+            "#include <variant>\n",
+            "\n",
+            "#include <nlohmann/json.hpp>\n",
+        ]
+        .concat()];
+        if !header.includes.is_empty() {
+            let includes: Vec<String> = header
+                .includes
+                .iter()
+                .map(|m| format!("#include \"{}\"\n", header_path(m)))
+                .collect();
+            sections.push(includes.concat());
+        }
+        let namespace = self.cpp_namespace(module);
+        if !namespace.is_empty() {
+            sections.push(format!("namespace {} {{\n", namespace));
+        }
+        if !header.forward_declarations.is_empty() {
+            let declarations: Vec<String> = header
+                .forward_declarations
+                .iter()
+                .map(|name| format!("struct {};\n", name))
+                .collect();
+            sections.push(declarations.concat());
+        }
+        sections.extend(header.definitions.iter().cloned());
+        if !header.prototypes.is_empty() {
+            sections.push(header.prototypes.concat());
+        }
+        sections.extend(header.functions.iter().cloned());
+        if !namespace.is_empty() {
+            sections.push(format!("}}  // namespace {}\n", namespace));
+        }
+        sections.join("\n")
+    }
+}
+
+/// The header of the root module is `types.hpp`, other modules get a header named after them.
+fn header_path(module: &str) -> String {
+    if module.is_empty() {
+        "types.hpp".to_string()
+    } else {
+        format!("{}.hpp", module)
+    }
+}
+
+pub struct CppBackend {
+    /// The namespace that contains all generated namespaces, if any.
+    pub namespace: Option<String>,
+}
+
+impl Backend for CppBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = HeaderWriter {
+            compiler,
+            namespace: self.namespace.as_deref(),
+            name_allocator: TypeNameAllocator::new()
+                .with_declared(compiler)
+                .with_naming(naming.clone()),
+            states: HashMap::new(),
+            headers: BTreeMap::new(),
+            diagnostics: Vec::new(),
+        };
+
+        for (id, named_type) in compiler.iter_types() {
+            if let TypeName::Variable(_) = named_type.name {
+                writer.visit(*id);
+            }
+        }
+
+        writer.check_include_cycles();
+        if !writer.diagnostics.is_empty() {
+            return Err(BackendError::new(writer.diagnostics));
+        }
+        Ok(writer
+            .headers
+            .iter()
+            .map(|(module, header)| {
+                OutputFile::new(header_path(module), writer.render(module, header))
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,58 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use roto_core::backend::write_files;
+use roto_core::backend::Backend;
+use roto_core::frontend::IRCompiler;
+use roto_core::RotoError;
+use roto_cpp_backend::CppBackend;
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), RotoError> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 && args.len() != 3 {
+        eprintln!("Usage: {} <file_path> [<output_dir>]", args[0]);
+        process::exit(1);
+    }
+
+    let file_path = &args[1];
+    let file_contents = fs::read_to_string(file_path).map_err(|e| RotoError::io(file_path, e))?;
+
+    let parsed = roto_core::parse(&file_contents, file_path)?;
+
+    let backend = CppBackend { namespace: None };
+    let mut compiler = IRCompiler::new();
+    backend.register_annotations(&mut compiler.annotations);
+    compiler.register_program(parsed, file_path, &file_contents);
+
+    for warning in compiler.check_annotations()? {
+        eprintln!("{}", warning);
+    }
+
+    compiler.compile_globals()?;
+
+    let files = backend.generate(&compiler)?;
+    match args.get(2) {
+        Some(output_dir) => {
+            write_files(Path::new(output_dir), &files).map_err(|e| RotoError::io(output_dir, e))?;
+        }
+        None => {
+            for (i, file) in files.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                println!("// {}", file.path);
+                print!("{}", file.contents);
+            }
+        }
+    }
+    Ok(())
+}
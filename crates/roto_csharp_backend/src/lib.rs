@@ -0,0 +1,287 @@
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::Language;
+use roto_core::backend::OutputFile;
+use roto_core::backend::Scope;
+use roto_core::backend::TypeNaming;
+use roto_core::backend::Worklist;
+use roto_core::builtins::BuiltinRegistry;
+use roto_core::frontend::IRCompiler;
+use roto_core::frontend::TypeAllocator;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// Converts a roto member name like `display_name` to a C# member name like `DisplayName`.
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn csharp_string(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// C# has no type aliases, so an alias of a struct or variant becomes a record of its own, like
+/// the one of the type it names, and aliases of builtins are not written. References to aliases
+/// are resolved to the type they name.
+pub struct RecordWriter<'a> {
+    pub name_allocator: &'a mut TypeNameAllocator,
+    pub allocator: &'a TypeAllocator,
+    pub builtins: &'a BuiltinRegistry,
+    pub worklist: Worklist,
+}
+
+impl<'a> RecordWriter<'a> {
+    pub fn allocate_name(&mut self, type_name: &TypeName) -> String {
+        self.name_allocator.allocate_name(type_name)
+    }
+
+    fn convert_builtin(&self, t: &ast::Builtin) -> String {
+        match t {
+            ast::Builtin::Int => "long".to_string(),
+            ast::Builtin::Float => "double".to_string(),
+            ast::Builtin::String => "string".to_string(),
+            ast::Builtin::Bool => "bool".to_string(),
+            ast::Builtin::Unit => "object?".to_string(),
+            ast::Builtin::Custom(id) => self
+                .builtins
+                .lower(*id, "csharp")
+                .unwrap_or_else(|| self.convert_builtin(&self.builtins.representation(t))),
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> String {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin),
+            PrimitiveType::Reference(id) => {
+                let r = self.allocator.get(*id).unwrap();
+                match &r.type_ {
+                    IRType::Reference(target) => {
+                        self.convert_primitive_type(&PrimitiveType::Reference(*target))
+                    }
+                    IRType::Builtin(builtin) => self.convert_builtin(builtin),
+                    IRType::Struct(_) | IRType::Variant(_) => {
+                        self.worklist.push_back(r);
+                        self.allocate_name(&r.name)
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_summary(result: &mut String, indent: &str, doc: &Option<String>) {
+        if let Some(doc) = doc {
+            result.push_str(&format!("{}/// <summary>\n", indent));
+            for line in doc.lines() {
+                result.push_str(&format!("{}/// {}\n", indent, xml_escape(line)));
+            }
+            result.push_str(&format!("{}/// </summary>\n", indent));
+        }
+    }
+
+    fn write_obsolete(result: &mut String, indent: &str, annotations: &[ast::Annotation]) {
+        let Some(deprecated) = annotations.iter().find(|a| a.name == "deprecated") else {
+            return;
+        };
+        match deprecated.arg("reason") {
+            Some(ast::AnnotationValue::String(reason)) => {
+                result.push_str(&format!(
+                    "{}[Obsolete({})]\n",
+                    indent,
+                    csharp_string(reason)
+                ));
+            }
+            _ => result.push_str(&format!("{}[Obsolete]\n", indent)),
+        }
+    }
+
+    pub fn convert_named_ir_type(&mut self, name: &str, named_type: &NamedIRType) -> String {
+        let mut result = String::new();
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                Self::write_summary(&mut result, "", &named_type.metadata.doc);
+                Self::write_obsolete(&mut result, "", &named_type.metadata.annotations);
+                result.push_str(&format!("public sealed record {}\n{{\n", name));
+                // Members can not be named like the record they are in.
+                let mut scope = Scope::new(Language::CSharp);
+                scope.reserve(name);
+                for (i, field) in struct_type.fields.iter().enumerate() {
+                    if i > 0 {
+                        result.push('\n');
+                    }
+                    Self::write_summary(&mut result, "    ", &field.comment);
+                    Self::write_obsolete(&mut result, "    ", &field.annotations);
+                    let json_name = match field
+                        .annotations
+                        .iter()
+                        .find(|a| a.name == "rename")
+                        .and_then(|a| a.arg("name"))
+                    {
+                        Some(ast::AnnotationValue::String(rename)) => rename.clone(),
+                        _ => field.name.to_string(),
+                    };
+                    result.push_str(&format!(
+                        "    [JsonPropertyName({})]\n",
+                        csharp_string(&json_name)
+                    ));
+                    let mut field_type = self.convert_primitive_type(&field.type_);
+                    let modifier = if field.optional {
+                        if !field_type.ends_with('?') {
+                            field_type.push('?');
+                        }
+                        ""
+                    } else {
+                        "required "
+                    };
+                    result.push_str(&format!(
+                        "    public {}{} {} {{ get; init; }}\n",
+                        modifier,
+                        field_type,
+                        scope.identifier(&pascal_case(&field.name))
+                    ));
+                }
+                result.push_str("}\n");
+            }
+            // Variants are adjacently tagged: `{ "type": "option", "value": payload }`, where the
+            // value is left out for unit options. Every option is a nested derived record.
+            IRType::Variant(variant_type) => {
+                Self::write_summary(&mut result, "", &named_type.metadata.doc);
+                Self::write_obsolete(&mut result, "", &named_type.metadata.annotations);
+                result.push_str("[JsonPolymorphic(TypeDiscriminatorPropertyName = \"type\")]\n");
+                // Option records can not be named like the record they are in, or like the
+                // `Value` property they have themselves.
+                let mut scope = Scope::new(Language::CSharp);
+                scope.reserve(name);
+                scope.reserve("Value");
+                let option_names: Vec<String> = variant_type
+                    .variants
+                    .iter()
+                    .map(|option| scope.identifier(&pascal_case(&option.name)))
+                    .collect();
+                for (option, option_name) in variant_type.variants.iter().zip(&option_names) {
+                    result.push_str(&format!(
+                        "[JsonDerivedType(typeof({}.{}), {})]\n",
+                        name,
+                        option_name,
+                        csharp_string(&option.name)
+                    ));
+                }
+                result.push_str(&format!("public abstract record {}\n{{\n", name));
+                for (i, (option, option_name)) in
+                    variant_type.variants.iter().zip(&option_names).enumerate()
+                {
+                    if i > 0 {
+                        result.push('\n');
+                    }
+                    Self::write_summary(&mut result, "    ", &option.comment);
+                    Self::write_obsolete(&mut result, "    ", &option.annotations);
+                    match &option.type_ {
+                        PrimitiveType::Builtin(ast::Builtin::Unit) => {
+                            result.push_str(&format!(
+                                "    public sealed record {} : {};\n",
+                                option_name, name
+                            ));
+                        }
+                        payload => {
+                            let payload = self.convert_primitive_type(payload);
+                            result.push_str(&format!(
+                                "    public sealed record {} : {}\n    {{\n",
+                                option_name, name
+                            ));
+                            result.push_str("        [JsonPropertyName(\"value\")]\n");
+                            result.push_str(&format!(
+                                "        public required {} Value {{ get; init; }}\n",
+                                payload
+                            ));
+                            result.push_str("    }\n");
+                        }
+                    }
+                }
+                result.push_str("}\n");
+            }
+            IRType::Reference(id) => {
+                let mut target = self.allocator.get(*id).unwrap();
+                while let IRType::Reference(id) = &target.type_ {
+                    target = self.allocator.get(*id).unwrap();
+                }
+                let alias = NamedIRType {
+                    name: named_type.name.clone(),
+                    type_: target.type_.clone(),
+                    metadata: named_type.metadata.clone(),
+                };
+                return self.convert_named_ir_type(name, &alias);
+            }
+            IRType::Builtin(_) => {}
+        }
+        result
+    }
+}
+
+pub struct CSharpBackend {
+    /// The namespace of the generated records, if any.
+    pub namespace: Option<String>,
+}
+
+impl Backend for CSharpBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut type_name_allocator = TypeNameAllocator::with_variable_name(|name| {
+            Language::CSharp.escape(&name.replace('.', "_"))
+        })
+        .with_declared(compiler)
+        .with_naming(naming.clone());
+        let mut writer = RecordWriter {
+            name_allocator: &mut type_name_allocator,
+            allocator: compiler.allocator(),
+            builtins: &compiler.builtins,
+            worklist: Worklist::new(compiler),
+        };
+
+        let mut header =
+            "#nullable enable\n\nusing System;\nusing System.Text.Json.Serialization;\n"
+                .to_string();
+        if let Some(namespace) = &self.namespace {
+            header.push_str(&format!("\nnamespace {};\n", namespace));
+        }
+        let mut output = vec![header];
+        while let Some(named_type) = writer.worklist.pop() {
+            let csharp_name = writer.allocate_name(&named_type.name);
+            let record = writer.convert_named_ir_type(&csharp_name, &named_type);
+            if !record.is_empty() {
+                output.push(record);
+            }
+        }
+        Ok(vec![OutputFile::new("Types.cs", output.join("\n"))])
+    }
+}
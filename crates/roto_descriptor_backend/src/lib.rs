@@ -0,0 +1,50 @@
+//! Emits the compiled types themselves as data, for runtime reflection: generic form builders,
+//! dynamic validators and the like.
+//!
+//! Every type is described by its name, doc comment and annotations, plus its shape: the fields
+//! of a struct, the options of a variant or the target of an alias. Types are named like the
+//! other backends name them, so a descriptor can be matched to the generated class.
+
+pub mod python;
+pub mod rust;
+
+use roto_core::ast;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::PrimitiveType;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// The type of a field, an option's payload or an alias target.
+enum TypeRef {
+    Builtin(&'static str),
+    Named(String),
+}
+
+impl TypeRef {
+    fn new(
+        compiler: &IRCompiler,
+        name_allocator: &mut TypeNameAllocator,
+        t: &PrimitiveType,
+    ) -> TypeRef {
+        match t {
+            PrimitiveType::Builtin(builtin) => TypeRef::Builtin(builtin_name(builtin)),
+            PrimitiveType::Reference(id) => {
+                let name = &compiler.named_type(*id).name;
+                TypeRef::Named(name_allocator.allocate_name(name))
+            }
+        }
+    }
+}
+
+/// The name of `builtin` as written in a schema, custom builtins included, so that reflection can
+/// tell them apart from their representation.
+fn builtin_name(builtin: &ast::Builtin) -> &'static str {
+    match builtin {
+        ast::Builtin::Int => "int",
+        ast::Builtin::Float => "float",
+        ast::Builtin::String => "string",
+        ast::Builtin::Bool => "bool",
+        ast::Builtin::Unit => "unit",
+        ast::Builtin::Custom(id) => id.name(),
+    }
+}
@@ -0,0 +1,204 @@
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::OutputFile;
+use roto_core::backend::TypeNaming;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+
+use crate::builtin_name;
+use crate::TypeNameAllocator;
+use crate::TypeRef;
+
+fn python_string(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn python_optional_string(s: &Option<String>) -> String {
+    match s {
+        Some(s) => python_string(s),
+        None => "None".to_string(),
+    }
+}
+
+fn python_value(value: &ast::AnnotationValue) -> String {
+    match value {
+        ast::AnnotationValue::Ident(ident) => python_string(ident),
+        ast::AnnotationValue::Int(value) => value.to_string(),
+        ast::AnnotationValue::String(value) => python_string(value),
+    }
+}
+
+fn python_type_ref(t: &TypeRef) -> String {
+    match t {
+        TypeRef::Builtin(name) => format!("{{\"builtin\": {}}}", python_string(name)),
+        TypeRef::Named(name) => format!("{{\"ref\": {}}}", python_string(name)),
+    }
+}
+
+/// A list of `{"name": ..., "args": {...}}` dicts.
+fn python_annotations(annotations: &[ast::Annotation]) -> String {
+    let annotations: Vec<_> = annotations
+        .iter()
+        .map(|annotation| {
+            let args: Vec<_> = annotation
+                .args
+                .iter()
+                .map(|(name, value)| format!("{}: {}", python_string(name), python_value(value)))
+                .collect();
+            format!(
+                "{{\"name\": {}, \"args\": {{{}}}}}",
+                python_string(&annotation.name),
+                args.join(", ")
+            )
+        })
+        .collect();
+    format!("[{}]", annotations.join(", "))
+}
+
+struct DescriptorWriter<'a> {
+    compiler: &'a IRCompiler,
+    name_allocator: TypeNameAllocator,
+}
+
+impl<'a> DescriptorWriter<'a> {
+    fn type_ref(&mut self, t: &PrimitiveType) -> String {
+        python_type_ref(&TypeRef::new(self.compiler, &mut self.name_allocator, t))
+    }
+
+    /// A field or option, which differ only in `optional`.
+    fn write_member(
+        &mut self,
+        result: &mut String,
+        name: &str,
+        t: &PrimitiveType,
+        optional: Option<bool>,
+        annotations: &[ast::Annotation],
+        comment: &Option<String>,
+    ) {
+        result.push_str("            {\n");
+        result.push_str(&format!(
+            "                \"name\": {},\n",
+            python_string(name)
+        ));
+        result.push_str(&format!(
+            "                \"type\": {},\n",
+            self.type_ref(t)
+        ));
+        if let Some(optional) = optional {
+            let optional = if optional { "True" } else { "False" };
+            result.push_str(&format!("                \"optional\": {},\n", optional));
+        }
+        result.push_str(&format!(
+            "                \"doc\": {},\n",
+            python_optional_string(comment)
+        ));
+        result.push_str(&format!(
+            "                \"annotations\": {},\n",
+            python_annotations(annotations)
+        ));
+        result.push_str("            },\n");
+    }
+
+    fn convert_named_ir_type(&mut self, name: &str, named_type: &NamedIRType) -> String {
+        let kind = match &named_type.type_ {
+            IRType::Struct(_) => "struct",
+            IRType::Variant(_) => "variant",
+            IRType::Reference(_) | IRType::Builtin(_) => "alias",
+        };
+        let mut result = format!("    {}: {{\n", python_string(name));
+        result.push_str(&format!("        \"kind\": {},\n", python_string(kind)));
+        result.push_str(&format!(
+            "        \"doc\": {},\n",
+            python_optional_string(&named_type.metadata.doc)
+        ));
+        result.push_str(&format!(
+            "        \"annotations\": {},\n",
+            python_annotations(&named_type.metadata.annotations)
+        ));
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                result.push_str("        \"fields\": [\n");
+                for field in struct_type.fields.iter() {
+                    self.write_member(
+                        &mut result,
+                        &field.name,
+                        &field.type_,
+                        Some(field.optional),
+                        &field.annotations,
+                        &field.comment,
+                    );
+                }
+                result.push_str("        ],\n");
+            }
+            IRType::Variant(variant_type) => {
+                result.push_str("        \"options\": [\n");
+                for option in variant_type.variants.iter() {
+                    self.write_member(
+                        &mut result,
+                        &option.name,
+                        &option.type_,
+                        None,
+                        &option.annotations,
+                        &option.comment,
+                    );
+                }
+                result.push_str("        ],\n");
+            }
+            IRType::Reference(id) => {
+                let target = self.type_ref(&PrimitiveType::Reference(*id));
+                result.push_str(&format!("        \"target\": {},\n", target));
+            }
+            IRType::Builtin(builtin) => {
+                let target = python_type_ref(&TypeRef::Builtin(builtin_name(builtin)));
+                result.push_str(&format!("        \"target\": {},\n", target));
+            }
+        }
+        result.push_str("    },\n");
+        result
+    }
+}
+
+/// Emits `descriptors.py`, which describes every compiled type in a `TYPES` dict keyed by type
+/// name.
+pub struct PythonDescriptorBackend;
+
+impl Backend for PythonDescriptorBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = DescriptorWriter {
+            compiler,
+            name_allocator: TypeNameAllocator::new()
+                .with_declared(compiler)
+                .with_naming(naming.clone()),
+        };
+
+        let mut output = String::new();
+        output.push_str("from typing import Any, Dict\n\n");
+        output.push_str(
+            "# Member types are either `{\"builtin\": name}` or `{\"ref\": name}`, where\n",
+        );
+        output.push_str("# the name of a reference is a key of TYPES.\n");
+        output.push_str("TYPES: Dict[str, Dict[str, Any]] = {\n");
+        for (_id, named_type) in compiler.iter_types() {
+            let name = writer.name_allocator.allocate_name(&named_type.name);
+            output.push_str(&writer.convert_named_ir_type(&name, named_type));
+        }
+        output.push_str("}\n");
+        Ok(vec![OutputFile::new("descriptors.py", output)])
+    }
+}
@@ -0,0 +1,225 @@
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::OutputFile;
+use roto_core::backend::TypeNaming;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+
+use crate::builtin_name;
+use crate::TypeNameAllocator;
+use crate::TypeRef;
+
+/// The descriptor types every generated module starts with.
+const RUNTIME: &str = r#"#![allow(dead_code)]
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeRef {
+    Builtin(&'static str),
+    /// The name of another type in `TYPES`.
+    Named(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnnotationValue {
+    Ident(&'static str),
+    Int(i64),
+    String(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnnotationDescriptor {
+    pub name: &'static str,
+    pub args: &'static [(&'static str, AnnotationValue)],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldDescriptor {
+    pub name: &'static str,
+    pub type_: TypeRef,
+    pub optional: bool,
+    pub doc: Option<&'static str>,
+    pub annotations: &'static [AnnotationDescriptor],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OptionDescriptor {
+    pub name: &'static str,
+    pub type_: TypeRef,
+    pub doc: Option<&'static str>,
+    pub annotations: &'static [AnnotationDescriptor],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeKind {
+    Struct(&'static [FieldDescriptor]),
+    Variant(&'static [OptionDescriptor]),
+    Alias(TypeRef),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypeDescriptor {
+    pub name: &'static str,
+    pub doc: Option<&'static str>,
+    pub annotations: &'static [AnnotationDescriptor],
+    pub kind: TypeKind,
+}
+
+pub fn find_type(name: &str) -> Option<&'static TypeDescriptor> {
+    TYPES.iter().find(|t| t.name == name)
+}
+"#;
+
+fn rust_optional_string(s: &Option<String>) -> String {
+    match s {
+        Some(s) => format!("Some({:?})", s),
+        None => "None".to_string(),
+    }
+}
+
+fn rust_value(value: &ast::AnnotationValue) -> String {
+    match value {
+        ast::AnnotationValue::Ident(ident) => format!("AnnotationValue::Ident({:?})", ident),
+        ast::AnnotationValue::Int(value) => format!("AnnotationValue::Int({})", value),
+        ast::AnnotationValue::String(value) => format!("AnnotationValue::String({:?})", value),
+    }
+}
+
+fn rust_type_ref(t: &TypeRef) -> String {
+    match t {
+        TypeRef::Builtin(name) => format!("TypeRef::Builtin({:?})", name),
+        TypeRef::Named(name) => format!("TypeRef::Named({:?})", name),
+    }
+}
+
+fn rust_annotations(annotations: &[ast::Annotation]) -> String {
+    let annotations: Vec<_> = annotations
+        .iter()
+        .map(|annotation| {
+            let args: Vec<_> = annotation
+                .args
+                .iter()
+                .map(|(name, value)| format!("({:?}, {})", name, rust_value(value)))
+                .collect();
+            format!(
+                "AnnotationDescriptor {{ name: {:?}, args: &[{}] }}",
+                annotation.name,
+                args.join(", ")
+            )
+        })
+        .collect();
+    format!("&[{}]", annotations.join(", "))
+}
+
+struct DescriptorWriter<'a> {
+    compiler: &'a IRCompiler,
+    name_allocator: TypeNameAllocator,
+}
+
+impl<'a> DescriptorWriter<'a> {
+    fn type_ref(&mut self, t: &PrimitiveType) -> String {
+        rust_type_ref(&TypeRef::new(self.compiler, &mut self.name_allocator, t))
+    }
+
+    fn convert_named_ir_type(&mut self, name: &str, named_type: &NamedIRType) -> String {
+        let mut result = "    TypeDescriptor {\n".to_string();
+        result.push_str(&format!("        name: {:?},\n", name));
+        result.push_str(&format!(
+            "        doc: {},\n",
+            rust_optional_string(&named_type.metadata.doc)
+        ));
+        result.push_str(&format!(
+            "        annotations: {},\n",
+            rust_annotations(&named_type.metadata.annotations)
+        ));
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                result.push_str("        kind: TypeKind::Struct(&[\n");
+                for field in struct_type.fields.iter() {
+                    result.push_str("            FieldDescriptor {\n");
+                    result.push_str(&format!("                name: {:?},\n", field.name));
+                    result.push_str(&format!(
+                        "                type_: {},\n",
+                        self.type_ref(&field.type_)
+                    ));
+                    result.push_str(&format!("                optional: {},\n", field.optional));
+                    result.push_str(&format!(
+                        "                doc: {},\n",
+                        rust_optional_string(&field.comment)
+                    ));
+                    result.push_str(&format!(
+                        "                annotations: {},\n",
+                        rust_annotations(&field.annotations)
+                    ));
+                    result.push_str("            },\n");
+                }
+                result.push_str("        ]),\n");
+            }
+            IRType::Variant(variant_type) => {
+                result.push_str("        kind: TypeKind::Variant(&[\n");
+                for option in variant_type.variants.iter() {
+                    result.push_str("            OptionDescriptor {\n");
+                    result.push_str(&format!("                name: {:?},\n", option.name));
+                    result.push_str(&format!(
+                        "                type_: {},\n",
+                        self.type_ref(&option.type_)
+                    ));
+                    result.push_str(&format!(
+                        "                doc: {},\n",
+                        rust_optional_string(&option.comment)
+                    ));
+                    result.push_str(&format!(
+                        "                annotations: {},\n",
+                        rust_annotations(&option.annotations)
+                    ));
+                    result.push_str("            },\n");
+                }
+                result.push_str("        ]),\n");
+            }
+            IRType::Reference(id) => {
+                let target = self.type_ref(&PrimitiveType::Reference(*id));
+                result.push_str(&format!("        kind: TypeKind::Alias({}),\n", target));
+            }
+            IRType::Builtin(builtin) => {
+                let target = rust_type_ref(&TypeRef::Builtin(builtin_name(builtin)));
+                result.push_str(&format!("        kind: TypeKind::Alias({}),\n", target));
+            }
+        }
+        result.push_str("    },\n");
+        result
+    }
+}
+
+/// Emits `descriptors.rs`, which describes every compiled type in a `TYPES` static and can be
+/// included as a module.
+pub struct RustDescriptorBackend;
+
+impl Backend for RustDescriptorBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = DescriptorWriter {
+            compiler,
+            name_allocator: TypeNameAllocator::new()
+                .with_declared(compiler)
+                .with_naming(naming.clone()),
+        };
+
+        let mut output = RUNTIME.to_string();
+        output.push_str("\npub static TYPES: &[TypeDescriptor] = &[\n");
+        for (_id, named_type) in compiler.iter_types() {
+            let name = writer.name_allocator.allocate_name(&named_type.name);
+            output.push_str(&writer.convert_named_ir_type(&name, named_type));
+        }
+        output.push_str("];\n");
+        Ok(vec![OutputFile::new("descriptors.rs", output)])
+    }
+}
@@ -0,0 +1,186 @@
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::OutputFile;
+use roto_core::docs::MemberDoc;
+use roto_core::docs::TypeDoc;
+use roto_core::docs::TypeDocKind;
+use roto_core::docs::TypeRef;
+use roto_core::frontend::IRCompiler;
+
+use crate::title;
+use crate::Site;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn code(text: &str) -> String {
+    format!("<code>{}</code>", escape(text))
+}
+
+/// A doc comment, with a paragraph per blank line separated block.
+fn paragraphs(text: &str) -> String {
+    text.split("\n\n")
+        .map(|paragraph| format!("<p>{}</p>\n", escape(paragraph).replace('\n', "<br>\n")))
+        .collect()
+}
+
+/// How an alias with a `@format` continues after its target, like `` in the format `email` ``.
+fn format_suffix(t: &TypeDoc) -> String {
+    t.format
+        .as_deref()
+        .map(|format| format!(" in the format {}", code(format)))
+        .unwrap_or_default()
+}
+
+fn annotations(annotations: &[ast::Annotation]) -> String {
+    annotations
+        .iter()
+        .map(|a| code(&a.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        body
+    )
+}
+
+impl Site {
+    fn html_link(&self, type_ref: &TypeRef) -> String {
+        match self.link(type_ref) {
+            (title, Some(page)) => {
+                format!("<a href=\"{}.html\">{}</a>", escape(page), code(&title))
+            }
+            (title, None) => code(&title),
+        }
+    }
+
+    fn html_index(&self) -> String {
+        let mut body = String::from("<h1>Schema</h1>\n");
+        for (heading, types) in self.sections() {
+            if types.is_empty() {
+                continue;
+            }
+            body.push_str(&format!("<h2>{}</h2>\n<table>\n", heading));
+            body.push_str("<tr><th>Type</th><th>Kind</th><th>Description</th></tr>\n");
+            for t in types {
+                let summary = t
+                    .description
+                    .as_deref()
+                    .and_then(|d| d.lines().next())
+                    .unwrap_or("");
+                body.push_str(&format!(
+                    "<tr><td><a href=\"{}.html\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+                    escape(self.page(t.id)),
+                    code(&title(&t.name)),
+                    Site::kind(t),
+                    escape(summary)
+                ));
+            }
+            body.push_str("</table>\n");
+        }
+        document("Schema", &body)
+    }
+
+    fn html_members(&self, body: &mut String, heading: &str, members: &[MemberDoc]) {
+        body.push_str(&format!("<h2>{}</h2>\n<table>\n", heading));
+        body.push_str("<tr><th>Name</th><th>Type</th><th>Description</th></tr>\n");
+        for member in members {
+            let optional = if member.optional { " (optional)" } else { "" };
+            let mut description = member
+                .description
+                .as_deref()
+                .map(paragraphs)
+                .unwrap_or_default();
+            if !member.annotations.is_empty() {
+                description.push_str(&format!("<p>{}</p>\n", annotations(&member.annotations)));
+            }
+            body.push_str(&format!(
+                "<tr><td>{}{}</td><td>{}</td><td>{}</td></tr>\n",
+                code(&member.name),
+                optional,
+                self.html_link(&member.type_),
+                description
+            ));
+        }
+        body.push_str("</table>\n");
+    }
+
+    fn html_page(&self, t: &TypeDoc) -> String {
+        let type_title = title(&t.name);
+        let mut body = format!(
+            "<nav><a href=\"index.html\">Index</a></nav>\n<h1>{}</h1>\n",
+            code(&type_title)
+        );
+        body.push_str(&format!("<p><em>{}</em>", Site::kind(t)));
+        if let Some(location) = &t.location {
+            body.push_str(&format!(", declared at {}", code(&location.to_string())));
+        }
+        body.push_str("</p>\n");
+        if let Some(description) = &t.description {
+            body.push_str(&paragraphs(description));
+        }
+        if !t.annotations.is_empty() {
+            body.push_str(&format!(
+                "<p>Annotations: {}</p>\n",
+                annotations(&t.annotations)
+            ));
+        }
+        match &t.kind {
+            TypeDocKind::Struct { fields } => self.html_members(&mut body, "Fields", fields),
+            TypeDocKind::Variant { options } => self.html_members(&mut body, "Options", options),
+            TypeDocKind::Alias { target } => {
+                body.push_str(&format!(
+                    "<p>Alias of {}{}.</p>\n",
+                    self.html_link(target),
+                    format_suffix(t)
+                ));
+            }
+            TypeDocKind::Builtin(builtin) => {
+                body.push_str(&format!(
+                    "<p>Alias of {}{}.</p>\n",
+                    code(&builtin.to_string()),
+                    format_suffix(t)
+                ));
+            }
+        }
+        let referenced_by = self.referenced_by(t);
+        if !referenced_by.is_empty() {
+            body.push_str("<h2>Referenced by</h2>\n<ul>\n");
+            for other in referenced_by {
+                body.push_str(&format!(
+                    "<li><a href=\"{}.html\">{}</a></li>\n",
+                    escape(self.page(other.id)),
+                    code(&title(&other.name))
+                ));
+            }
+            body.push_str("</ul>\n");
+        }
+        document(&type_title, &body)
+    }
+}
+
+/// Writes `index.html` and an HTML page per type.
+pub struct HtmlDocBackend;
+
+impl Backend for HtmlDocBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        let site = Site::new(compiler);
+        let mut files = vec![OutputFile::new("index.html", site.html_index())];
+        for t in site.model.types.values() {
+            files.push(OutputFile::new(
+                format!("{}.html", site.page(t.id)),
+                site.html_page(t),
+            ));
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,104 @@
+//! Renders the [`DocModel`] of a schema as a set of linked pages: an index of the declared types
+//! and one page per type, with its fields or options, the types it refers to and the types that
+//! refer to it.
+
+pub mod html;
+pub mod markdown;
+
+use std::collections::HashMap;
+
+use roto_core::docs::{build_doc_model, DocModel, TypeDoc, TypeDocKind, TypeRef};
+use roto_core::format::format_type;
+use roto_core::frontend::IRCompiler;
+use roto_core::intern;
+use roto_core::ir::TypeName;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// The name of a type as it is written in the schema, like `auth.User`, `Array<T=User>` or
+/// `Tree.children` for an inline type.
+pub fn title(type_name: &TypeName) -> String {
+    match type_name {
+        TypeName::Variable(name) => name.to_string(),
+        TypeName::Generic(name, args) => {
+            let args: Vec<_> = args
+                .iter()
+                .map(|(param, arg)| format!("{}={}", param, format_type(arg)))
+                .collect();
+            format!("{}<{}>", name, args.join(", "))
+        }
+        TypeName::Temporary(owner, path) => format!("{}.{}", title(owner), intern::join(path, ".")),
+    }
+}
+
+/// The pages of a documentation site, by type id.
+struct Site {
+    model: DocModel,
+    /// The page of every type, without extension.
+    pages: HashMap<usize, String>,
+}
+
+impl Site {
+    fn new(compiler: &IRCompiler) -> Self {
+        let model = build_doc_model(compiler);
+        // Qualified names like `auth.User` make good file names as they are.
+        let mut name_allocator =
+            TypeNameAllocator::with_variable_name(str::to_string).with_declared(compiler);
+        let pages = model
+            .types
+            .values()
+            .map(|t| (t.id, name_allocator.allocate_name(&t.name)))
+            .collect();
+        Site { model, pages }
+    }
+
+    fn page(&self, id: usize) -> &str {
+        &self.pages[&id]
+    }
+
+    /// The declared types, then the generic instances and inline types, each by title.
+    fn sections(&self) -> [(&'static str, Vec<&TypeDoc>); 2] {
+        let mut declared: Vec<_> = self.model.iter_globals().collect();
+        let mut other: Vec<_> = self
+            .model
+            .types
+            .values()
+            .filter(|t| !t.is_global())
+            .collect();
+        declared.sort_by_key(|t| title(&t.name));
+        other.sort_by_key(|t| title(&t.name));
+        [("Types", declared), ("Other types", other)]
+    }
+
+    /// The kind of `t`, as shown next to its title.
+    fn kind(t: &TypeDoc) -> &'static str {
+        match t.kind {
+            TypeDocKind::Struct { .. } => "struct",
+            TypeDocKind::Variant { .. } => "variant",
+            TypeDocKind::Alias { .. } | TypeDocKind::Builtin(_) => "alias",
+        }
+    }
+
+    /// The types that mention `t`, by title.
+    fn referenced_by(&self, t: &TypeDoc) -> Vec<&TypeDoc> {
+        let mut types: Vec<_> = t
+            .referenced_by
+            .iter()
+            .filter_map(|id| self.model.get(*id))
+            .collect();
+        types.sort_by_key(|t| title(&t.name));
+        types.dedup_by_key(|t| t.id);
+        types
+    }
+
+    /// The title of a referenced type and the page it links to, if it has one.
+    fn link(&self, type_ref: &TypeRef) -> (String, Option<&str>) {
+        match type_ref {
+            TypeRef::Builtin(builtin) => (builtin.to_string(), None),
+            TypeRef::Type { id, name } => match self.model.get(*id) {
+                Some(t) => (title(&t.name), Some(self.page(*id))),
+                None => (name.clone(), None),
+            },
+        }
+    }
+}
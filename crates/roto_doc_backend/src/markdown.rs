@@ -0,0 +1,158 @@
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::OutputFile;
+use roto_core::docs::MemberDoc;
+use roto_core::docs::TypeDoc;
+use roto_core::docs::TypeDocKind;
+use roto_core::docs::TypeRef;
+use roto_core::frontend::IRCompiler;
+
+use crate::title;
+use crate::Site;
+
+/// Escapes `text` for a table cell, which has to stay on one line.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn code(text: &str) -> String {
+    format!("`{}`", text)
+}
+
+/// How an alias with a `@format` continues after its target, like `` in the format `email` ``.
+fn format_suffix(t: &TypeDoc) -> String {
+    t.format
+        .as_deref()
+        .map(|format| format!(" in the format {}", code(format)))
+        .unwrap_or_default()
+}
+
+fn annotations(annotations: &[ast::Annotation]) -> String {
+    annotations
+        .iter()
+        .map(|a| code(&a.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Site {
+    fn markdown_link(&self, type_ref: &TypeRef) -> String {
+        match self.link(type_ref) {
+            (title, Some(page)) => format!("[{}]({}.md)", code(&title), page),
+            (title, None) => code(&title),
+        }
+    }
+
+    fn markdown_index(&self) -> String {
+        let mut result = String::from("# Schema\n");
+        for (heading, types) in self.sections() {
+            if types.is_empty() {
+                continue;
+            }
+            result.push_str(&format!("\n## {}\n\n", heading));
+            result.push_str("| Type | Kind | Description |\n| --- | --- | --- |\n");
+            for t in types {
+                let summary = t
+                    .description
+                    .as_deref()
+                    .and_then(|d| d.lines().next())
+                    .unwrap_or("");
+                result.push_str(&format!(
+                    "| [{}]({}.md) | {} | {} |\n",
+                    cell(&code(&title(&t.name))),
+                    self.page(t.id),
+                    Site::kind(t),
+                    cell(summary)
+                ));
+            }
+        }
+        result
+    }
+
+    fn markdown_members(&self, result: &mut String, heading: &str, members: &[MemberDoc]) {
+        result.push_str(&format!("\n## {}\n\n", heading));
+        result.push_str("| Name | Type | Description |\n| --- | --- | --- |\n");
+        for member in members {
+            let optional = if member.optional { " (optional)" } else { "" };
+            let mut description = member.description.clone().unwrap_or_default();
+            if !member.annotations.is_empty() {
+                if !description.is_empty() {
+                    description.push('\n');
+                }
+                description.push_str(&annotations(&member.annotations));
+            }
+            result.push_str(&format!(
+                "| {}{} | {} | {} |\n",
+                code(&member.name),
+                optional,
+                cell(&self.markdown_link(&member.type_)),
+                cell(&description)
+            ));
+        }
+    }
+
+    fn markdown_page(&self, t: &TypeDoc) -> String {
+        let mut result = format!("[Index](index.md)\n\n# {}\n\n", code(&title(&t.name)));
+        result.push_str(&format!("*{}*", Site::kind(t)));
+        if let Some(location) = &t.location {
+            result.push_str(&format!(", declared at {}", code(&location.to_string())));
+        }
+        result.push('\n');
+        if let Some(description) = &t.description {
+            result.push_str(&format!("\n{}\n", description));
+        }
+        if !t.annotations.is_empty() {
+            result.push_str(&format!("\nAnnotations: {}\n", annotations(&t.annotations)));
+        }
+        match &t.kind {
+            TypeDocKind::Struct { fields } => self.markdown_members(&mut result, "Fields", fields),
+            TypeDocKind::Variant { options } => {
+                self.markdown_members(&mut result, "Options", options)
+            }
+            TypeDocKind::Alias { target } => {
+                result.push_str(&format!(
+                    "\nAlias of {}{}.\n",
+                    self.markdown_link(target),
+                    format_suffix(t)
+                ));
+            }
+            TypeDocKind::Builtin(builtin) => {
+                result.push_str(&format!(
+                    "\nAlias of {}{}.\n",
+                    code(&builtin.to_string()),
+                    format_suffix(t)
+                ));
+            }
+        }
+        let referenced_by = self.referenced_by(t);
+        if !referenced_by.is_empty() {
+            result.push_str("\n## Referenced by\n\n");
+            for other in referenced_by {
+                result.push_str(&format!(
+                    "- [{}]({}.md)\n",
+                    code(&title(&other.name)),
+                    self.page(other.id)
+                ));
+            }
+        }
+        result
+    }
+}
+
+/// Writes `index.md` and a Markdown page per type.
+pub struct MarkdownDocBackend;
+
+impl Backend for MarkdownDocBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        let site = Site::new(compiler);
+        let mut files = vec![OutputFile::new("index.md", site.markdown_index())];
+        for t in site.model.types.values() {
+            files.push(OutputFile::new(
+                format!("{}.md", site.page(t.id)),
+                site.markdown_page(t),
+            ));
+        }
+        Ok(files)
+    }
+}
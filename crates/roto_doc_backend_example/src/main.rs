@@ -0,0 +1,57 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use roto_core::backend::write_files;
+use roto_core::backend::Backend;
+use roto_core::frontend::IRCompiler;
+use roto_core::RotoError;
+use roto_doc_backend::html::HtmlDocBackend;
+use roto_doc_backend::markdown::MarkdownDocBackend;
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), RotoError> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 4 {
+        eprintln!(
+            "Usage: {} <file_path> <markdown|html> <output_dir>",
+            args[0]
+        );
+        process::exit(1);
+    }
+
+    let backend: Box<dyn Backend> = match args[2].as_str() {
+        "markdown" => Box::new(MarkdownDocBackend),
+        "html" => Box::new(HtmlDocBackend),
+        format => {
+            eprintln!("Unknown format {}, expected markdown or html", format);
+            process::exit(1);
+        }
+    };
+
+    let file_path = &args[1];
+    let file_contents = fs::read_to_string(file_path).map_err(|e| RotoError::io(file_path, e))?;
+
+    let parsed = roto_core::parse(&file_contents, file_path)?;
+
+    let mut compiler = IRCompiler::new();
+    backend.register_annotations(&mut compiler.annotations);
+    compiler.register_program(parsed, file_path, &file_contents);
+
+    for warning in compiler.check_annotations()? {
+        eprintln!("{}", warning);
+    }
+
+    compiler.compile_globals()?;
+
+    let files = backend.generate(&compiler)?;
+    write_files(Path::new(&args[3]), &files).map_err(|e| RotoError::io(&args[3], e))?;
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::OutputFile;
+use roto_core::backend::TypeNaming;
+use roto_core::frontend::IRCompiler;
+use roto_core::intern;
+use roto_core::intern::Atom;
+use roto_core::ir::IRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+use roto_core::symbols::split_qualified_name;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The module a type is declared in. Temporaries belong to the module of their owner.
+fn module(type_name: &TypeName) -> &str {
+    match type_name {
+        TypeName::Variable(name) | TypeName::Generic(name, _) => split_qualified_name(name).0,
+        TypeName::Temporary(owner, _) => module(owner),
+    }
+}
+
+/// A reference from one named type to another, labelled with the member that holds it. Aliases
+/// have no label.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Edge {
+    from: String,
+    to: String,
+    label: Option<String>,
+}
+
+struct GraphWriter<'a> {
+    compiler: &'a IRCompiler,
+    collapse_temporaries: bool,
+    name_allocator: TypeNameAllocator,
+    ids: HashMap<TypeName, usize>,
+}
+
+impl<'a> GraphWriter<'a> {
+    /// Returns the type that represents `id` in the graph, and the member path from that type to
+    /// `id`. Without collapsing every type represents itself.
+    fn representative(&self, id: usize) -> (usize, Vec<Atom>) {
+        let mut name = &self.compiler.named_type(id).name;
+        let mut path = Vec::new();
+        if self.collapse_temporaries {
+            while let TypeName::Temporary(owner, segments) = name {
+                match self.ids.get(owner.as_ref()) {
+                    Some(owner_id) => {
+                        let mut segments = segments.clone();
+                        segments.append(&mut path);
+                        path = segments;
+                        name = &self.compiler.named_type(*owner_id).name;
+                    }
+                    None => break,
+                }
+            }
+        }
+        (self.ids[name], path)
+    }
+
+    fn node_id(&mut self, id: usize) -> String {
+        let compiler = self.compiler;
+        self.name_allocator
+            .allocate_name(&compiler.named_type(id).name)
+    }
+
+    fn label(&mut self, type_name: &TypeName) -> String {
+        match type_name {
+            TypeName::Variable(name) => split_qualified_name(name).1.to_string(),
+            TypeName::Generic(name, _) => {
+                let module = split_qualified_name(name).0;
+                let name = self.name_allocator.allocate_name(type_name);
+                // The allocated name starts with the module path, joined by underscores.
+                let prefix = if module.is_empty() {
+                    0
+                } else {
+                    module.len() + 1
+                };
+                name[prefix..].to_string()
+            }
+            TypeName::Temporary(owner, path) => {
+                format!("{}.{}", self.label(owner), intern::join(path, "."))
+            }
+        }
+    }
+
+    fn edge(&mut self, from: usize, to: &PrimitiveType, member: Option<&str>) -> Option<Edge> {
+        let PrimitiveType::Reference(to) = to else {
+            return None;
+        };
+        let (from, mut path) = self.representative(from);
+        let (to, to_path) = self.representative(*to);
+        // A collapsed temporary is drawn as part of its owner, so there is nothing to connect.
+        if from == to && !to_path.is_empty() {
+            return None;
+        }
+        path.extend(member.map(Atom::from));
+        let label = if path.is_empty() {
+            None
+        } else {
+            Some(intern::join(&path, "."))
+        };
+        Some(Edge {
+            from: self.node_id(from),
+            to: self.node_id(to),
+            label,
+        })
+    }
+
+    fn write(&mut self) -> String {
+        let compiler = self.compiler;
+        let mut modules: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut edges = BTreeSet::new();
+        for (id, named_type) in compiler.iter_types() {
+            let (representative, _) = self.representative(*id);
+            if representative == *id {
+                let shape = match named_type.type_ {
+                    IRType::Struct(_) => "shape=box",
+                    IRType::Variant(_) => "shape=box, style=rounded",
+                    IRType::Reference(_) | IRType::Builtin(_) => "shape=ellipse",
+                };
+                let node = format!(
+                    "{} [label={}, {}];",
+                    dot_string(&self.node_id(*id)),
+                    dot_string(&self.label(&named_type.name)),
+                    shape
+                );
+                modules
+                    .entry(module(&named_type.name).to_string())
+                    .or_default()
+                    .push(node);
+            }
+
+            match &named_type.type_ {
+                IRType::Struct(struct_type) => {
+                    for field in struct_type.fields.iter() {
+                        edges.extend(self.edge(*id, &field.type_, Some(&field.name)));
+                    }
+                }
+                IRType::Variant(variant_type) => {
+                    for option in variant_type.variants.iter() {
+                        edges.extend(self.edge(*id, &option.type_, Some(&option.name)));
+                    }
+                }
+                IRType::Reference(target) => {
+                    edges.extend(self.edge(*id, &PrimitiveType::Reference(*target), None));
+                }
+                IRType::Builtin(_) => {}
+            }
+        }
+
+        let mut result = "digraph roto {\n    rankdir=LR;\n".to_string();
+        for (module, nodes) in modules.iter() {
+            result.push('\n');
+            if module.is_empty() {
+                for node in nodes {
+                    result.push_str(&format!("    {}\n", node));
+                }
+            } else {
+                result.push_str(&format!(
+                    "    subgraph {} {{\n        label={};\n",
+                    dot_string(&format!("cluster_{}", module)),
+                    dot_string(module)
+                ));
+                for node in nodes {
+                    result.push_str(&format!("        {}\n", node));
+                }
+                result.push_str("    }\n");
+            }
+        }
+        if !edges.is_empty() {
+            result.push('\n');
+        }
+        for edge in edges.iter() {
+            let attributes = match &edge.label {
+                Some(label) => format!("label={}", dot_string(label)),
+                None => "style=dashed".to_string(),
+            };
+            result.push_str(&format!(
+                "    {} -> {} [{}];\n",
+                dot_string(&edge.from),
+                dot_string(&edge.to),
+                attributes
+            ));
+        }
+        result.push_str("}\n");
+        result
+    }
+}
+
+/// Emits a DOT graph of the compiled types. Nodes are named types, edges are the references of
+/// struct fields and variant options, and dashed edges lead from aliases to the types they name.
+/// Types are grouped into one cluster per module.
+pub struct DotBackend {
+    /// Draws temporaries as part of the type that declares them instead of as nodes of their own.
+    pub collapse_temporaries: bool,
+}
+
+impl Backend for DotBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = GraphWriter {
+            compiler,
+            collapse_temporaries: self.collapse_temporaries,
+            name_allocator: TypeNameAllocator::new()
+                .with_declared(compiler)
+                .with_naming(naming.clone()),
+            ids: compiler
+                .iter_types()
+                .map(|(id, named_type)| (named_type.name.clone(), *id))
+                .collect(),
+        };
+        Ok(vec![OutputFile::new("types.dot", writer.write())])
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use roto_core::annotations::AnnotationRegistry;
+use roto_core::annotations::AnnotationSpec;
+use roto_core::annotations::ArgumentKind;
+use roto_core::ast;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+/// The range of numbers and string lengths used when no constraint narrows it.
+const DEFAULT_MIN: i64 = 0;
+const DEFAULT_MAX: i64 = 1000;
+const DEFAULT_MAX_LENGTH: i64 = 16;
+/// How often a pattern may repeat an unbounded item like `a+`.
+const MAX_REPEAT: u32 = 8;
+
+/// Registers the constraints understood by the generator. `@min` and `@max` bound numbers and the
+/// length of strings, `@pattern` is a regular expression that strings match.
+pub fn register_annotations(registry: &mut AnnotationRegistry) {
+    registry.register(AnnotationSpec::new("min").arg("value", ArgumentKind::Int));
+    registry.register(AnnotationSpec::new("max").arg("value", ArgumentKind::Int));
+    registry.register(AnnotationSpec::new("pattern").arg("regex", ArgumentKind::String));
+}
+
+/// The constraints that apply to a value, collected from the member that holds it and the aliases
+/// it goes through. Constraints closer to the member win.
+#[derive(Debug, Clone, Default)]
+struct Constraints {
+    min: Option<i64>,
+    max: Option<i64>,
+    pattern: Option<String>,
+}
+
+impl Constraints {
+    fn extend(&mut self, annotations: &[ast::Annotation]) {
+        for annotation in annotations {
+            match (annotation.name.as_str(), annotation.args.as_slice()) {
+                ("min", [(_, ast::AnnotationValue::Int(value))]) => {
+                    self.min.get_or_insert(*value);
+                }
+                ("max", [(_, ast::AnnotationValue::Int(value))]) => {
+                    self.max.get_or_insert(*value);
+                }
+                ("pattern", [(_, ast::AnnotationValue::String(regex))]) => {
+                    self.pattern.get_or_insert(regex.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Generates random instances of compiled types as JSON. The same seed always produces the same
+/// instances.
+pub struct FakeGenerator<'a> {
+    compiler: &'a IRCompiler,
+    rng: ChaCha8Rng,
+    /// How often a type may be nested in itself before recursion is cut off.
+    pub max_depth: usize,
+    stack: Vec<usize>,
+    patterns: HashMap<String, rand_regex::Regex>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> FakeGenerator<'a> {
+    pub fn new(compiler: &'a IRCompiler, seed: u64) -> Self {
+        FakeGenerator {
+            compiler,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            max_depth: 3,
+            stack: Vec::new(),
+            patterns: HashMap::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Generates an instance of the global type `name`.
+    pub fn generate(&mut self, name: &str) -> Result<Value, Vec<Diagnostic>> {
+        let compiler = self.compiler;
+        let Some((id, _)) = compiler
+            .iter_types()
+            .find(|(_, t)| t.name == TypeName::Variable(name.into()))
+        else {
+            return Err(vec![Diagnostic::error(
+                format!("{} is not a compiled type", name),
+                None,
+            )]);
+        };
+
+        let value = self.generate_type(*id, Constraints::default());
+        if !self.diagnostics.is_empty() {
+            return Err(std::mem::take(&mut self.diagnostics));
+        }
+        value.ok_or_else(|| {
+            vec![Diagnostic::error(
+                format!("{} has no finite instance", name),
+                compiler.named_type(*id).metadata.location.clone(),
+            )]
+        })
+    }
+
+    fn generate_member(
+        &mut self,
+        t: &PrimitiveType,
+        annotations: &[ast::Annotation],
+    ) -> Option<Value> {
+        let mut constraints = Constraints::default();
+        constraints.extend(annotations);
+        match t {
+            PrimitiveType::Builtin(builtin) => Some(self.generate_builtin(builtin, &constraints)),
+            PrimitiveType::Reference(id) => self.generate_type(*id, constraints),
+        }
+    }
+
+    fn generate_builtin(&mut self, builtin: &ast::Builtin, constraints: &Constraints) -> Value {
+        match builtin {
+            ast::Builtin::Int => {
+                let (min, max) = self.bounds(constraints, DEFAULT_MIN, DEFAULT_MAX);
+                json!(self.rng.gen_range(min..=max))
+            }
+            ast::Builtin::Float => {
+                let (min, max) = self.bounds(constraints, DEFAULT_MIN, DEFAULT_MAX);
+                json!(self.rng.gen_range(min as f64..=max as f64))
+            }
+            ast::Builtin::String => match &constraints.pattern {
+                Some(pattern) => json!(self.generate_pattern(pattern)),
+                None => {
+                    let (min, max) = self.bounds(constraints, 1, DEFAULT_MAX_LENGTH);
+                    let length = self.rng.gen_range(min.max(0)..=max.max(0));
+                    let string: String = (0..length)
+                        .map(|_| self.rng.sample(rand::distributions::Alphanumeric) as char)
+                        .collect();
+                    json!(string)
+                }
+            },
+            ast::Builtin::Bool => json!(self.rng.gen_bool(0.5)),
+            ast::Builtin::Unit => Value::Null,
+            ast::Builtin::Custom(_) => {
+                let representation = self.compiler.builtins.representation(builtin);
+                self.generate_builtin(&representation, constraints)
+            }
+        }
+    }
+
+    /// Returns the range allowed by `constraints`. A missing bound is derived from the other one,
+    /// so `@min(value=5000)` alone still gives a valid range.
+    fn bounds(
+        &mut self,
+        constraints: &Constraints,
+        default_min: i64,
+        default_max: i64,
+    ) -> (i64, i64) {
+        let span = default_max - default_min;
+        let (min, max) = match (constraints.min, constraints.max) {
+            (Some(min), Some(max)) => (min, max),
+            (Some(min), None) => (min, min.saturating_add(span)),
+            (None, Some(max)) => (max.saturating_sub(span), max),
+            (None, None) => (default_min, default_max),
+        };
+        if min > max {
+            self.diagnostics.push(Diagnostic::error(
+                format!("@min({}) is larger than @max({})", min, max),
+                None,
+            ));
+            return (min, min);
+        }
+        (min, max)
+    }
+
+    fn generate_pattern(&mut self, pattern: &str) -> String {
+        if !self.patterns.contains_key(pattern) {
+            // Matches are generated from scratch, so anchors around the whole pattern do not add
+            // anything.
+            let mut unanchored = pattern.strip_prefix('^').unwrap_or(pattern);
+            if unanchored.ends_with('$') && !unanchored.ends_with("\\$") {
+                unanchored = &unanchored[..unanchored.len() - 1];
+            }
+            match rand_regex::Regex::compile(unanchored, MAX_REPEAT) {
+                Ok(regex) => {
+                    self.patterns.insert(pattern.to_string(), regex);
+                }
+                Err(error) => {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("invalid @pattern {:?}: {}", pattern, error),
+                        None,
+                    ));
+                    return String::new();
+                }
+            }
+        }
+        self.rng.sample(&self.patterns[pattern])
+    }
+
+    /// Generates an instance of the type `id`, or returns `None` if recursion had to be cut off.
+    /// Optional fields that can not be generated are left out, and variants fall back to other
+    /// options.
+    fn generate_type(&mut self, id: usize, mut constraints: Constraints) -> Option<Value> {
+        let compiler = self.compiler;
+        let named_type = compiler.named_type(id);
+        constraints.extend(&named_type.metadata.annotations);
+        if self.stack.iter().filter(|other| **other == id).count() >= self.max_depth {
+            return None;
+        }
+
+        self.stack.push(id);
+        let value = match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                let mut object = Map::new();
+                let mut complete = true;
+                for field in struct_type.fields.iter() {
+                    if field.optional && self.rng.gen_bool(0.5) {
+                        continue;
+                    }
+                    let name = match field
+                        .annotations
+                        .iter()
+                        .find(|a| a.name == "rename")
+                        .and_then(|a| a.arg("name"))
+                    {
+                        Some(ast::AnnotationValue::String(name)) => name.clone(),
+                        _ => field.name.to_string(),
+                    };
+                    match self.generate_member(&field.type_, &field.annotations) {
+                        Some(value) => {
+                            object.insert(name, value);
+                        }
+                        None if field.optional => {}
+                        None => {
+                            complete = false;
+                            break;
+                        }
+                    }
+                }
+                complete.then_some(Value::Object(object))
+            }
+            // Variants are adjacently tagged: `{ "type": "option", "value": payload }`, where the
+            // value is left out for unit options.
+            IRType::Variant(variant_type) => {
+                let mut options: Vec<_> = variant_type.variants.iter().collect();
+                options.shuffle(&mut self.rng);
+                options.into_iter().find_map(|option| {
+                    if matches!(option.type_, PrimitiveType::Builtin(ast::Builtin::Unit)) {
+                        return Some(json!({ "type": option.name.as_str() }));
+                    }
+                    let value = self.generate_member(&option.type_, &option.annotations)?;
+                    Some(json!({ "type": option.name.as_str(), "value": value }))
+                })
+            }
+            IRType::Reference(target) => self.generate_type(*target, constraints),
+            IRType::Builtin(builtin) => Some(self.generate_builtin(builtin, &constraints)),
+        };
+        self.stack.pop();
+        value
+    }
+}
@@ -0,0 +1,53 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use roto_core::backend::BackendError;
+use roto_core::frontend::IRCompiler;
+use roto_core::RotoError;
+use roto_fake::FakeGenerator;
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), RotoError> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 && args.len() != 4 {
+        eprintln!("Usage: {} <file_path> <type_name> [<seed>]", args[0]);
+        process::exit(1);
+    }
+
+    let file_path = &args[1];
+    let file_contents = fs::read_to_string(file_path).map_err(|e| RotoError::io(file_path, e))?;
+    let seed = match args.get(3) {
+        Some(seed) => match seed.parse() {
+            Ok(seed) => seed,
+            Err(_) => {
+                eprintln!("Invalid seed {}, expected a number", seed);
+                process::exit(1);
+            }
+        },
+        None => 0,
+    };
+
+    let parsed = roto_core::parse(&file_contents, file_path)?;
+
+    let mut compiler = IRCompiler::new();
+    roto_fake::register_annotations(&mut compiler.annotations);
+    compiler.register_program(parsed, file_path, &file_contents);
+
+    for warning in compiler.check_annotations()? {
+        eprintln!("{}", warning);
+    }
+
+    compiler.compile_globals()?;
+
+    let mut generator = FakeGenerator::new(&compiler, seed);
+    let value = generator.generate(&args[2]).map_err(BackendError::new)?;
+    println!("{}", serde_json::to_string_pretty(&value).unwrap());
+    Ok(())
+}
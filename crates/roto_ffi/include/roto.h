@@ -0,0 +1,58 @@
+/*
+ * The C API of roto, implemented by the roto_ffi crate (libroto).
+ *
+ * A source is compiled into a RotoCompilation, from which code is generated into a RotoOutput.
+ * Strings returned by these functions belong to the handle they came from and stay valid until
+ * it is freed. Functions that take a handle accept NULL, and return 0, false or NULL for it.
+ */
+
+#ifndef ROTO_H
+#define ROTO_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RotoCompilation RotoCompilation;
+typedef struct RotoOutput RotoOutput;
+
+/* Compiles `length` bytes of UTF-8 at `source`, the contents of the file `file_name` (or NULL),
+ * with the annotations of every backend known. Never returns NULL. */
+RotoCompilation *roto_compile(const uint8_t *source, size_t length, const char *file_name);
+
+/* Whether the source compiled, possibly with warnings. */
+bool roto_compilation_ok(const RotoCompilation *compilation);
+size_t roto_compilation_diagnostic_count(const RotoCompilation *compilation);
+/* The diagnostic at `index`, formatted like `roto check` prints it. NULL if out of range. */
+const char *roto_compilation_diagnostic(const RotoCompilation *compilation, size_t index);
+/* The declared types, none if the source did not compile. */
+size_t roto_compilation_type_count(const RotoCompilation *compilation);
+const char *roto_compilation_type_name(const RotoCompilation *compilation, size_t index);
+/* Frees `compilation`. Outputs generated from it stay valid. */
+void roto_compilation_free(RotoCompilation *compilation);
+
+/* Runs the backend `target`, like "ts" or "py-msgspec", on `compilation`. `options` are
+ * `option_count` strings of the form "key=value", like the options of `roto build`. Never
+ * returns NULL. */
+RotoOutput *roto_generate(const RotoCompilation *compilation, const char *target,
+                          const char *const *options, size_t option_count);
+
+/* Whether the backend generated its files. */
+bool roto_output_ok(const RotoOutput *output);
+size_t roto_output_file_count(const RotoOutput *output);
+/* The path of the file at `index`, relative to the output directory. NULL if out of range. */
+const char *roto_output_file_path(const RotoOutput *output, size_t index);
+const char *roto_output_file_contents(const RotoOutput *output, size_t index);
+size_t roto_output_diagnostic_count(const RotoOutput *output);
+const char *roto_output_diagnostic(const RotoOutput *output, size_t index);
+void roto_output_free(RotoOutput *output);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ROTO_H */
@@ -0,0 +1,278 @@
+//! A C API, declared in `include/roto.h`, so that tools in other languages can link roto instead
+//! of running the `roto` binary.
+//!
+//! Sources are compiled into a [`RotoCompilation`], from which code is generated into a
+//! [`RotoOutput`]. Both own every string they hand out: the strings stay valid until the handle
+//! is freed with [`roto_compilation_free`] or [`roto_output_free`]. Diagnostics are handed out as
+//! `roto check` prints them. Functions that take a handle accept null, and return 0, false or
+//! null for it.
+
+use std::ffi::c_char;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::ptr;
+use std::slice;
+
+use roto_backends::compile;
+use roto_backends::registry;
+use roto_core::backend::BackendConfig;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::frontend::IRCompiler;
+
+/// A compiled source, or the errors that stopped its compilation.
+pub struct RotoCompilation {
+    compiler: Option<IRCompiler>,
+    diagnostics: Vec<CString>,
+    types: Vec<CString>,
+}
+
+/// The files generated by a backend, or the errors that stopped it.
+pub struct RotoOutput {
+    ok: bool,
+    paths: Vec<CString>,
+    contents: Vec<CString>,
+    diagnostics: Vec<CString>,
+}
+
+/// C strings end at the first NUL, which is dropped instead.
+fn c_string(s: impl Into<String>) -> CString {
+    let mut s = s.into();
+    s.retain(|c| c != '\0');
+    CString::new(s).unwrap()
+}
+
+fn c_strings(diagnostics: &[Diagnostic]) -> Vec<CString> {
+    diagnostics
+        .iter()
+        .map(|d| c_string(d.to_string()))
+        .collect()
+}
+
+fn get(strings: &[CString], index: usize) -> *const c_char {
+    strings.get(index).map_or(ptr::null(), |s| s.as_ptr())
+}
+
+/// Reads a NUL terminated UTF-8 string, `None` for null or invalid UTF-8.
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL terminated string.
+unsafe fn read_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// Compiles the `length` bytes of UTF-8 at `source`, the contents of the file `file_name`, with
+/// the annotations of every backend known. Never returns null.
+///
+/// # Safety
+///
+/// `source` must point to `length` readable bytes, and `file_name` must be null or point to a
+/// NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn roto_compile(
+    source: *const u8,
+    length: usize,
+    file_name: *const c_char,
+) -> *mut RotoCompilation {
+    let file_name = read_str(file_name).unwrap_or("<source>");
+    let bytes = if source.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(source, length)
+    };
+    let compilation = match std::str::from_utf8(bytes) {
+        Ok(source) => {
+            let registry = registry();
+            let backends: Vec<_> = registry.iter().collect();
+            let (compiler, diagnostics) = compile(source, file_name, &backends);
+            let types = compiler
+                .iter()
+                .flat_map(|compiler| compiler.iter_globals())
+                .map(|(name, _)| c_string(name.as_str()))
+                .collect();
+            RotoCompilation {
+                compiler,
+                diagnostics: c_strings(&diagnostics),
+                types,
+            }
+        }
+        Err(_) => RotoCompilation {
+            compiler: None,
+            diagnostics: c_strings(&[Diagnostic::error(
+                format!("{} is not UTF-8", file_name),
+                None,
+            )]),
+            types: Vec::new(),
+        },
+    };
+    Box::into_raw(Box::new(compilation))
+}
+
+/// Whether the source compiled, possibly with warnings.
+#[no_mangle]
+pub extern "C" fn roto_compilation_ok(compilation: Option<&RotoCompilation>) -> bool {
+    compilation.is_some_and(|c| c.compiler.is_some())
+}
+
+#[no_mangle]
+pub extern "C" fn roto_compilation_diagnostic_count(
+    compilation: Option<&RotoCompilation>,
+) -> usize {
+    compilation.map_or(0, |c| c.diagnostics.len())
+}
+
+/// The diagnostic at `index`, null if it is out of range.
+#[no_mangle]
+pub extern "C" fn roto_compilation_diagnostic(
+    compilation: Option<&RotoCompilation>,
+    index: usize,
+) -> *const c_char {
+    compilation.map_or(ptr::null(), |c| get(&c.diagnostics, index))
+}
+
+/// The number of declared types, 0 if the source did not compile.
+#[no_mangle]
+pub extern "C" fn roto_compilation_type_count(compilation: Option<&RotoCompilation>) -> usize {
+    compilation.map_or(0, |c| c.types.len())
+}
+
+/// The name of the declared type at `index`, null if it is out of range.
+#[no_mangle]
+pub extern "C" fn roto_compilation_type_name(
+    compilation: Option<&RotoCompilation>,
+    index: usize,
+) -> *const c_char {
+    compilation.map_or(ptr::null(), |c| get(&c.types, index))
+}
+
+/// Frees `compilation`. Outputs generated from it stay valid.
+///
+/// # Safety
+///
+/// `compilation` must be null or returned by [`roto_compile`], and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn roto_compilation_free(compilation: *mut RotoCompilation) {
+    if !compilation.is_null() {
+        drop(Box::from_raw(compilation));
+    }
+}
+
+/// Runs the backend `target` on `compilation`, configured by the `option_count` strings of the
+/// form `key=value` at `options`, like the options of `roto build`. Never returns null.
+///
+/// # Safety
+///
+/// `target` must be null or point to a NUL terminated string, and `options` must point to
+/// `option_count` such strings.
+#[no_mangle]
+pub unsafe extern "C" fn roto_generate(
+    compilation: Option<&RotoCompilation>,
+    target: *const c_char,
+    options: *const *const c_char,
+    option_count: usize,
+) -> *mut RotoOutput {
+    let registry = registry();
+    let result = match (
+        compilation.and_then(|c| c.compiler.as_ref()),
+        read_str(target),
+    ) {
+        (None, _) => Err(vec![Diagnostic::error(
+            "the source did not compile".to_string(),
+            None,
+        )]),
+        (_, None) => Err(vec![Diagnostic::error("no target given".to_string(), None)]),
+        (Some(compiler), Some(target)) => match registry.get(target) {
+            None => Err(vec![Diagnostic::error(
+                format!("Unknown target `{}`", target),
+                None,
+            )]),
+            Some(backend) => {
+                let mut config = BackendConfig::new();
+                if !options.is_null() {
+                    for option in slice::from_raw_parts(options, option_count) {
+                        if let Some(option) = read_str(*option) {
+                            config.set_pair(option);
+                        }
+                    }
+                }
+                backend
+                    .generate(compiler, &config)
+                    .map_err(|error| error.diagnostics)
+            }
+        },
+    };
+    let output = match result {
+        Ok(files) => RotoOutput {
+            ok: true,
+            paths: files.iter().map(|f| c_string(f.path.as_str())).collect(),
+            contents: files.into_iter().map(|f| c_string(f.contents)).collect(),
+            diagnostics: Vec::new(),
+        },
+        Err(diagnostics) => RotoOutput {
+            ok: false,
+            paths: Vec::new(),
+            contents: Vec::new(),
+            diagnostics: c_strings(&diagnostics),
+        },
+    };
+    Box::into_raw(Box::new(output))
+}
+
+/// Whether the backend generated its files.
+#[no_mangle]
+pub extern "C" fn roto_output_ok(output: Option<&RotoOutput>) -> bool {
+    output.is_some_and(|o| o.ok)
+}
+
+#[no_mangle]
+pub extern "C" fn roto_output_file_count(output: Option<&RotoOutput>) -> usize {
+    output.map_or(0, |o| o.paths.len())
+}
+
+/// The path of the file at `index`, relative to the output directory. Null if it is out of
+/// range.
+#[no_mangle]
+pub extern "C" fn roto_output_file_path(
+    output: Option<&RotoOutput>,
+    index: usize,
+) -> *const c_char {
+    output.map_or(ptr::null(), |o| get(&o.paths, index))
+}
+
+/// The contents of the file at `index`, null if it is out of range.
+#[no_mangle]
+pub extern "C" fn roto_output_file_contents(
+    output: Option<&RotoOutput>,
+    index: usize,
+) -> *const c_char {
+    output.map_or(ptr::null(), |o| get(&o.contents, index))
+}
+
+#[no_mangle]
+pub extern "C" fn roto_output_diagnostic_count(output: Option<&RotoOutput>) -> usize {
+    output.map_or(0, |o| o.diagnostics.len())
+}
+
+/// The diagnostic at `index`, null if it is out of range.
+#[no_mangle]
+pub extern "C" fn roto_output_diagnostic(
+    output: Option<&RotoOutput>,
+    index: usize,
+) -> *const c_char {
+    output.map_or(ptr::null(), |o| get(&o.diagnostics, index))
+}
+
+/// Frees `output`.
+///
+/// # Safety
+///
+/// `output` must be null or returned by [`roto_generate`], and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn roto_output_free(output: *mut RotoOutput) {
+    if !output.is_null() {
+        drop(Box::from_raw(output));
+    }
+}
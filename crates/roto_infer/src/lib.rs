@@ -0,0 +1,313 @@
+//! Infers a schema from example JSON documents, as a starting point for writing one.
+//!
+//! The samples are merged into one shape: a field that is missing or `null` in some of them is
+//! optional, integers and floats merge to floats and values of different kinds become a variant
+//! with an option per kind. Every object becomes a declaration named after the field it was found
+//! in. Arrays cannot be expressed and are inferred as `unit` with a warning.
+
+use std::collections::HashSet;
+
+use roto_core::ast::{
+    Annotation, AnnotationValue, Builtin, Item, Span, StructField, StructTypeExpression,
+    TypeAliasDeclaration, TypeExpression, VariantOption, VariantTypeExpression, Visibility,
+};
+use roto_core::diagnostics::Diagnostic;
+use roto_core::syntax::identifier;
+use serde_json::{Map, Value};
+
+/// The declarations inferred from the samples, and what could not be inferred faithfully.
+#[derive(Debug)]
+pub struct Inference {
+    pub items: Vec<Item>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Infers the declaration `name` from `samples`, and a declaration for every object in them.
+pub fn infer(name: &str, samples: &[Value]) -> Inference {
+    let shape = samples.iter().filter_map(Shape::of).reduce(Shape::merge);
+    let mut writer = Writer {
+        items: Vec::new(),
+        names: Names::default(),
+        diagnostics: Vec::new(),
+    };
+    let name = writer.names.unique(identifier(name));
+    match shape {
+        Some(Shape::Object(object)) => writer.declare_object(name, &object, "$"),
+        shape => {
+            let type_ = match &shape {
+                Some(shape) => writer.type_expression(shape, &name, "$"),
+                None => writer.unsupported("$", "samples that are all null"),
+            };
+            writer.items.push(Some(declaration(name, type_)));
+        }
+    }
+    Inference {
+        items: writer.items.into_iter().flatten().collect(),
+        diagnostics: writer.diagnostics,
+    }
+}
+
+/// What the values found at one place in the samples have in common.
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    Bool,
+    Int,
+    Float,
+    String,
+    Array,
+    Object(Object),
+    /// Values of different kinds, at most one shape per kind.
+    Union(Vec<Shape>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Object {
+    /// How many objects were merged into this one.
+    samples: usize,
+    fields: Vec<(String, Field)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    /// `None` while the field was only ever `null`.
+    shape: Option<Shape>,
+    /// In how many of the objects the field has a value other than `null`.
+    present: usize,
+}
+
+impl Shape {
+    /// The shape of `value`, `None` for `null`.
+    fn of(value: &Value) -> Option<Shape> {
+        Some(match value {
+            Value::Null => return None,
+            Value::Bool(_) => Shape::Bool,
+            Value::Number(number) if number.is_f64() => Shape::Float,
+            Value::Number(_) => Shape::Int,
+            Value::String(_) => Shape::String,
+            Value::Array(_) => Shape::Array,
+            Value::Object(map) => Shape::Object(Object::of(map)),
+        })
+    }
+
+    /// Names the kind of value, shapes of the same kind merge into one.
+    fn kind(&self) -> &'static str {
+        match self {
+            Shape::Bool => "boolean",
+            Shape::Int | Shape::Float => "number",
+            Shape::String => "text",
+            Shape::Array => "array",
+            Shape::Object(_) => "object",
+            Shape::Union(_) => "union",
+        }
+    }
+
+    fn merge(self, other: Shape) -> Shape {
+        match (self, other) {
+            (Shape::Union(mut shapes), other) | (other, Shape::Union(mut shapes)) => {
+                let others = match other {
+                    Shape::Union(others) => others,
+                    other => vec![other],
+                };
+                for other in others {
+                    match shapes.iter().position(|s| s.kind() == other.kind()) {
+                        Some(i) => shapes[i] = shapes[i].clone().merge(other),
+                        None => shapes.push(other),
+                    }
+                }
+                Shape::Union(shapes)
+            }
+            (Shape::Object(a), Shape::Object(b)) => Shape::Object(a.merge(b)),
+            (Shape::Int, Shape::Float) | (Shape::Float, Shape::Int) => Shape::Float,
+            (a, b) if a == b => a,
+            (a, b) => Shape::Union(vec![a, b]),
+        }
+    }
+}
+
+impl Object {
+    fn of(map: &Map<String, Value>) -> Object {
+        Object {
+            samples: 1,
+            fields: map
+                .iter()
+                .map(|(name, value)| {
+                    let shape = Shape::of(value);
+                    let present = usize::from(shape.is_some());
+                    (name.clone(), Field { shape, present })
+                })
+                .collect(),
+        }
+    }
+
+    fn merge(mut self, other: Object) -> Object {
+        self.samples += other.samples;
+        for (name, field) in other.fields {
+            match self.fields.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, existing)) => {
+                    existing.present += field.present;
+                    existing.shape = match (existing.shape.take(), field.shape) {
+                        (Some(a), Some(b)) => Some(a.merge(b)),
+                        (a, b) => a.or(b),
+                    };
+                }
+                None => self.fields.push((name, field)),
+            }
+        }
+        self
+    }
+}
+
+/// Hands out names that were not handed out before, by appending a number.
+#[derive(Default)]
+struct Names {
+    used: HashSet<String>,
+}
+
+impl Names {
+    fn unique(&mut self, name: String) -> String {
+        let mut candidate = name.clone();
+        let mut i = 1;
+        while !self.used.insert(candidate.clone()) {
+            i += 1;
+            candidate = format!("{}{}", name, i);
+        }
+        candidate
+    }
+}
+
+/// `first_name` as `FirstName`, to name the type of a field after it.
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+/// `@rename(name=...)` if the member `name` had to be renamed to become `identifier`.
+fn rename(name: &str, identifier: &str) -> Vec<Annotation> {
+    if name == identifier {
+        return Vec::new();
+    }
+    vec![Annotation {
+        name: "rename".to_string(),
+        args: vec![(
+            "name".to_string(),
+            AnnotationValue::String(name.to_string()),
+        )],
+    }]
+}
+
+fn declaration(name: String, type_: TypeExpression) -> Item {
+    Item::Type(TypeAliasDeclaration {
+        doc: None,
+        annotations: Vec::new(),
+        visibility: Visibility::Private,
+        name,
+        params: Vec::new(),
+        type_,
+        span: Span::default(),
+    })
+}
+
+struct Writer {
+    /// The declarations in the order they were named, parents before the types of their fields.
+    items: Vec<Option<Item>>,
+    names: Names,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Writer {
+    fn warn(&mut self, path: &str, message: String) {
+        self.diagnostics
+            .push(Diagnostic::warning(format!("{}: {}", path, message), None).with_code("infer"));
+    }
+
+    /// Warns that the values at `path` cannot be expressed, and infers them as `unit`.
+    fn unsupported(&mut self, path: &str, what: &str) -> TypeExpression {
+        self.warn(
+            path,
+            format!("{} are not supported, inferred as unit", what),
+        );
+        TypeExpression::Builtin(Builtin::Unit)
+    }
+
+    fn declare_object(&mut self, name: String, object: &Object, path: &str) {
+        let index = self.items.len();
+        self.items.push(None);
+        let type_ = self.object(&name, object, path);
+        self.items[index] = Some(declaration(name, type_));
+    }
+
+    fn object(&mut self, name: &str, object: &Object, path: &str) -> TypeExpression {
+        if object.fields.is_empty() {
+            return self.unsupported(path, "objects without fields");
+        }
+        let mut names = Names::default();
+        let mut fields = Vec::new();
+        for (field_name, field) in &object.fields {
+            let path = format!("{}.{}", path, field_name);
+            let identifier = names.unique(identifier(field_name));
+            let hint = format!("{}{}", name, pascal_case(field_name));
+            let type_ = match &field.shape {
+                Some(shape) => self.type_expression(shape, &hint, &path),
+                None => self.unsupported(&path, "fields that are always null"),
+            };
+            fields.push(StructField {
+                annotations: rename(field_name, &identifier),
+                name: identifier.into(),
+                type_,
+                optional: field.present < object.samples,
+                comment: None,
+                location: None,
+            });
+        }
+        TypeExpression::Struct(StructTypeExpression { fields })
+    }
+
+    /// The type of the values at `path`. Objects are declared as `hint`.
+    fn type_expression(&mut self, shape: &Shape, hint: &str, path: &str) -> TypeExpression {
+        match shape {
+            Shape::Bool => TypeExpression::Builtin(Builtin::Bool),
+            Shape::Int => TypeExpression::Builtin(Builtin::Int),
+            Shape::Float => TypeExpression::Builtin(Builtin::Float),
+            Shape::String => TypeExpression::Builtin(Builtin::String),
+            Shape::Array => self.unsupported(path, "arrays"),
+            Shape::Object(object) => {
+                let name = self.names.unique(identifier(hint));
+                self.declare_object(name.clone(), object, path);
+                TypeExpression::Variable(name.into())
+            }
+            Shape::Union(shapes) => {
+                let kinds: Vec<_> = shapes.iter().map(Shape::kind).collect();
+                self.warn(
+                    path,
+                    format!(
+                        "values are {}, inferred as a variant, which is encoded differently",
+                        kinds.join(" or ")
+                    ),
+                );
+                let variants = shapes
+                    .iter()
+                    .map(|shape| VariantOption {
+                        name: shape.kind().into(),
+                        type_: self.type_expression(
+                            shape,
+                            &format!("{}{}", hint, pascal_case(shape.kind())),
+                            path,
+                        ),
+                        annotations: Vec::new(),
+                        comment: None,
+                        location: None,
+                    })
+                    .collect();
+                TypeExpression::Variant(VariantTypeExpression { variants })
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use roto_core::annotations::AnnotationRegistry;
+use roto_core::annotations::AnnotationSpec;
+use roto_core::annotations::ArgumentKind;
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::OutputFile;
+use roto_core::builtins::BuiltinRegistry;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+/// Registers `@example(value=...)`, which replaces the generated example of a type, a field or a
+/// variant option's payload.
+pub fn register_annotations(registry: &mut AnnotationRegistry) {
+    registry.register(AnnotationSpec::new("example").arg("value", ArgumentKind::Any));
+}
+
+fn example_annotation(annotations: &[ast::Annotation]) -> Option<&ast::AnnotationValue> {
+    annotations
+        .iter()
+        .find(|a| a.name == "example")
+        .and_then(|a| a.arg("value"))
+}
+
+fn builtin_example(builtins: &BuiltinRegistry, builtin: &ast::Builtin) -> Value {
+    match builtin {
+        ast::Builtin::Int => json!(0),
+        ast::Builtin::Float => json!(0.0),
+        ast::Builtin::String => json!("string"),
+        ast::Builtin::Bool => json!(false),
+        ast::Builtin::Unit => Value::Null,
+        ast::Builtin::Custom(_) => builtin_example(builtins, &builtins.representation(builtin)),
+    }
+}
+
+/// Converts an `@example` value. Strings are taken literally for string types and parsed as JSON
+/// otherwise, so examples of structs can be written as `@example(value="{\"id\": 1}")`.
+/// Identifiers stand for `true`, `false` and `null`.
+fn annotation_example(value: &ast::AnnotationValue, is_string: bool) -> Value {
+    match value {
+        ast::AnnotationValue::Int(value) => json!(value),
+        ast::AnnotationValue::String(value) if is_string => json!(value),
+        ast::AnnotationValue::String(value) => {
+            serde_json::from_str(value).unwrap_or_else(|_| json!(value))
+        }
+        ast::AnnotationValue::Ident(ident) => match ident.as_str() {
+            "true" => json!(true),
+            "false" => json!(false),
+            "null" => Value::Null,
+            _ => json!(ident),
+        },
+    }
+}
+
+struct ExampleWriter<'a> {
+    compiler: &'a IRCompiler,
+    include_optional: bool,
+    /// The types whose example is being built, to cut off recursion.
+    stack: Vec<usize>,
+}
+
+impl<'a> ExampleWriter<'a> {
+    fn is_string_builtin(&self, builtin: &ast::Builtin) -> bool {
+        self.compiler.builtins.representation(builtin) == ast::Builtin::String
+    }
+
+    fn is_string(&self, t: &PrimitiveType) -> bool {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.is_string_builtin(builtin),
+            PrimitiveType::Reference(id) => match &self.compiler.named_type(*id).type_ {
+                IRType::Builtin(builtin) => self.is_string_builtin(builtin),
+                IRType::Reference(target) => self.is_string(&PrimitiveType::Reference(*target)),
+                IRType::Struct(_) | IRType::Variant(_) => false,
+            },
+        }
+    }
+
+    /// The example of a member, which prefers the member's own `@example`.
+    fn member_example(
+        &mut self,
+        t: &PrimitiveType,
+        annotations: &[ast::Annotation],
+    ) -> Option<Value> {
+        match example_annotation(annotations) {
+            Some(value) => Some(annotation_example(value, self.is_string(t))),
+            None => self.primitive_example(t),
+        }
+    }
+
+    fn primitive_example(&mut self, t: &PrimitiveType) -> Option<Value> {
+        match t {
+            PrimitiveType::Builtin(builtin) => {
+                Some(builtin_example(&self.compiler.builtins, builtin))
+            }
+            PrimitiveType::Reference(id) => self.example(*id),
+        }
+    }
+
+    /// Builds an example of the type `id`, or returns `None` if every example would be infinite.
+    /// Optional fields that lead back into a type under construction are left out, and variants
+    /// use the first option that has an example.
+    fn example(&mut self, id: usize) -> Option<Value> {
+        let compiler = self.compiler;
+        let named_type = compiler.named_type(id);
+        if let Some(value) = example_annotation(&named_type.metadata.annotations) {
+            let is_string = self.is_string(&PrimitiveType::Reference(id));
+            return Some(annotation_example(value, is_string));
+        }
+        // Variants may be entered a second time, so recursive types get one level of nesting
+        // before an option is picked that ends the recursion.
+        let limit = match named_type.type_ {
+            IRType::Variant(_) => 2,
+            _ => 1,
+        };
+        if self.stack.iter().filter(|other| **other == id).count() >= limit {
+            return None;
+        }
+
+        self.stack.push(id);
+        let example = match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                let mut object = Map::new();
+                let mut complete = true;
+                for field in struct_type.fields.iter() {
+                    if field.optional && !self.include_optional {
+                        continue;
+                    }
+                    let name = match field
+                        .annotations
+                        .iter()
+                        .find(|a| a.name == "rename")
+                        .and_then(|a| a.arg("name"))
+                    {
+                        Some(ast::AnnotationValue::String(name)) => name.clone(),
+                        _ => field.name.to_string(),
+                    };
+                    match self.member_example(&field.type_, &field.annotations) {
+                        Some(value) => {
+                            object.insert(name, value);
+                        }
+                        None if field.optional => {}
+                        None => {
+                            complete = false;
+                            break;
+                        }
+                    }
+                }
+                complete.then_some(Value::Object(object))
+            }
+            // Variants are adjacently tagged: `{ "type": "option", "value": payload }`, where the
+            // value is left out for unit options.
+            IRType::Variant(variant_type) => variant_type.variants.iter().find_map(|option| {
+                if matches!(option.type_, PrimitiveType::Builtin(ast::Builtin::Unit)) {
+                    return Some(json!({ "type": option.name.as_str() }));
+                }
+                let value = self.member_example(&option.type_, &option.annotations)?;
+                Some(json!({ "type": option.name.as_str(), "value": value }))
+            }),
+            IRType::Reference(target) => self.example(*target),
+            IRType::Builtin(builtin) => Some(builtin_example(&self.compiler.builtins, builtin)),
+        };
+        self.stack.pop();
+        example
+    }
+}
+
+/// Generates one example JSON document per global type.
+pub struct JsonExampleBackend {
+    /// Whether optional fields are part of the examples.
+    pub include_optional: bool,
+}
+
+impl Backend for JsonExampleBackend {
+    fn register_annotations(&self, registry: &mut AnnotationRegistry) {
+        register_annotations(registry);
+    }
+
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = ExampleWriter {
+            compiler,
+            include_optional: self.include_optional,
+            stack: Vec::new(),
+        };
+
+        let mut files = Vec::new();
+        let mut diagnostics = Vec::new();
+        for (id, named_type) in compiler.iter_types() {
+            let TypeName::Variable(name) = &named_type.name else {
+                continue;
+            };
+            match writer.example(*id) {
+                Some(example) => {
+                    let mut output = serde_json::to_string_pretty(&example).unwrap();
+                    output.push('\n');
+                    files.push(OutputFile::new(format!("{}.json", name), output));
+                }
+                None => diagnostics.push(Diagnostic::error(
+                    format!("{} has no finite example", name),
+                    named_type.metadata.location.clone(),
+                )),
+            }
+        }
+
+        if !diagnostics.is_empty() {
+            return Err(BackendError::new(diagnostics));
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,92 @@
+//! `schema!`, which compiles a roto schema along with the crate that uses it, so that Rust code
+//! can use the types of a schema without a build script.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use proc_macro::TokenStream;
+use roto_backends::compile;
+use roto_backends::registry;
+use roto_core::backend::BackendConfig;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::diagnostics::Severity;
+use syn::parse_macro_input;
+use syn::LitStr;
+
+/// The backend whose output the macro expands to.
+const TARGET: &str = "wire-rust";
+
+/// Expands to the Rust types of the schema at the given path, relative to the directory of the
+/// crate's `Cargo.toml`, with the encoding and decoding of the roto wire format.
+///
+/// ```ignore
+/// mod user {
+///     roto_macros::schema!("schemas/user.roto");
+/// }
+///
+/// let bytes = user::User { id: "1".to_string() }.to_bytes();
+/// ```
+///
+/// Errors in the schema are reported at the path. The crate is compiled again whenever the schema
+/// changes.
+#[proc_macro]
+pub fn schema(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    match expand(&path.value()) {
+        Ok(tokens) => tokens,
+        Err(message) => syn::Error::new(path.span(), message)
+            .to_compile_error()
+            .into(),
+    }
+}
+
+/// The compiler reports the message as an error already, only the location of each diagnostic is
+/// added.
+fn describe(errors: &[&Diagnostic]) -> String {
+    let lines: Vec<_> = errors
+        .iter()
+        .map(|error| match &error.location {
+            Some(location) => format!("{}: {}", location, error.message),
+            None => error.message.clone(),
+        })
+        .collect();
+    lines.join("\n")
+}
+
+fn expand(path: &str) -> Result<TokenStream, String> {
+    let root = env::var("CARGO_MANIFEST_DIR").map_err(|_| "CARGO_MANIFEST_DIR is not set")?;
+    let file = Path::new(&root).join(path);
+    let source = fs::read_to_string(&file)
+        .map_err(|e| format!("failed to read {}: {}", file.display(), e))?;
+
+    let registry = registry();
+    let backend = registry.get(TARGET).unwrap();
+    let (compiler, diagnostics) = compile(&source, path, &[backend]);
+    let Some(compiler) = compiler else {
+        let errors: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .collect();
+        return Err(describe(&errors));
+    };
+    let files = backend
+        .generate(&compiler, &BackendConfig::new())
+        .map_err(|error| describe(&error.diagnostics.iter().collect::<Vec<_>>()))?;
+
+    // Inner attributes are not allowed in a macro expansion, the caller decides which lints apply
+    // to the generated module instead.
+    let code: Vec<&str> = files
+        .iter()
+        .flat_map(|file| file.contents.lines())
+        .filter(|line| !line.starts_with("#!["))
+        .collect();
+    // `include_bytes!` makes cargo track the schema like a source file of the crate.
+    let tracked = format!(
+        "const _: &[u8] = include_bytes!({:?});\n",
+        file.display().to_string()
+    );
+    (tracked + &code.join("\n"))
+        .parse()
+        .map_err(|error| format!("{} generated invalid Rust: {}", TARGET, error))
+}
@@ -0,0 +1,116 @@
+//! Node.js bindings, published as `@roto/core`, so that JavaScript tools can compile schemas and
+//! generate code without running the `roto` binary, like a Vite plugin that regenerates
+//! TypeScript types whenever a schema changes.
+
+use std::collections::HashMap;
+
+use napi_derive::napi;
+use roto_backends::compile;
+use roto_backends::registry;
+use roto_core::backend::BackendConfig;
+use roto_core::diagnostics::Diagnostic;
+
+/// A diagnostic as `roto --error-format json` reports it. Lines and columns start at 1, the
+/// location is missing for problems that are not tied to the source.
+#[napi(object)]
+pub struct JsDiagnostic {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub end_line: Option<u32>,
+    pub end_column: Option<u32>,
+    /// `error` or `warning`.
+    pub severity: String,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+impl From<&Diagnostic> for JsDiagnostic {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        let location = diagnostic.location.as_ref();
+        JsDiagnostic {
+            file: location.map(|location| location.file.clone()),
+            line: location.map(|location| location.line as u32),
+            column: location.map(|location| location.column as u32),
+            end_line: location.map(|location| location.end_line as u32),
+            end_column: location.map(|location| location.end_column as u32),
+            severity: diagnostic.severity.to_string(),
+            code: diagnostic.code.map(str::to_string),
+            message: diagnostic.message.clone(),
+        }
+    }
+}
+
+/// A generated file, with a path relative to the output directory.
+#[napi(object)]
+pub struct JsOutputFile {
+    pub path: String,
+    pub contents: String,
+}
+
+/// What [`generate`] produced: no files if anything failed, and the diagnostics.
+#[napi(object)]
+pub struct GenerateResult {
+    pub files: Vec<JsOutputFile>,
+    pub diagnostics: Vec<JsDiagnostic>,
+}
+
+/// The names of the targets `generate` accepts, sorted.
+#[napi]
+pub fn targets() -> Vec<String> {
+    registry().names().map(str::to_string).collect()
+}
+
+/// Compiles `source`, the contents of `file_name`, with the annotations of every backend known.
+/// Returns the diagnostics, empty if the source compiles cleanly.
+#[napi(js_name = "compile")]
+pub fn compile_schema(source: String, file_name: String) -> Vec<JsDiagnostic> {
+    let registry = registry();
+    let backends: Vec<_> = registry.iter().collect();
+    let (_, diagnostics) = compile(&source, &file_name, &backends);
+    diagnostics.iter().map(JsDiagnostic::from).collect()
+}
+
+/// Compiles `source`, the contents of `file_name`, and runs the backend `target` on it,
+/// configured by `options` like the options of `roto build`.
+#[napi]
+pub fn generate(
+    source: String,
+    file_name: String,
+    target: String,
+    options: Option<HashMap<String, String>>,
+) -> GenerateResult {
+    let registry = registry();
+    let mut diagnostics = Vec::new();
+    let mut files = Vec::new();
+    match registry.get(&target) {
+        None => diagnostics.push(Diagnostic::error(
+            format!("Unknown target `{}`", target),
+            None,
+        )),
+        Some(backend) => {
+            let (compiler, compiled) = compile(&source, &file_name, &[backend]);
+            diagnostics = compiled;
+            if let Some(compiler) = compiler {
+                let mut config = BackendConfig::new();
+                for (key, value) in options.unwrap_or_default() {
+                    config.set(key, value);
+                }
+                match backend.generate(&compiler, &config) {
+                    Ok(generated) => files = generated,
+                    Err(error) => diagnostics.extend(error.diagnostics),
+                }
+            }
+        }
+    }
+    GenerateResult {
+        files: files
+            .into_iter()
+            .map(|file| JsOutputFile {
+                path: file.path,
+                contents: file.contents,
+            })
+            .collect(),
+        diagnostics: diagnostics.iter().map(JsDiagnostic::from).collect(),
+    }
+}
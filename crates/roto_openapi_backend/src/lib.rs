@@ -0,0 +1,196 @@
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::OutputFile;
+use roto_core::backend::TypeNaming;
+use roto_core::backend::Worklist;
+use roto_core::builtins::BuiltinRegistry;
+use roto_core::frontend::IRCompiler;
+use roto_core::frontend::TypeAllocator;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeMetadata;
+use roto_core::ir::TypeName;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+}
+
+pub struct SchemaWriter<'a> {
+    pub name_allocator: &'a mut TypeNameAllocator,
+    pub allocator: &'a TypeAllocator,
+    pub builtins: &'a BuiltinRegistry,
+    pub worklist: Worklist,
+    /// What the names of referenced schemas are appended to, like `#/components/schemas/`.
+    pub reference_prefix: &'a str,
+}
+
+impl<'a> SchemaWriter<'a> {
+    pub fn allocate_name(&mut self, type_name: &TypeName) -> String {
+        self.name_allocator.allocate_name(type_name)
+    }
+
+    fn convert_builtin(&self, t: &ast::Builtin) -> Value {
+        match t {
+            ast::Builtin::Int => json!({ "type": "integer" }),
+            ast::Builtin::Float => json!({ "type": "number" }),
+            ast::Builtin::String => json!({ "type": "string" }),
+            ast::Builtin::Bool => json!({ "type": "boolean" }),
+            ast::Builtin::Unit => json!({ "type": "null" }),
+            // Lowerings are schemas, like `{"type": "string", "format": "decimal"}`.
+            ast::Builtin::Custom(id) => self
+                .builtins
+                .lower(*id, "openapi")
+                .and_then(|schema| serde_json::from_str(&schema).ok())
+                .unwrap_or_else(|| self.convert_builtin(&self.builtins.representation(t))),
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> Value {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin),
+            PrimitiveType::Reference(id) => {
+                let r = self.allocator.get(*id).unwrap();
+                self.worklist.push_back(r);
+                let name = self.allocate_name(&r.name);
+                json!({ "$ref": format!("{}{}", self.reference_prefix, name) })
+            }
+        }
+    }
+
+    fn describe(schema: &mut Value, description: Option<&String>) {
+        if let (Some(description), Value::Object(map)) = (description, schema) {
+            map.insert("description".to_string(), json!(description));
+        }
+    }
+
+    pub fn convert_named_ir_type(&mut self, t: &IRType, metadata: &TypeMetadata) -> Value {
+        let mut schema = match t {
+            IRType::Struct(struct_type) => {
+                let mut properties = Map::new();
+                let mut required = Vec::new();
+                for field in struct_type.fields.iter() {
+                    let rename = field
+                        .annotations
+                        .iter()
+                        .find(|a| a.name == "rename")
+                        .and_then(|a| a.arg("name"));
+                    let name = match rename {
+                        Some(ast::AnnotationValue::String(name)) => name.clone(),
+                        _ => field.name.to_string(),
+                    };
+                    let mut property = self.convert_primitive_type(&field.type_);
+                    Self::describe(&mut property, field.comment.as_ref());
+                    if field.annotations.iter().any(|a| a.name == "deprecated") {
+                        property["deprecated"] = json!(true);
+                    }
+                    if !field.optional {
+                        required.push(json!(name));
+                    }
+                    properties.insert(name, property);
+                }
+                let mut schema = json!({ "type": "object", "properties": properties });
+                if !required.is_empty() {
+                    schema["required"] = Value::Array(required);
+                }
+                schema
+            }
+            // Variants are adjacently tagged: `{ "type": "option", "value": payload }`, where the
+            // value is left out for unit options.
+            IRType::Variant(variant_type) => {
+                let mut options = Vec::new();
+                for option in variant_type.variants.iter() {
+                    let mut properties = Map::new();
+                    properties.insert("type".to_string(), json!({ "const": option.name.as_str() }));
+                    let mut required = vec![json!("type")];
+                    if !matches!(option.type_, PrimitiveType::Builtin(ast::Builtin::Unit)) {
+                        properties.insert(
+                            "value".to_string(),
+                            self.convert_primitive_type(&option.type_),
+                        );
+                        required.push(json!("value"));
+                    }
+                    let mut schema = json!({
+                        "type": "object",
+                        "properties": properties,
+                        "required": required,
+                    });
+                    Self::describe(&mut schema, option.comment.as_ref());
+                    options.push(schema);
+                }
+                json!({ "oneOf": options })
+            }
+            IRType::Reference(id) => self.convert_primitive_type(&PrimitiveType::Reference(*id)),
+            IRType::Builtin(builtin) => self.convert_builtin(builtin),
+        };
+        Self::describe(&mut schema, metadata.doc.as_ref());
+        if metadata.annotation("deprecated").is_some() {
+            schema["deprecated"] = json!(true);
+        }
+        if let Some(format) = metadata.format() {
+            schema["format"] = json!(format);
+        }
+        schema
+    }
+}
+
+/// Emits a `components.schemas` fragment of an OpenAPI 3.1 document.
+pub struct OpenApiBackend {
+    pub format: OutputFormat,
+}
+
+impl Backend for OpenApiBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut type_name_allocator = TypeNameAllocator::new()
+            .with_declared(compiler)
+            .with_naming(naming.clone());
+        let mut writer = SchemaWriter {
+            name_allocator: &mut type_name_allocator,
+            allocator: compiler.allocator(),
+            builtins: &compiler.builtins,
+            worklist: Worklist::new(compiler),
+            reference_prefix: "#/components/schemas/",
+        };
+
+        let mut schemas = Map::new();
+        while let Some(NamedIRType {
+            name,
+            type_: t,
+            metadata,
+        }) = writer.worklist.pop()
+        {
+            let schema_name = writer.allocate_name(&name);
+            let schema = writer.convert_named_ir_type(&t, &metadata);
+            schemas.insert(schema_name, schema);
+        }
+
+        let document = json!({ "components": { "schemas": schemas } });
+        let file = match self.format {
+            OutputFormat::Json => {
+                let mut output = serde_json::to_string_pretty(&document).unwrap();
+                output.push('\n');
+                OutputFile::new("components.json", output)
+            }
+            OutputFormat::Yaml => {
+                OutputFile::new("components.yaml", serde_yaml::to_string(&document).unwrap())
+            }
+        };
+        Ok(vec![file])
+    }
+}
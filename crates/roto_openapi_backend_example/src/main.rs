@@ -0,0 +1,51 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use roto_core::backend::Backend;
+use roto_core::frontend::IRCompiler;
+use roto_core::RotoError;
+use roto_openapi_backend::OpenApiBackend;
+use roto_openapi_backend::OutputFormat;
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), RotoError> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 && !(args.len() == 3 && args[2] == "--yaml") {
+        eprintln!("Usage: {} <file_path> [--yaml]", args[0]);
+        process::exit(1);
+    }
+
+    let file_path = &args[1];
+    let file_contents = fs::read_to_string(file_path).map_err(|e| RotoError::io(file_path, e))?;
+
+    let parsed = roto_core::parse(&file_contents, file_path)?;
+
+    let format = if args.len() == 3 {
+        OutputFormat::Yaml
+    } else {
+        OutputFormat::Json
+    };
+    let backend = OpenApiBackend { format };
+    let mut compiler = IRCompiler::new();
+    backend.register_annotations(&mut compiler.annotations);
+    compiler.register_program(parsed, file_path, &file_contents);
+
+    for warning in compiler.check_annotations()? {
+        eprintln!("{}", warning);
+    }
+
+    compiler.compile_globals()?;
+
+    let files = backend.generate(&compiler)?;
+    for file in files {
+        print!("{}", file.contents);
+    }
+    Ok(())
+}
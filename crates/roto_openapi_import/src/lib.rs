@@ -0,0 +1,455 @@
+//! Imports the `components.schemas` of an OpenAPI 3.x document as roto declarations, the
+//! reverse of `roto_openapi_backend`.
+//!
+//! Objects become structs, `oneOf` and string enums become variants and `allOf` becomes an
+//! intersection. Descriptions are kept as doc comments. Schemas roto cannot express, like arrays
+//! and maps, are imported as `unit` with a warning, so that every reference stays valid and the
+//! result compiles.
+
+use std::collections::{HashMap, HashSet};
+
+use roto_core::ast::{
+    Annotation, AnnotationValue, Builtin, Item, Span, StructField, StructTypeExpression,
+    TypeAliasDeclaration, TypeExpression, VariantOption, VariantTypeExpression, Visibility,
+};
+use roto_core::diagnostics::Diagnostic;
+use roto_core::syntax::identifier;
+use serde_json::{Map, Value};
+
+const SCHEMAS: &str = "#/components/schemas/";
+
+/// Keywords that are understood, or that describe a schema without changing its shape.
+const KNOWN_KEYWORDS: [&str; 22] = [
+    "$ref",
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "enum",
+    "type",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "nullable",
+    "description",
+    "deprecated",
+    "title",
+    "format",
+    "example",
+    "examples",
+    "default",
+    "readOnly",
+    "writeOnly",
+    "externalDocs",
+    "xml",
+];
+
+/// The declarations of an imported document, and what could not be imported faithfully.
+#[derive(Debug)]
+pub struct Import {
+    pub items: Vec<Item>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Imports an OpenAPI document in JSON or YAML.
+pub fn import(source: &str) -> Result<Import, Diagnostic> {
+    let document: Value = if source.trim_start().starts_with('{') {
+        serde_json::from_str(source).map_err(|e| e.to_string())
+    } else {
+        serde_yaml::from_str(source).map_err(|e| e.to_string())
+    }
+    .map_err(|error| Diagnostic::error(format!("invalid OpenAPI document: {}", error), None))?;
+    import_document(&document)
+}
+
+/// Imports the schemas of a parsed OpenAPI document. Only 3.x documents are supported.
+pub fn import_document(document: &Value) -> Result<Import, Diagnostic> {
+    let version = document.get("openapi").and_then(Value::as_str);
+    if !version.is_some_and(|version| version.starts_with("3.")) {
+        return Err(Diagnostic::error(
+            "only OpenAPI 3.x documents are supported, `openapi` is missing or not 3.x".to_string(),
+            None,
+        ));
+    }
+    let schemas = document
+        .pointer("/components/schemas")
+        .and_then(Value::as_object);
+    let mut names = Names::default();
+    let mut importer = Importer {
+        declared: schemas
+            .into_iter()
+            .flatten()
+            .map(|(name, _)| (name.clone(), names.unique(identifier(name))))
+            .collect(),
+        diagnostics: Vec::new(),
+    };
+    let items = schemas
+        .into_iter()
+        .flatten()
+        .map(|(name, schema)| {
+            let pointer = format!("{}{}", SCHEMAS, escape(name));
+            let declared = importer.declared[name].clone();
+            Item::Type(importer.declaration(declared, schema, &pointer))
+        })
+        .collect();
+    Ok(Import {
+        items,
+        diagnostics: importer.diagnostics,
+    })
+}
+
+/// Escapes `segment` for use in a JSON pointer.
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// Hands out names that were not handed out before, by appending a number.
+#[derive(Default)]
+struct Names {
+    used: HashSet<String>,
+}
+
+impl Names {
+    fn unique(&mut self, name: String) -> String {
+        let mut candidate = name.clone();
+        let mut i = 1;
+        while !self.used.insert(candidate.clone()) {
+            i += 1;
+            candidate = format!("{}{}", name, i);
+        }
+        candidate
+    }
+}
+
+/// `@rename(name=...)` if the member `name` had to be renamed to become `identifier`.
+fn rename(name: &str, identifier: &str) -> Vec<Annotation> {
+    if name == identifier {
+        return Vec::new();
+    }
+    vec![Annotation {
+        name: "rename".to_string(),
+        args: vec![(
+            "name".to_string(),
+            AnnotationValue::String(name.to_string()),
+        )],
+    }]
+}
+
+fn description(schema: &Value) -> Option<String> {
+    let description = schema.get("description")?.as_str()?.trim();
+    if description.is_empty() {
+        return None;
+    }
+    Some(
+        description
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+fn deprecated(schema: &Value) -> bool {
+    schema.get("deprecated") == Some(&Value::Bool(true))
+}
+
+/// `nullable: true` of OpenAPI 3.0, or `null` among the types of OpenAPI 3.1.
+fn nullable(schema: &Value) -> bool {
+    schema.get("nullable") == Some(&Value::Bool(true))
+        || schema
+            .get("type")
+            .and_then(Value::as_array)
+            .is_some_and(|types| types.iter().any(|t| t == "null"))
+}
+
+fn unit() -> TypeExpression {
+    TypeExpression::Builtin(Builtin::Unit)
+}
+
+struct Importer {
+    /// The name each schema is declared as.
+    declared: HashMap<String, String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Importer {
+    fn warn(&mut self, pointer: &str, message: impl Into<String>) {
+        self.diagnostics.push(
+            Diagnostic::warning(format!("{}: {}", pointer, message.into()), None)
+                .with_code("unsupported-openapi"),
+        );
+    }
+
+    /// Warns that the schema at `pointer` cannot be imported, and imports it as `unit`.
+    fn unsupported(&mut self, pointer: &str, what: &str) -> TypeExpression {
+        self.warn(
+            pointer,
+            format!("{} are not supported, imported as unit", what),
+        );
+        unit()
+    }
+
+    fn declaration(&mut self, name: String, schema: &Value, pointer: &str) -> TypeAliasDeclaration {
+        let mut annotations = Vec::new();
+        if deprecated(schema) {
+            annotations.push(Annotation {
+                name: "deprecated".to_string(),
+                args: Vec::new(),
+            });
+        }
+        if nullable(schema) {
+            self.warn(pointer, "nullable types are not supported, null is ignored");
+        }
+        TypeAliasDeclaration {
+            doc: description(schema),
+            annotations,
+            visibility: Visibility::Private,
+            name,
+            params: Vec::new(),
+            type_: self.type_expression(schema, pointer),
+            span: Span::default(),
+        }
+    }
+
+    fn check_keywords(&mut self, schema: &Map<String, Value>, pointer: &str) {
+        for keyword in schema.keys() {
+            if !KNOWN_KEYWORDS.contains(&keyword.as_str()) && !keyword.starts_with("x-") {
+                self.warn(
+                    pointer,
+                    format!("`{}` is not supported and is ignored", keyword),
+                );
+            }
+        }
+    }
+
+    fn type_expression(&mut self, schema: &Value, pointer: &str) -> TypeExpression {
+        let Some(map) = schema.as_object() else {
+            return self.unsupported(pointer, "boolean schemas");
+        };
+        self.check_keywords(map, pointer);
+        if let Some(reference) = map.get("$ref") {
+            let name = reference.as_str().and_then(|r| r.strip_prefix(SCHEMAS));
+            return match name.and_then(|name| self.declared.get(&unescape(name))) {
+                Some(name) => TypeExpression::Variable(name.into()),
+                None => self.unsupported(pointer, "references outside of components.schemas"),
+            };
+        }
+        if let Some(members) = map.get("allOf").and_then(Value::as_array) {
+            return self.intersection(members, pointer);
+        }
+        if let Some(options) = map.get("oneOf").and_then(Value::as_array) {
+            return self.variant(options, &format!("{}/oneOf", pointer));
+        }
+        if let Some(options) = map.get("anyOf").and_then(Value::as_array) {
+            self.warn(
+                pointer,
+                "anyOf is imported like oneOf, as exactly one of the options",
+            );
+            return self.variant(options, &format!("{}/anyOf", pointer));
+        }
+        if let Some(values) = map.get("enum").and_then(Value::as_array) {
+            if values.iter().all(Value::is_string) && !values.is_empty() {
+                return self.string_enum(values);
+            }
+            self.warn(
+                pointer,
+                "only enums of strings are supported, the values are ignored",
+            );
+        }
+        let type_ = match map.get("type") {
+            Some(Value::String(type_)) => Some(type_.as_str()),
+            Some(Value::Array(types)) => {
+                let types: Vec<_> = types.iter().filter(|t| *t != "null").collect();
+                match types.as_slice() {
+                    [] => Some("null"),
+                    [type_] => type_.as_str(),
+                    _ => return self.unsupported(pointer, "schemas of several types"),
+                }
+            }
+            _ => None,
+        };
+        match type_ {
+            Some("object") => self.object(map, pointer),
+            None if map.contains_key("properties") => self.object(map, pointer),
+            Some("integer") => TypeExpression::Builtin(Builtin::Int),
+            Some("number") => TypeExpression::Builtin(Builtin::Float),
+            Some("string") => TypeExpression::Builtin(Builtin::String),
+            Some("boolean") => TypeExpression::Builtin(Builtin::Bool),
+            Some("null") => unit(),
+            Some("array") => self.unsupported(pointer, "arrays"),
+            Some(_) => self.unsupported(pointer, "unknown types"),
+            None => self.unsupported(pointer, "schemas without a type"),
+        }
+    }
+
+    fn object(&mut self, schema: &Map<String, Value>, pointer: &str) -> TypeExpression {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let additional = schema
+            .get("additionalProperties")
+            .is_some_and(|additional| *additional != Value::Bool(false));
+        let Some(properties) = properties.filter(|properties| !properties.is_empty()) else {
+            if additional {
+                return self.unsupported(pointer, "maps");
+            }
+            return self.unsupported(pointer, "objects without properties");
+        };
+        if additional {
+            self.warn(
+                pointer,
+                "additionalProperties is not supported and is ignored",
+            );
+        }
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        let mut names = Names::default();
+        let mut fields = Vec::new();
+        for (name, property) in properties {
+            let pointer = format!("{}/properties/{}", pointer, escape(name));
+            let field_name = names.unique(identifier(name));
+            let mut annotations = rename(name, &field_name);
+            if deprecated(property) {
+                annotations.push(Annotation {
+                    name: "deprecated".to_string(),
+                    args: Vec::new(),
+                });
+            }
+            let optional = !required.contains(&name.as_str()) || nullable(property);
+            if nullable(property) {
+                self.warn(&pointer, "nullable fields are imported as optional fields");
+            }
+            fields.push(StructField {
+                name: field_name.into(),
+                type_: self.type_expression(property, &pointer),
+                optional,
+                annotations,
+                comment: description(property),
+                location: None,
+            });
+        }
+        TypeExpression::Struct(StructTypeExpression { fields })
+    }
+
+    fn intersection(&mut self, members: &[Value], pointer: &str) -> TypeExpression {
+        let mut members = members
+            .iter()
+            .enumerate()
+            .map(|(i, member)| self.type_expression(member, &format!("{}/allOf/{}", pointer, i)));
+        let Some(first) = members.next() else {
+            return self.unsupported(pointer, "empty allOfs");
+        };
+        let members: Vec<_> = members.collect();
+        members.into_iter().fold(first, intersect)
+    }
+
+    fn string_enum(&mut self, values: &[Value]) -> TypeExpression {
+        let mut names = Names::default();
+        let variants = values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|value| {
+                let name = names.unique(identifier(value));
+                VariantOption {
+                    annotations: rename(value, &name),
+                    name: name.into(),
+                    type_: unit(),
+                    comment: None,
+                    location: None,
+                }
+            })
+            .collect();
+        TypeExpression::Variant(VariantTypeExpression { variants })
+    }
+
+    /// `options` tagged like `roto_openapi_backend` writes variants, `{ "type": "name",
+    /// "value": payload }`, import as they were. Other options are named after the schema they
+    /// refer to, or numbered.
+    fn variant(&mut self, options: &[Value], pointer: &str) -> TypeExpression {
+        if options.is_empty() {
+            return self.unsupported(pointer, "empty oneOfs");
+        }
+        let tagged: Option<Vec<_>> = options.iter().map(tagged_option).collect();
+        let mut names = Names::default();
+        let mut variants = Vec::new();
+        match tagged {
+            Some(tagged) => {
+                for (i, (tag, value)) in tagged.into_iter().enumerate() {
+                    let pointer = format!("{}/{}", pointer, i);
+                    let name = names.unique(identifier(tag));
+                    let type_ = match value {
+                        Some(value) => {
+                            self.type_expression(value, &format!("{}/properties/value", pointer))
+                        }
+                        None => unit(),
+                    };
+                    variants.push(VariantOption {
+                        annotations: rename(tag, &name),
+                        name: name.into(),
+                        type_,
+                        comment: description(&options[i]),
+                        location: None,
+                    });
+                }
+            }
+            None => {
+                self.warn(
+                    pointer,
+                    "the options are not tagged with `type` and `value` like roto variants, the encoding differs",
+                );
+                for (i, option) in options.iter().enumerate() {
+                    let pointer = format!("{}/{}", pointer, i);
+                    let referenced = option
+                        .get("$ref")
+                        .and_then(Value::as_str)
+                        .and_then(|r| r.strip_prefix(SCHEMAS));
+                    let name = match referenced.and_then(|name| self.declared.get(&unescape(name)))
+                    {
+                        Some(name) => name.clone(),
+                        None => format!("option{}", i),
+                    };
+                    variants.push(VariantOption {
+                        name: names.unique(name).into(),
+                        type_: self.type_expression(option, &pointer),
+                        annotations: Vec::new(),
+                        comment: description(option),
+                        location: None,
+                    });
+                }
+            }
+        }
+        TypeExpression::Variant(VariantTypeExpression { variants })
+    }
+}
+
+/// `left & right`. The right operand of `&` has to be an atom, so a nested intersection is
+/// flattened into a chain that groups to the left.
+fn intersect(left: TypeExpression, right: TypeExpression) -> TypeExpression {
+    match right {
+        TypeExpression::Intersection(a, b) => intersect(intersect(left, *a), *b),
+        right => TypeExpression::Intersection(Box::new(left), Box::new(right)),
+    }
+}
+
+/// The tag and payload schema of an option written as `{ "type": { "const": tag }, "value":
+/// payload }`, or `None` if the option is shaped differently.
+fn tagged_option(option: &Value) -> Option<(&str, Option<&Value>)> {
+    let properties = option.get("properties")?.as_object()?;
+    let tag = properties.get("type")?;
+    let tag = match (tag.get("const"), tag.get("enum")) {
+        (Some(Value::String(tag)), _) => tag,
+        (None, Some(Value::Array(values))) if values.len() == 1 => values[0].as_str()?,
+        _ => return None,
+    };
+    if properties.keys().any(|key| key != "type" && key != "value") {
+        return None;
+    }
+    Some((tag, properties.get("value")))
+}
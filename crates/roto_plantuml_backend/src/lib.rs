@@ -0,0 +1,156 @@
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::OutputFile;
+use roto_core::backend::TypeNaming;
+use roto_core::frontend::IRCompiler;
+use roto_core::intern;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+use roto_core::symbols::qualify_name;
+use roto_core::symbols::split_qualified_name;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// Writes one class per named type. Class names keep their module path, which PlantUML turns into
+/// nested packages.
+struct DiagramWriter<'a> {
+    compiler: &'a IRCompiler,
+    name_allocator: TypeNameAllocator,
+    classes: Vec<String>,
+    relations: Vec<String>,
+}
+
+impl<'a> DiagramWriter<'a> {
+    fn class_name(&mut self, type_name: &TypeName) -> String {
+        match type_name {
+            TypeName::Variable(name) => name.to_string(),
+            TypeName::Generic(name, _) => {
+                let module = split_qualified_name(name).0;
+                let name = self.name_allocator.allocate_name(type_name);
+                // The allocated name starts with the module path, joined by underscores.
+                let prefix = if module.is_empty() {
+                    0
+                } else {
+                    module.len() + 1
+                };
+                qualify_name(module, &name[prefix..])
+            }
+            TypeName::Temporary(owner, path) => {
+                format!("{}_{}", self.class_name(owner), intern::join(path, "_"))
+            }
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> String {
+        match t {
+            PrimitiveType::Builtin(builtin) => builtin.to_string(),
+            PrimitiveType::Reference(id) => {
+                let compiler = self.compiler;
+                let name = self.class_name(&compiler.named_type(*id).name);
+                split_qualified_name(&name).1.to_string()
+            }
+        }
+    }
+
+    fn relate(&mut self, from: &str, to: &PrimitiveType, arrow: &str, label: &str) {
+        if let PrimitiveType::Reference(id) = to {
+            let compiler = self.compiler;
+            let to = self.class_name(&compiler.named_type(*id).name);
+            self.relations
+                .push(format!("{} {} {} : {}\n", from, arrow, to, label));
+        }
+    }
+
+    fn convert_named_ir_type(&mut self, named_type: &NamedIRType) {
+        let name = self.class_name(&named_type.name);
+        let mut result = String::new();
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                result.push_str(&format!("class {} <<struct>> {{\n", name));
+                for field in struct_type.fields.iter() {
+                    let field_type = self.convert_primitive_type(&field.type_);
+                    let optional = if field.optional { "?" } else { "" };
+                    result.push_str(&format!("  {}{} : {}\n", field.name, optional, field_type));
+                    let arrow = if field.optional {
+                        "--> \"0..1\""
+                    } else {
+                        "-->"
+                    };
+                    self.relate(&name, &field.type_, arrow, &field.name);
+                }
+                result.push_str("}\n");
+            }
+            IRType::Variant(variant_type)
+                if variant_type
+                    .variants
+                    .iter()
+                    .all(|o| matches!(o.type_, PrimitiveType::Builtin(ast::Builtin::Unit))) =>
+            {
+                result.push_str(&format!("enum {} {{\n", name));
+                for option in variant_type.variants.iter() {
+                    result.push_str(&format!("  {}\n", option.name));
+                }
+                result.push_str("}\n");
+            }
+            IRType::Variant(variant_type) => {
+                result.push_str(&format!("class {} <<variant>> {{\n", name));
+                for option in variant_type.variants.iter() {
+                    let option_type = self.convert_primitive_type(&option.type_);
+                    result.push_str(&format!("  {}({})\n", option.name, option_type));
+                    self.relate(&name, &option.type_, "o--", &option.name);
+                }
+                result.push_str("}\n");
+            }
+            IRType::Reference(id) => {
+                result.push_str(&format!("class {} <<alias>>\n", name));
+                let compiler = self.compiler;
+                let target = self.class_name(&compiler.named_type(*id).name);
+                self.relations.push(format!("{} ..> {}\n", name, target));
+            }
+            IRType::Builtin(builtin) => {
+                result.push_str(&format!("class {} <<alias>> {{\n  {}\n}}\n", name, builtin));
+            }
+        }
+        self.classes.push(result);
+    }
+}
+
+/// Emits a PlantUML class diagram of the compiled types. Struct fields and references are
+/// associations, variant options are aggregations and aliases depend on the type they name.
+pub struct PlantUmlBackend;
+
+impl Backend for PlantUmlBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = DiagramWriter {
+            compiler,
+            name_allocator: TypeNameAllocator::new()
+                .with_declared(compiler)
+                .with_naming(naming.clone()),
+            classes: Vec::new(),
+            relations: Vec::new(),
+        };
+
+        for (_id, named_type) in compiler.iter_types() {
+            writer.convert_named_ir_type(named_type);
+        }
+
+        let mut output = vec!["@startuml\nhide empty members\n".to_string()];
+        output.extend(writer.classes);
+        if !writer.relations.is_empty() {
+            output.push(writer.relations.concat());
+        }
+        output.push("@enduml\n".to_string());
+        Ok(vec![OutputFile::new("types.puml", output.join("\n"))])
+    }
+}
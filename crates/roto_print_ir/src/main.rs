@@ -1,49 +1,291 @@
+use std::collections::BTreeSet;
 use std::env;
+use std::fmt::Write;
 use std::fs;
 use std::process;
 
+use roto_core::ast;
 use roto_core::frontend::IRCompiler;
-use roto_core::frontend::TypePrototype;
+use roto_core::ir::IRType;
 use roto_core::ir::NamedIRType;
-use roto_core::parser;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeMetadata;
+use roto_core::ir::TypeName;
+use roto_core::RotoError;
+use serde::Serialize;
 
-fn main() {
+/// What to print, from the command line.
+#[derive(Default)]
+struct Options {
+    file_path: String,
+    /// Prints the types that declarations own inline where they are used, and refers to the
+    /// others by name instead of `reference N`.
+    inline: bool,
+    /// Prints every type after the types it refers to, instead of in id order.
+    dependency_order: bool,
+    /// Only prints these types and the types they refer to.
+    roots: Vec<String>,
+    /// Prints the serialized types as a JSON array instead of as text.
+    json: bool,
+}
+
+/// A compiled type in the output of `--format json`.
+#[derive(Serialize)]
+struct TypeEntry<'a> {
+    id: usize,
+    /// The name as the text format prints it, like `auth.User` or `Page<T=Variable("User")>`.
+    display_name: String,
+    name: &'a TypeName,
+    #[serde(rename = "type")]
+    type_: &'a IRType,
+    metadata: &'a TypeMetadata,
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} <file_path> [--format text|json] [--inline] [--dependency-order] [--root <type>...]",
+        program
+    );
+    process::exit(1);
+}
+
+fn parse_args() -> Options {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} <file_path>", args[0]);
+    let mut options = Options::default();
+    let mut file_path = None;
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--inline" => options.inline = true,
+            "--dependency-order" => options.dependency_order = true,
+            "--format" => match rest.next().map(String::as_str) {
+                Some("text") => options.json = false,
+                Some("json") => options.json = true,
+                _ => usage(&args[0]),
+            },
+            "--root" => match rest.next() {
+                Some(root) => options.roots.push(root.clone()),
+                None => usage(&args[0]),
+            },
+            _ if file_path.is_none() && !arg.starts_with("--") => file_path = Some(arg.clone()),
+            _ => usage(&args[0]),
+        }
+    }
+    // References are ids in JSON, there is nothing to print inline.
+    if options.inline && options.json {
+        usage(&args[0]);
+    }
+    match file_path {
+        Some(file_path) => Options {
+            file_path,
+            ..options
+        },
+        None => usage(&args[0]),
+    }
+}
+
+fn main() {
+    if let Err(error) = run(parse_args()) {
+        eprintln!("{}", error);
         process::exit(1);
     }
+}
 
-    let file_path = &args[1];
-    let file_contents = fs::read_to_string(file_path).expect("Failed to read file");
+fn run(options: Options) -> Result<(), RotoError> {
+    let file_path = &options.file_path;
+    let file_contents = fs::read_to_string(file_path).map_err(|e| RotoError::io(file_path, e))?;
 
-    let parsed = parser::ProgramParser::new()
-        .parse(&file_contents)
-        .expect("Failed to parse content");
+    let parsed = roto_core::parse(&file_contents, file_path)?;
 
     let mut compiler = IRCompiler::new();
-    for decl in parsed {
-        compiler.register_global_type(
-            decl.name,
-            TypePrototype {
-                params: decl.params,
-                type_: decl.type_,
-            },
-        );
+    compiler.register_program(parsed, file_path, &file_contents);
+
+    for warning in compiler.check_annotations()? {
+        eprintln!("{}", warning);
+    }
+
+    compiler.compile_globals()?;
+
+    if !options.roots.is_empty() {
+        let mut roots = Vec::new();
+        for root in &options.roots {
+            let id = compiler.iter_types().find_map(|(id, t)| match &t.name {
+                TypeName::Variable(name) if name == root.as_str() => Some(*id),
+                _ => None,
+            });
+            match id {
+                Some(id) => roots.push(id),
+                None => {
+                    eprintln!("Unknown root type `{}`", root);
+                    process::exit(1);
+                }
+            }
+        }
+        compiler.retain_reachable(&roots);
+    }
+
+    let printer = Printer {
+        compiler: &compiler,
+        inline: options.inline,
+    };
+    let ids = if options.dependency_order {
+        printer.dependency_order()
+    } else {
+        compiler.iter_types().map(|(id, _)| *id).collect()
+    };
+    if options.json {
+        let entries: Vec<_> = ids
+            .into_iter()
+            .map(|id| {
+                let named_type = compiler.named_type(id);
+                TypeEntry {
+                    id,
+                    display_name: named_type.name.to_string(),
+                    name: &named_type.name,
+                    type_: &named_type.type_,
+                    metadata: &named_type.metadata,
+                }
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries).unwrap());
+        return Ok(());
+    }
+    for id in ids {
+        let NamedIRType {
+            name,
+            type_: t,
+            metadata,
+        } = compiler.named_type(id);
+        if printer.is_inlined(id) {
+            continue;
+        }
+        if let Some(doc) = &metadata.doc {
+            for line in doc.lines() {
+                println!("// {}", line);
+            }
+        }
+        for annotation in &metadata.annotations {
+            println!("{}", annotation);
+        }
+        if options.inline {
+            let mut body = String::new();
+            printer.write_type(&mut body, t, 0, &mut BTreeSet::from([id]));
+            println!("type {}#{} = {}", name, id, body);
+        } else {
+            println!("type {}#{} = {}", name, id, t);
+        }
     }
-    
-    let globals = compiler
-        .iter_globals()
-        .filter(|(_, t)| t.params.is_empty())
-        .map(|(name, t)| (name.clone(), t.type_.clone()))
-        .collect::<Vec<_>>();
-    for (name, expr) in globals {
-        compiler.compile_global(name.clone(), &expr);
+    Ok(())
+}
+
+struct Printer<'a> {
+    compiler: &'a IRCompiler,
+    inline: bool,
+}
+
+impl Printer<'_> {
+    /// Whether the type `id` is printed where it is used rather than on its own.
+    fn is_inlined(&self, id: usize) -> bool {
+        self.inline && matches!(self.compiler.named_type(id).name, TypeName::Temporary(..))
     }
 
-    for (i, NamedIRType { name, type_: t }) in compiler.iter_types() {
-        println!("type {}#{} = {}", name, i, t);
+    /// The ids of the types, each after the types it refers to. Types that refer to each other
+    /// are ordered by id.
+    fn dependency_order(&self) -> Vec<usize> {
+        fn visit(
+            compiler: &IRCompiler,
+            id: usize,
+            seen: &mut BTreeSet<usize>,
+            out: &mut Vec<usize>,
+        ) {
+            if !seen.insert(id) {
+                return;
+            }
+            for reference in compiler.named_type(id).type_.references() {
+                visit(compiler, reference, seen, out);
+            }
+            out.push(id);
+        }
+        let mut seen = BTreeSet::new();
+        let mut out = Vec::new();
+        for (id, _) in self.compiler.iter_types() {
+            visit(self.compiler, *id, &mut seen, &mut out);
+        }
+        out
     }
 
+    /// Writes `t` at `indent` levels of two spaces. `expanding` are the types that are being
+    /// written inline, which are referred to by name if they refer to themselves.
+    fn write_type(
+        &self,
+        out: &mut String,
+        t: &IRType,
+        indent: usize,
+        expanding: &mut BTreeSet<usize>,
+    ) {
+        let pad = "  ".repeat(indent + 1);
+        match t {
+            IRType::Struct(s) => {
+                out.push_str("struct {");
+                for field in &s.fields {
+                    self.write_member_header(out, &pad, &field.comment, &field.annotations);
+                    let marker = if field.optional { "?" } else { "" };
+                    write!(out, "\n{}{}{}: ", pad, field.name, marker).unwrap();
+                    self.write_primitive_type(out, &field.type_, indent + 1, expanding);
+                    out.push(',');
+                }
+                write!(out, "\n{}}}", "  ".repeat(indent)).unwrap();
+            }
+            IRType::Variant(v) => {
+                out.push_str("enum {");
+                for option in &v.variants {
+                    self.write_member_header(out, &pad, &option.comment, &option.annotations);
+                    write!(out, "\n{}{}(", pad, option.name).unwrap();
+                    self.write_primitive_type(out, &option.type_, indent + 1, expanding);
+                    out.push_str("),");
+                }
+                write!(out, "\n{}}}", "  ".repeat(indent)).unwrap();
+            }
+            IRType::Reference(id) => {
+                self.write_primitive_type(out, &PrimitiveType::Reference(*id), indent, expanding)
+            }
+            IRType::Builtin(builtin) => write!(out, "{}", builtin).unwrap(),
+        }
+    }
+
+    fn write_member_header(
+        &self,
+        out: &mut String,
+        pad: &str,
+        comment: &Option<String>,
+        annotations: &[ast::Annotation],
+    ) {
+        if let Some(comment) = comment {
+            for line in comment.lines() {
+                write!(out, "\n{}// {}", pad, line).unwrap();
+            }
+        }
+        for annotation in annotations {
+            write!(out, "\n{}{}", pad, annotation).unwrap();
+        }
+    }
 
+    fn write_primitive_type(
+        &self,
+        out: &mut String,
+        t: &PrimitiveType,
+        indent: usize,
+        expanding: &mut BTreeSet<usize>,
+    ) {
+        match t {
+            PrimitiveType::Reference(id) if self.is_inlined(*id) && expanding.insert(*id) => {
+                self.write_type(out, &self.compiler.named_type(*id).type_, indent, expanding);
+                expanding.remove(id);
+            }
+            PrimitiveType::Reference(id) => {
+                write!(out, "{}#{}", self.compiler.named_type(*id).name, id).unwrap()
+            }
+            PrimitiveType::Builtin(builtin) => write!(out, "{}", builtin).unwrap(),
+        }
+    }
 }
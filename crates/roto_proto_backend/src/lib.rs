@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::OutputFile;
+use roto_core::backend::TypeNaming;
+use roto_core::backend::Worklist;
+use roto_core::builtins::BuiltinRegistry;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::frontend::IRCompiler;
+use roto_core::frontend::TypeAllocator;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+use roto_core::lint::Rule;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// The largest field number protobuf allows.
+const MAX_TAG: i64 = 536_870_911;
+/// Field numbers reserved for the protobuf implementation.
+const RESERVED_TAGS: std::ops::RangeInclusive<i64> = 19_000..=19_999;
+
+fn tag(annotations: &[ast::Annotation]) -> Option<i64> {
+    let tag = annotations.iter().find(|a| a.name == "tag")?;
+    match tag.arg("value")? {
+        ast::AnnotationValue::Int(value) => Some(*value),
+        _ => None,
+    }
+}
+
+/// Protobuf has no type aliases, so only structs and variants become messages. References to
+/// aliases are resolved to the type they name.
+pub struct MessageWriter<'a> {
+    pub name_allocator: &'a mut TypeNameAllocator,
+    pub allocator: &'a TypeAllocator,
+    pub builtins: &'a BuiltinRegistry,
+    pub worklist: Worklist,
+    pub diagnostics: Vec<Diagnostic>,
+    pub uses_empty: bool,
+}
+
+impl<'a> MessageWriter<'a> {
+    pub fn allocate_name(&mut self, type_name: &TypeName) -> String {
+        self.name_allocator.allocate_name(type_name)
+    }
+
+    fn convert_builtin(&mut self, t: &ast::Builtin) -> String {
+        match t {
+            ast::Builtin::Int => "int64".to_string(),
+            ast::Builtin::Float => "double".to_string(),
+            ast::Builtin::String => "string".to_string(),
+            ast::Builtin::Bool => "bool".to_string(),
+            ast::Builtin::Unit => {
+                self.uses_empty = true;
+                "google.protobuf.Empty".to_string()
+            }
+            ast::Builtin::Custom(id) => {
+                let builtins = self.builtins;
+                builtins
+                    .lower(*id, "proto")
+                    .unwrap_or_else(|| self.convert_builtin(&builtins.representation(t)))
+            }
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> String {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin),
+            PrimitiveType::Reference(id) => {
+                let r = self.allocator.get(*id).unwrap();
+                match &r.type_ {
+                    IRType::Reference(target) => {
+                        self.convert_primitive_type(&PrimitiveType::Reference(*target))
+                    }
+                    IRType::Builtin(builtin) => self.convert_builtin(builtin),
+                    IRType::Struct(_) | IRType::Variant(_) => {
+                        self.worklist.push_back(r);
+                        self.allocate_name(&r.name)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the field number of `member` and reports missing, invalid and duplicate tags where
+    /// `member` is declared, or else where its type is.
+    fn check_tag(
+        &mut self,
+        named_type: &NamedIRType,
+        member: &str,
+        annotations: &[ast::Annotation],
+        location: Option<&ast::SourceLocation>,
+        seen: &mut HashMap<i64, String>,
+    ) -> i64 {
+        let location = location.or(named_type.metadata.location.as_ref()).cloned();
+        let Some(tag) = tag(annotations) else {
+            self.diagnostics.push(Diagnostic::error(
+                format!("{}.{} has no @tag annotation", named_type.name, member),
+                location,
+            ));
+            return 0;
+        };
+        if !(1..=MAX_TAG).contains(&tag) || RESERVED_TAGS.contains(&tag) {
+            self.diagnostics.push(Diagnostic::error(
+                format!("{}.{} has an invalid tag {}", named_type.name, member, tag),
+                location,
+            ));
+        } else if let Some(other) = seen.insert(tag, member.to_string()) {
+            self.diagnostics.push(Diagnostic::error(
+                format!(
+                    "{}.{} and {}.{} have the same tag {}",
+                    named_type.name, other, named_type.name, member, tag
+                ),
+                location,
+            ));
+        }
+        tag
+    }
+
+    fn write_comment(result: &mut String, indent: &str, comment: &Option<String>) {
+        if let Some(comment) = comment {
+            for line in comment.lines() {
+                result.push_str(&format!("{}// {}\n", indent, line));
+            }
+        }
+    }
+
+    pub fn convert_named_ir_type(&mut self, name: &str, named_type: &NamedIRType) -> String {
+        let mut result = String::new();
+        let mut seen = HashMap::new();
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                Self::write_comment(&mut result, "", &named_type.metadata.doc);
+                result.push_str(&format!("message {} {{\n", name));
+                for field in struct_type.fields.iter() {
+                    Self::write_comment(&mut result, "  ", &field.comment);
+                    let tag = self.check_tag(
+                        named_type,
+                        &field.name,
+                        &field.annotations,
+                        field.location.as_ref(),
+                        &mut seen,
+                    );
+                    let field_type = self.convert_primitive_type(&field.type_);
+                    let label = if field.optional { "optional " } else { "" };
+                    result.push_str(&format!(
+                        "  {}{} {} = {};\n",
+                        label, field_type, field.name, tag
+                    ));
+                }
+                result.push_str("}\n");
+            }
+            IRType::Variant(variant_type) => {
+                Self::write_comment(&mut result, "", &named_type.metadata.doc);
+                result.push_str(&format!("message {} {{\n", name));
+                result.push_str("  oneof value {\n");
+                for option in variant_type.variants.iter() {
+                    Self::write_comment(&mut result, "    ", &option.comment);
+                    let tag = self.check_tag(
+                        named_type,
+                        &option.name,
+                        &option.annotations,
+                        option.location.as_ref(),
+                        &mut seen,
+                    );
+                    let option_type = self.convert_primitive_type(&option.type_);
+                    result.push_str(&format!("    {} {} = {};\n", option_type, option.name, tag));
+                }
+                result.push_str("  }\n");
+                result.push_str("}\n");
+            }
+            IRType::Reference(id) => {
+                // Aliases are not emitted, but the type they name has to be.
+                self.convert_primitive_type(&PrimitiveType::Reference(*id));
+            }
+            IRType::Builtin(_) => {}
+        }
+        result
+    }
+}
+
+pub struct ProtoBackend;
+
+impl Backend for ProtoBackend {
+    fn required_rules(&self) -> &'static [Rule] {
+        &[Rule::MissingTag, Rule::MissingOrdinal]
+    }
+
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut type_name_allocator = TypeNameAllocator::new()
+            .with_declared(compiler)
+            .with_naming(naming.clone());
+        let mut writer = MessageWriter {
+            name_allocator: &mut type_name_allocator,
+            allocator: compiler.allocator(),
+            builtins: &compiler.builtins,
+            worklist: Worklist::new(compiler),
+            diagnostics: Vec::new(),
+            uses_empty: false,
+        };
+
+        let mut messages = Vec::new();
+        while let Some(named_type) = writer.worklist.pop() {
+            let proto_name = writer.allocate_name(&named_type.name);
+            let message = writer.convert_named_ir_type(&proto_name, &named_type);
+            if !message.is_empty() {
+                messages.push(message);
+            }
+        }
+
+        if !writer.diagnostics.is_empty() {
+            return Err(BackendError::new(writer.diagnostics));
+        }
+
+        let mut header = "syntax = \"proto3\";\n".to_string();
+        if writer.uses_empty {
+            header.push_str("\nimport \"google/protobuf/empty.proto\";\n");
+        }
+        messages.insert(0, header);
+        Ok(vec![OutputFile::new("types.proto", messages.join("\n"))])
+    }
+}
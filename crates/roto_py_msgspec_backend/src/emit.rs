@@ -0,0 +1,82 @@
+//! Writing generated files to a sink line by line, instead of building each file as one string.
+
+use std::fmt;
+use std::io;
+
+/// Passes text on to `inner` a line at a time. Trailing whitespace is removed, at most two blank
+/// lines are kept in a row and the output ends after its last non-blank line, so that regenerated
+/// files only differ where the types do.
+pub struct LineWriter<W: fmt::Write> {
+    inner: W,
+    line: String,
+    /// Blank lines that are only written once another line follows.
+    blank_lines: usize,
+}
+
+impl<W: fmt::Write> LineWriter<W> {
+    pub fn new(inner: W) -> Self {
+        LineWriter {
+            inner,
+            line: String::new(),
+            blank_lines: 0,
+        }
+    }
+
+    fn write_line(&mut self) -> fmt::Result {
+        let line = self.line.trim_end();
+        if line.is_empty() {
+            self.blank_lines += 1;
+        } else {
+            for _ in 0..self.blank_lines.min(2) {
+                self.inner.write_char('\n')?;
+            }
+            self.blank_lines = 0;
+            self.inner.write_str(line)?;
+            self.inner.write_char('\n')?;
+        }
+        self.line.clear();
+        Ok(())
+    }
+
+    /// Writes the last line and returns the sink. Trailing blank lines are dropped.
+    pub fn finish(mut self) -> Result<W, fmt::Error> {
+        if !self.line.is_empty() {
+            self.write_line()?;
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for LineWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut rest = s;
+        while let Some(end) = rest.find('\n') {
+            self.line.push_str(&rest[..end]);
+            self.write_line()?;
+            rest = &rest[end + 1..];
+        }
+        self.line.push_str(rest);
+        Ok(())
+    }
+}
+
+/// Adapts an [`io::Write`] to [`fmt::Write`], keeping the error that [`fmt::Error`] can not carry.
+pub struct IoSink<W: io::Write> {
+    inner: W,
+    pub error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoSink<W> {
+    pub fn new(inner: W) -> Self {
+        IoSink { inner, error: None }
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoSink<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
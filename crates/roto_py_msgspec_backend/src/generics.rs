@@ -0,0 +1,311 @@
+//! Generic prototypes written as generic classes, like `class Page(msgspec.Struct, Generic[T])`,
+//! instead of one class per instantiation.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+
+use roto_core::ast;
+use roto_core::frontend::IRCompiler;
+use roto_core::intern::Atom;
+use roto_core::ir::TypeName;
+use roto_core::symbols::split_qualified_name;
+
+use crate::python_identifier;
+use crate::type_arguments;
+use crate::Field;
+use crate::PrimitiveTypeWriter;
+use crate::VariantOption;
+
+/// A generic prototype, with the references in its body qualified.
+#[derive(Debug, Clone)]
+struct GenericPrototype {
+    params: Vec<Atom>,
+    body: ast::TypeExpression,
+    doc: Option<String>,
+}
+
+/// The generic prototypes that are written as generic classes. Their bodies have to be a struct
+/// or a variant, and may not contain intersections, unions or conditionals, which are resolved
+/// per instantiation.
+#[derive(Debug, Default)]
+pub struct GenericPrototypes {
+    prototypes: BTreeMap<Atom, GenericPrototype>,
+    /// The prototypes that are referenced. The ones that are not written yet are also pending.
+    used: BTreeSet<Atom>,
+    pending: VecDeque<Atom>,
+}
+
+impl GenericPrototypes {
+    pub fn new(compiler: &IRCompiler) -> Self {
+        let mut prototypes = BTreeMap::new();
+        for (name, prototype) in compiler.iter_globals() {
+            if prototype.params.is_empty() {
+                continue;
+            }
+            let module = split_qualified_name(name).0;
+            let Ok(body) = compiler.symbols().qualify(
+                module,
+                &prototype.type_,
+                &prototype.params,
+                &compiler.builtins,
+            ) else {
+                continue;
+            };
+            if let ast::TypeExpression::Struct(_) | ast::TypeExpression::Variant(_) = body {
+                prototypes.insert(
+                    Atom::from(name),
+                    GenericPrototype {
+                        params: prototype.params.clone(),
+                        body,
+                        doc: prototype.doc.clone(),
+                    },
+                );
+            }
+        }
+
+        // A prototype that refers to a prototype that is monomorphized has to be monomorphized as
+        // well, which may rule out further prototypes.
+        loop {
+            let unsupported: Vec<Atom> = prototypes
+                .iter()
+                .filter(|(_, prototype)| !Self::is_supported(&prototypes, &prototype.body))
+                .map(|(name, _)| *name)
+                .collect();
+            if unsupported.is_empty() {
+                break;
+            }
+            for name in unsupported {
+                prototypes.remove(&name);
+            }
+        }
+
+        GenericPrototypes {
+            prototypes,
+            ..Default::default()
+        }
+    }
+
+    fn is_supported(
+        prototypes: &BTreeMap<Atom, GenericPrototype>,
+        t: &ast::TypeExpression,
+    ) -> bool {
+        match t {
+            ast::TypeExpression::Variable(_) | ast::TypeExpression::Builtin(_) => true,
+            ast::TypeExpression::Generic(name, args) => {
+                prototypes.contains_key(name)
+                    && args.values().all(|arg| Self::is_supported(prototypes, arg))
+            }
+            ast::TypeExpression::Struct(struct_type) => struct_type
+                .fields
+                .iter()
+                .all(|field| Self::is_supported(prototypes, &field.type_)),
+            // A variant without options has no `Union` that could be parameterized.
+            ast::TypeExpression::Variant(variant_type) => {
+                !variant_type.variants.is_empty()
+                    && variant_type
+                        .variants
+                        .iter()
+                        .all(|option| Self::is_supported(prototypes, &option.type_))
+            }
+            ast::TypeExpression::Intersection(_, _)
+            | ast::TypeExpression::Union(_, _)
+            | ast::TypeExpression::Conditional(_) => false,
+        }
+    }
+
+    pub fn contains(&self, name: Atom) -> bool {
+        self.prototypes.contains_key(&name)
+    }
+
+    /// Returns the next prototype that is referenced but not written yet.
+    pub fn next_pending(&mut self) -> Option<Atom> {
+        self.pending.pop_front()
+    }
+}
+
+/// The generic prototype that is being written.
+struct Scope {
+    params: Vec<Atom>,
+    type_vars: Vec<String>,
+    /// The name of the prototype's class and the path of members leading to the current one,
+    /// which name the classes of inline structs and variants.
+    owner: String,
+    path: Vec<String>,
+    classes: Vec<String>,
+}
+
+impl<'a> PrimitiveTypeWriter<'a> {
+    /// Converts a reference to an instantiation of the generic prototype `name`, like
+    /// `Page[int]`. Returns `None` if the instantiation has to be monomorphized, because the
+    /// prototype or one of the arguments can not be written as a generic class.
+    pub fn convert_generic_instance(
+        &mut self,
+        name: Atom,
+        args: &BTreeMap<Atom, ast::TypeExpression>,
+    ) -> Option<String> {
+        if !self.generics.contains(name) {
+            return None;
+        }
+        self.convert_expression(&ast::TypeExpression::Generic(name, args.clone()), None)
+    }
+
+    /// Converts a type expression that is either an argument of an instantiation or, inside of
+    /// `scope`, part of a generic prototype.
+    fn convert_expression(
+        &mut self,
+        t: &ast::TypeExpression,
+        scope: Option<&mut Scope>,
+    ) -> Option<String> {
+        match t {
+            ast::TypeExpression::Builtin(builtin) => Some(self.convert_builtin(builtin)),
+            ast::TypeExpression::Variable(name) => {
+                if let Some(scope) = &scope {
+                    if scope.params.contains(name) {
+                        return Some(python_identifier(name));
+                    }
+                }
+                Some(self.convert_compiled(&TypeName::Variable(*name)))
+            }
+            ast::TypeExpression::Generic(name, args) => {
+                let Some(prototype) = self.generics.prototypes.get(name) else {
+                    // Arguments are concrete, so their instantiation has been compiled.
+                    let instance = TypeName::Generic(*name, args.clone());
+                    return self
+                        .has_compiled(&instance)
+                        .then(|| self.convert_compiled(&instance));
+                };
+                let params = prototype.params.clone();
+                let mut scope = scope;
+                let mut converted = Vec::new();
+                for param in params.iter() {
+                    let arg = args.get(param)?;
+                    converted.push(self.convert_expression(arg, scope.as_deref_mut())?);
+                }
+                if self.generics.used.insert(*name) {
+                    self.generics.pending.push_back(*name);
+                }
+                let module = self.module_of_global(name);
+                let class = self.reference(module, python_identifier(&name.replace('.', "_")));
+                Some(format!("{}{}", class, type_arguments(&converted)))
+            }
+            // Inline structs and variants of a prototype become generic classes of their own,
+            // named like the temporaries of an instantiation.
+            ast::TypeExpression::Struct(_) | ast::TypeExpression::Variant(_) => {
+                let scope = scope?;
+                let name = format!("{}_{}", scope.owner, scope.path.join("_"));
+                let class = self.write_generic_body(&name, t, None, scope);
+                scope.classes.push(class);
+                Some(format!("{}{}", name, type_arguments(&scope.type_vars)))
+            }
+            ast::TypeExpression::Intersection(_, _)
+            | ast::TypeExpression::Union(_, _)
+            | ast::TypeExpression::Conditional(_) => None,
+        }
+    }
+
+    fn has_compiled(&self, name: &TypeName) -> bool {
+        self.allocator.iter().any(|(_, t)| &t.name == name)
+    }
+
+    /// Converts a reference to a compiled type and makes sure it is written.
+    fn convert_compiled(&mut self, name: &TypeName) -> String {
+        let compiled = self
+            .allocator
+            .iter()
+            .map(|(_, t)| t)
+            .find(|t| &t.name == name);
+        if let Some(named_type) = compiled {
+            self.worklist.push_back(named_type);
+        }
+        self.reference_name(name)
+    }
+
+    fn convert_member(
+        &mut self,
+        member: &str,
+        t: &ast::TypeExpression,
+        scope: &mut Scope,
+    ) -> String {
+        scope.path.push(member.to_string());
+        let converted = self
+            .convert_expression(t, Some(scope))
+            .expect("unsupported prototypes are monomorphized");
+        scope.path.pop();
+        converted
+    }
+
+    fn write_generic_body(
+        &mut self,
+        name: &str,
+        body: &ast::TypeExpression,
+        doc: Option<&str>,
+        scope: &mut Scope,
+    ) -> String {
+        let type_vars = scope.type_vars.clone();
+        match body {
+            ast::TypeExpression::Struct(struct_type) => {
+                let fields = struct_type
+                    .fields
+                    .iter()
+                    .map(|field| Field {
+                        name: &field.name,
+                        type_: self.convert_member(&field.name, &field.type_, scope),
+                        optional: field.optional,
+                        annotations: &field.annotations,
+                        comment: field.comment.as_deref(),
+                    })
+                    .collect();
+                self.write_struct(name, &type_vars, fields, doc)
+            }
+            ast::TypeExpression::Variant(variant_type) => {
+                let options = variant_type
+                    .variants
+                    .iter()
+                    .map(|option| VariantOption {
+                        name: &option.name,
+                        payload: match &option.type_ {
+                            ast::TypeExpression::Builtin(ast::Builtin::Unit) => None,
+                            payload => Some(self.convert_member(&option.name, payload, scope)),
+                        },
+                        comment: option.comment.as_deref(),
+                    })
+                    .collect();
+                self.write_variant(name, &type_vars, options, doc)
+            }
+            _ => unreachable!("only structs and variants are written as generic classes"),
+        }
+    }
+
+    /// Defines the generic prototype `name` and the classes of its inline structs and variants in
+    /// the current module.
+    pub fn write_generic_prototype(&mut self, name: Atom) {
+        let prototype = self.generics.prototypes[&name].clone();
+        let type_vars: Vec<String> = prototype
+            .params
+            .iter()
+            .map(|param| python_identifier(param))
+            .collect();
+        self.module.type_vars.extend(type_vars.iter().cloned());
+        self.module.imports.import_from("typing", "TypeVar");
+        // Prototypes are written to their own module, so this is the name they are defined as.
+        let module = self.module_of_global(&name);
+        let owner = self.reference(module, python_identifier(&name.replace('.', "_")));
+
+        let mut scope = Scope {
+            params: prototype.params.clone(),
+            type_vars,
+            owner: owner.clone(),
+            path: Vec::new(),
+            classes: Vec::new(),
+        };
+        let class = self.write_generic_body(
+            &owner,
+            &prototype.body,
+            prototype.doc.as_deref(),
+            &mut scope,
+        );
+        scope.classes.push(class);
+        self.module.define(owner, scope.classes.join("\n"));
+    }
+}
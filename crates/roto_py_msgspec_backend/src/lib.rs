@@ -1,56 +1,411 @@
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::collections::VecDeque;
+mod emit;
+mod generics;
+mod package;
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io;
+
+use roto_core::annotations::format_pattern;
+use roto_core::annotations::AnnotationRegistry;
+use roto_core::annotations::AnnotationSpec;
+use roto_core::annotations::ArgumentKind;
 use roto_core::ast;
+use roto_core::backend::check_features;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendConfig;
+use roto_core::backend::BackendError;
+use roto_core::backend::CodegenBackend;
+use roto_core::backend::Feature;
+use roto_core::backend::Language;
+use roto_core::backend::OutputFile;
+use roto_core::backend::Scope;
+use roto_core::backend::TypeNaming;
+use roto_core::backend::Worklist;
+use roto_core::builtins::BuiltinRegistry;
+use roto_core::frontend::IRCompiler;
 use roto_core::frontend::TypeAllocator;
 use roto_core::ir::IRType;
 use roto_core::ir::NamedIRType;
 use roto_core::ir::PrimitiveType;
+use roto_core::ir::ResolvedIRType;
+use roto_core::ir::TypeMetadata;
 use roto_core::ir::TypeName;
+use roto_core::symbols::split_qualified_name;
+
+pub use crate::generics::GenericPrototypes;
+pub use crate::package::PythonModule;
+
+use crate::emit::IoSink;
+use crate::emit::LineWriter;
+use crate::package::module_path;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// Annotations that are enforced by `msgspec.Meta` when decoding, with their argument and the
+/// keyword of `msgspec.Meta` they are passed as. `@min` and `@max` bound numbers.
+const CONSTRAINTS: [(&str, &str, ArgumentKind, &str); 5] = [
+    ("min", "value", ArgumentKind::Int, "ge"),
+    ("max", "value", ArgumentKind::Int, "le"),
+    ("min_length", "value", ArgumentKind::Int, "min_length"),
+    ("max_length", "value", ArgumentKind::Int, "max_length"),
+    ("pattern", "regex", ArgumentKind::String, "pattern"),
+];
+
+/// Registers the annotations understood by the msgspec backend.
+pub fn register_annotations(registry: &mut AnnotationRegistry) {
+    registry.register(AnnotationSpec::new("json").arg("strategy", ArgumentKind::Ident));
+    registry.register(AnnotationSpec::new("default").arg("value", ArgumentKind::Any));
+    for (name, arg, kind, _) in CONSTRAINTS {
+        registry.register(AnnotationSpec::new(name).arg(arg, kind));
+    }
+}
+
+/// Turns `name` into a valid Python identifier. Characters that can not appear in identifiers
+/// become underscores and keywords get a trailing underscore, so `class` becomes `class_`.
+fn python_identifier(name: &str) -> String {
+    Language::Python.escape(&python_name(name))
+}
+
+/// `name` with the characters that can not appear in identifiers replaced by underscores, and an
+/// underscore before a leading digit. Keywords are left to the [`Scope`] the name is used in.
+fn python_name(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+fn python_string(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn python_value(value: &ast::AnnotationValue) -> String {
+    match value {
+        ast::AnnotationValue::Ident(ident) => python_string(ident),
+        ast::AnnotationValue::Int(value) => value.to_string(),
+        ast::AnnotationValue::String(value) => python_string(value),
+    }
+}
 
-pub struct TypeNameAllocator {
-    next_id: usize,
-    names: HashMap<TypeName, usize>,
+/// The Python literal of a `@default` value. The identifiers `true` and `false` are booleans, other
+/// identifiers are strings.
+fn python_literal(value: &ast::AnnotationValue) -> String {
+    match value {
+        ast::AnnotationValue::Ident(ident) if ident == "true" => "True".to_string(),
+        ast::AnnotationValue::Ident(ident) if ident == "false" => "False".to_string(),
+        value => python_value(value),
+    }
 }
 
-impl TypeNameAllocator {
-    pub fn new() -> Self {
-        Self {
-            next_id: 0,
-            names: HashMap::new(),
+/// Converts a class name like `HTTPRequest` or `auth_Account` to a function name part like
+/// `http_request` or `auth_account`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            let starts_word = previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lower);
+            if starts_word {
+                result.push('_');
+            }
         }
+        result.extend(c.to_lowercase());
     }
+    result
+}
 
-    pub fn allocate_name(&mut self, type_name: &TypeName) -> String {
-        match type_name {
-            TypeName::Variable(name) => name.clone(),
-            TypeName::Generic(name, params) => {
-                let existing = self
-                    .names
-                    .get(&TypeName::Generic(name.clone(), params.clone()));
-                match existing {
-                    Some(id) => format!("{}{}", name, id),
-                    None => {
-                        let id = self.next_id;
-                        self.next_id += 1;
-                        self.names
-                            .insert(TypeName::Generic(name.clone(), params.clone()), id);
-                        format!("{}{}", name, id)
-                    }
-                }
+/// Converts a roto option name like `not_found` to a type name like `NotFound`.
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
             }
-            TypeName::Temporary(id) => format!("T{}", id),
+        })
+        .collect()
+}
+
+/// Modules that are not part of the standard library. Their imports go in a separate group after
+/// the standard library ones.
+const THIRD_PARTY_MODULES: [&str; 1] = ["msgspec"];
+
+/// The import groups in the order they are written. `__future__` imports have to come first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportGroup {
+    Future,
+    Standard,
+    ThirdParty,
+}
+
+impl ImportGroup {
+    fn of(module: &str) -> ImportGroup {
+        if module == "__future__" {
+            ImportGroup::Future
+        } else if THIRD_PARTY_MODULES.contains(&module) {
+            ImportGroup::ThirdParty
+        } else {
+            ImportGroup::Standard
         }
     }
 }
 
+/// The imports a module needs, collected while its types are written.
+#[derive(Debug, Default)]
+pub struct Imports {
+    modules: BTreeSet<&'static str>,
+    names: BTreeMap<&'static str, BTreeSet<&'static str>>,
+}
+
+impl Imports {
+    /// Records `import {module}`.
+    pub fn import(&mut self, module: &'static str) {
+        self.modules.insert(module);
+    }
+
+    /// Records `from {module} import {name}`.
+    pub fn import_from(&mut self, module: &'static str, name: &'static str) {
+        self.names.entry(module).or_default().insert(name);
+    }
+
+    fn write_group(&self, group: ImportGroup) -> String {
+        let mut result = String::new();
+        for module in self.modules.iter().filter(|m| ImportGroup::of(m) == group) {
+            result.push_str(&format!("import {}\n", module));
+        }
+        for (module, names) in self
+            .names
+            .iter()
+            .filter(|(m, _)| ImportGroup::of(m) == group)
+        {
+            let names: Vec<_> = names.iter().copied().collect();
+            result.push_str(&format!("from {} import {}\n", module, names.join(", ")));
+        }
+        result
+    }
+
+    /// The import statements, grouped by [`ImportGroup`].
+    pub fn render(&self) -> String {
+        [
+            ImportGroup::Future,
+            ImportGroup::Standard,
+            ImportGroup::ThirdParty,
+        ]
+        .into_iter()
+        .map(|group| self.write_group(group))
+        .filter(|group| !group.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+}
+
+/// How msgspec renames fields when encoding and decoding, see `rename` in the msgspec docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameStrategy {
+    Lower,
+    Upper,
+    Camel,
+    Pascal,
+    Kebab,
+}
+
+impl RenameStrategy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RenameStrategy::Lower => "lower",
+            RenameStrategy::Upper => "upper",
+            RenameStrategy::Camel => "camel",
+            RenameStrategy::Pascal => "pascal",
+            RenameStrategy::Kebab => "kebab",
+        }
+    }
+}
+
+/// How variants whose options all carry `unit` are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnitVariantStyle {
+    /// One empty struct per option, like any other variant. Values are encoded as
+    /// `{"type": "option"}`, like in the other backends.
+    #[default]
+    Structs,
+    /// A `str` based `enum.Enum`. Values are encoded as the bare option name.
+    Enum,
+    /// A `Literal[...]` of the option names. Values are encoded as the bare option name.
+    Literal,
+}
+
+/// The files that are generated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PythonOutput {
+    /// `types.py`, or the `.py` modules of a package.
+    #[default]
+    Module,
+    /// `types.pyi`, or the `.pyi` stubs of a package, for consumers that only need static types.
+    Stub,
+    /// Both modules and stubs.
+    ModuleAndStub,
+}
+
+/// The options passed to every generated `msgspec.Struct`. The default leaves all of them at
+/// msgspec's defaults.
+#[derive(Debug, Clone, Default)]
+pub struct PyMsgspecConfig {
+    pub frozen: bool,
+    pub kw_only: bool,
+    pub omit_defaults: bool,
+    pub rename: Option<RenameStrategy>,
+    pub forbid_unknown_fields: bool,
+    /// Encodes structs as arrays instead of objects. Tagged options start with their tag.
+    pub array_like: bool,
+    /// Writes generic types as generic classes that are parameterized where they are used, like
+    /// `Page[int]`, instead of one class per instantiation. Generic types that use
+    /// intersections, unions or conditionals are still instantiated.
+    pub generic_classes: bool,
+    pub unit_variants: UnitVariantStyle,
+    pub output: PythonOutput,
+    /// Writes a package with one module per roto module, `auth/__init__.py` for `auth.User`,
+    /// instead of a single `types.py`.
+    pub package: bool,
+    /// Writes `decode_user` and `encode_user` functions for every named type, which decode from
+    /// and encode to JSON with `msgspec.json`.
+    pub helpers: bool,
+    /// Starts every file with a `# generated by roto` comment.
+    pub header: Option<GeneratedHeader>,
+    /// The prefix, suffix and casing of class names.
+    pub naming: TypeNaming,
+}
+
+/// The comment generated files start with, like
+/// `# generated by roto 0.1.0 from schema.roto`.
+#[derive(Debug, Clone, Default)]
+pub struct GeneratedHeader {
+    /// The schema the files are generated from.
+    pub source: Option<String>,
+    /// Adds the time of generation, which makes every regeneration a change.
+    pub timestamp: bool,
+}
+
+impl GeneratedHeader {
+    fn render(&self) -> String {
+        let mut result = format!("# generated by roto {}", env!("CARGO_PKG_VERSION"));
+        if let Some(source) = &self.source {
+            result.push_str(&format!(" from {}", source));
+        }
+        result.push('\n');
+        if self.timestamp {
+            let seconds = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            result.push_str(&format!("# generated at {} (unix time)\n", seconds));
+        }
+        result
+    }
+}
+
+impl PyMsgspecConfig {
+    /// The keyword arguments of a struct definition, like `frozen=True`.
+    fn struct_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        let flags = [
+            ("frozen", self.frozen),
+            ("kw_only", self.kw_only),
+            ("omit_defaults", self.omit_defaults),
+            ("forbid_unknown_fields", self.forbid_unknown_fields),
+            ("array_like", self.array_like),
+        ];
+        for (name, enabled) in flags {
+            if enabled {
+                options.push(format!("{}=True", name));
+            }
+        }
+        if let Some(rename) = self.rename {
+            options.push(format!("rename=\"{}\"", rename.as_str()));
+        }
+        options
+    }
+}
+
+/// A struct field whose type has already been converted.
+struct Field<'f> {
+    name: &'f str,
+    type_: String,
+    optional: bool,
+    annotations: &'f [ast::Annotation],
+    comment: Option<&'f str>,
+}
+
+impl<'f> Field<'f> {
+    /// The Python literal the field defaults to. Optional fields default to `None` unless they
+    /// have a `@default`.
+    fn default(&self) -> Option<String> {
+        let default = self
+            .annotations
+            .iter()
+            .find(|a| a.name == "default")
+            .and_then(|a| a.arg("value"));
+        match default {
+            Some(value) => Some(python_literal(value)),
+            None if self.optional => Some("None".to_string()),
+            None => None,
+        }
+    }
+}
+
+/// A variant option whose payload has already been converted. Unit options have no payload.
+struct VariantOption<'f> {
+    name: &'f str,
+    payload: Option<String>,
+    comment: Option<&'f str>,
+}
+
+/// The arguments that refer to a generic class, like `[T, U]`, or nothing if there are no type
+/// variables.
+fn type_arguments(params: &[String]) -> String {
+    if params.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]", params.join(", "))
+    }
+}
+
 pub struct PrimitiveTypeWriter<'a> {
     pub name_allocator: &'a mut TypeNameAllocator,
     pub allocator: &'a TypeAllocator,
+    pub builtins: &'a BuiltinRegistry,
     //
-    pub compiled: HashSet<TypeName>,
-    pub stack: VecDeque<NamedIRType>,
+    pub worklist: Worklist,
+    /// The module that is written to, and the other ones by roto module.
+    pub module_name: String,
+    pub module: PythonModule,
+    pub modules: BTreeMap<String, PythonModule>,
+    pub config: &'a PyMsgspecConfig,
+    pub generics: GenericPrototypes,
+    /// Whether a `.pyi` stub is written instead of a module.
+    pub stub: bool,
 }
 
 impl<'a> PrimitiveTypeWriter<'a> {
@@ -65,6 +420,10 @@ impl<'a> PrimitiveTypeWriter<'a> {
             ast::Builtin::String => "str".to_string(),
             ast::Builtin::Bool => "bool".to_string(),
             ast::Builtin::Unit => "None".to_string(),
+            ast::Builtin::Custom(id) => self
+                .builtins
+                .lower(*id, "py-msgspec")
+                .unwrap_or_else(|| self.convert_builtin(&self.builtins.representation(t))),
         }
     }
 
@@ -72,42 +431,607 @@ impl<'a> PrimitiveTypeWriter<'a> {
         match t {
             PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin),
             PrimitiveType::Reference(name) => {
-                let r = self.allocator.types.get(name).unwrap();
-                if !self.compiled.contains(&r.name) {
-                    self.stack.push_front(r.clone());
+                let r = self.allocator.get(*name).unwrap();
+                if let TypeName::Generic(generic, args) = &r.name {
+                    if let Some(instance) = self.convert_generic_instance(*generic, args) {
+                        return instance;
+                    }
                 }
-                self.allocate_name(&r.name)
+                self.worklist.push_front(r);
+                self.reference_name(&r.name)
             }
         }
     }
 
-    pub fn convert_named_ir_type(&mut self, name: &str, t: &IRType) -> String {
+    /// The body of a class without fields or docstring.
+    fn empty_body(&self) -> &'static str {
+        if self.stub {
+            "..."
+        } else {
+            "pass"
+        }
+    }
+
+    /// The base classes and options of a struct definition, like
+    /// `(msgspec.Struct, Generic[T], frozen=True)`.
+    fn struct_bases(&mut self, params: &[String], extra_options: &[String]) -> String {
+        let mut bases = vec!["msgspec.Struct".to_string()];
+        if !params.is_empty() {
+            self.module.imports.import_from("typing", "Generic");
+            bases.push(format!("Generic[{}]", params.join(", ")));
+        }
+        bases.extend(extra_options.iter().cloned());
+        bases.extend(self.config.struct_options());
+        format!("({})", bases.join(", "))
+    }
+
+    /// Writes `doc` as a docstring. Docstrings of classes come first in their body, docstrings of
+    /// fields and aliases right after their definition, which IDEs pick up as well. Stubs have no
+    /// docstrings.
+    fn write_docstring(&self, result: &mut String, indent: &str, doc: Option<&str>) {
+        if self.stub {
+            return;
+        }
+        if let Some(doc) = doc {
+            let mut escaped = doc.replace('\\', "\\\\");
+            // A quote right before the closing quotes would end the docstring early.
+            if escaped.ends_with('"') {
+                escaped.pop();
+                escaped.push_str("\\\"");
+            }
+            let escaped = escaped.replace("\"\"\"", "\\\"\\\"\\\"");
+            result.push_str(&format!("{}\"\"\"{}\"\"\"\n", indent, escaped));
+        }
+    }
+
+    /// Wraps `type_` in `Annotated` with the constraints among `annotations`, like
+    /// `Annotated[int, msgspec.Meta(ge=0)]`.
+    fn constrained_type(&mut self, type_: String, annotations: &[ast::Annotation]) -> String {
+        let mut constraints = Vec::new();
+        for (name, arg, _, keyword) in CONSTRAINTS {
+            let value = annotations
+                .iter()
+                .find(|a| a.name == name)
+                .and_then(|a| a.arg(arg));
+            if let Some(value) = value {
+                constraints.push(format!("{}={}", keyword, python_value(value)));
+            }
+        }
+        if constraints.is_empty() {
+            return type_;
+        }
+        self.module.imports.import_from("typing", "Annotated");
+        format!(
+            "Annotated[{}, msgspec.Meta({})]",
+            type_,
+            constraints.join(", ")
+        )
+    }
+
+    /// Writes a struct. `params` are the type variables of generic structs. Fields with defaults
+    /// have to come after the ones without, unless they are keyword-only, so they are moved to
+    /// the end, which also moves them in array-like encodings.
+    fn write_struct(
+        &mut self,
+        name: &str,
+        params: &[String],
+        mut fields: Vec<Field>,
+        doc: Option<&str>,
+    ) -> String {
+        if !self.config.kw_only {
+            fields.sort_by_key(|field| field.default().is_some());
+        }
+        self.module.imports.import("msgspec");
+        self.module.names.push(name.to_string());
+        let mut result = format!("class {}{}:\n", name, self.struct_bases(params, &[]));
+        self.write_docstring(&mut result, "    ", doc);
+        let is_empty = fields.is_empty();
+        let mut scope = Scope::new(Language::Python);
+        for field in fields {
+            let field_name = scope.identifier(&python_name(field.name));
+            let wire_name = match field
+                .annotations
+                .iter()
+                .find(|a| a.name == "rename")
+                .and_then(|a| a.arg("name"))
+            {
+                Some(ast::AnnotationValue::String(name)) => name.clone(),
+                _ => field.name.to_string(),
+            };
+            let default = field.default();
+            let mut type_ = self.constrained_type(field.type_, field.annotations);
+            if field.optional {
+                self.module.imports.import_from("typing", "Optional");
+                type_ = format!("Optional[{}]", type_);
+            }
+            let value = if wire_name == field_name {
+                default
+            } else {
+                let default = default
+                    .map(|default| format!(", default={}", default))
+                    .unwrap_or_default();
+                Some(format!(
+                    "msgspec.field(name={}{})",
+                    python_string(&wire_name),
+                    default
+                ))
+            };
+            match value {
+                Some(value) => {
+                    result.push_str(&format!("    {}: {} = {}\n", field_name, type_, value))
+                }
+                None => result.push_str(&format!("    {}: {}\n", field_name, type_)),
+            }
+            self.write_docstring(&mut result, "    ", field.comment);
+        }
+        if is_empty && (doc.is_none() || self.stub) {
+            result.push_str(&format!("    {}\n", self.empty_body()));
+        }
+        result
+    }
+
+    /// Writes a variant. Every option becomes a struct tagged by `type`, with the payload in
+    /// `value`, which matches the adjacently tagged JSON of the other backends. Unit options have
+    /// no payload.
+    fn write_variant(
+        &mut self,
+        name: &str,
+        params: &[String],
+        options: Vec<VariantOption>,
+        doc: Option<&str>,
+    ) -> String {
+        // Enums and literals can not be parameterized, so generic variants always use structs.
+        let is_unit_only = !options.is_empty() && options.iter().all(|o| o.payload.is_none());
+        if is_unit_only && params.is_empty() {
+            match self.config.unit_variants {
+                UnitVariantStyle::Structs => {}
+                UnitVariantStyle::Enum => return self.write_enum(name, options, doc),
+                UnitVariantStyle::Literal => return self.write_literal(name, options, doc),
+            }
+        }
+
+        self.module.imports.import("msgspec");
+        self.module.imports.import_from("typing", "TypeAlias");
+        let mut result = String::new();
+        let mut option_names = Vec::new();
+        let mut scope = Scope::new(Language::Python);
+        for option in options {
+            // Inline payloads are already named `{name}_{option}`.
+            let option_name = scope.unique(format!("{}_{}", name, pascal_case(option.name)));
+            let tag = [
+                "tag_field=\"type\"".to_string(),
+                format!("tag={:?}", option.name),
+            ];
+            let bases = self.struct_bases(params, &tag);
+            self.module.names.push(option_name.clone());
+            result.push_str(&format!("class {}{}:\n", option_name, bases));
+            self.write_docstring(&mut result, "    ", option.comment);
+            match option.payload {
+                Some(payload) => result.push_str(&format!("    value: {}\n", payload)),
+                None if option.comment.is_none() || self.stub => {
+                    result.push_str(&format!("    {}\n", self.empty_body()))
+                }
+                None => {}
+            }
+            result.push('\n');
+            option_names.push(format!("{}{}", option_name, type_arguments(params)));
+        }
+        let rhs = match option_names.as_slice() {
+            [] => {
+                self.module.imports.import_from("typing", "NoReturn");
+                "NoReturn".to_string()
+            }
+            [single] => single.clone(),
+            _ => {
+                self.module.imports.import_from("typing", "Union");
+                format!("Union[{}]", option_names.join(", "))
+            }
+        };
+        self.module.names.push(name.to_string());
+        result.push_str(&format!("{}: TypeAlias = {}\n", name, rhs));
+        self.write_docstring(&mut result, "", doc);
+        result
+    }
+
+    fn write_enum(&mut self, name: &str, options: Vec<VariantOption>, doc: Option<&str>) -> String {
+        self.module.imports.import("enum");
+        self.module.names.push(name.to_string());
+        let mut result = format!("class {}(str, enum.Enum):\n", name);
+        self.write_docstring(&mut result, "    ", doc);
+        let mut scope = Scope::new(Language::Python);
+        for option in options {
+            result.push_str(&format!(
+                "    {} = {}\n",
+                scope.identifier(&python_name(&option.name.to_uppercase())),
+                python_string(option.name)
+            ));
+            self.write_docstring(&mut result, "    ", option.comment);
+        }
+        result
+    }
+
+    fn write_literal(
+        &mut self,
+        name: &str,
+        options: Vec<VariantOption>,
+        doc: Option<&str>,
+    ) -> String {
+        self.module.imports.import_from("typing", "Literal");
+        self.module.imports.import_from("typing", "TypeAlias");
+        let names: Vec<_> = options
+            .iter()
+            .map(|option| python_string(option.name))
+            .collect();
+        self.module.names.push(name.to_string());
+        let mut result = format!("{}: TypeAlias = Literal[{}]\n", name, names.join(", "));
+        self.write_docstring(&mut result, "", doc);
+        result
+    }
+
+    /// Writes the `decode_{name}` and `encode_{name}` functions of the class or alias `name`.
+    fn write_helpers(&mut self, name: &str) -> String {
+        self.module.imports.import("msgspec");
+        let function = snake_case(name);
+        let decode = format!("decode_{}", function);
+        let encode = format!("encode_{}", function);
+        let (decode_body, encode_body) = if self.stub {
+            (" ...".to_string(), " ...".to_string())
+        } else {
+            (
+                format!("\n    return msgspec.json.decode(data, type={})", name),
+                "\n    return msgspec.json.encode(value)".to_string(),
+            )
+        };
+        self.module.names.push(decode.clone());
+        self.module.names.push(encode.clone());
+        format!(
+            "def {}(data: bytes) -> {}:{}\n\ndef {}(value: {}) -> bytes:{}\n",
+            decode, name, decode_body, encode, name, encode_body
+        )
+    }
+
+    /// An `@opaque` alias of `builtin`, which type checkers treat as a distinct type.
+    fn write_new_type(&mut self, name: &str, builtin: &ast::Builtin, doc: Option<&str>) -> String {
+        self.module.imports.import_from("typing", "NewType");
+        let rhs = self.convert_builtin(builtin);
+        self.module.names.push(name.to_string());
+        let mut result = format!("{} = NewType({}, {})\n", name, python_string(name), rhs);
+        self.write_docstring(&mut result, "", doc);
+        result
+    }
+
+    /// The definition of `t` as `name`. Aliases of builtins with a `@format` that has a pattern
+    /// check it when decoding, like `Annotated[str, msgspec.Meta(pattern="...")]`.
+    pub fn convert_named_ir_type(
+        &mut self,
+        name: &str,
+        t: &IRType,
+        metadata: &TypeMetadata,
+    ) -> String {
+        let doc = metadata.doc.as_deref();
         match t {
             IRType::Struct(struct_type) => {
-                let mut result = "class ".to_string();
-                result.push_str(name);
-                result.push_str("(msgspec.Struct):\n");
-                for field in struct_type.fields.iter() {
-                    if let Some(comment) = &field.comment {
-                        result.push_str(&format!("    # {}\n", comment));
-                    }
-                    result.push_str(&format!(
-                        "    {}: {}\n",
-                        field.name,
-                        self.convert_primitive_type(&field.type_)
-                    ));
+                let fields = struct_type
+                    .fields
+                    .iter()
+                    .map(|field| Field {
+                        name: &field.name,
+                        type_: self.convert_primitive_type(&field.type_),
+                        optional: field.optional,
+                        annotations: &field.annotations,
+                        comment: field.comment.as_deref(),
+                    })
+                    .collect();
+                self.write_struct(name, &[], fields, doc)
+            }
+            IRType::Variant(variant_type) => {
+                let options = variant_type
+                    .variants
+                    .iter()
+                    .map(|option| VariantOption {
+                        name: &option.name,
+                        payload: match &option.type_ {
+                            PrimitiveType::Builtin(ast::Builtin::Unit) => None,
+                            payload => Some(self.convert_primitive_type(payload)),
+                        },
+                        comment: option.comment.as_deref(),
+                    })
+                    .collect();
+                self.write_variant(name, &[], options, doc)
+            }
+            IRType::Reference(reference) => {
+                self.module.imports.import_from("typing", "TypeAlias");
+                let rhs = self.allocator.get(*reference).unwrap();
+                let rhs_name = self.reference_name(&rhs.name);
+                self.module.names.push(name.to_string());
+                let mut result = format!("{}: TypeAlias = {}\n", name, rhs_name);
+                self.write_docstring(&mut result, "", doc);
+                result
+            }
+            IRType::Builtin(builtin) => {
+                self.module.imports.import_from("typing", "TypeAlias");
+                let mut rhs = self.convert_builtin(builtin);
+                if let Some(pattern) = metadata.format().and_then(format_pattern) {
+                    self.module.imports.import("msgspec");
+                    self.module.imports.import_from("typing", "Annotated");
+                    rhs = format!(
+                        "Annotated[{}, msgspec.Meta(pattern={})]",
+                        rhs,
+                        python_string(pattern)
+                    );
                 }
+                self.module.names.push(name.to_string());
+                let mut result = format!("{}: TypeAlias = {}\n", name, rhs);
+                self.write_docstring(&mut result, "", doc);
                 result
             }
-            IRType::Reference(reference) => {
-                let rhs = self.allocator.types.get(reference).unwrap();
-                let rhs_name = self.allocate_name(&rhs.name);
-                format!("{}: TypeAlias = {}\n", name, rhs_name)
+        }
+    }
+}
+
+/// An alias of `unit` would be `Nothing: TypeAlias = None`, which is the value `None` rather than
+/// a type once annotations are evaluated.
+fn supports(feature: &Feature) -> bool {
+    !matches!(feature, Feature::BuiltinAlias(ast::Builtin::Unit))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MsgspecBackend {
+    pub config: PyMsgspecConfig,
+}
+
+impl Backend for MsgspecBackend {
+    fn register_annotations(&self, registry: &mut AnnotationRegistry) {
+        register_annotations(registry);
+    }
+
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        check_features(compiler, "py-msgspec", supports)?;
+        let mut files = Vec::new();
+        if self.config.output != PythonOutput::Stub {
+            files.extend(self.write_files(compiler, false));
+        }
+        if self.config.output != PythonOutput::Module {
+            files.extend(self.write_files(compiler, true));
+        }
+        Ok(files)
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        if naming.is_default() {
+            return Backend::generate(self, compiler);
+        }
+        let mut config = self.config.clone();
+        config.naming = naming.clone();
+        Backend::generate(&MsgspecBackend { config }, compiler)
+    }
+}
+
+impl CodegenBackend for MsgspecBackend {
+    fn name(&self) -> &'static str {
+        "py-msgspec"
+    }
+
+    fn register_annotations(&self, registry: &mut AnnotationRegistry) {
+        register_annotations(registry);
+    }
+
+    /// Options override `self.config`. Flags have the names of the config fields, `rename`,
+    /// `unit_variants` and `output` take the lowercase names of their variants, `source` adds
+    /// a header naming the schema, and the options of [`TypeNaming`] name the classes.
+    fn generate(
+        &self,
+        compiler: &IRCompiler,
+        options: &BackendConfig,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        type Flag = fn(&mut PyMsgspecConfig) -> &mut bool;
+        let flags: [(&str, Flag); 8] = [
+            ("frozen", |c| &mut c.frozen),
+            ("kw_only", |c| &mut c.kw_only),
+            ("omit_defaults", |c| &mut c.omit_defaults),
+            ("forbid_unknown_fields", |c| &mut c.forbid_unknown_fields),
+            ("array_like", |c| &mut c.array_like),
+            ("generic_classes", |c| &mut c.generic_classes),
+            ("package", |c| &mut c.package),
+            ("helpers", |c| &mut c.helpers),
+        ];
+        let mut known: Vec<_> = flags.iter().map(|(name, _)| *name).collect();
+        known.extend(["rename", "unit_variants", "output", "source"]);
+        known.extend(TypeNaming::OPTIONS);
+        options.expect_keys(&known)?;
+
+        let mut config = self.config.clone();
+        for (name, field) in flags {
+            if options.flag(name)? {
+                *field(&mut config) = true;
             }
-            _ => {
-                let rhs = self.convert_primitive_type(&PrimitiveType::Builtin(ast::Builtin::Unit));
-                format!("{}: TypeAlias = {}\n", name, rhs)
+        }
+        let renames = ["lower", "upper", "camel", "pascal", "kebab"];
+        if let Some(rename) = options.choice("rename", &renames)? {
+            config.rename = Some(match rename {
+                "lower" => RenameStrategy::Lower,
+                "upper" => RenameStrategy::Upper,
+                "camel" => RenameStrategy::Camel,
+                "pascal" => RenameStrategy::Pascal,
+                _ => RenameStrategy::Kebab,
+            });
+        }
+        let styles = ["structs", "enum", "literal"];
+        if let Some(style) = options.choice("unit_variants", &styles)? {
+            config.unit_variants = match style {
+                "structs" => UnitVariantStyle::Structs,
+                "enum" => UnitVariantStyle::Enum,
+                _ => UnitVariantStyle::Literal,
+            };
+        }
+        let outputs = ["module", "stub", "module_and_stub"];
+        if let Some(output) = options.choice("output", &outputs)? {
+            config.output = match output {
+                "module" => PythonOutput::Module,
+                "stub" => PythonOutput::Stub,
+                _ => PythonOutput::ModuleAndStub,
+            };
+        }
+        if let Some(source) = options.get("source") {
+            config.header = Some(GeneratedHeader {
+                source: Some(source.to_string()),
+                timestamp: false,
+            });
+        }
+        let naming = TypeNaming::from_config(options)?;
+        if !naming.is_default() {
+            config.naming = naming;
+        }
+        Backend::generate(&MsgspecBackend { config }, compiler)
+    }
+}
+
+impl MsgspecBackend {
+    /// Writes the single module, or the stub if only stubs are generated, to `out` as it is
+    /// rendered. Packages have several files and are only written by `generate`.
+    pub fn write_to(&self, compiler: &IRCompiler, out: impl io::Write) -> io::Result<()> {
+        check_features(compiler, "py-msgspec", supports).map_err(io::Error::other)?;
+        let stub = self.config.output == PythonOutput::Stub;
+        let modules = self.write_modules(compiler, stub);
+        let mut sink = IoSink::new(out);
+        let written = self.write_file(&mut sink, &modules, "", false);
+        match sink.error.take() {
+            Some(error) => Err(error),
+            None => written.map_err(|_| io::Error::other("failed to write module")),
+        }
+    }
+
+    /// Writes the header and `module` to `out`, with normalized whitespace.
+    fn write_file(
+        &self,
+        out: &mut impl fmt::Write,
+        modules: &BTreeMap<String, PythonModule>,
+        module: &str,
+        package: bool,
+    ) -> fmt::Result {
+        let mut writer = LineWriter::new(out);
+        if let Some(header) = &self.config.header {
+            fmt::Write::write_str(&mut writer, &header.render())?;
+        }
+        match modules.get(module) {
+            Some(python_module) => python_module.write_to(&mut writer, module, package)?,
+            None => PythonModule::default().write_to(&mut writer, module, package)?,
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn write_files(&self, compiler: &IRCompiler, stub: bool) -> Vec<OutputFile> {
+        let extension = if stub { "pyi" } else { "py" };
+        let modules = self.write_modules(compiler, stub);
+        let render = |module: &str, package: bool| {
+            let mut contents = String::new();
+            self.write_file(&mut contents, &modules, module, package)
+                .expect("writing to a string can not fail");
+            contents
+        };
+        if !self.config.package {
+            return vec![OutputFile::new(
+                format!("types.{}", extension),
+                render("", false),
+            )];
+        }
+
+        // Every module needs its parents to be packages as well, even if they have no types.
+        let mut names = BTreeSet::new();
+        for name in modules.keys() {
+            let mut name = name.as_str();
+            names.insert(name);
+            while !name.is_empty() {
+                name = split_qualified_name(name).0;
+                names.insert(name);
+            }
+        }
+        names
+            .into_iter()
+            .map(|name| OutputFile::new(module_path(name, extension), render(name, true)))
+            .collect()
+    }
+
+    fn write_modules(&self, compiler: &IRCompiler, stub: bool) -> BTreeMap<String, PythonModule> {
+        // Qualified names like `auth.User` are not valid Python identifiers.
+        let mut type_name_allocator = TypeNameAllocator::with_variable_name(|name| {
+            python_identifier(&name.replace('.', "_"))
+        })
+        .with_declared(compiler)
+        .with_naming(self.config.naming.clone());
+        let mut primitive_type_writer = PrimitiveTypeWriter {
+            name_allocator: &mut type_name_allocator,
+            allocator: compiler.allocator(),
+            builtins: &compiler.builtins,
+            worklist: Worklist::new(compiler),
+            module_name: String::new(),
+            module: PythonModule::default(),
+            modules: BTreeMap::new(),
+            config: &self.config,
+            generics: if self.config.generic_classes {
+                GenericPrototypes::new(compiler)
+            } else {
+                GenericPrototypes::default()
+            },
+            stub,
+        };
+
+        loop {
+            let Some(named_type) = primitive_type_writer.worklist.pop() else {
+                // Generic classes are written once no other type is left, since writing them may
+                // reference further types.
+                match primitive_type_writer.generics.next_pending() {
+                    Some(prototype) => {
+                        let module = primitive_type_writer.module_of_global(&prototype);
+                        primitive_type_writer.enter_module(module);
+                        primitive_type_writer.write_generic_prototype(prototype);
+                        continue;
+                    }
+                    None => break,
+                }
+            };
+            let NamedIRType {
+                name,
+                type_: t,
+                metadata,
+            } = named_type;
+            let module = primitive_type_writer.module_of(&name);
+            primitive_type_writer.enter_module(module);
+            let py_name = primitive_type_writer.definition_name(&name);
+            let doc = metadata.doc.as_deref();
+            let py_type = match compiler.resolve_ir_type(&t) {
+                ResolvedIRType::Builtin(builtin) if metadata.annotation("opaque").is_some() => {
+                    primitive_type_writer.write_new_type(&py_name, &builtin, doc)
+                }
+                q => primitive_type_writer.convert_named_ir_type(&py_name, &q.into(), &metadata),
+            };
+            primitive_type_writer
+                .module
+                .define(py_name.clone(), py_type);
+            if self.config.helpers {
+                if let TypeName::Variable(_) = name {
+                    let helpers = primitive_type_writer.write_helpers(&py_name);
+                    primitive_type_writer.module.helpers.push_str(&helpers);
+                    primitive_type_writer.module.helpers.push('\n');
+                }
+            }
+        }
+
+        let mut modules = primitive_type_writer.finish();
+        // Types that reference each other can not all come after the types they reference, so
+        // annotations have to be evaluated lazily. The only names that are evaluated right away are the members of a
+        // variant's `Union`, which are written just before it, and the types of other modules,
+        // which are imported last. Stubs are never evaluated.
+        if !stub {
+            for module in modules.values_mut() {
+                module.imports.import_from("__future__", "annotations");
             }
         }
+        modules
     }
 }
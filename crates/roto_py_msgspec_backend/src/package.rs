@@ -0,0 +1,241 @@
+//! Output split into a Python package, with one module per roto module. The types of `auth.User`
+//! are written to `auth/__init__.py` as `User`, and other modules import it as `auth_User`, the
+//! name it has in a single module.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+
+use roto_core::ir::TypeName;
+use roto_core::symbols::split_qualified_name;
+
+use crate::python_identifier;
+use crate::python_string;
+use crate::Imports;
+use crate::PrimitiveTypeWriter;
+
+/// A Python module, collected while its types are written.
+#[derive(Debug, Default)]
+pub struct PythonModule {
+    pub imports: Imports,
+    /// The type variables of the generic classes in the module.
+    pub type_vars: BTreeSet<String>,
+    /// The classes and aliases defined in the module, which make up `__all__`.
+    pub names: Vec<String>,
+    /// The types of other modules that are referenced, as module, name in that module and the
+    /// name they are imported as.
+    pub references: BTreeSet<(String, String, String)>,
+    pub definitions: Vec<Definition>,
+    /// The names of the module that the definition being written references.
+    pub dependencies: BTreeSet<String>,
+    /// The `decode_` and `encode_` functions, which come after every type.
+    pub helpers: String,
+}
+
+/// A class or alias of a module, with the classes that belong to it, like the options of a
+/// variant.
+#[derive(Debug)]
+pub struct Definition {
+    pub name: String,
+    pub code: String,
+    /// The other definitions of the module it references.
+    pub dependencies: BTreeSet<String>,
+}
+
+impl PythonModule {
+    /// Adds `code`, which defines `name`, with the names referenced since the last definition.
+    pub fn define(&mut self, name: String, code: String) {
+        let mut dependencies = std::mem::take(&mut self.dependencies);
+        dependencies.remove(&name);
+        self.definitions.push(Definition {
+            name,
+            code,
+            dependencies,
+        });
+    }
+
+    /// The definitions, each after the ones it references and otherwise by name, so that the
+    /// order does not depend on the order types are found in. Definitions that reference each
+    /// other are ordered by name as well, which is fine since annotations are evaluated lazily.
+    fn body(&self) -> String {
+        let mut remaining: BTreeMap<&str, &Definition> = self
+            .definitions
+            .iter()
+            .map(|definition| (definition.name.as_str(), definition))
+            .collect();
+        let mut result = String::new();
+        while let Some(&definition) = remaining
+            .values()
+            .find(|definition| {
+                definition
+                    .dependencies
+                    .iter()
+                    .all(|name| !remaining.contains_key(name.as_str()))
+            })
+            .or_else(|| remaining.values().next())
+        {
+            remaining.remove(definition.name.as_str());
+            result.push_str(&definition.code);
+            result.push('\n');
+        }
+        result
+    }
+
+    /// The `TypeVar` declarations of the module's generic classes.
+    pub fn type_var_declarations(&self) -> String {
+        self.type_vars
+            .iter()
+            .map(|name| format!("{} = TypeVar({:?})\n", name, name))
+            .collect()
+    }
+
+    /// The imports of the referenced types, relative to the package root. They come last, so
+    /// that modules that import each other find the classes they need: annotations are only
+    /// evaluated once the package is imported.
+    fn write_references(&self, module: &str) -> String {
+        let depth = if module.is_empty() {
+            0
+        } else {
+            module.split('.').count()
+        };
+        let root = ".".repeat(depth + 1);
+        let mut by_module: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for (target, name, alias) in &self.references {
+            let import = if name == alias {
+                name.clone()
+            } else {
+                format!("{} as {}", name, alias)
+            };
+            by_module.entry(target).or_default().push(import);
+        }
+        let mut result = String::new();
+        for (target, names) in by_module {
+            result.push_str(&format!(
+                "from {}{} import {}\n",
+                root,
+                target,
+                names.join(", ")
+            ));
+        }
+        result
+    }
+
+    /// Writes the module to `out`. In a package, it lists its names in `__all__` and imports
+    /// the types of other modules.
+    pub fn write_to(&self, out: &mut impl fmt::Write, module: &str, package: bool) -> fmt::Result {
+        let imports = self.imports.render();
+        if !imports.is_empty() {
+            write!(out, "{}\n\n", imports)?;
+        }
+        let type_vars = self.type_var_declarations();
+        if package {
+            if self.names.is_empty() {
+                out.write_str("__all__ = []\n")?;
+            } else {
+                out.write_str("__all__ = [\n")?;
+                for name in &self.names {
+                    writeln!(out, "    {},", python_string(name))?;
+                }
+                out.write_str("]\n")?;
+            }
+            if !type_vars.is_empty() || !self.definitions.is_empty() || !self.helpers.is_empty() {
+                out.write_char('\n')?;
+            }
+        }
+        if !type_vars.is_empty() {
+            writeln!(out, "{}", type_vars)?;
+        }
+        out.write_str(&self.body())?;
+        out.write_str(&self.helpers)?;
+        if package && !self.references.is_empty() {
+            out.write_str(&self.write_references(module))?;
+        }
+        Ok(())
+    }
+}
+
+/// The path of the file that module `module` is written to, like `auth/__init__.py`.
+pub fn module_path(module: &str, extension: &str) -> String {
+    if module.is_empty() {
+        format!("__init__.{}", extension)
+    } else {
+        format!("{}/__init__.{}", module.replace('.', "/"), extension)
+    }
+}
+
+impl<'a> PrimitiveTypeWriter<'a> {
+    /// The module `type_name` is written to. Temporaries belong to the module of their owner.
+    /// Everything is written to the root module unless the output is a package.
+    pub fn module_of(&self, type_name: &TypeName) -> String {
+        if !self.config.package {
+            return String::new();
+        }
+        match type_name {
+            TypeName::Variable(name) | TypeName::Generic(name, _) => self.module_of_global(name),
+            TypeName::Temporary(owner, _) => self.module_of(owner),
+        }
+    }
+
+    /// Continues writing to `module`, which is created on first use.
+    pub fn enter_module(&mut self, module: String) {
+        if module == self.module_name {
+            return;
+        }
+        let next = self.modules.remove(&module).unwrap_or_default();
+        let previous = std::mem::replace(&mut self.module, next);
+        let previous_name = std::mem::replace(&mut self.module_name, module);
+        self.modules.insert(previous_name, previous);
+    }
+
+    /// The module the global `name` is written to, like `auth` for `auth.User`.
+    pub fn module_of_global(&self, name: &str) -> String {
+        if !self.config.package {
+            return String::new();
+        }
+        split_qualified_name(name).0.to_string()
+    }
+
+    /// Every module that was written to, by roto module.
+    pub fn finish(mut self) -> BTreeMap<String, PythonModule> {
+        let name = std::mem::take(&mut self.module_name);
+        let module = std::mem::take(&mut self.module);
+        self.modules.insert(name, module);
+        self.modules
+    }
+
+    /// The name a type with the single module name `name` has inside of `module`.
+    fn local_name(module: &str, name: String) -> String {
+        if module.is_empty() {
+            return name;
+        }
+        let prefix = format!("{}_", module.replace('.', "_"));
+        python_identifier(name.strip_prefix(&prefix).unwrap_or(&name))
+    }
+
+    /// The name `type_name` is defined as, in its own module.
+    pub fn definition_name(&mut self, type_name: &TypeName) -> String {
+        let module = self.module_of(type_name);
+        let name = self.allocate_name(type_name);
+        Self::local_name(&module, name)
+    }
+
+    /// The name `type_name` is referred to by from the current module. Types of other modules
+    /// are imported under their single module name, which can not clash.
+    pub fn reference_name(&mut self, type_name: &TypeName) -> String {
+        let module = self.module_of(type_name);
+        let name = self.allocate_name(type_name);
+        self.reference(module, name)
+    }
+
+    /// Like [`Self::reference_name`], for a type `name` of `module`.
+    pub fn reference(&mut self, module: String, name: String) -> String {
+        if module == self.module_name {
+            let local = Self::local_name(&module, name);
+            self.module.dependencies.insert(local.clone());
+            return local;
+        }
+        let local = Self::local_name(&module, name.clone());
+        self.module.references.insert((module, local, name.clone()));
+        name
+    }
+}
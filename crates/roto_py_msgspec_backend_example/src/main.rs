@@ -1,81 +1,83 @@
-use std::collections::HashSet;
-use std::collections::VecDeque;
 use std::env;
 use std::fs;
+use std::io;
+use std::path::Path;
 use std::process;
 
+use roto_core::backend::write_files;
+use roto_core::backend::Backend;
 use roto_core::frontend::IRCompiler;
-use roto_core::frontend::TypePrototype;
-use roto_core::ir::NamedIRType;
-use roto_core::ir::TypeName;
-use roto_core::parser;
-use roto_py_msgspec_backend::PrimitiveTypeWriter;
-use roto_py_msgspec_backend::TypeNameAllocator;
+use roto_core::RotoError;
+use roto_py_msgspec_backend::GeneratedHeader;
+use roto_py_msgspec_backend::MsgspecBackend;
+use roto_py_msgspec_backend::PyMsgspecConfig;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} <file_path>", args[0]);
+    if let Err(error) = run() {
+        eprintln!("{}", error);
         process::exit(1);
     }
+}
 
-    let file_path = &args[1];
-    let file_contents = fs::read_to_string(file_path).expect("Failed to read file");
-
-    let parsed = parser::ProgramParser::new()
-        .parse(&file_contents)
-        .expect("Failed to parse content");
-
-    let mut compiler = IRCompiler::new();
-    for decl in parsed {
-        compiler.register_global_type(
-            decl.name,
-            TypePrototype {
-                params: decl.params,
-                type_: decl.type_,
-            },
+fn run() -> Result<(), RotoError> {
+    let args: Vec<String> = env::args().collect();
+    let mut generic_classes = false;
+    let mut helpers = false;
+    let mut output_dir = None;
+    let mut valid = args.len() >= 2;
+    let mut flags = args.iter().skip(2);
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--generic-classes" => generic_classes = true,
+            "--helpers" => helpers = true,
+            "--package" => {
+                output_dir = flags.next();
+                valid &= output_dir.is_some();
+            }
+            _ => valid = false,
+        }
+    }
+    if !valid {
+        eprintln!(
+            "Usage: {} <file_path> [--generic-classes] [--helpers] [--package <output_dir>]",
+            args[0]
         );
+        process::exit(1);
     }
 
-    let globals = compiler
-        .iter_globals()
-        .filter(|(_, t)| t.params.is_empty())
-        .map(|(name, t)| (name.clone(), t.type_.clone()))
-        .collect::<Vec<_>>();
-    for (name, expr) in globals {
-        compiler.compile_global(name.clone(), &expr);
-    }
+    let file_path = &args[1];
+    let file_contents = fs::read_to_string(file_path).map_err(|e| RotoError::io(file_path, e))?;
 
-    let mut type_name_allocator = TypeNameAllocator::new();
-    let mut primitive_type_writer = PrimitiveTypeWriter {
-        name_allocator: &mut type_name_allocator,
-        allocator: &compiler.allocator,
-        compiled: HashSet::new(),
-        stack: VecDeque::new(),
+    let parsed = roto_core::parse(&file_contents, file_path)?;
+
+    let backend = MsgspecBackend {
+        config: PyMsgspecConfig {
+            generic_classes,
+            package: output_dir.is_some(),
+            helpers,
+            header: Some(GeneratedHeader {
+                source: Some(file_path.clone()),
+                timestamp: false,
+            }),
+            ..Default::default()
+        },
     };
+    let mut compiler = IRCompiler::new();
+    backend.register_annotations(&mut compiler.annotations);
+    compiler.register_program(parsed, file_path, &file_contents);
 
-    for (_i, NamedIRType { name, type_: t }) in compiler.iter_types() {
-        match name {
-            TypeName::Variable(_) => {
-                primitive_type_writer.stack.push_back(NamedIRType {
-                    name: name.clone(),
-                    type_: t.clone(),
-                });
-            }
-            _ => {}
-        }
+    for warning in compiler.check_annotations()? {
+        eprintln!("{}", warning);
     }
 
-    while !primitive_type_writer.stack.is_empty() {
-        let NamedIRType { name, type_: t } = primitive_type_writer.stack.pop_front().unwrap();
-        if primitive_type_writer.compiled.contains(&name) {
-            continue;
-        }
-        primitive_type_writer.compiled.insert(name.clone());
+    compiler.compile_globals()?;
 
-        let py_name = primitive_type_writer.allocate_name(&name);
-        let q = compiler.resolve_ir_type(&t);
-        let py_type = primitive_type_writer.convert_named_ir_type(&py_name, &q.into());
-        println!("{}", py_type);
-    }
+    let Some(output_dir) = output_dir else {
+        return backend
+            .write_to(&compiler, io::stdout().lock())
+            .map_err(|e| RotoError::io("<stdout>", e));
+    };
+    let files = backend.generate(&compiler)?;
+    write_files(Path::new(output_dir), &files).map_err(|e| RotoError::io(output_dir, e))?;
+    Ok(())
 }
@@ -0,0 +1,350 @@
+mod migrations;
+
+use std::collections::HashMap;
+
+use roto_core::annotations::AnnotationRegistry;
+use roto_core::annotations::AnnotationSpec;
+use roto_core::annotations::ArgumentKind;
+use roto_core::ast;
+use roto_core::backend::check_features;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::Feature;
+use roto_core::backend::Language;
+use roto_core::backend::MappedText;
+use roto_core::backend::OutputFile;
+use roto_core::backend::Scope;
+use roto_core::backend::TypeNaming;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::PrimitiveStructField;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeMetadata;
+use roto_core::ir::TypeName;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// Annotations that are passed to `Field(...)` as constraints of the same name.
+const CONSTRAINTS: [(&str, &str, ArgumentKind); 7] = [
+    ("ge", "value", ArgumentKind::Int),
+    ("gt", "value", ArgumentKind::Int),
+    ("le", "value", ArgumentKind::Int),
+    ("lt", "value", ArgumentKind::Int),
+    ("min_length", "value", ArgumentKind::Int),
+    ("max_length", "value", ArgumentKind::Int),
+    ("pattern", "regex", ArgumentKind::String),
+];
+
+/// Registers the constraint annotations understood by the pydantic backend.
+pub fn register_annotations(registry: &mut AnnotationRegistry) {
+    for (name, arg, kind) in CONSTRAINTS {
+        registry.register(AnnotationSpec::new(name).arg(arg, kind));
+    }
+}
+
+fn python_string(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn python_value(value: &ast::AnnotationValue) -> String {
+    match value {
+        ast::AnnotationValue::Ident(ident) => python_string(ident),
+        ast::AnnotationValue::Int(value) => value.to_string(),
+        ast::AnnotationValue::String(value) => python_string(value),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    InProgress,
+    Done,
+}
+
+/// Writes models in dependency order. Field annotations are postponed, so models can refer to
+/// each other in cycles, but type aliases have to be declared after the types they name.
+struct ModelWriter<'a> {
+    compiler: &'a IRCompiler,
+    name_allocator: TypeNameAllocator,
+    states: HashMap<usize, State>,
+    output: Vec<MappedText>,
+    /// Whether an `@opaque` alias was written as a `NewType`, which then has to be imported.
+    uses_new_type: bool,
+    /// Whether a variant without options was written as `NoReturn`, which then has to be
+    /// imported.
+    uses_no_return: bool,
+}
+
+impl<'a> ModelWriter<'a> {
+    fn convert_builtin(&self, t: &ast::Builtin) -> String {
+        match t {
+            ast::Builtin::Int => "int".to_string(),
+            ast::Builtin::Float => "float".to_string(),
+            ast::Builtin::String => "str".to_string(),
+            ast::Builtin::Bool => "bool".to_string(),
+            ast::Builtin::Unit => "None".to_string(),
+            ast::Builtin::Custom(id) => {
+                let builtins = &self.compiler.builtins;
+                builtins
+                    .lower(*id, "py-pydantic")
+                    .unwrap_or_else(|| self.convert_builtin(&builtins.representation(t)))
+            }
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> String {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin),
+            PrimitiveType::Reference(id) => {
+                let name = &self.compiler.named_type(*id).name;
+                self.name_allocator.allocate_name(name)
+            }
+        }
+    }
+
+    fn dependencies(t: &IRType) -> Vec<usize> {
+        let reference = |t: &PrimitiveType| match t {
+            PrimitiveType::Reference(id) => Some(*id),
+            PrimitiveType::Builtin(_) => None,
+        };
+        match t {
+            IRType::Struct(s) => s
+                .fields
+                .iter()
+                .filter_map(|f| reference(&f.type_))
+                .collect(),
+            IRType::Variant(v) => v
+                .variants
+                .iter()
+                .filter_map(|o| reference(&o.type_))
+                .collect(),
+            IRType::Reference(id) => vec![*id],
+            IRType::Builtin(_) => Vec::new(),
+        }
+    }
+
+    fn visit(&mut self, id: usize) {
+        if self.states.contains_key(&id) {
+            return;
+        }
+        self.states.insert(id, State::InProgress);
+
+        let compiler = self.compiler;
+        let named_type = compiler.named_type(id);
+        for dependency in Self::dependencies(&named_type.type_) {
+            self.visit(dependency);
+        }
+
+        let name = self.name_allocator.allocate_name(&named_type.name);
+        let model = self.convert_named_ir_type(&name, &named_type.type_, &named_type.metadata);
+        self.output.push(model);
+        self.states.insert(id, State::Done);
+    }
+
+    fn convert_field(&mut self, scope: &mut Scope, field: &PrimitiveStructField) -> String {
+        let mut field_type = self.convert_primitive_type(&field.type_);
+        let mut args = Vec::new();
+        // Keywords and names taken by another field are escaped, and keep their name in JSON
+        // unless they are renamed anyway.
+        let attribute = scope.identifier(&field.name);
+        let renamed = field.annotations.iter().any(|a| a.name == "rename");
+        if attribute != field.name.as_str() && !renamed {
+            args.push(format!("alias={}", python_string(&field.name)));
+        }
+        if field.optional {
+            field_type = format!("{} | None", field_type);
+            args.push("default=None".to_string());
+        }
+        for annotation in &field.annotations {
+            match annotation.name.as_str() {
+                "rename" => {
+                    if let Some(value) = annotation.arg("name") {
+                        args.push(format!("alias={}", python_value(value)));
+                    }
+                }
+                "deprecated" => match annotation.arg("reason") {
+                    Some(reason) => args.push(format!("deprecated={}", python_value(reason))),
+                    None => args.push("deprecated=True".to_string()),
+                },
+                name => {
+                    let constraint = CONSTRAINTS.iter().find(|(c, _, _)| *c == name);
+                    if let Some((_, arg, _)) = constraint {
+                        if let Some(value) = annotation.arg(arg) {
+                            args.push(format!("{}={}", name, python_value(value)));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(comment) = &field.comment {
+            args.push(format!("description={}", python_string(comment)));
+        }
+
+        match args.as_slice() {
+            [] => format!("    {}: {}\n", attribute, field_type),
+            [default] if default == "default=None" => {
+                format!("    {}: {} = None\n", attribute, field_type)
+            }
+            _ => format!(
+                "    {}: {} = Field({})\n",
+                attribute,
+                field_type,
+                args.join(", ")
+            ),
+        }
+    }
+
+    fn write_docstring(result: &mut MappedText, doc: Option<&str>) {
+        if let Some(doc) = doc {
+            result.push_str(&format!(
+                "    \"\"\"{}\"\"\"\n",
+                doc.replace("\"\"\"", "\\\"\\\"\\\"")
+            ));
+        }
+    }
+
+    fn convert_named_ir_type(
+        &mut self,
+        name: &str,
+        t: &IRType,
+        metadata: &TypeMetadata,
+    ) -> MappedText {
+        let doc = metadata.doc.as_deref();
+        let location = metadata.location.as_ref();
+        let mut result = MappedText::new();
+        match t {
+            IRType::Struct(struct_type) => {
+                result.push_mapped(&format!("class {}(BaseModel):\n", name), location);
+                Self::write_docstring(&mut result, doc);
+                let mut scope = Scope::new(Language::Python);
+                for field in struct_type.fields.iter() {
+                    let line = self.convert_field(&mut scope, field);
+                    result.push_mapped(&line, field.location.as_ref());
+                }
+                if struct_type.fields.is_empty() && doc.is_none() {
+                    result.push_str("    pass\n");
+                }
+            }
+            // Every option becomes a model tagged by a `type` literal, with the payload in
+            // `value`. Unit options have no payload.
+            IRType::Variant(variant_type) => {
+                let mut option_names = Vec::new();
+                for option in variant_type.variants.iter() {
+                    let option_name = format!("{}_{}", name, option.name);
+                    result.push_mapped(
+                        &format!("class {}(BaseModel):\n", option_name),
+                        option.location.as_ref(),
+                    );
+                    Self::write_docstring(&mut result, option.comment.as_deref());
+                    result.push_str(&format!(
+                        "    type: Literal[{}] = {}\n",
+                        python_string(&option.name),
+                        python_string(&option.name)
+                    ));
+                    if !matches!(option.type_, PrimitiveType::Builtin(ast::Builtin::Unit)) {
+                        let payload = self.convert_primitive_type(&option.type_);
+                        result.push_str(&format!("    value: {}\n", payload));
+                    }
+                    result.push_str("\n");
+                    option_names.push(option_name);
+                }
+                let rhs = match option_names.as_slice() {
+                    [] => {
+                        self.uses_no_return = true;
+                        "NoReturn".to_string()
+                    }
+                    [single] => single.clone(),
+                    _ => format!(
+                        "Annotated[Union[{}], Field(discriminator=\"type\")]",
+                        option_names.join(", ")
+                    ),
+                };
+                result.push_mapped(&format!("{}: TypeAlias = {}\n", name, rhs), location);
+            }
+            IRType::Reference(id) => {
+                let rhs = self.convert_primitive_type(&PrimitiveType::Reference(*id));
+                result.push_mapped(&format!("{}: TypeAlias = {}\n", name, rhs), location);
+            }
+            IRType::Builtin(builtin) if metadata.annotation("opaque").is_some() => {
+                self.uses_new_type = true;
+                let rhs = self.convert_builtin(builtin);
+                result.push_mapped(
+                    &format!("{} = NewType({}, {})\n", name, python_string(name), rhs),
+                    location,
+                );
+            }
+            IRType::Builtin(builtin) => {
+                let rhs = self.convert_builtin(builtin);
+                result.push_mapped(&format!("{}: TypeAlias = {}\n", name, rhs), location);
+            }
+        }
+        result
+    }
+}
+
+/// An alias of `unit` would be `Nothing: TypeAlias = None`, which pydantic does not accept as
+/// the type of a field.
+fn supports(feature: &Feature) -> bool {
+    !matches!(feature, Feature::BuiltinAlias(ast::Builtin::Unit))
+}
+
+pub struct PydanticBackend;
+
+impl Backend for PydanticBackend {
+    fn register_annotations(&self, registry: &mut AnnotationRegistry) {
+        register_annotations(registry);
+    }
+
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        check_features(compiler, "py-pydantic", supports)?;
+        let mut writer = ModelWriter {
+            compiler,
+            name_allocator: TypeNameAllocator::with_variable_name(|name| {
+                Language::Python.escape(&name.replace('.', "_"))
+            })
+            .with_declared(compiler)
+            .with_naming(naming.clone()),
+            states: HashMap::new(),
+            output: Vec::new(),
+            uses_new_type: false,
+            uses_no_return: false,
+        };
+
+        for (id, named_type) in compiler.iter_types() {
+            if let TypeName::Variable(_) = named_type.name {
+                writer.visit(*id);
+            }
+        }
+        let mut typing = vec!["Annotated", "Literal"];
+        if writer.uses_new_type {
+            typing.push("NewType");
+        }
+        if writer.uses_no_return {
+            typing.push("NoReturn");
+        }
+        typing.extend(["TypeAlias", "Union"]);
+        let imports = [
+            "from __future__ import annotations\n".to_string(),
+            format!("from typing import {}\n", typing.join(", ")),
+            "from pydantic import BaseModel, Field\n".to_string(),
+        ];
+        writer
+            .output
+            .insert(0, MappedText::from(imports.join("\n")));
+        Ok(vec![
+            MappedText::join(writer.output, "\n\n").into_file("models.py")
+        ])
+    }
+}
@@ -0,0 +1,67 @@
+//! Migrations as Python functions on the JSON objects of the models, which run before the
+//! payloads are validated with a model of the new version.
+
+use roto_core::backend::OutputFile;
+use roto_core::migrate::Migration;
+use roto_core::migrate::MigrationStep;
+use roto_core::migrate::MigrationWriter;
+
+use crate::python_string;
+use crate::PydanticBackend;
+
+const HEADER: &str = r#""""Migrations of payloads between versions of the schema, generated by roto.
+
+Each function takes the JSON object of a type at one version and returns it at a later one.
+Changes that need a decision are marked TODO.
+"""
+from typing import Any, Dict
+"#;
+
+fn function_name(migration: &Migration) -> String {
+    format!(
+        "migrate_{}_v{}_to_v{}",
+        migration.type_name.replace('.', "_"),
+        migration.from,
+        migration.to
+    )
+}
+
+impl MigrationWriter for PydanticBackend {
+    fn write_migrations(&self, migrations: &[Migration]) -> Vec<OutputFile> {
+        let mut contents = HEADER.to_string();
+        for migration in migrations {
+            contents.push_str(&format!(
+                "\n\ndef {}(payload: Dict[str, Any]) -> Dict[str, Any]:\n    payload = dict(payload)\n",
+                function_name(migration)
+            ));
+            for step in &migration.steps {
+                match step {
+                    MigrationStep::RenameField { old, new } => contents.push_str(&format!(
+                        "    if {0} in payload:\n        payload[{1}] = payload.pop({0})\n",
+                        python_string(old),
+                        python_string(new)
+                    )),
+                    MigrationStep::RemoveField { key } => contents
+                        .push_str(&format!("    payload.pop({}, None)\n", python_string(key))),
+                    MigrationStep::Manual(change) => {
+                        contents.push_str(&format!("    # TODO: {}\n", change))
+                    }
+                }
+            }
+            contents.push_str("    return payload\n");
+        }
+        contents.push_str(
+            "\n\n# The migration of every type, by its name and the version it migrates from.\nMIGRATIONS = {\n",
+        );
+        for migration in migrations {
+            contents.push_str(&format!(
+                "    ({}, {}): {},\n",
+                python_string(&migration.type_name),
+                migration.from,
+                function_name(migration)
+            ));
+        }
+        contents.push_str("}\n");
+        vec![OutputFile::new("migrations.py", contents)]
+    }
+}
@@ -0,0 +1,146 @@
+//! Avro schemas of compiled types.
+//!
+//! Structs become records and variants become unions of records, one per option, named like
+//! `Status_active_option` with the payload in a `value` field. Optional fields are unions with
+//! `null` that default to `null`. A record is written in full where it is first used and referred
+//! to by its name after that, as Avro requires.
+
+use std::collections::HashSet;
+
+use roto_core::ast::Builtin;
+use roto_core::backend::TypeNameAllocator;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::{
+    IRType, PrimitiveStruct, PrimitiveType, PrimitiveVariant, PrimitiveVariantOption,
+};
+use serde_json::{json, Map, Value};
+
+/// The Avro schema of the compiled type `id`, with every type it refers to.
+pub fn avro_schema(compiler: &IRCompiler, id: usize) -> Value {
+    AvroWriter {
+        compiler,
+        names: TypeNameAllocator::new().with_declared(compiler),
+        defined: HashSet::new(),
+    }
+    .named_type(id)
+}
+
+struct AvroWriter<'a> {
+    compiler: &'a IRCompiler,
+    names: TypeNameAllocator,
+    /// The records that were written in full.
+    defined: HashSet<String>,
+}
+
+impl AvroWriter<'_> {
+    fn builtin(&self, builtin: &Builtin) -> Value {
+        let builtins = &self.compiler.builtins;
+        json!(match builtin {
+            Builtin::Int => "long",
+            Builtin::Float => "double",
+            Builtin::String => "string",
+            Builtin::Bool => "boolean",
+            Builtin::Unit => "null",
+            // Lowerings are schemas, like `{"type": "string", "logicalType": "uuid"}`.
+            Builtin::Custom(id) => {
+                return builtins
+                    .lower(*id, "avro")
+                    .and_then(|schema| serde_json::from_str(&schema).ok())
+                    .unwrap_or_else(|| self.builtin(&builtins.representation(builtin)));
+            }
+        })
+    }
+
+    fn primitive_type(&mut self, t: &PrimitiveType) -> Value {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.builtin(builtin),
+            PrimitiveType::Reference(id) => self.named_type(*id),
+        }
+    }
+
+    fn named_type(&mut self, id: usize) -> Value {
+        let named_type = self.compiler.named_type(id);
+        let name = self.names.allocate_name(&named_type.name);
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                self.record(name, struct_type, named_type.metadata.doc.as_ref())
+            }
+            IRType::Variant(variant_type) => self.union(&name, variant_type),
+            IRType::Reference(id) => self.named_type(*id),
+            IRType::Builtin(builtin) => self.builtin(builtin),
+        }
+    }
+
+    fn record(
+        &mut self,
+        name: String,
+        struct_type: &PrimitiveStruct,
+        doc: Option<&String>,
+    ) -> Value {
+        if !self.defined.insert(name.clone()) {
+            return json!(name);
+        }
+        let fields = struct_type
+            .fields
+            .iter()
+            .map(|field| {
+                let mut avro_field = Map::new();
+                avro_field.insert("name".to_string(), json!(field.name.as_str()));
+                let type_ = self.primitive_type(&field.type_);
+                if field.optional {
+                    avro_field.insert("type".to_string(), nullable(type_));
+                    avro_field.insert("default".to_string(), Value::Null);
+                } else {
+                    avro_field.insert("type".to_string(), type_);
+                }
+                if let Some(comment) = &field.comment {
+                    avro_field.insert("doc".to_string(), json!(comment));
+                }
+                Value::Object(avro_field)
+            })
+            .collect();
+        record(name, fields, doc)
+    }
+
+    fn union(&mut self, name: &str, variant_type: &PrimitiveVariant) -> Value {
+        let options = variant_type
+            .variants
+            .iter()
+            .map(|option| self.option(name, option))
+            .collect();
+        Value::Array(options)
+    }
+
+    fn option(&mut self, variant_name: &str, option: &PrimitiveVariantOption) -> Value {
+        let name = format!("{}_{}_option", variant_name, option.name);
+        if !self.defined.insert(name.clone()) {
+            return json!(name);
+        }
+        let fields = match &option.type_ {
+            PrimitiveType::Builtin(Builtin::Unit) => Vec::new(),
+            payload => vec![json!({ "name": "value", "type": self.primitive_type(payload) })],
+        };
+        record(name, fields, option.comment.as_ref())
+    }
+}
+
+fn record(name: String, fields: Vec<Value>, doc: Option<&String>) -> Value {
+    let mut record = json!({ "type": "record", "name": name, "fields": fields });
+    if let Some(doc) = doc {
+        record["doc"] = json!(doc);
+    }
+    record
+}
+
+/// `type_` or `null`, with `null` first so that it can be the default. Unions cannot be nested,
+/// so the options of a variant are joined by `null` instead.
+fn nullable(type_: Value) -> Value {
+    match type_ {
+        Value::Array(mut options) => {
+            options.insert(0, json!("null"));
+            Value::Array(options)
+        }
+        Value::String(null) if null == "null" => Value::String(null),
+        type_ => json!(["null", type_]),
+    }
+}
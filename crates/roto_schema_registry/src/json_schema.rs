@@ -0,0 +1,39 @@
+//! JSON Schemas of compiled types, in the encoding of the `openapi` target.
+
+use roto_core::backend::{TypeNameAllocator, Worklist};
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::NamedIRType;
+use roto_openapi_backend::SchemaWriter;
+use serde_json::{json, Map, Value};
+
+/// The JSON Schema of the compiled type `id`, with the types it refers to under `$defs`.
+pub fn json_schema(compiler: &IRCompiler, id: usize) -> Value {
+    let mut name_allocator = TypeNameAllocator::new().with_declared(compiler);
+    let mut writer = SchemaWriter {
+        name_allocator: &mut name_allocator,
+        allocator: compiler.allocator(),
+        builtins: &compiler.builtins,
+        worklist: Worklist::default(),
+        reference_prefix: "#/$defs/",
+    };
+    let root = compiler.named_type(id);
+    let root_name = writer.allocate_name(&root.name);
+    writer.worklist.push_back(root);
+
+    let mut definitions = Map::new();
+    while let Some(NamedIRType {
+        name,
+        type_: t,
+        metadata,
+    }) = writer.worklist.pop()
+    {
+        let schema_name = writer.allocate_name(&name);
+        let schema = writer.convert_named_ir_type(&t, &metadata);
+        definitions.insert(schema_name, schema);
+    }
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$ref": format!("#/$defs/{}", root_name),
+        "$defs": definitions,
+    })
+}
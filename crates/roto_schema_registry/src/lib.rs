@@ -0,0 +1,206 @@
+//! Publishes compiled types to a Confluent Schema Registry, as Avro or JSON Schema.
+//!
+//! Before a new version is registered, it is compared with the previous one by
+//! [`check_compatibility`](roto_core::compat::check_compatibility), and changes that the
+//! [`CompatibilityMode`] of the subject does not allow are reported, instead of being rejected by
+//! the registry one subject at a time. The registry is talked to with `curl`.
+
+use core::fmt;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use roto_core::compat::{Compatibility, CompatibilityIssue};
+use roto_core::diff::SchemaChange;
+use roto_core::frontend::IRCompiler;
+use serde_json::{json, Value};
+
+pub mod avro;
+pub mod json_schema;
+
+/// The schema languages of the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFormat {
+    Avro,
+    JsonSchema,
+}
+
+impl SchemaFormat {
+    /// Parses the name of a format, `avro` or `json`.
+    pub fn from_name(name: &str) -> Option<SchemaFormat> {
+        match name {
+            "avro" => Some(SchemaFormat::Avro),
+            "json" => Some(SchemaFormat::JsonSchema),
+            _ => None,
+        }
+    }
+
+    /// The schema of the compiled type `id`, with every type it refers to.
+    pub fn schema(self, compiler: &IRCompiler, id: usize) -> Value {
+        match self {
+            SchemaFormat::Avro => avro::avro_schema(compiler, id),
+            SchemaFormat::JsonSchema => json_schema::json_schema(compiler, id),
+        }
+    }
+
+    /// The `schemaType` the registry knows the format by.
+    fn schema_type(self) -> &'static str {
+        match self {
+            SchemaFormat::Avro => "AVRO",
+            SchemaFormat::JsonSchema => "JSON",
+        }
+    }
+}
+
+/// Which versions of a subject have to be able to read each other's data, by the names the
+/// registry uses for its compatibility levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatibilityMode {
+    None,
+    /// The new version reads data written with the previous one.
+    Backward,
+    /// The previous version reads data written with the new one.
+    Forward,
+    Full,
+}
+
+impl CompatibilityMode {
+    /// Parses the name of a mode, like `backward`.
+    pub fn from_name(name: &str) -> Option<CompatibilityMode> {
+        match name {
+            "none" => Some(CompatibilityMode::None),
+            "backward" => Some(CompatibilityMode::Backward),
+            "forward" => Some(CompatibilityMode::Forward),
+            "full" => Some(CompatibilityMode::Full),
+            _ => None,
+        }
+    }
+
+    /// Whether the mode allows `issue`. Breaking changes are only breaking in one direction if
+    /// they add something the other version does not know, or remove something it needs.
+    pub fn allows(self, issue: &CompatibilityIssue) -> bool {
+        if issue.compatibility != Compatibility::Breaking {
+            return true;
+        }
+        let (backward, forward) = match &issue.change {
+            // Versions of the removed type stay registered, new versions of the types that
+            // referred to it are reported as retyped.
+            SchemaChange::TypeRemoved { .. } => (true, true),
+            SchemaChange::FieldAdded {
+                optional: false, ..
+            }
+            | SchemaChange::FieldOptionalityChanged {
+                optional: false, ..
+            }
+            | SchemaChange::VariantRemoved { .. } => (false, true),
+            SchemaChange::FieldRemoved { .. }
+            | SchemaChange::FieldOptionalityChanged { optional: true, .. }
+            | SchemaChange::VariantAdded { .. } => (true, false),
+            _ => (false, false),
+        };
+        match self {
+            CompatibilityMode::None => true,
+            CompatibilityMode::Backward => backward,
+            CompatibilityMode::Forward => forward,
+            CompatibilityMode::Full => backward && forward,
+        }
+    }
+}
+
+impl Display for CompatibilityMode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.pad(match self {
+            CompatibilityMode::None => "none",
+            CompatibilityMode::Backward => "backward",
+            CompatibilityMode::Forward => "forward",
+            CompatibilityMode::Full => "full",
+        })
+    }
+}
+
+/// A schema registry, by its base URL like `http://localhost:8081`. Credentials can be part of
+/// the URL.
+pub struct Registry {
+    url: String,
+}
+
+impl Registry {
+    pub fn new(url: &str) -> Self {
+        Registry {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Registers `schema` as the next version of `subject` and returns the id the registry
+    /// assigned to it. A schema that is registered already keeps its id.
+    pub fn register(
+        &self,
+        subject: &str,
+        format: SchemaFormat,
+        schema: &Value,
+    ) -> Result<u64, String> {
+        let mut request = json!({ "schema": schema.to_string() });
+        if format != SchemaFormat::Avro {
+            request["schemaType"] = json!(format.schema_type());
+        }
+        let url = format!("{}/subjects/{}/versions", self.url, encode(subject));
+        let response = self.post(&url, &request.to_string())?;
+        response["id"]
+            .as_u64()
+            .ok_or_else(|| format!("{} answered without an id: {}", url, response))
+    }
+
+    /// Posts `body` to `url` and returns the JSON it answered with, or the message of the error.
+    fn post(&self, url: &str, body: &str) -> Result<Value, String> {
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--request", "POST"])
+            .args([
+                "--header",
+                "Content-Type: application/vnd.schemaregistry.v1+json",
+            ])
+            .args(["--data-binary", "@-", "--write-out", "\n%{http_code}"])
+            .arg(url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to run curl: {}", e))?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(body.as_bytes())
+            .map_err(|e| format!("failed to send the request to curl: {}", e))?;
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("failed to run curl: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "failed to reach {}: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let output = String::from_utf8_lossy(&output.stdout);
+        let (body, status) = output.rsplit_once('\n').unwrap_or(("", &output));
+        let response: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+        if status != "200" {
+            let message = response["message"].as_str().unwrap_or(body);
+            return Err(format!("{} answered {}: {}", url, status, message));
+        }
+        Ok(response)
+    }
+}
+
+/// `subject` as a segment of a URL.
+fn encode(subject: &str) -> String {
+    subject
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
@@ -0,0 +1,459 @@
+//! Imports the tables of a SQL schema as roto structs, for teams whose schema lives in a
+//! database.
+//!
+//! The schema is read from DDL, like a dump of `pg_dump --schema-only` or of the `.schema`
+//! command of `sqlite3`. [`dump_postgres`] and [`dump_sqlite`] run those to introspect a live
+//! database. Every table becomes a struct with a field per column, nullable columns become
+//! optional fields and Postgres enums become variants. Comments on tables and columns are kept
+//! as doc comments. Columns roto cannot express, like arrays, are imported as `unit` with a
+//! warning.
+
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+
+use roto_core::ast::{
+    Annotation, AnnotationValue, Builtin, Item, Span, StructField, StructTypeExpression,
+    TypeAliasDeclaration, TypeExpression, VariantOption, VariantTypeExpression, Visibility,
+};
+use roto_core::diagnostics::Diagnostic;
+use roto_core::syntax::identifier;
+use sqlparser::ast::{
+    ColumnDef, ColumnOption, CommentObject, CreateTable, DataType, ObjectName, Statement,
+    TableConstraint,
+};
+use sqlparser::dialect::{GenericDialect, PostgreSqlDialect, SQLiteDialect};
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+/// The SQL dialect the schema is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Postgres,
+    Sqlite,
+    Generic,
+}
+
+impl Dialect {
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        match name {
+            "postgres" => Some(Dialect::Postgres),
+            "sqlite" => Some(Dialect::Sqlite),
+            "generic" => Some(Dialect::Generic),
+            _ => None,
+        }
+    }
+
+    fn parser_dialect(self) -> Box<dyn sqlparser::dialect::Dialect> {
+        match self {
+            Dialect::Postgres => Box::new(PostgreSqlDialect {}),
+            Dialect::Sqlite => Box::new(SQLiteDialect {}),
+            Dialect::Generic => Box::new(GenericDialect {}),
+        }
+    }
+}
+
+/// The declarations of an imported schema, and what could not be imported faithfully.
+#[derive(Debug)]
+pub struct Import {
+    pub items: Vec<Item>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Runs `command` and returns what it printed.
+fn run(command: &mut Command) -> Result<String, String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command
+        .output()
+        .map_err(|e| format!("failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{} did not print UTF-8", program))
+}
+
+/// The schema of the Postgres database at `url`, as printed by `pg_dump`.
+pub fn dump_postgres(url: &str) -> Result<String, String> {
+    run(Command::new("pg_dump")
+        .arg("--schema-only")
+        .arg("--no-owner")
+        .arg("--no-privileges")
+        .arg(url))
+}
+
+/// The schema of the SQLite database in the file `path`, as printed by `sqlite3`.
+pub fn dump_sqlite(path: &str) -> Result<String, String> {
+    run(Command::new("sqlite3").arg(path).arg(".schema"))
+}
+
+/// Imports the tables and enums declared in `sql`. Statements other than `CREATE TABLE`,
+/// `CREATE TYPE ... AS ENUM` and `COMMENT ON` are ignored.
+pub fn import(sql: &str, dialect: Dialect) -> Result<Import, Diagnostic> {
+    let parser_dialect = dialect.parser_dialect();
+    let tokens = Tokenizer::new(parser_dialect.as_ref(), sql)
+        .tokenize()
+        .map_err(|error| Diagnostic::error(format!("invalid SQL: {}", error), None))?;
+    let mut schema = Schema::default();
+    let mut diagnostics = Vec::new();
+    // Statements are parsed one by one, so that a statement the parser does not understand,
+    // like a function in a dump, does not stop the import.
+    for statement in tokens.split(|token| *token == Token::SemiColon) {
+        let statement: Vec<Token> = statement
+            .iter()
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .cloned()
+            .collect();
+        if statement.is_empty() {
+            continue;
+        }
+        if let Some((name, labels)) = create_enum(&statement) {
+            schema.declarations.push(Declaration::Enum(name, labels));
+            continue;
+        }
+        let parsed = Parser::new(parser_dialect.as_ref())
+            .with_tokens(statement.clone())
+            .parse_statement();
+        match parsed {
+            Ok(Statement::CreateTable(table)) => {
+                schema
+                    .declarations
+                    .push(Declaration::Table(Box::new(table)));
+            }
+            Ok(Statement::Comment {
+                object_type,
+                object_name,
+                comment: Some(comment),
+                ..
+            }) => {
+                let key = match object_type {
+                    CommentObject::Table => (last(&object_name), None),
+                    CommentObject::Column => {
+                        let [.., table, column] = object_name.0.as_slice() else {
+                            continue;
+                        };
+                        (table.value.clone(), Some(column.value.clone()))
+                    }
+                    _ => continue,
+                };
+                schema.comments.insert(key, comment);
+            }
+            Ok(_) => {}
+            Err(error) if starts_with(&statement, &[Keyword::CREATE, Keyword::TABLE]) => {
+                diagnostics.push(
+                    Diagnostic::warning(
+                        format!("skipped a CREATE TABLE that could not be parsed: {}", error),
+                        None,
+                    )
+                    .with_code("unsupported-sql"),
+                );
+            }
+            Err(_) => {}
+        }
+    }
+    let mut importer = Importer {
+        enums: schema
+            .declarations
+            .iter()
+            .filter_map(|declaration| match declaration {
+                Declaration::Enum(name, _) => Some(name.clone()),
+                Declaration::Table(_) => None,
+            })
+            .collect(),
+        comments: &schema.comments,
+        names: HashMap::new(),
+        diagnostics,
+    };
+    let items = schema
+        .declarations
+        .iter()
+        .map(|declaration| importer.declaration(declaration))
+        .collect();
+    Ok(Import {
+        items,
+        diagnostics: importer.diagnostics,
+    })
+}
+
+fn starts_with(statement: &[Token], keywords: &[Keyword]) -> bool {
+    statement.len() >= keywords.len()
+        && statement
+            .iter()
+            .zip(keywords)
+            .all(|(token, keyword)| matches!(token, Token::Word(word) if word.keyword == *keyword))
+}
+
+/// The name and labels of `CREATE TYPE name AS ENUM ('label', ...)`, which the parser does not
+/// support.
+fn create_enum(statement: &[Token]) -> Option<(String, Vec<String>)> {
+    if !starts_with(statement, &[Keyword::CREATE, Keyword::TYPE]) {
+        return None;
+    }
+    let as_enum = statement
+        .windows(2)
+        .position(|pair| {
+            matches!(
+                pair,
+                [Token::Word(a), Token::Word(b)] if a.keyword == Keyword::AS && b.keyword == Keyword::ENUM
+            )
+        })?;
+    // The name may be qualified, like `public.mood`.
+    let Token::Word(name) = &statement[as_enum - 1] else {
+        return None;
+    };
+    let labels = statement[as_enum + 2..]
+        .iter()
+        .filter_map(|token| match token {
+            Token::SingleQuotedString(label) => Some(label.clone()),
+            _ => None,
+        })
+        .collect();
+    Some((name.value.clone(), labels))
+}
+
+fn last(name: &ObjectName) -> String {
+    name.0
+        .last()
+        .map(|ident| ident.value.clone())
+        .unwrap_or_default()
+}
+
+enum Declaration {
+    Table(Box<CreateTable>),
+    Enum(String, Vec<String>),
+}
+
+#[derive(Default)]
+struct Schema {
+    declarations: Vec<Declaration>,
+    /// The comments of tables, and of their columns, by table and column name.
+    comments: HashMap<(String, Option<String>), String>,
+}
+
+/// `user_accounts` as `UserAccounts`, to name a type after a table.
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+/// `@rename(name=...)` if the member `name` had to be renamed to become `identifier`.
+fn rename(name: &str, identifier: &str) -> Vec<Annotation> {
+    if name == identifier {
+        return Vec::new();
+    }
+    vec![Annotation {
+        name: "rename".to_string(),
+        args: vec![(
+            "name".to_string(),
+            AnnotationValue::String(name.to_string()),
+        )],
+    }]
+}
+
+/// The builtin a SQL type is imported as, by its name without arguments like `varchar`. Names
+/// that are not known are classified by the type affinity rules of SQLite.
+fn builtin(type_name: &str) -> Option<Builtin> {
+    let type_name = type_name.to_ascii_lowercase();
+    let base = type_name.split('(').next().unwrap_or_default().trim();
+    Some(match base {
+        "boolean" | "bool" => Builtin::Bool,
+        "real" | "float" | "float4" | "float8" | "double" | "double precision" | "numeric"
+        | "decimal" | "dec" | "money" => Builtin::Float,
+        "uuid" | "date" | "time" | "timestamp" | "timestamptz" | "interval" | "json" | "jsonb"
+        | "inet" | "cidr" | "macaddr" | "xml" | "citext" => Builtin::String,
+        _ if base.starts_with("time") => Builtin::String,
+        _ if base.contains("int") || base.contains("serial") => Builtin::Int,
+        _ if ["char", "clob", "text", "string"]
+            .iter()
+            .any(|text| base.contains(text)) =>
+        {
+            Builtin::String
+        }
+        _ if ["real", "floa", "doub"]
+            .iter()
+            .any(|real| base.contains(real)) =>
+        {
+            Builtin::Float
+        }
+        _ => return None,
+    })
+}
+
+struct Importer<'a> {
+    /// The names of the declared enums.
+    enums: HashSet<String>,
+    comments: &'a HashMap<(String, Option<String>), String>,
+    /// The type each table or enum is declared as.
+    names: HashMap<String, String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Importer<'_> {
+    fn warn(&mut self, place: &str, message: String) {
+        self.diagnostics.push(
+            Diagnostic::warning(format!("{}: {}", place, message), None)
+                .with_code("unsupported-sql"),
+        );
+    }
+
+    /// The name the table or enum `name` is declared as.
+    fn type_name(&mut self, name: &str) -> String {
+        if let Some(declared) = self.names.get(name) {
+            return declared.clone();
+        }
+        let mut declared = identifier(&pascal_case(name));
+        let mut i = 1;
+        while self.names.values().any(|n| *n == declared) {
+            i += 1;
+            declared = format!("{}{}", identifier(&pascal_case(name)), i);
+        }
+        self.names.insert(name.to_string(), declared.clone());
+        declared
+    }
+
+    fn declaration(&mut self, declaration: &Declaration) -> Item {
+        let (name, doc, type_) = match declaration {
+            Declaration::Table(table) => {
+                let name = last(&table.name);
+                let doc = self.comments.get(&(name.clone(), None)).cloned();
+                (name.clone(), doc, self.table(&name, table))
+            }
+            Declaration::Enum(name, labels) => (name.clone(), None, self.enumeration(labels)),
+        };
+        Item::Type(TypeAliasDeclaration {
+            doc,
+            annotations: Vec::new(),
+            visibility: Visibility::Private,
+            name: self.type_name(&name),
+            params: Vec::new(),
+            type_,
+            span: Span::default(),
+        })
+    }
+
+    fn enumeration(&mut self, labels: &[String]) -> TypeExpression {
+        let mut used = HashSet::new();
+        let variants = labels
+            .iter()
+            .map(|label| {
+                let mut name = identifier(label);
+                while !used.insert(name.clone()) {
+                    name.push('_');
+                }
+                VariantOption {
+                    annotations: rename(label, &name),
+                    name: name.into(),
+                    type_: TypeExpression::Builtin(Builtin::Unit),
+                    comment: None,
+                    location: None,
+                }
+            })
+            .collect();
+        TypeExpression::Variant(VariantTypeExpression { variants })
+    }
+
+    fn table(&mut self, name: &str, table: &CreateTable) -> TypeExpression {
+        if table.columns.is_empty() {
+            self.warn(
+                name,
+                "tables without columns are imported as unit".to_string(),
+            );
+            return TypeExpression::Builtin(Builtin::Unit);
+        }
+        let primary_key: Vec<&str> = table
+            .constraints
+            .iter()
+            .flat_map(|constraint| match constraint {
+                TableConstraint::PrimaryKey { columns, .. } => columns.as_slice(),
+                _ => &[],
+            })
+            .map(|column| column.value.as_str())
+            .collect();
+        let mut used = HashSet::new();
+        let mut fields = Vec::new();
+        for column in &table.columns {
+            let column_name = &column.name.value;
+            let mut field_name = identifier(column_name);
+            while !used.insert(field_name.clone()) {
+                field_name.push('_');
+            }
+            let required = primary_key.contains(&column_name.as_str()) || not_null(column);
+            let place = format!("{}.{}", name, column_name);
+            fields.push(StructField {
+                annotations: rename(column_name, &field_name),
+                name: field_name.into(),
+                type_: self.column_type(&column.data_type, &place),
+                optional: !required,
+                comment: self
+                    .comments
+                    .get(&(name.to_string(), Some(column_name.clone())))
+                    .cloned(),
+                location: None,
+            });
+        }
+        TypeExpression::Struct(StructTypeExpression { fields })
+    }
+
+    fn column_type(&mut self, data_type: &DataType, place: &str) -> TypeExpression {
+        match data_type {
+            DataType::Array(_) => {
+                self.warn(
+                    place,
+                    "arrays are not supported, imported as unit".to_string(),
+                );
+                TypeExpression::Builtin(Builtin::Unit)
+            }
+            DataType::Custom(name, _) if self.enums.contains(&last(name)) => {
+                TypeExpression::Variable(self.type_name(&last(name)).into())
+            }
+            DataType::Unspecified => {
+                self.warn(
+                    place,
+                    "columns without a type are imported as unit".to_string(),
+                );
+                TypeExpression::Builtin(Builtin::Unit)
+            }
+            DataType::Blob(_) | DataType::Bytea | DataType::Binary(_) | DataType::Varbinary(_) => {
+                self.warn(
+                    place,
+                    "binary columns are imported as string, like base64 in JSON".to_string(),
+                );
+                TypeExpression::Builtin(Builtin::String)
+            }
+            data_type => match builtin(&data_type.to_string()) {
+                Some(builtin) => TypeExpression::Builtin(builtin),
+                None => {
+                    self.warn(
+                        place,
+                        format!("type {} is not supported, imported as unit", data_type),
+                    );
+                    TypeExpression::Builtin(Builtin::Unit)
+                }
+            },
+        }
+    }
+}
+
+fn not_null(column: &ColumnDef) -> bool {
+    column.options.iter().any(|option| {
+        matches!(
+            option.option,
+            ColumnOption::NotNull
+                | ColumnOption::Unique {
+                    is_primary: true,
+                    ..
+                }
+        )
+    })
+}
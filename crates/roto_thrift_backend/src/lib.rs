@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::Language;
+use roto_core::backend::OutputFile;
+use roto_core::backend::Scope;
+use roto_core::backend::TypeNaming;
+use roto_core::diagnostics::Diagnostic;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+use roto_core::lint::Rule;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// Thrift has no unit type, so unit payloads refer to this empty struct.
+const UNIT_STRUCT: &str = "Unit";
+
+fn tag(annotations: &[ast::Annotation]) -> Option<i64> {
+    let tag = annotations.iter().find(|a| a.name == "tag")?;
+    match tag.arg("value")? {
+        ast::AnnotationValue::Int(value) => Some(*value),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    InProgress,
+    Done,
+}
+
+/// Writes definitions in dependency order, so typedefs come after the types they name. Structs
+/// become `struct`, variants become `enum` if none of their options has a payload and `union`
+/// otherwise.
+struct DefinitionWriter<'a> {
+    compiler: &'a IRCompiler,
+    name_allocator: TypeNameAllocator,
+    states: HashMap<usize, State>,
+    output: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    uses_unit: bool,
+}
+
+impl<'a> DefinitionWriter<'a> {
+    fn convert_builtin(&mut self, t: &ast::Builtin) -> String {
+        match t {
+            ast::Builtin::Int => "i64".to_string(),
+            ast::Builtin::Float => "double".to_string(),
+            ast::Builtin::String => "string".to_string(),
+            ast::Builtin::Bool => "bool".to_string(),
+            ast::Builtin::Unit => {
+                self.uses_unit = true;
+                UNIT_STRUCT.to_string()
+            }
+            ast::Builtin::Custom(id) => {
+                let builtins = &self.compiler.builtins;
+                builtins
+                    .lower(*id, "thrift")
+                    .unwrap_or_else(|| self.convert_builtin(&builtins.representation(t)))
+            }
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> String {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin),
+            PrimitiveType::Reference(id) => {
+                let name = &self.compiler.named_type(*id).name;
+                self.name_allocator.allocate_name(name)
+            }
+        }
+    }
+
+    fn dependencies(t: &IRType) -> Vec<usize> {
+        let reference = |t: &PrimitiveType| match t {
+            PrimitiveType::Reference(id) => Some(*id),
+            PrimitiveType::Builtin(_) => None,
+        };
+        match t {
+            IRType::Struct(s) => s
+                .fields
+                .iter()
+                .filter_map(|f| reference(&f.type_))
+                .collect(),
+            IRType::Variant(v) => v
+                .variants
+                .iter()
+                .filter_map(|o| reference(&o.type_))
+                .collect(),
+            IRType::Reference(id) => vec![*id],
+            IRType::Builtin(_) => Vec::new(),
+        }
+    }
+
+    fn visit(&mut self, id: usize) {
+        if self.states.contains_key(&id) {
+            return;
+        }
+        self.states.insert(id, State::InProgress);
+
+        let compiler = self.compiler;
+        let named_type = compiler.named_type(id);
+        for dependency in Self::dependencies(&named_type.type_) {
+            self.visit(dependency);
+        }
+
+        let name = self.name_allocator.allocate_name(&named_type.name);
+        let definition = self.convert_named_ir_type(&name, named_type);
+        self.output.push(definition);
+        self.states.insert(id, State::Done);
+    }
+
+    /// Checks the field id of `member` and reports missing, invalid and duplicate tags where
+    /// `member` is declared, or else where its type is.
+    fn check_tag(
+        &mut self,
+        named_type: &NamedIRType,
+        member: &str,
+        annotations: &[ast::Annotation],
+        location: Option<&ast::SourceLocation>,
+        seen: &mut HashMap<i64, String>,
+    ) -> i64 {
+        let location = location.or(named_type.metadata.location.as_ref()).cloned();
+        let Some(tag) = tag(annotations) else {
+            self.diagnostics.push(Diagnostic::error(
+                format!("{}.{} has no @tag annotation", named_type.name, member),
+                location,
+            ));
+            return 0;
+        };
+        if !(1..=i16::MAX as i64).contains(&tag) {
+            self.diagnostics.push(Diagnostic::error(
+                format!("{}.{} has an invalid tag {}", named_type.name, member, tag),
+                location,
+            ));
+        } else if let Some(other) = seen.insert(tag, member.to_string()) {
+            self.diagnostics.push(Diagnostic::error(
+                format!(
+                    "{}.{} and {}.{} have the same tag {}",
+                    named_type.name, other, named_type.name, member, tag
+                ),
+                location,
+            ));
+        }
+        tag
+    }
+
+    fn write_comment(result: &mut String, indent: &str, comment: &Option<String>) {
+        if let Some(comment) = comment {
+            for line in comment.lines() {
+                result.push_str(&format!("{}// {}\n", indent, line));
+            }
+        }
+    }
+
+    fn convert_named_ir_type(&mut self, name: &str, named_type: &NamedIRType) -> String {
+        let mut result = String::new();
+        let mut seen = HashMap::new();
+        let mut scope = Scope::new(Language::Thrift);
+        Self::write_comment(&mut result, "", &named_type.metadata.doc);
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                result.push_str(&format!("struct {} {{\n", name));
+                for field in struct_type.fields.iter() {
+                    Self::write_comment(&mut result, "  ", &field.comment);
+                    let tag = self.check_tag(
+                        named_type,
+                        &field.name,
+                        &field.annotations,
+                        field.location.as_ref(),
+                        &mut seen,
+                    );
+                    let field_type = self.convert_primitive_type(&field.type_);
+                    let label = if field.optional { "optional " } else { "" };
+                    let field_name = scope.identifier(&field.name);
+                    result.push_str(&format!(
+                        "  {}: {}{} {},\n",
+                        tag, label, field_type, field_name
+                    ));
+                }
+                result.push_str("}\n");
+            }
+            IRType::Variant(variant_type)
+                if variant_type
+                    .variants
+                    .iter()
+                    .all(|o| matches!(o.type_, PrimitiveType::Builtin(ast::Builtin::Unit))) =>
+            {
+                result.push_str(&format!("enum {} {{\n", name));
+                for option in variant_type.variants.iter() {
+                    Self::write_comment(&mut result, "  ", &option.comment);
+                    let tag = self.check_tag(
+                        named_type,
+                        &option.name,
+                        &option.annotations,
+                        option.location.as_ref(),
+                        &mut seen,
+                    );
+                    let option_name = scope.identifier(&option.name);
+                    result.push_str(&format!("  {} = {},\n", option_name, tag));
+                }
+                result.push_str("}\n");
+            }
+            IRType::Variant(variant_type) => {
+                result.push_str(&format!("union {} {{\n", name));
+                for option in variant_type.variants.iter() {
+                    Self::write_comment(&mut result, "  ", &option.comment);
+                    let tag = self.check_tag(
+                        named_type,
+                        &option.name,
+                        &option.annotations,
+                        option.location.as_ref(),
+                        &mut seen,
+                    );
+                    let option_type = self.convert_primitive_type(&option.type_);
+                    let option_name = scope.identifier(&option.name);
+                    result.push_str(&format!("  {}: {} {},\n", tag, option_type, option_name));
+                }
+                result.push_str("}\n");
+            }
+            IRType::Reference(id) => {
+                let target = self.convert_primitive_type(&PrimitiveType::Reference(*id));
+                result.push_str(&format!("typedef {} {}\n", target, name));
+            }
+            IRType::Builtin(builtin) => {
+                let target = self.convert_builtin(builtin);
+                result.push_str(&format!("typedef {} {}\n", target, name));
+            }
+        }
+        result
+    }
+}
+
+pub struct ThriftBackend;
+
+impl Backend for ThriftBackend {
+    fn required_rules(&self) -> &'static [Rule] {
+        &[Rule::MissingTag, Rule::MissingOrdinal]
+    }
+
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = DefinitionWriter {
+            compiler,
+            name_allocator: TypeNameAllocator::with_variable_name(|name| {
+                Language::Thrift.escape(&name.replace('.', "_"))
+            })
+            .with_declared(compiler)
+            .with_naming(naming.clone()),
+            states: HashMap::new(),
+            output: Vec::new(),
+            diagnostics: Vec::new(),
+            uses_unit: false,
+        };
+
+        for (id, named_type) in compiler.iter_types() {
+            if let TypeName::Variable(_) = named_type.name {
+                writer.visit(*id);
+            }
+        }
+
+        if !writer.diagnostics.is_empty() {
+            return Err(BackendError::new(writer.diagnostics));
+        }
+        if writer.uses_unit {
+            writer
+                .output
+                .insert(0, format!("struct {} {{}}\n", UNIT_STRUCT));
+        }
+        Ok(vec![OutputFile::new(
+            "types.thrift",
+            writer.output.join("\n"),
+        )])
+    }
+}
@@ -0,0 +1,181 @@
+pub mod zod;
+
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::Language;
+use roto_core::backend::MappedText;
+use roto_core::backend::OutputFile;
+use roto_core::backend::TypeNaming;
+use roto_core::backend::Worklist;
+use roto_core::builtins::BuiltinRegistry;
+use roto_core::frontend::IRCompiler;
+use roto_core::frontend::TypeAllocator;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeMetadata;
+use roto_core::ir::TypeName;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+pub struct TypeWriter<'a> {
+    pub name_allocator: &'a mut TypeNameAllocator,
+    pub allocator: &'a TypeAllocator,
+    pub builtins: &'a BuiltinRegistry,
+    pub worklist: Worklist,
+}
+
+impl<'a> TypeWriter<'a> {
+    pub fn allocate_name(&mut self, type_name: &TypeName) -> String {
+        self.name_allocator.allocate_name(type_name)
+    }
+
+    fn convert_builtin(&self, t: &ast::Builtin) -> String {
+        match t {
+            ast::Builtin::Int => "number".to_string(),
+            ast::Builtin::Float => "number".to_string(),
+            ast::Builtin::String => "string".to_string(),
+            ast::Builtin::Bool => "boolean".to_string(),
+            ast::Builtin::Unit => "null".to_string(),
+            ast::Builtin::Custom(id) => self
+                .builtins
+                .lower(*id, "ts")
+                .unwrap_or_else(|| self.convert_builtin(&self.builtins.representation(t))),
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> String {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin),
+            PrimitiveType::Reference(id) => {
+                let r = self.allocator.get(*id).unwrap();
+                self.worklist.push_back(r);
+                self.allocate_name(&r.name)
+            }
+        }
+    }
+
+    fn write_doc(&self, result: &mut MappedText, indent: &str, doc: &str) {
+        result.push_str(&format!("{}/**\n", indent));
+        for line in doc.lines() {
+            result.push_str(&format!("{} * {}\n", indent, line));
+        }
+        result.push_str(&format!("{} */\n", indent));
+    }
+
+    pub fn convert_named_ir_type(
+        &mut self,
+        name: &str,
+        t: &IRType,
+        metadata: &TypeMetadata,
+    ) -> MappedText {
+        let mut result = MappedText::new();
+        if let Some(doc) = &metadata.doc {
+            self.write_doc(&mut result, "", doc);
+        }
+        let location = metadata.location.as_ref();
+        match t {
+            IRType::Struct(struct_type) => {
+                result.push_mapped(&format!("export interface {} {{\n", name), location);
+                for field in struct_type.fields.iter() {
+                    if let Some(comment) = &field.comment {
+                        self.write_doc(&mut result, "  ", comment);
+                    }
+                    let marker = if field.optional { "?" } else { "" };
+                    let field_type = self.convert_primitive_type(&field.type_);
+                    result.push_mapped(
+                        &format!("  {}{}: {};\n", field.name, marker, field_type),
+                        field.location.as_ref(),
+                    );
+                }
+                result.push_str("}\n");
+            }
+            // Variants are adjacently tagged: `{ type: "option", value: payload }`, where the
+            // value is left out for unit options.
+            IRType::Variant(variant_type) => {
+                result.push_mapped(&format!("export type {} =\n", name), location);
+                for option in variant_type.variants.iter() {
+                    if let Some(comment) = &option.comment {
+                        self.write_doc(&mut result, "  ", comment);
+                    }
+                    match &option.type_ {
+                        PrimitiveType::Builtin(ast::Builtin::Unit) => {
+                            result.push_mapped(
+                                &format!("  | {{ type: \"{}\" }}\n", option.name),
+                                option.location.as_ref(),
+                            );
+                        }
+                        payload => {
+                            let payload = self.convert_primitive_type(payload);
+                            result.push_mapped(
+                                &format!(
+                                    "  | {{ type: \"{}\"; value: {} }}\n",
+                                    option.name, payload
+                                ),
+                                option.location.as_ref(),
+                            );
+                        }
+                    }
+                }
+                if variant_type.variants.is_empty() {
+                    result.push_str("  never\n");
+                }
+                result.push_str(";\n");
+            }
+            IRType::Reference(_) | IRType::Builtin(_) => {
+                let mut rhs = match t {
+                    IRType::Reference(id) => {
+                        self.convert_primitive_type(&PrimitiveType::Reference(*id))
+                    }
+                    IRType::Builtin(builtin) => self.convert_builtin(builtin),
+                    _ => unreachable!(),
+                };
+                // Opaque aliases are branded, so that values of the target have to be cast.
+                if metadata.annotation("opaque").is_some() {
+                    rhs = format!("{} & {{ readonly __brand: \"{}\" }}", rhs, name);
+                }
+                result.push_mapped(&format!("export type {} = {};\n", name, rhs), location);
+            }
+        }
+        result
+    }
+}
+
+pub struct TsBackend;
+
+impl Backend for TsBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut type_name_allocator = TypeNameAllocator::with_variable_name(|name| {
+            Language::TypeScript.escape(&name.replace('.', "_"))
+        })
+        .with_declared(compiler)
+        .with_naming(naming.clone());
+        let mut writer = TypeWriter {
+            name_allocator: &mut type_name_allocator,
+            allocator: compiler.allocator(),
+            builtins: &compiler.builtins,
+            worklist: Worklist::new(compiler),
+        };
+
+        let mut output = Vec::new();
+        while let Some(NamedIRType {
+            name,
+            type_: t,
+            metadata,
+        }) = writer.worklist.pop()
+        {
+            let ts_name = writer.allocate_name(&name);
+            output.push(writer.convert_named_ir_type(&ts_name, &t, &metadata));
+        }
+        Ok(vec![MappedText::join(output, "\n").into_file("types.ts")])
+    }
+}
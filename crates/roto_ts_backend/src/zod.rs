@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::Language;
+use roto_core::backend::MappedText;
+use roto_core::backend::OutputFile;
+use roto_core::backend::TypeNaming;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+
+use crate::TypeNameAllocator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    InProgress,
+    Done,
+}
+
+/// Emits a Zod schema and an inferred type for every type. Schemas are declared after the
+/// schemas they depend on. References that close a cycle are wrapped in `z.lazy`, and the
+/// inferred types of such recursive schemas are not precise.
+pub struct ZodBackend;
+
+struct SchemaWriter<'a> {
+    compiler: &'a IRCompiler,
+    name_allocator: TypeNameAllocator,
+    states: HashMap<usize, State>,
+    output: Vec<MappedText>,
+}
+
+impl<'a> SchemaWriter<'a> {
+    fn schema_name(&mut self, name: &TypeName) -> String {
+        format!("{}Schema", self.name_allocator.allocate_name(name))
+    }
+
+    fn convert_builtin(&self, t: &ast::Builtin) -> String {
+        match t {
+            ast::Builtin::Int => "z.number().int()".to_string(),
+            ast::Builtin::Float => "z.number()".to_string(),
+            ast::Builtin::String => "z.string()".to_string(),
+            ast::Builtin::Bool => "z.boolean()".to_string(),
+            ast::Builtin::Unit => "z.null()".to_string(),
+            ast::Builtin::Custom(id) => {
+                let builtins = &self.compiler.builtins;
+                builtins
+                    .lower(*id, "ts-zod")
+                    .unwrap_or_else(|| self.convert_builtin(&builtins.representation(t)))
+            }
+        }
+    }
+
+    fn convert_reference(&mut self, id: usize) -> String {
+        let name = self.compiler.named_type(id).name.clone();
+        let schema = self.schema_name(&name);
+        match self.states.get(&id) {
+            Some(State::Done) => schema,
+            _ => format!("z.lazy(() => {})", schema),
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> String {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin),
+            PrimitiveType::Reference(id) => self.convert_reference(*id),
+        }
+    }
+
+    fn dependencies(t: &IRType) -> Vec<usize> {
+        let reference = |t: &PrimitiveType| match t {
+            PrimitiveType::Reference(id) => Some(*id),
+            PrimitiveType::Builtin(_) => None,
+        };
+        match t {
+            IRType::Struct(s) => s
+                .fields
+                .iter()
+                .filter_map(|f| reference(&f.type_))
+                .collect(),
+            IRType::Variant(v) => v
+                .variants
+                .iter()
+                .filter_map(|o| reference(&o.type_))
+                .collect(),
+            IRType::Reference(id) => vec![*id],
+            IRType::Builtin(_) => Vec::new(),
+        }
+    }
+
+    fn visit(&mut self, id: usize) {
+        if self.states.contains_key(&id) {
+            return;
+        }
+        self.states.insert(id, State::InProgress);
+
+        let compiler = self.compiler;
+        let named_type = compiler.named_type(id);
+        for dependency in Self::dependencies(&named_type.type_) {
+            self.visit(dependency);
+        }
+
+        let schema = self.convert_ir_type(&named_type.type_);
+        let type_name = self.name_allocator.allocate_name(&named_type.name);
+        let schema_name = self.schema_name(&named_type.name);
+
+        let mut result = MappedText::new();
+        if let Some(doc) = &named_type.metadata.doc {
+            result.push_str("/**\n");
+            for line in doc.lines() {
+                result.push_str(&format!(" * {}\n", line));
+            }
+            result.push_str(" */\n");
+        }
+        result.push_mapped(
+            &format!("export const {} = ", schema_name),
+            named_type.metadata.location.as_ref(),
+        );
+        result.append(schema);
+        let is_alias = matches!(named_type.type_, IRType::Reference(_) | IRType::Builtin(_));
+        if is_alias && named_type.metadata.annotation("opaque").is_some() {
+            result.push_str(&format!(".brand<\"{}\">()", type_name));
+        }
+        result.push_str(";\n");
+        result.push_str(&format!(
+            "export type {} = z.infer<typeof {}>;\n",
+            type_name, schema_name
+        ));
+        self.output.push(result);
+        self.states.insert(id, State::Done);
+    }
+
+    fn convert_ir_type(&mut self, t: &IRType) -> MappedText {
+        match t {
+            IRType::Struct(struct_type) => {
+                let mut result = MappedText::from("z.object({\n");
+                for field in struct_type.fields.iter() {
+                    let mut field_type = self.convert_primitive_type(&field.type_);
+                    if field.optional {
+                        field_type.push_str(".optional()");
+                    }
+                    result.push_mapped(
+                        &format!("  {}: {},\n", field.name, field_type),
+                        field.location.as_ref(),
+                    );
+                }
+                result.push_str("})");
+                result
+            }
+            // Variants are adjacently tagged: `{ type: "option", value: payload }`, where the
+            // value is left out for unit options.
+            IRType::Variant(variant_type) => {
+                if variant_type.variants.is_empty() {
+                    return MappedText::from("z.never()");
+                }
+                let mut result = MappedText::from("z.discriminatedUnion(\"type\", [\n");
+                for option in variant_type.variants.iter() {
+                    match &option.type_ {
+                        PrimitiveType::Builtin(ast::Builtin::Unit) => {
+                            result.push_mapped(
+                                &format!(
+                                    "  z.object({{ type: z.literal(\"{}\") }}),\n",
+                                    option.name
+                                ),
+                                option.location.as_ref(),
+                            );
+                        }
+                        payload => {
+                            let payload = self.convert_primitive_type(payload);
+                            result.push_mapped(
+                                &format!(
+                                    "  z.object({{ type: z.literal(\"{}\"), value: {} }}),\n",
+                                    option.name, payload
+                                ),
+                                option.location.as_ref(),
+                            );
+                        }
+                    }
+                }
+                result.push_str("])");
+                result
+            }
+            IRType::Reference(id) => MappedText::from(self.convert_reference(*id)),
+            IRType::Builtin(builtin) => MappedText::from(self.convert_builtin(builtin)),
+        }
+    }
+}
+
+impl Backend for ZodBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = SchemaWriter {
+            compiler,
+            name_allocator: TypeNameAllocator::with_variable_name(|name| {
+                Language::TypeScript.escape(&name.replace('.', "_"))
+            })
+            .with_declared(compiler)
+            .with_naming(naming.clone()),
+            states: HashMap::new(),
+            output: vec![MappedText::from("import { z } from \"zod\";\n")],
+        };
+
+        for (id, named_type) in compiler.iter_types() {
+            if let TypeName::Variable(_) = named_type.name {
+                writer.visit(*id);
+            }
+        }
+        Ok(vec![
+            MappedText::join(writer.output, "\n").into_file("schemas.ts")
+        ])
+    }
+}
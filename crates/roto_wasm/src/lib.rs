@@ -0,0 +1,86 @@
+//! Bindings for JavaScript, so that roto can run in the browser: a playground compiles the
+//! source as it is typed and shows what every backend generates for it.
+//!
+//! Results are returned as JSON strings. Diagnostics have the fields of `roto --error-format
+//! json`, generated files have a `path` and `contents`.
+
+use roto_backends::compile;
+use roto_backends::registry;
+use roto_core::backend::BackendConfig;
+use roto_core::diagnostics::Diagnostic;
+use serde_json::json;
+use serde_json::Value;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// The name diagnostics refer to the source by.
+const FILE_NAME: &str = "playground.roto";
+
+/// The fields of `diagnostic`, with a `null` file and span if it has no location.
+fn to_json(diagnostic: &Diagnostic) -> Value {
+    let location = diagnostic.location.as_ref();
+    let span = location.map(|location| {
+        json!({
+            "start": {"line": location.line, "column": location.column},
+            "end": {"line": location.end_line, "column": location.end_column},
+        })
+    });
+    json!({
+        "file": location.map(|location| &location.file),
+        "span": span,
+        "severity": diagnostic.severity.to_string(),
+        "code": diagnostic.code,
+        "message": diagnostic.message,
+    })
+}
+
+/// The names of the targets `generate` accepts, sorted.
+#[wasm_bindgen]
+pub fn targets() -> Vec<String> {
+    registry().names().map(str::to_string).collect()
+}
+
+/// Checks `source` with the annotations of every backend known. Returns a JSON array of
+/// diagnostics, empty if the source compiles cleanly.
+#[wasm_bindgen]
+pub fn check(source: &str) -> String {
+    let registry = registry();
+    let backends: Vec<_> = registry.iter().collect();
+    let (_, diagnostics) = compile(source, FILE_NAME, &backends);
+    Value::from_iter(diagnostics.iter().map(to_json)).to_string()
+}
+
+/// Compiles `source` and runs the backend `target` on it, configured by `options` of the form
+/// `key=value`. Returns a JSON object with the generated `files`, empty if anything failed, and
+/// the `diagnostics`.
+#[wasm_bindgen]
+pub fn generate(source: &str, target: &str, options: Vec<String>) -> String {
+    let registry = registry();
+    let mut diagnostics = Vec::new();
+    let mut files = Vec::new();
+    match registry.get(target) {
+        None => diagnostics.push(Diagnostic::error(
+            format!("Unknown target `{}`", target),
+            None,
+        )),
+        Some(backend) => {
+            let (compiler, compiled) = compile(source, FILE_NAME, &[backend]);
+            diagnostics = compiled;
+            if let Some(compiler) = compiler {
+                let mut config = BackendConfig::new();
+                for option in &options {
+                    config.set_pair(option);
+                }
+                match backend.generate(&compiler, &config) {
+                    Ok(generated) => files = generated,
+                    Err(error) => diagnostics.extend(error.diagnostics),
+                }
+            }
+        }
+    }
+    let files: Vec<_> = files
+        .iter()
+        .map(|file| json!({"path": file.path, "contents": file.contents}))
+        .collect();
+    let diagnostics: Vec<_> = diagnostics.iter().map(to_json).collect();
+    json!({"files": files, "diagnostics": diagnostics}).to_string()
+}
@@ -0,0 +1,33 @@
+//! Encoders and decoders for the roto wire format, a compact binary encoding of roto types.
+//!
+//! The format is not self-describing, both sides need the same schema:
+//!
+//! - `int` is a zigzag encoded LEB128 varint.
+//! - `float` is an IEEE 754 double in 8 little endian bytes.
+//! - `bool` is a single byte, `0` or `1`.
+//! - `string` is a varint byte length followed by UTF-8 bytes.
+//! - `unit` takes no bytes.
+//! - Structs are their fields in declaration order. Optional fields start with a presence byte,
+//!   `0` or `1`, and the value follows only if it is present.
+//! - Variants are the varint index of the option in declaration order, followed by the payload.
+//!
+//! Adding, removing or reordering fields and options changes the encoding.
+
+pub mod python;
+pub mod rust;
+
+pub use roto_core::backend::TypeNameAllocator;
+
+/// Converts a roto option name like `not_found` to a type name like `NotFound`.
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
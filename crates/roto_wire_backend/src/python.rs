@@ -0,0 +1,375 @@
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::Language;
+use roto_core::backend::OutputFile;
+use roto_core::backend::Scope;
+use roto_core::backend::TypeNaming;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+
+use crate::pascal_case;
+use crate::TypeNameAllocator;
+
+/// The imports and primitive codecs every generated module starts with.
+const RUNTIME: &str = r#"from __future__ import annotations
+
+import struct
+from dataclasses import dataclass
+from typing import Callable, NoReturn, Tuple, TypeAlias, TypeVar, Union
+
+T = TypeVar("T")
+
+
+class DecodeError(Exception):
+    pass
+
+
+def _write_varint(out: bytearray, value: int) -> None:
+    while value >= 0x80:
+        out.append((value & 0x7F) | 0x80)
+        value >>= 7
+    out.append(value)
+
+
+def _read_varint(data: bytes, pos: int) -> Tuple[int, int]:
+    value = 0
+    for shift in range(0, 64, 7):
+        if pos >= len(data):
+            raise DecodeError("unexpected end of input")
+        byte = data[pos]
+        pos += 1
+        value |= (byte & 0x7F) << shift
+        if not byte & 0x80:
+            return value, pos
+    raise DecodeError("invalid varint")
+
+
+def _read_flag(data: bytes, pos: int) -> Tuple[bool, int]:
+    if pos >= len(data):
+        raise DecodeError("unexpected end of input")
+    if data[pos] > 1:
+        raise DecodeError(f"invalid bool {data[pos]}")
+    return data[pos] == 1, pos + 1
+
+
+def _encode_int(out: bytearray, value: int) -> None:
+    if not -(2**63) <= value < 2**63:
+        raise ValueError(f"{value} does not fit into 64 bits")
+    _write_varint(out, ((value << 1) ^ (value >> 63)) & 0xFFFFFFFFFFFFFFFF)
+
+
+def _decode_int(data: bytes, pos: int) -> Tuple[int, int]:
+    value, pos = _read_varint(data, pos)
+    return (value >> 1) ^ -(value & 1), pos
+
+
+def _encode_float(out: bytearray, value: float) -> None:
+    out += struct.pack("<d", value)
+
+
+def _decode_float(data: bytes, pos: int) -> Tuple[float, int]:
+    if pos + 8 > len(data):
+        raise DecodeError("unexpected end of input")
+    return struct.unpack_from("<d", data, pos)[0], pos + 8
+
+
+def _encode_bool(out: bytearray, value: bool) -> None:
+    out.append(1 if value else 0)
+
+
+def _decode_bool(data: bytes, pos: int) -> Tuple[bool, int]:
+    return _read_flag(data, pos)
+
+
+def _encode_str(out: bytearray, value: str) -> None:
+    encoded = value.encode("utf-8")
+    _write_varint(out, len(encoded))
+    out += encoded
+
+
+def _decode_str(data: bytes, pos: int) -> Tuple[str, int]:
+    length, pos = _read_varint(data, pos)
+    if pos + length > len(data):
+        raise DecodeError("unexpected end of input")
+    try:
+        return data[pos : pos + length].decode("utf-8"), pos + length
+    except UnicodeDecodeError as error:
+        raise DecodeError("invalid utf-8") from error
+
+
+def _encode_unit(out: bytearray, value: Tuple[()]) -> None:
+    pass
+
+
+def _decode_unit(data: bytes, pos: int) -> Tuple[Tuple[()], int]:
+    return (), pos
+
+
+def _encode_optional(
+    out: bytearray, value: T | None, encode: Callable[[bytearray, T], None]
+) -> None:
+    if value is None:
+        out.append(0)
+    else:
+        out.append(1)
+        encode(out, value)
+
+
+def _decode_optional(
+    data: bytes, pos: int, decode: Callable[[bytes, int], Tuple[T, int]]
+) -> Tuple[T | None, int]:
+    present, pos = _read_flag(data, pos)
+    if not present:
+        return None, pos
+    return decode(data, pos)
+"#;
+
+/// Writes a dataclass and a pair of `encode_*`/`decode_*` functions per type. Annotations are
+/// postponed and alias targets are quoted, so the order of definitions does not matter.
+struct ModuleWriter<'a> {
+    compiler: &'a IRCompiler,
+    name_allocator: TypeNameAllocator,
+}
+
+impl<'a> ModuleWriter<'a> {
+    /// Custom builtins are encoded as their representation, the wire format has no lowerings.
+    fn convert_builtin(&self, t: &ast::Builtin) -> &'static str {
+        match t {
+            ast::Builtin::Int => "int",
+            ast::Builtin::Float => "float",
+            ast::Builtin::String => "str",
+            ast::Builtin::Bool => "bool",
+            // Not `None`, so that an optional unit field that is present stays distinguishable.
+            ast::Builtin::Unit => "Tuple[()]",
+            ast::Builtin::Custom(_) => {
+                self.convert_builtin(&self.compiler.builtins.representation(t))
+            }
+        }
+    }
+
+    fn codec_name(&self, t: &ast::Builtin) -> &'static str {
+        match t {
+            ast::Builtin::Int => "int",
+            ast::Builtin::Float => "float",
+            ast::Builtin::String => "str",
+            ast::Builtin::Bool => "bool",
+            ast::Builtin::Unit => "unit",
+            ast::Builtin::Custom(_) => self.codec_name(&self.compiler.builtins.representation(t)),
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> String {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin).to_string(),
+            PrimitiveType::Reference(id) => {
+                let name = &self.compiler.named_type(*id).name;
+                self.name_allocator.allocate_name(name)
+            }
+        }
+    }
+
+    /// The names of the functions that encode and decode `t`.
+    fn codec(&mut self, t: &PrimitiveType) -> (String, String) {
+        match t {
+            PrimitiveType::Builtin(builtin) => {
+                let name = self.codec_name(builtin);
+                (format!("_encode_{}", name), format!("_decode_{}", name))
+            }
+            PrimitiveType::Reference(_) => {
+                let name = self.convert_primitive_type(t);
+                (format!("encode_{}", name), format!("decode_{}", name))
+            }
+        }
+    }
+
+    fn write_docstring(result: &mut String, doc: &Option<String>) {
+        if let Some(doc) = doc {
+            result.push_str(&format!(
+                "    \"\"\"{}\"\"\"\n",
+                doc.replace("\"\"\"", "\\\"\\\"\\\"")
+            ));
+        }
+    }
+
+    fn write_codec(result: &mut String, name: &str, encode: &str, decode: &str) {
+        result.push_str(&format!(
+            "\n\ndef encode_{}(out: bytearray, value: {}) -> None:\n{}",
+            name, name, encode
+        ));
+        result.push_str(&format!(
+            "\n\ndef decode_{}(data: bytes, pos: int = 0) -> Tuple[{}, int]:\n{}",
+            name, name, decode
+        ));
+    }
+
+    fn convert_named_ir_type(&mut self, name: &str, named_type: &NamedIRType) -> String {
+        let mut result = String::new();
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                let mut encode = String::new();
+                let mut decode = String::new();
+                let mut args = Vec::new();
+                result.push_str(&format!("@dataclass\nclass {}:\n", name));
+                Self::write_docstring(&mut result, &named_type.metadata.doc);
+                let mut scope = Scope::new(Language::Python);
+                for field in struct_type.fields.iter() {
+                    let mut field_type = self.convert_primitive_type(&field.type_);
+                    let field_name = scope.identifier(&field.name);
+                    let (encoder, decoder) = self.codec(&field.type_);
+                    if field.optional {
+                        field_type = format!("{} | None", field_type);
+                        encode.push_str(&format!(
+                            "    _encode_optional(out, value.{}, {})\n",
+                            field_name, encoder
+                        ));
+                        decode.push_str(&format!(
+                            "    v_{}, pos = _decode_optional(data, pos, {})\n",
+                            field_name, decoder
+                        ));
+                    } else {
+                        encode.push_str(&format!("    {}(out, value.{})\n", encoder, field_name));
+                        decode.push_str(&format!(
+                            "    v_{}, pos = {}(data, pos)\n",
+                            field_name, decoder
+                        ));
+                    }
+                    result.push_str(&format!("    {}: {}\n", field_name, field_type));
+                    args.push(format!("{}=v_{}", field_name, field_name));
+                }
+                if struct_type.fields.is_empty() && named_type.metadata.doc.is_none() {
+                    result.push_str("    pass\n");
+                }
+                if encode.is_empty() {
+                    encode.push_str("    pass\n");
+                }
+                decode.push_str(&format!("    return {}({}), pos\n", name, args.join(", ")));
+                Self::write_codec(&mut result, name, &encode, &decode);
+            }
+            // Every option is a dataclass, with the payload in `value`. Unit options have no
+            // payload.
+            IRType::Variant(variant_type) => {
+                let mut encode = String::new();
+                let mut decode = "    index, pos = _read_varint(data, pos)\n".to_string();
+                let mut option_names = Vec::new();
+                let mut scope = Scope::new(Language::Python);
+                for (index, option) in variant_type.variants.iter().enumerate() {
+                    let option_name =
+                        scope.unique(format!("{}_{}", name, pascal_case(&option.name)));
+                    result.push_str(&format!("@dataclass\nclass {}:\n", option_name));
+                    Self::write_docstring(&mut result, &option.comment);
+                    let branch = if index == 0 { "if" } else { "elif" };
+                    encode.push_str(&format!(
+                        "    {} isinstance(value, {}):\n        _write_varint(out, {})\n",
+                        branch, option_name, index
+                    ));
+                    decode.push_str(&format!("    if index == {}:\n", index));
+                    match &option.type_ {
+                        PrimitiveType::Builtin(ast::Builtin::Unit) => {
+                            if option.comment.is_none() {
+                                result.push_str("    pass\n");
+                            }
+                            decode.push_str(&format!("        return {}(), pos\n", option_name));
+                        }
+                        payload => {
+                            let payload_type = self.convert_primitive_type(payload);
+                            let (encoder, decoder) = self.codec(payload);
+                            result.push_str(&format!("    value: {}\n", payload_type));
+                            encode.push_str(&format!("        {}(out, value.value)\n", encoder));
+                            decode.push_str(&format!(
+                                "        v_value, pos = {}(data, pos)\n        return {}(value=v_value), pos\n",
+                                decoder, option_name
+                            ));
+                        }
+                    }
+                    result.push_str("\n\n");
+                    option_names.push(format!("\"{}\"", option_name));
+                }
+                if encode.is_empty() {
+                    encode.push_str(&format!(
+                        "    raise TypeError(f\"{} has no options\")\n",
+                        name
+                    ));
+                } else {
+                    encode.push_str(&format!(
+                        "    else:\n        raise TypeError(f\"{{value!r}} is not a valid {}\")\n",
+                        name
+                    ));
+                }
+                decode.push_str(&format!(
+                    "    raise DecodeError(f\"unknown option {{index}} of {}\")\n",
+                    name
+                ));
+                let rhs = match option_names.as_slice() {
+                    [] => "NoReturn".to_string(),
+                    [single] => single.clone(),
+                    _ => format!("Union[{}]", option_names.join(", ")),
+                };
+                result.push_str(&format!("{}: TypeAlias = {}\n", name, rhs));
+                Self::write_codec(&mut result, name, &encode, &decode);
+            }
+            IRType::Reference(id) => {
+                let reference = PrimitiveType::Reference(*id);
+                let target = self.convert_primitive_type(&reference);
+                let (encoder, decoder) = self.codec(&reference);
+                result.push_str(&format!("{}: TypeAlias = \"{}\"\n", name, target));
+                Self::write_codec(
+                    &mut result,
+                    name,
+                    &format!("    {}(out, value)\n", encoder),
+                    &format!("    return {}(data, pos)\n", decoder),
+                );
+            }
+            IRType::Builtin(builtin) => {
+                let builtin_type = PrimitiveType::Builtin(builtin.clone());
+                let (encoder, decoder) = self.codec(&builtin_type);
+                result.push_str(&format!(
+                    "{}: TypeAlias = {}\n",
+                    name,
+                    self.convert_builtin(builtin)
+                ));
+                Self::write_codec(
+                    &mut result,
+                    name,
+                    &format!("    {}(out, value)\n", encoder),
+                    &format!("    return {}(data, pos)\n", decoder),
+                );
+            }
+        }
+        result
+    }
+}
+
+/// Emits a Python module with a dataclass for every compiled type and functions that encode and
+/// decode them in the roto wire format.
+pub struct PythonWireBackend;
+
+impl Backend for PythonWireBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = ModuleWriter {
+            compiler,
+            name_allocator: TypeNameAllocator::with_variable_name(|name| {
+                Language::Python.escape(&name.replace('.', "_"))
+            })
+            .with_declared(compiler)
+            .with_naming(naming.clone()),
+        };
+
+        let mut output = vec![RUNTIME.to_string()];
+        for (_id, named_type) in compiler.iter_types() {
+            let name = writer.name_allocator.allocate_name(&named_type.name);
+            output.push(writer.convert_named_ir_type(&name, named_type));
+        }
+        Ok(vec![OutputFile::new("wire.py", output.join("\n\n"))])
+    }
+}
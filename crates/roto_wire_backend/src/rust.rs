@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+
+use roto_core::ast;
+use roto_core::backend::Backend;
+use roto_core::backend::BackendError;
+use roto_core::backend::Language;
+use roto_core::backend::OutputFile;
+use roto_core::backend::Scope;
+use roto_core::backend::TypeNaming;
+use roto_core::frontend::IRCompiler;
+use roto_core::ir::IRType;
+use roto_core::ir::NamedIRType;
+use roto_core::ir::PrimitiveType;
+use roto_core::ir::TypeName;
+
+use crate::pascal_case;
+use crate::TypeNameAllocator;
+
+/// The traits and primitive codecs every generated module starts with.
+const RUNTIME: &str = r#"#![allow(dead_code, non_camel_case_types)]
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    InvalidVarint,
+    InvalidBool(u8),
+    InvalidUtf8,
+    UnknownOption(u64),
+    TrailingBytes(usize),
+}
+
+pub trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf);
+        buf
+    }
+}
+
+pub trait Decode: Sized {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError>;
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self, DecodeError> {
+        let value = Self::decode(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(DecodeError::TrailingBytes(bytes.len()));
+        }
+        Ok(value)
+    }
+}
+
+pub fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+pub fn read_varint(input: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_bytes(input, 1)?[0];
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecodeError::InvalidVarint)
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < len {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+impl Encode for i64 {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, ((self << 1) ^ (self >> 63)) as u64);
+    }
+}
+
+impl Decode for i64 {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let value = read_varint(input)?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+}
+
+impl Encode for f64 {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for f64 {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let bytes = read_bytes(input, 8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match read_bytes(input, 1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(DecodeError::InvalidBool(byte)),
+        }
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.len() as u64);
+        buf.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = read_varint(input)?;
+        let len = usize::try_from(len).map_err(|_| DecodeError::UnexpectedEnd)?;
+        let bytes = read_bytes(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+}
+
+impl Encode for () {
+    fn encode(&self, _buf: &mut Vec<u8>) {}
+}
+
+impl Decode for () {
+    fn decode(_input: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Some(value) => {
+                buf.push(1);
+                value.encode(buf);
+            }
+            None => buf.push(0),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match read_bytes(input, 1)?[0] {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            byte => Err(DecodeError::InvalidBool(byte)),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.as_ref().encode(buf);
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(Box::new(T::decode(input)?))
+    }
+}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    InProgress,
+    Done,
+}
+
+/// Writes Rust types with `Encode` and `Decode` implementations. References that close a cycle
+/// are boxed, so recursive types have a finite size.
+struct ModuleWriter<'a> {
+    compiler: &'a IRCompiler,
+    name_allocator: TypeNameAllocator,
+    states: HashMap<usize, State>,
+    output: Vec<String>,
+}
+
+impl<'a> ModuleWriter<'a> {
+    /// Custom builtins are encoded as their representation, the wire format has no lowerings.
+    fn convert_builtin(&self, t: &ast::Builtin) -> String {
+        match t {
+            ast::Builtin::Int => "i64".to_string(),
+            ast::Builtin::Float => "f64".to_string(),
+            ast::Builtin::String => "String".to_string(),
+            ast::Builtin::Bool => "bool".to_string(),
+            ast::Builtin::Unit => "()".to_string(),
+            ast::Builtin::Custom(_) => {
+                self.convert_builtin(&self.compiler.builtins.representation(t))
+            }
+        }
+    }
+
+    fn convert_primitive_type(&mut self, t: &PrimitiveType) -> String {
+        match t {
+            PrimitiveType::Builtin(builtin) => self.convert_builtin(builtin),
+            PrimitiveType::Reference(id) => {
+                let name = &self.compiler.named_type(*id).name;
+                let name = self.name_allocator.allocate_name(name);
+                if self.states.get(id) == Some(&State::InProgress) {
+                    format!("Box<{}>", name)
+                } else {
+                    name
+                }
+            }
+        }
+    }
+
+    fn dependencies(t: &IRType) -> Vec<usize> {
+        let reference = |t: &PrimitiveType| match t {
+            PrimitiveType::Reference(id) => Some(*id),
+            PrimitiveType::Builtin(_) => None,
+        };
+        match t {
+            IRType::Struct(s) => s
+                .fields
+                .iter()
+                .filter_map(|f| reference(&f.type_))
+                .collect(),
+            IRType::Variant(v) => v
+                .variants
+                .iter()
+                .filter_map(|o| reference(&o.type_))
+                .collect(),
+            IRType::Reference(id) => vec![*id],
+            IRType::Builtin(_) => Vec::new(),
+        }
+    }
+
+    fn visit(&mut self, id: usize) {
+        if self.states.contains_key(&id) {
+            return;
+        }
+        self.states.insert(id, State::InProgress);
+
+        let compiler = self.compiler;
+        let named_type = compiler.named_type(id);
+        for dependency in Self::dependencies(&named_type.type_) {
+            self.visit(dependency);
+        }
+
+        let name = self.name_allocator.allocate_name(&named_type.name);
+        let definition = self.convert_named_ir_type(&name, named_type);
+        self.output.push(definition);
+        self.states.insert(id, State::Done);
+    }
+
+    fn write_comment(result: &mut String, indent: &str, comment: &Option<String>) {
+        if let Some(comment) = comment {
+            for line in comment.lines() {
+                result.push_str(&format!("{}/// {}\n", indent, line));
+            }
+        }
+    }
+
+    fn convert_named_ir_type(&mut self, name: &str, named_type: &NamedIRType) -> String {
+        let mut result = String::new();
+        Self::write_comment(&mut result, "", &named_type.metadata.doc);
+        match &named_type.type_ {
+            IRType::Struct(struct_type) => {
+                let mut encode = String::new();
+                let mut decode = String::new();
+                result.push_str("#[derive(Debug, Clone, PartialEq)]\n");
+                result.push_str(&format!("pub struct {} {{\n", name));
+                let mut scope = Scope::new(Language::Rust);
+                for field in struct_type.fields.iter() {
+                    Self::write_comment(&mut result, "    ", &field.comment);
+                    let mut field_type = self.convert_primitive_type(&field.type_);
+                    if field.optional {
+                        field_type = format!("Option<{}>", field_type);
+                    }
+                    let field_name = scope.identifier(&field.name);
+                    result.push_str(&format!("    pub {}: {},\n", field_name, field_type));
+                    encode.push_str(&format!("        self.{}.encode(buf);\n", field_name));
+                    decode.push_str(&format!(
+                        "            {}: Decode::decode(input)?,\n",
+                        field_name
+                    ));
+                }
+                result.push_str("}\n\n");
+                result.push_str(&format!(
+                    "impl Encode for {} {{\n    fn encode(&self, {}buf: &mut Vec<u8>) {{\n{}    }}\n}}\n\n",
+                    name,
+                    if encode.is_empty() { "_" } else { "" },
+                    encode
+                ));
+                result.push_str(&format!(
+                    "impl Decode for {} {{\n    fn decode({}input: &mut &[u8]) -> Result<Self, DecodeError> {{\n        Ok({} {{\n{}        }})\n    }}\n}}\n",
+                    name,
+                    if decode.is_empty() { "_" } else { "" },
+                    name,
+                    decode
+                ));
+            }
+            IRType::Variant(variant_type) => {
+                let mut encode = String::new();
+                let mut decode = String::new();
+                result.push_str("#[derive(Debug, Clone, PartialEq)]\n");
+                result.push_str(&format!("pub enum {} {{\n", name));
+                let mut scope = Scope::new(Language::Rust);
+                for (index, option) in variant_type.variants.iter().enumerate() {
+                    Self::write_comment(&mut result, "    ", &option.comment);
+                    let option_name = scope.identifier(&pascal_case(&option.name));
+                    match &option.type_ {
+                        PrimitiveType::Builtin(ast::Builtin::Unit) => {
+                            result.push_str(&format!("    {},\n", option_name));
+                            encode.push_str(&format!(
+                                "            {}::{} => write_varint(buf, {}),\n",
+                                name, option_name, index
+                            ));
+                            decode.push_str(&format!(
+                                "            {} => Ok({}::{}),\n",
+                                index, name, option_name
+                            ));
+                        }
+                        payload => {
+                            let payload = self.convert_primitive_type(payload);
+                            result.push_str(&format!("    {}({}),\n", option_name, payload));
+                            encode.push_str(&format!(
+                                "            {}::{}(value) => {{\n                write_varint(buf, {});\n                value.encode(buf);\n            }}\n",
+                                name, option_name, index
+                            ));
+                            decode.push_str(&format!(
+                                "            {} => Ok({}::{}(Decode::decode(input)?)),\n",
+                                index, name, option_name
+                            ));
+                        }
+                    }
+                }
+                result.push_str("}\n\n");
+                if variant_type.variants.is_empty() {
+                    result.push_str(&format!(
+                        "impl Encode for {} {{\n    fn encode(&self, _buf: &mut Vec<u8>) {{\n        match *self {{}}\n    }}\n}}\n\n",
+                        name
+                    ));
+                } else {
+                    result.push_str(&format!(
+                        "impl Encode for {} {{\n    fn encode(&self, buf: &mut Vec<u8>) {{\n        match self {{\n{}        }}\n    }}\n}}\n\n",
+                        name, encode
+                    ));
+                }
+                result.push_str(&format!(
+                    "impl Decode for {} {{\n    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {{\n        match read_varint(input)? {{\n{}            index => Err(DecodeError::UnknownOption(index)),\n        }}\n    }}\n}}\n",
+                    name, decode
+                ));
+            }
+            IRType::Reference(id) => {
+                let target = self.convert_primitive_type(&PrimitiveType::Reference(*id));
+                result.push_str(&format!("pub type {} = {};\n", name, target));
+            }
+            IRType::Builtin(builtin) => {
+                let target = self.convert_builtin(builtin);
+                result.push_str(&format!("pub type {} = {};\n", name, target));
+            }
+        }
+        result
+    }
+}
+
+/// Emits a Rust module with a type for every compiled type and implementations of `Encode` and
+/// `Decode` for the roto wire format.
+pub struct RustWireBackend;
+
+impl Backend for RustWireBackend {
+    fn generate(&self, compiler: &IRCompiler) -> Result<Vec<OutputFile>, BackendError> {
+        self.generate_named(compiler, &TypeNaming::default())
+    }
+
+    fn generate_named(
+        &self,
+        compiler: &IRCompiler,
+        naming: &TypeNaming,
+    ) -> Result<Vec<OutputFile>, BackendError> {
+        let mut writer = ModuleWriter {
+            compiler,
+            name_allocator: TypeNameAllocator::with_variable_name(|name| {
+                Language::Rust.escape(&name.replace('.', "_"))
+            })
+            .with_declared(compiler)
+            .with_naming(naming.clone()),
+            states: HashMap::new(),
+            output: vec![RUNTIME.to_string()],
+        };
+
+        for (id, named_type) in compiler.iter_types() {
+            if let TypeName::Variable(_) = named_type.name {
+                writer.visit(*id);
+            }
+        }
+        Ok(vec![OutputFile::new("wire.rs", writer.output.join("\n"))])
+    }
+}